@@ -4,6 +4,8 @@ use actix_web::{cookie::Cookie, http::header, web, HttpRequest, HttpResponse};
 pub use authkestra_engine::auth::{Session, SessionConfig, SessionStore};
 #[cfg(feature = "flow")]
 use authkestra_engine::pkce::Pkce;
+#[cfg(all(feature = "flow", feature = "session"))]
+use authkestra_engine::ErrorLevels;
 #[cfg(all(feature = "flow", not(feature = "session")))]
 use authkestra_engine::SessionConfig;
 #[cfg(feature = "flow")]
@@ -21,6 +23,27 @@ pub struct OAuthCallbackParams {
 pub struct OAuthLoginParams {
     pub scope: Option<String>,
     pub success_url: Option<String>,
+    /// OIDC `prompt` value, e.g. `login` to force re-authentication.
+    pub prompt: Option<String>,
+    /// OIDC `login_hint` value, e.g. the user's email address.
+    pub login_hint: Option<String>,
+    /// `true` to create a long-lived session using `SessionConfig::remember_max_age`.
+    pub remember: Option<bool>,
+}
+
+#[cfg(feature = "flow")]
+impl OAuthLoginParams {
+    /// Validates `prompt` and collects the extra authorization parameters.
+    pub fn authorization_params(
+        &self,
+    ) -> Result<authkestra_engine::AuthorizationParams, authkestra_engine::AuthError> {
+        Ok(authkestra_engine::AuthorizationParams {
+            prompt: self.prompt.as_deref().map(str::parse).transpose()?,
+            login_hint: self.login_hint.clone(),
+            remember: self.remember.unwrap_or(false),
+            correlation_id: None,
+        })
+    }
 }
 
 #[cfg(feature = "session")]
@@ -40,7 +63,7 @@ pub fn create_actix_cookie<'a>(config: &SessionConfig, value: String) -> Cookie<
         .http_only(config.http_only)
         .same_site(to_actix_same_site(config.same_site));
 
-    if let Some(max_age) = config.max_age {
+    if let Some(max_age) = config.cookie_lifetime() {
         builder = builder.max_age(actix_web::cookie::time::Duration::seconds(
             max_age.num_seconds(),
         ));
@@ -48,6 +71,23 @@ pub fn create_actix_cookie<'a>(config: &SessionConfig, value: String) -> Cookie<
     builder.finish()
 }
 
+/// Add a session cookie built by [`create_actix_cookie`] to `response`.
+///
+/// actix-web's cookie crate cannot set `Partitioned`, so when
+/// `config.partitioned` is set the attribute is appended to the header here.
+#[cfg(feature = "session")]
+pub fn add_session_cookie(
+    response: &mut actix_web::HttpResponseBuilder,
+    config: &SessionConfig,
+    cookie: Cookie<'_>,
+) {
+    if config.partitioned {
+        response.append_header((header::SET_COOKIE, format!("{cookie}; Partitioned")));
+    } else {
+        response.cookie(cookie);
+    }
+}
+
 /// Helper to initiate the OAuth2 login flow.
 ///
 /// This generates the authorization URL and sets a CSRF state cookie.
@@ -71,12 +111,44 @@ pub fn initiate_oauth_login_erased(
     scopes: &[&str],
     config: &SessionConfig,
     success_url: Option<String>,
+) -> HttpResponse {
+    initiate_oauth_login_with_params(
+        flow,
+        scopes,
+        config,
+        success_url,
+        &authkestra_engine::AuthorizationParams::default(),
+    )
+}
+
+/// Like [`initiate_oauth_login_erased`], appending `prompt`/`login_hint` to the authorization URL
+/// and recording `remember` and `correlation_id` in the state cookie.
+///
+/// Answers `500` if the provider's authorization URL cannot be parsed to
+/// append them.
+#[cfg(feature = "flow")]
+pub fn initiate_oauth_login_with_params(
+    flow: &dyn ErasedOAuthFlow,
+    scopes: &[&str],
+    config: &SessionConfig,
+    success_url: Option<String>,
+    params: &authkestra_engine::AuthorizationParams,
 ) -> HttpResponse {
     let pkce = Pkce::new();
     let (url, mut auth_state) = flow.initiate_login(scopes, Some(&pkce.code_challenge));
+    let url = match params.apply(&url) {
+        Ok(url) => url,
+        Err(e) => {
+            e.log("failed to build the authorization URL");
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
 
     auth_state.code_verifier = Some(pkce.code_verifier);
     auth_state.success_url = success_url;
+    auth_state.remember = params.remember;
+    auth_state.correlation_id = params.correlation_id.clone();
+    auth_state.expires_at = chrono::Utc::now().timestamp() + config.flow_state_ttl.num_seconds();
 
     let encrypted = auth_state
         .encrypt(&config.state_encryption_key)
@@ -89,7 +161,9 @@ pub fn initiate_oauth_login_erased(
         .http_only(true)
         .same_site(actix_web::cookie::SameSite::Lax)
         .secure(true)
-        .max_age(actix_web::cookie::time::Duration::minutes(15))
+        .max_age(actix_web::cookie::time::Duration::seconds(
+            config.flow_state_ttl.num_seconds(),
+        ))
         .finish();
 
     HttpResponse::Found()
@@ -99,6 +173,8 @@ pub fn initiate_oauth_login_erased(
 }
 
 /// Helper to handle the OAuth2 callback and create a server-side session.
+///
+/// See [`handle_oauth_callback_erased`].
 #[cfg(all(feature = "flow", feature = "session"))]
 pub async fn handle_oauth_callback<P, M>(
     req: HttpRequest,
@@ -106,15 +182,20 @@ pub async fn handle_oauth_callback<P, M>(
     params: OAuthCallbackParams,
     store: Arc<dyn SessionStore>,
     config: SessionConfig,
+    consent: &dyn authkestra_engine::ConsentSink,
     success_url: &str,
 ) -> Result<HttpResponse, actix_web::Error>
 where
     P: authkestra_engine::OAuthProvider + Send + Sync + 'static,
     M: authkestra_engine::UserMapper + Send + Sync + 'static,
 {
-    handle_oauth_callback_erased(req, flow, params, store, config, success_url).await
+    handle_oauth_callback_erased(req, flow, params, store, config, consent, success_url).await
 }
 
+/// Helper to handle the OAuth2 callback and create a server-side session.
+///
+/// The scopes granted by the login are reported to `consent`; pass
+/// `engine.consent_sink.as_ref()`, or `&()` to discard them.
 #[cfg(all(feature = "flow", feature = "session"))]
 pub async fn handle_oauth_callback_erased(
     req: HttpRequest,
@@ -122,52 +203,97 @@ pub async fn handle_oauth_callback_erased(
     params: OAuthCallbackParams,
     store: Arc<dyn SessionStore>,
     config: SessionConfig,
+    consent: &dyn authkestra_engine::ConsentSink,
     _success_url: &str,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let cookie_name = "ak_state";
-    let encrypted_state = req
-        .cookie(cookie_name)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| {
-            actix_web::error::ErrorUnauthorized("CSRF validation failed or session expired")
-        })?;
+    let expected_state = decrypt_state_cookie(&req, &config)?;
+    complete_oauth_callback(
+        flow,
+        params,
+        expected_state,
+        store,
+        config,
+        consent,
+        &ErrorLevels::default(),
+    )
+    .await
+}
 
-    let expected_state = OAuth2State::decrypt(&encrypted_state, &config.state_encryption_key)
-        .map_err(|e| actix_web::error::ErrorUnauthorized(format!("Invalid state cookie: {e}")))?;
+/// Reads and decrypts the `ak_state` cookie set by the login leg.
+#[cfg(all(feature = "flow", feature = "session"))]
+fn decrypt_state_cookie(
+    req: &HttpRequest,
+    config: &SessionConfig,
+) -> Result<OAuth2State, actix_web::Error> {
+    let encrypted_state = req.cookie("ak_state").ok_or_else(|| {
+        actix_web::error::ErrorUnauthorized("CSRF validation failed or session expired")
+    })?;
+
+    OAuth2State::decrypt(encrypted_state.value(), &config.state_encryption_key)
+        .map_err(|e| actix_web::error::ErrorUnauthorized(format!("Invalid state cookie: {e}")))
+}
+
+/// Finalizes the login, reports the granted scopes to `consent` and creates the session.
+#[cfg(all(feature = "flow", feature = "session"))]
+async fn complete_oauth_callback(
+    flow: &dyn ErasedOAuthFlow,
+    params: OAuthCallbackParams,
+    expected_state: OAuth2State,
+    store: Arc<dyn SessionStore>,
+    config: SessionConfig,
+    consent: &dyn authkestra_engine::ConsentSink,
+    levels: &ErrorLevels,
+) -> Result<HttpResponse, actix_web::Error> {
+    let cookie_name = "ak_state";
 
     // Exchange code
     let (mut identity, token) = flow
         .finalize_login(&params.code, &params.state, &expected_state)
         .await
-        .map_err(|e| actix_web::error::ErrorUnauthorized(format!("Authentication failed: {e}")))?;
+        .map_err(|e| {
+            levels.log(&e, "OAuth callback failed");
+            actix_web::error::ErrorUnauthorized(format!("Authentication failed: {e}"))
+        })?;
+
+    consent
+        .record(authkestra_engine::ConsentRecord::from_login(
+            &identity,
+            &expected_state,
+            &token,
+        ))
+        .await;
 
     // Store tokens in identity attributes for convenience
+    if let Some(expires_at) = token.expires_at() {
+        identity
+            .attributes
+            .insert("expires_at".to_string(), expires_at.timestamp().to_string());
+    }
     identity
         .attributes
         .insert("access_token".to_string(), token.access_token);
 
-    if let Some(expires_in) = token.expires_in {
-        let expires_at = chrono::Utc::now().timestamp() + expires_in as i64;
-        identity
-            .attributes
-            .insert("expires_at".to_string(), expires_at.to_string());
-    }
     if let Some(rt) = token.refresh_token {
         identity.attributes.insert("refresh_token".to_string(), rt);
     }
 
-    let session_duration = config.max_age.unwrap_or(chrono::Duration::hours(24));
+    let config = if expected_state.remember {
+        config.remembered()
+    } else {
+        config
+    };
     let session = Session {
         id: uuid::Uuid::new_v4().to_string(),
         identity,
-        expires_at: chrono::Utc::now() + session_duration,
+        expires_at: chrono::Utc::now() + config.session_lifetime(),
+        created_at: chrono::Utc::now(),
     };
 
-    store.save_session(&session).await.map_err(|e| {
+    let session_id = store.create_session(&session).await.map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Failed to save session: {e}"))
     })?;
 
-    let cookie = create_actix_cookie(&config, session.id);
+    let cookie = create_actix_cookie(&config, session_id);
 
     // Remove the flow cookie
     let remove_cookie = Cookie::build(cookie_name, "")
@@ -176,45 +302,108 @@ pub async fn handle_oauth_callback_erased(
         .max_age(actix_web::cookie::time::Duration::ZERO)
         .finish();
 
-    let final_success_url = expected_state
-        .success_url
-        .unwrap_or_else(|| "/".to_string());
+    let final_success_url = config.safe_redirect(expected_state.success_url.as_deref());
 
-    Ok(HttpResponse::Found()
-        .insert_header((header::LOCATION, final_success_url))
-        .cookie(cookie)
-        .cookie(remove_cookie)
-        .finish())
+    let mut response = HttpResponse::Found();
+    response.insert_header((header::LOCATION, final_success_url));
+    add_session_cookie(&mut response, &config, cookie);
+    Ok(response.cookie(remove_cookie).finish())
 }
 
+/// Login route handler.
+///
+/// Runs in a span carrying the `X-Request-Id` of the request (generated if
+/// absent), which is echoed on the response and carried to the callback in the
+/// state cookie.
 #[cfg(feature = "flow")]
 pub async fn actix_login_handler<S, T>(
+    req: HttpRequest,
     path: web::Path<String>,
     authkestra: web::Data<Engine<S, T>>,
     params: web::Query<OAuthLoginParams>,
 ) -> impl actix_web::Responder {
     let provider = path.into_inner();
-    let flow = match authkestra.providers.get(&provider) {
+    let request_id = request_id(&req);
+    let span = tracing::info_span!("oauth_login", %provider, request_id = %request_id);
+    let response = span.in_scope(|| login(&provider, &authkestra, &params, request_id.clone()));
+    with_request_id(response, &request_id)
+}
+
+#[cfg(feature = "flow")]
+fn login<S, T>(
+    provider: &str,
+    authkestra: &Engine<S, T>,
+    params: &OAuthLoginParams,
+    request_id: String,
+) -> HttpResponse {
+    let flow = match authkestra.providers.get(provider) {
         Some(f) => f,
         None => {
-            return HttpResponse::NotFound().body(format!("Provider {provider} not found"));
+            tracing::warn!(%provider, "unknown provider requested");
+            return unknown_provider_response(authkestra, provider);
         }
     };
 
+    let mut authorization_params = match params.authorization_params() {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::warn!(error = %e, "rejected login request parameters");
+            return HttpResponse::BadRequest().body(e.to_string());
+        }
+    };
+    authorization_params.correlation_id = Some(request_id);
+
     let scopes_str = params.scope.clone().unwrap_or_default();
     let scopes: Vec<&str> = scopes_str
         .split(|c: char| [' ', ','].contains(&c))
         .filter(|s| !s.is_empty())
         .collect();
 
-    initiate_oauth_login_erased(
+    initiate_oauth_login_with_params(
         flow.as_ref(),
         &scopes,
         &authkestra.session_config,
         params.success_url.clone(),
+        &authorization_params,
     )
 }
 
+/// The correlation id of a request, from its `X-Request-Id` header or generated.
+#[cfg(feature = "flow")]
+fn request_id(req: &HttpRequest) -> String {
+    authkestra_engine::correlation_id(
+        req.headers()
+            .get(authkestra_engine::REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok()),
+    )
+}
+
+/// Echoes the correlation id on the response.
+#[cfg(feature = "flow")]
+fn with_request_id(mut response: HttpResponse, request_id: &str) -> HttpResponse {
+    if let Ok(value) = header::HeaderValue::from_str(request_id) {
+        response.headers_mut().insert(
+            header::HeaderName::from_static(authkestra_engine::REQUEST_ID_HEADER),
+            value,
+        );
+    }
+    response
+}
+
+#[cfg(feature = "flow")]
+fn unknown_provider_response<S, T>(authkestra: &Engine<S, T>, provider: &str) -> HttpResponse {
+    let (status, content_type, body) = authkestra.unknown_provider_response(provider);
+    let status = actix_web::http::StatusCode::from_u16(status)
+        .unwrap_or(actix_web::http::StatusCode::BAD_REQUEST);
+    HttpResponse::build(status)
+        .content_type(content_type)
+        .body(body)
+}
+
+/// Callback route handler.
+///
+/// Runs in a span carrying the correlation id of the login leg, read from the
+/// state cookie. Falls back to the request's own `X-Request-Id`.
 #[cfg(all(feature = "flow", feature = "session"))]
 pub async fn actix_callback_handler<S, T>(
     req: HttpRequest,
@@ -225,37 +414,128 @@ pub async fn actix_callback_handler<S, T>(
 where
     S: authkestra_engine::SessionStoreState,
 {
+    use tracing::Instrument;
     let provider = path.into_inner();
-    let flow = match authkestra.providers.get(&provider) {
+    let expected_state = decrypt_state_cookie(&req, &authkestra.session_config);
+    let request_id = expected_state
+        .as_ref()
+        .ok()
+        .and_then(|state| state.correlation_id.clone())
+        .unwrap_or_else(|| request_id(&req));
+    let span = tracing::info_span!("oauth_callback", %provider, request_id = %request_id);
+    let response = callback(&provider, &authkestra, params.into_inner(), expected_state)
+        .instrument(span)
+        .await
+        .unwrap_or_else(|e| e.error_response());
+    Ok(with_request_id(response, &request_id))
+}
+
+#[cfg(all(feature = "flow", feature = "session"))]
+async fn callback<S, T>(
+    provider: &str,
+    authkestra: &Engine<S, T>,
+    callback_params: OAuthCallbackParams,
+    expected_state: actix_web::Result<OAuth2State>,
+) -> actix_web::Result<HttpResponse>
+where
+    S: authkestra_engine::SessionStoreState,
+{
+    let flow = match authkestra.providers.get(provider) {
         Some(f) => f,
         None => {
-            return Ok(HttpResponse::NotFound().body(format!("Provider {provider} not found")));
+            tracing::warn!(%provider, "unknown provider requested");
+            return Ok(unknown_provider_response(authkestra, provider));
         }
     };
 
-    let callback_params = params.into_inner();
-
-    let response = handle_oauth_callback_erased(
-        req,
+    complete_oauth_callback(
         flow.as_ref(),
         callback_params,
+        expected_state?,
         authkestra.session_store.get_store(),
         authkestra.session_config.clone(),
-        "",
+        authkestra.consent_sink.as_ref(),
+        &authkestra.error_levels,
     )
-    .await?;
+    .await
+}
+
+/// The request header carrying the CSRF token of scripted requests.
+#[cfg(feature = "session")]
+pub const CSRF_HEADER: &str = "x-csrf-token";
 
-    Ok(response)
+/// Verify the CSRF `token` submitted with a form against the session cookie.
+///
+/// Issue the token with `session_config.csrf().issue(&session.id)` and render it
+/// into the form; pass the submitted field here. Fails with `403 Forbidden`
+/// when the request has no session cookie or the token is missing or was not
+/// issued for that session. See [`crate::ValidCsrf`] for requests sending the
+/// token in the [`CSRF_HEADER`] header.
+#[cfg(feature = "session")]
+pub fn verify_csrf(
+    req: &HttpRequest,
+    session_config: &SessionConfig,
+    token: Option<&str>,
+) -> actix_web::Result<()> {
+    let csrf = session_config.csrf();
+    let valid = token.is_some_and(|token| {
+        session_config
+            .lookup_cookie_names()
+            .filter_map(|name| req.cookie(name))
+            .any(|cookie| csrf.verify(cookie.value(), token))
+    });
+    if valid {
+        Ok(())
+    } else {
+        tracing::warn!("rejected request without a valid CSRF token");
+        Err(actix_web::error::ErrorForbidden(
+            "Missing or invalid CSRF token",
+        ))
+    }
 }
 
+/// Form fields of the logout route.
+#[derive(serde::Deserialize)]
+pub struct LogoutParams {
+    /// The CSRF token from [`SessionConfig::logout_token`].
+    pub logout_token: Option<String>,
+}
+
+/// Logout route handler.
+///
+/// Expects a `POST` whose form carries the `logout_token` of the current
+/// session (see [`SessionConfig::logout_token`]), so a cross-site request
+/// cannot log users out. Requests without a session cookie are let through,
+/// as there is nothing to log out. The `unprotected-logout` feature skips the
+/// check and also accepts `GET`.
 #[cfg(all(feature = "flow", feature = "session"))]
 pub async fn actix_logout_handler<S, T>(
     req: HttpRequest,
     authkestra: web::Data<Engine<S, T>>,
+    form: Option<web::Form<LogoutParams>>,
 ) -> actix_web::Result<impl actix_web::Responder>
 where
     S: authkestra_engine::SessionStoreState,
 {
+    #[cfg(not(feature = "unprotected-logout"))]
+    {
+        let config = &authkestra.session_config;
+        let token = form.and_then(|form| form.into_inner().logout_token);
+        let session_id = config
+            .lookup_cookie_names()
+            .find_map(|name| req.cookie(name).map(|c| c.value().to_string()));
+        if let Some(session_id) = session_id {
+            if !token.is_some_and(|token| config.verify_logout_token(&session_id, &token)) {
+                tracing::warn!("rejected logout without a valid logout token");
+                return Err(actix_web::error::ErrorForbidden(
+                    "Missing or invalid logout token",
+                ));
+            }
+        }
+    }
+    #[cfg(feature = "unprotected-logout")]
+    let _ = form;
+
     logout(
         req,
         authkestra.session_store.get_store(),
@@ -266,6 +546,8 @@ where
 }
 
 /// Helper to handle logout by deleting the session from the store and clearing the cookie.
+///
+/// Sessions found under fallback cookie names are deleted and their cookies cleared too.
 #[cfg(feature = "session")]
 pub async fn logout(
     req: HttpRequest,
@@ -273,26 +555,95 @@ pub async fn logout(
     config: SessionConfig,
     redirect_to: &str,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let session_id = req
-        .cookie(&config.cookie_name)
-        .map(|c| c.value().to_string());
-
-    if let Some(id) = session_id {
-        store
-            .delete_session(&id)
-            .await
-            .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+    let mut response = HttpResponse::Found();
+    response.insert_header((header::LOCATION, redirect_to));
+
+    for name in config.lookup_cookie_names() {
+        let session_id = req.cookie(name).map(|c| c.value().to_string());
+        if let Some(id) = &session_id {
+            store
+                .delete_session(id)
+                .await
+                .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+        } else if name != config.cookie_name {
+            continue;
+        }
+
+        let mut remove_cookie = create_actix_cookie(&config, "".to_string());
+        remove_cookie.set_name(name.to_string());
+        add_session_cookie(&mut response, &config, remove_cookie);
     }
 
-    let remove_cookie = create_actix_cookie(&config, "".to_string());
+    Ok(response.finish())
+}
+
+/// Mint a JWT for the identity of `session`, e.g. for an SPA calling an API
+/// directly while the web UI uses the session.
+///
+/// Responds with the same JSON as [`handle_oauth_callback_jwt`]:
+/// `access_token`, `token_type` and `expires_in`. Every token issued is
+/// reported as a security event: an `INFO` event with target
+/// `authkestra::security` and `event = "session_token_issued"`. Keep
+/// `expires_in_secs` short, since the token outlives a logout of the session.
+#[cfg(all(feature = "session", feature = "token"))]
+pub fn issue_session_token(
+    session: &Session,
+    token_manager: &authkestra_engine::TokenManager,
+    expires_in_secs: u64,
+) -> Result<HttpResponse, actix_web::Error> {
+    let jwt = token_manager
+        .issue_user_token(session.identity.clone(), expires_in_secs, None, None)
+        .map_err(|e| {
+            e.log("failed to issue session token");
+            actix_web::error::ErrorInternalServerError(e.to_string())
+        })?;
 
-    Ok(HttpResponse::Found()
-        .insert_header((header::LOCATION, redirect_to))
-        .cookie(remove_cookie)
-        .finish())
+    tracing::info!(
+        target: "authkestra::security",
+        event = "session_token_issued",
+        session_id = %session.id,
+        user_id = %session.identity.external_id,
+        expires_in = expires_in_secs,
+        "issued a token for a session"
+    );
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "access_token": jwt,
+        "token_type": "Bearer",
+        "expires_in": expires_in_secs
+    })))
+}
+
+/// A resource with `POST /auth/token`, which exchanges the session of the
+/// request for a JWT valid for `expires_in_secs` (see [`issue_session_token`]).
+///
+/// The session store, session config and token manager are read like the
+/// extractors read them, e.g. from an [`AuthkestraState`](crate::AuthkestraState).
+/// Requests without a valid session are rejected with `401`. Register it before
+/// `actix_scope`, whose `/auth` scope would otherwise answer the request:
+///
+/// ```rust,ignore
+/// App::new()
+///     .app_data(web::Data::new(AuthkestraState::from(&engine)))
+///     .service(helpers::session_token_resource(300))
+///     .service(engine.actix_scope())
+/// ```
+#[cfg(all(feature = "flow", feature = "token"))]
+pub fn session_token_resource(expires_in_secs: u64) -> actix_web::Resource {
+    web::resource("/auth/token").route(web::post().to(
+        move |req: HttpRequest, crate::AuthSession(session): crate::AuthSession| async move {
+            let token_manager = crate::state::token_manager(&req).ok_or_else(|| {
+                tracing::error!("TokenManager not configured in actix app data");
+                actix_web::error::ErrorInternalServerError("TokenManager not configured")
+            })?;
+            issue_session_token(&session, &token_manager, expires_in_secs)
+        },
+    ))
 }
 
 /// Helper to handle the OAuth2 callback and return a JWT for stateless auth.
+///
+/// The scopes granted by the login are reported to `consent`; pass
+/// `engine.consent_sink.as_ref()`, or `&()` to discard them.
 #[cfg(all(feature = "flow", feature = "token"))]
 pub async fn handle_oauth_callback_jwt_erased(
     flow: &dyn ErasedOAuthFlow,
@@ -301,6 +652,7 @@ pub async fn handle_oauth_callback_jwt_erased(
     token_manager: Arc<authkestra_engine::TokenManager>,
     expires_in_secs: u64,
     config: SessionConfig,
+    consent: &dyn authkestra_engine::ConsentSink,
 ) -> Result<HttpResponse, actix_web::Error> {
     let cookie_name = "ak_state";
 
@@ -315,10 +667,21 @@ pub async fn handle_oauth_callback_jwt_erased(
         .map_err(|e| actix_web::error::ErrorUnauthorized(format!("Invalid state cookie: {e}")))?;
 
     // Exchange code
-    let (identity, _token) = flow
+    let (identity, token) = flow
         .finalize_login(&params.code, &params.state, &expected_state)
         .await
-        .map_err(|e| actix_web::error::ErrorUnauthorized(format!("Authentication failed: {e}")))?;
+        .map_err(|e| {
+            e.log("OAuth callback failed");
+            actix_web::error::ErrorUnauthorized(format!("Authentication failed: {e}"))
+        })?;
+
+    consent
+        .record(authkestra_engine::ConsentRecord::from_login(
+            &identity,
+            &expected_state,
+            &token,
+        ))
+        .await;
 
     let jwt = token_manager
         .issue_user_token(identity, expires_in_secs, None, None)
@@ -343,6 +706,8 @@ pub async fn handle_oauth_callback_jwt_erased(
 }
 
 /// Helper to handle the OAuth2 callback and return a JWT for stateless auth.
+///
+/// See [`handle_oauth_callback_jwt_erased`].
 #[cfg(all(feature = "flow", feature = "token"))]
 pub async fn handle_oauth_callback_jwt<P, M>(
     flow: &OAuth2Flow<P, M>,
@@ -351,11 +716,20 @@ pub async fn handle_oauth_callback_jwt<P, M>(
     token_manager: Arc<authkestra_engine::TokenManager>,
     expires_in_secs: u64,
     config: SessionConfig,
+    consent: &dyn authkestra_engine::ConsentSink,
 ) -> Result<HttpResponse, actix_web::Error>
 where
     P: authkestra_engine::OAuthProvider + Send + Sync + 'static,
     M: authkestra_engine::UserMapper + Send + Sync + 'static,
 {
-    handle_oauth_callback_jwt_erased(flow, req, params, token_manager, expires_in_secs, config)
-        .await
+    handle_oauth_callback_jwt_erased(
+        flow,
+        req,
+        params,
+        token_manager,
+        expires_in_secs,
+        config,
+        consent,
+    )
+    .await
 }
@@ -1,5 +1,7 @@
+#[cfg(any(feature = "session", feature = "resource"))]
+use actix_web::web;
 #[cfg(any(feature = "session", feature = "token", feature = "resource"))]
-use actix_web::{dev::Payload, http::header, web, Error, FromRequest, HttpRequest};
+use actix_web::{dev::Payload, http::header, Error, FromRequest, HttpRequest};
 #[cfg(feature = "session")]
 pub use authkestra_engine::auth::{Session, SessionStore};
 #[cfg(all(feature = "flow", any(feature = "session", feature = "token")))]
@@ -10,15 +12,27 @@ pub use authkestra_engine::SessionStoreState;
 pub use authkestra_engine::TokenManager;
 #[cfg(all(feature = "flow", feature = "token"))]
 pub use authkestra_engine::TokenManagerState;
+#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
+pub use authkestra_engine::TrustedProxies;
 #[cfg(feature = "flow")]
 pub use authkestra_engine::{Engine, SessionConfig};
+#[cfg(feature = "resource")]
+pub use authkestra_resource::{
+    AuthorizationEngine, Decision, Guard, LocalRules, PathTenant, Policy, RequireScopes,
+    ScopeRequirement, TenantBinding, TenantSource,
+};
 #[cfg(any(feature = "session", feature = "token", feature = "resource"))]
 use futures::future::LocalBoxFuture;
-#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
+#[cfg(feature = "resource")]
 use std::sync::Arc;
 
 pub mod helpers;
 
+#[cfg(feature = "flow")]
+pub mod state;
+#[cfg(feature = "flow")]
+pub use state::AuthkestraState;
+
 #[cfg(feature = "op")]
 pub mod op;
 
@@ -46,7 +60,11 @@ where
     T: Clone + 'static,
 {
     fn actix_scope(&self) -> actix_web::Scope {
-        let mut scope = web::scope("/auth");
+        // The handlers read the engine from the scope, so the app does not
+        // have to register it.
+        let mut scope = web::scope("/auth")
+            .app_data(web::Data::new(self.clone()))
+            .app_data(web::Data::new(self.error_levels.clone()));
 
         scope = scope.route(
             "/login/{provider}",
@@ -56,7 +74,11 @@ where
             "/callback/{provider}",
             web::get().to(actix_callback_handler::<S, T>),
         );
-        scope = scope.route("/logout", web::get().to(actix_logout_handler::<S, T>));
+        scope = scope.route("/logout", web::post().to(actix_logout_handler::<S, T>));
+        #[cfg(feature = "unprotected-logout")]
+        {
+            scope = scope.route("/logout", web::get().to(actix_logout_handler::<S, T>));
+        }
 
         scope
     }
@@ -72,18 +94,16 @@ impl FromRequest for AuthSession {
     type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
-        let store = req.app_data::<web::Data<Arc<dyn SessionStore>>>().cloned();
-
-        let config = req.app_data::<web::Data<SessionConfig>>().cloned();
+        let error_levels = state::error_levels(req);
+        let store = state::session_store(req);
+        let config = state::session_config(req);
 
-        let session_id = req
-            .cookie(
-                config
-                    .as_ref()
-                    .map(|c| c.cookie_name.as_str())
-                    .unwrap_or("authkestra_session"),
-            )
-            .map(|c| c.value().to_string());
+        let session_ids: Vec<String> = config
+            .iter()
+            .flat_map(|config| config.lookup_cookie_names())
+            .filter_map(|name| req.cookie(name))
+            .map(|c| c.value().to_string())
+            .collect();
 
         Box::pin(async move {
             tracing::debug!("extracting AuthSession from actix request");
@@ -91,28 +111,33 @@ impl FromRequest for AuthSession {
                 tracing::error!("SessionStore not configured in actix app data");
                 actix_web::error::ErrorInternalServerError("SessionStore not configured")
             })?;
-            let _config = config.ok_or_else(|| {
+            let config = config.ok_or_else(|| {
                 tracing::error!("SessionConfig not configured in actix app data");
                 actix_web::error::ErrorInternalServerError("SessionConfig not configured")
             })?;
 
-            let session_id = session_id.ok_or_else(|| {
+            if session_ids.is_empty() {
                 tracing::warn!("missing session cookie in request");
-                actix_web::error::ErrorUnauthorized("Missing session cookie")
-            })?;
+                return Err(actix_web::error::ErrorUnauthorized(
+                    "Missing session cookie",
+                ));
+            }
 
-            let session = store
-                .get_ref()
-                .load_session(&session_id)
-                .await
-                .map_err(|e| {
-                    tracing::error!(error = %e, "failed to load session from store");
+            let mut found = None;
+            for session_id in &session_ids {
+                let session = store.load_session(session_id).await.map_err(|e| {
+                    error_levels.log(&e, "failed to load session from store");
                     actix_web::error::ErrorInternalServerError(e.to_string())
-                })?
-                .ok_or_else(|| {
-                    tracing::warn!("session not found or invalid");
-                    actix_web::error::ErrorUnauthorized("Invalid session")
                 })?;
+                if let Some(session) = session.filter(|session| config.is_active(session)) {
+                    found = Some(session);
+                    break;
+                }
+            }
+            let session = found.ok_or_else(|| {
+                tracing::warn!("session not found, invalid or expired");
+                actix_web::error::ErrorUnauthorized("Invalid session")
+            })?;
 
             tracing::info!(session_id = %session.id, user_id = %session.identity.external_id, "successfully extracted actix AuthSession");
             Ok(AuthSession(session))
@@ -120,6 +145,40 @@ impl FromRequest for AuthSession {
     }
 }
 
+/// Guards a handler against cross-site requests.
+///
+/// Requires the [`helpers::CSRF_HEADER`] header to carry the token issued with
+/// `session_config.csrf().issue(&session.id)` for the session cookie of the
+/// request, and rejects the request with `403 Forbidden` otherwise. Forms that
+/// post the token as a field can call [`helpers::verify_csrf`] instead.
+#[cfg(feature = "session")]
+pub struct ValidCsrf;
+
+#[cfg(all(feature = "flow", feature = "session"))]
+impl FromRequest for ValidCsrf {
+    type Error = Error;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let result = match state::session_config(req) {
+            Some(config) => {
+                let token = req
+                    .headers()
+                    .get(helpers::CSRF_HEADER)
+                    .and_then(|h| h.to_str().ok());
+                helpers::verify_csrf(req, &config, token).map(|()| ValidCsrf)
+            }
+            None => {
+                tracing::error!("SessionConfig not configured in actix app data");
+                Err(actix_web::error::ErrorInternalServerError(
+                    "SessionConfig not configured",
+                ))
+            }
+        };
+        std::future::ready(result)
+    }
+}
+
 /// The extractor for a validated JWT.
 ///
 /// Expects an `Authorization: Bearer <token>` header.
@@ -132,7 +191,8 @@ impl FromRequest for AuthToken {
     type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
-        let token_manager = req.app_data::<web::Data<Arc<TokenManager>>>().cloned();
+        let error_levels = state::error_levels(req);
+        let token_manager = state::token_manager(req);
 
         let auth_header = req
             .headers()
@@ -159,13 +219,10 @@ impl FromRequest for AuthToken {
             }
 
             let token = &auth_header[7..];
-            let claims = token_manager
-                .get_ref()
-                .validate_token(token, None)
-                .map_err(|e| {
-                    tracing::error!(error = %e, "failed to validate token");
-                    actix_web::error::ErrorUnauthorized(format!("Invalid token: {e}"))
-                })?;
+            let claims = token_manager.validate_token(token, None).map_err(|e| {
+                error_levels.log(&e, "failed to validate token");
+                actix_web::error::ErrorUnauthorized(format!("Invalid token: {e}"))
+            })?;
 
             tracing::info!("successfully extracted and validated actix AuthToken");
             Ok(AuthToken(claims))
@@ -173,6 +230,128 @@ impl FromRequest for AuthToken {
     }
 }
 
+/// The extractor for a step-up token of at least assurance level `LEVEL`,
+/// issued with [`TokenManager::issue_stepup_token`](authkestra_engine::TokenManager::issue_stepup_token).
+///
+/// Expects an `Authorization: Bearer <token>` header. A missing or forged
+/// token is rejected with `401 Unauthorized`; a genuine token below `LEVEL`
+/// or expired is rejected with `403 Forbidden` and a `step-up required`
+/// body, so the client can send the user through step-up again.
+#[cfg(feature = "token")]
+pub struct StepUp<const LEVEL: u32>(pub authkestra_engine::Claims);
+
+#[cfg(all(feature = "flow", feature = "token"))]
+impl<const LEVEL: u32> FromRequest for StepUp<LEVEL> {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let error_levels = state::error_levels(req);
+        let token_manager = state::token_manager(req);
+
+        let auth_header = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .map(|s| s.to_string());
+
+        Box::pin(async move {
+            let token_manager = token_manager.ok_or_else(|| {
+                tracing::error!("Token manager not configured in actix app data");
+                actix_web::error::ErrorInternalServerError("Token manager not configured")
+            })?;
+            let token = auth_header
+                .as_deref()
+                .and_then(|h| h.strip_prefix("Bearer "))
+                .ok_or_else(|| {
+                    tracing::warn!("missing bearer token in actix request");
+                    actix_web::error::ErrorUnauthorized("Missing Authorization header")
+                })?;
+
+            match token_manager.validate_stepup_token(token, LEVEL) {
+                Ok(claims) => Ok(StepUp(claims)),
+                Err(authkestra_engine::AuthError::StepUpRequired) => {
+                    tracing::info!(level = LEVEL, "step-up required");
+                    Err(actix_web::error::ErrorForbidden("step-up required"))
+                }
+                Err(e) => {
+                    error_levels.log(&e, "invalid step-up token");
+                    Err(actix_web::error::ErrorUnauthorized(format!(
+                        "Invalid token: {e}"
+                    )))
+                }
+            }
+        })
+    }
+}
+
+/// A session and a JWT that must belong to the same user.
+///
+/// When the request carries both a session cookie and an `Authorization`
+/// header, both are validated as by [`AuthSession`] and [`AuthToken`] and the
+/// binding check `B` must pass; a mismatch is rejected with
+/// `401 Unauthorized` and reported as a security event (see
+/// [`check_token_binding`](authkestra_engine::auth::check_token_binding)).
+/// When only one is present, this behaves as the corresponding extractor and
+/// the other field is `None`.
+#[cfg(all(feature = "session", feature = "token"))]
+pub struct BoundAuth<B = authkestra_engine::auth::SameSubject> {
+    pub session: Option<Session>,
+    pub claims: Option<authkestra_engine::Claims>,
+    binding: std::marker::PhantomData<B>,
+}
+
+#[cfg(all(feature = "flow", feature = "session", feature = "token"))]
+impl<B: authkestra_engine::auth::TokenBinding> FromRequest for BoundAuth<B> {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let has_session = state::session_config(req).is_some_and(|config| {
+            config
+                .lookup_cookie_names()
+                .any(|name| req.cookie(name).is_some())
+        });
+        let has_token = req.headers().contains_key(header::AUTHORIZATION);
+
+        // Without either, the session extractor reports the missing cookie.
+        let session = (has_session || !has_token).then(|| AuthSession::from_request(req, payload));
+        let claims = has_token.then(|| AuthToken::from_request(req, payload));
+        let token_manager = state::token_manager(req);
+
+        Box::pin(async move {
+            let session = match session {
+                Some(session) => Some(session.await?.0),
+                None => None,
+            };
+            let claims = match claims {
+                Some(claims) => Some(claims.await?.0),
+                None => None,
+            };
+
+            if let (Some(session), Some(claims), Some(token_manager)) =
+                (&session, &claims, &token_manager)
+            {
+                if !authkestra_engine::auth::check_token_binding::<B>(
+                    session,
+                    claims,
+                    token_manager,
+                ) {
+                    return Err(actix_web::error::ErrorUnauthorized(
+                        "Token does not belong to the session",
+                    ));
+                }
+            }
+
+            Ok(BoundAuth {
+                session,
+                claims,
+                binding: std::marker::PhantomData,
+            })
+        })
+    }
+}
+
 /// A generic JWT extractor for resource server validation.
 ///
 /// Validates a Bearer token against a configured `JwksCache` and `jsonwebtoken::Validation`.
@@ -246,6 +425,18 @@ where
 #[cfg(feature = "resource")]
 pub struct Auth<I>(pub I);
 
+/// The error levels of the resource extractors: the `web::Data<ErrorLevels>`
+/// or [`AuthkestraState`](state::AuthkestraState) of the app, or the defaults.
+#[cfg(feature = "resource")]
+fn resource_error_levels(req: &HttpRequest) -> authkestra_engine::ErrorLevels {
+    #[cfg(feature = "flow")]
+    return state::error_levels(req);
+    #[cfg(not(feature = "flow"))]
+    req.app_data::<web::Data<authkestra_engine::ErrorLevels>>()
+        .map(|levels| levels.get_ref().clone())
+        .unwrap_or_default()
+}
+
 #[cfg(feature = "resource")]
 impl<I> FromRequest for Auth<I>
 where
@@ -255,6 +446,7 @@ where
     type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let error_levels = resource_error_levels(req);
         let guard = req
             .app_data::<web::Data<Arc<authkestra_resource::Guard<I>>>>()
             .cloned();
@@ -296,10 +488,197 @@ where
                     Err(actix_web::error::ErrorUnauthorized("Authentication failed"))
                 }
                 Err(e) => {
-                    tracing::error!(error = %e, "internal error during authentication");
+                    error_levels.log(&e, "internal error during authentication");
+                    Err(actix_web::error::ErrorInternalServerError(e.to_string()))
+                }
+            }
+        })
+    }
+}
+
+/// An extractor that authenticates with the `Guard` like [`Auth<I>`], then
+/// authorizes the identity with the [`Policy`](authkestra_resource::Policy) `P`.
+///
+/// Unauthenticated requests are rejected with `401`; authenticated requests
+/// the policy denies are rejected with `403` and a JSON body
+/// `{"error": "forbidden", "message": "<reason>"}`.
+#[cfg(feature = "resource")]
+pub struct Authz<I, P>(pub I, pub std::marker::PhantomData<P>);
+
+#[cfg(feature = "resource")]
+impl<I, P> FromRequest for Authz<I, P>
+where
+    I: Send + Sync + 'static,
+    P: authkestra_resource::Policy<I>,
+{
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let auth = Auth::<I>::from_request(req, payload);
+
+        Box::pin(async move {
+            let Auth(identity) = auth.await?;
+            match P::evaluate(&identity) {
+                authkestra_resource::Decision::Allow => {
+                    Ok(Authz(identity, std::marker::PhantomData))
+                }
+                authkestra_resource::Decision::Deny(reason) => {
+                    tracing::warn!(%reason, "authorization denied by policy");
+                    let response = actix_web::HttpResponse::Forbidden()
+                        .json(serde_json::json!({ "error": "forbidden", "message": reason }));
+                    Err(actix_web::error::InternalError::from_response(reason, response).into())
+                }
+            }
+        })
+    }
+}
+
+/// An extractor that authenticates with the `Guard` like [`Auth<I>`], then
+/// asks the [`AuthorizationEngine`](authkestra_resource::AuthorizationEngine)
+/// in the app data, as `web::Data<Arc<dyn AuthorizationEngine<I>>>`, whether
+/// the identity may perform the request's method on its path.
+///
+/// Unauthenticated requests are rejected with `401`, denied requests with
+/// `403` and a JSON body `{"error": "forbidden", "message": "<reason>"}`, and
+/// engine errors with `500`.
+#[cfg(feature = "resource")]
+pub struct Authorized<I>(pub I);
+
+#[cfg(feature = "resource")]
+impl<I> FromRequest for Authorized<I>
+where
+    I: Send + Sync + 'static,
+{
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let error_levels = resource_error_levels(req);
+        let auth = Auth::<I>::from_request(req, payload);
+        let engine = req
+            .app_data::<web::Data<Arc<dyn authkestra_resource::AuthorizationEngine<I>>>>()
+            .cloned();
+        let action = req.method().as_str().to_string();
+        let resource = req.path().to_string();
+
+        Box::pin(async move {
+            let Auth(identity) = auth.await?;
+            let engine = engine.ok_or_else(|| {
+                tracing::error!("AuthorizationEngine not configured in actix app data");
+                actix_web::error::ErrorInternalServerError("AuthorizationEngine not configured")
+            })?;
+            match engine.authorize(&identity, &action, &resource).await {
+                Ok(authkestra_resource::Decision::Allow) => Ok(Authorized(identity)),
+                Ok(authkestra_resource::Decision::Deny(reason)) => {
+                    tracing::warn!(%reason, %action, %resource, "authorization denied by engine");
+                    let response = actix_web::HttpResponse::Forbidden()
+                        .json(serde_json::json!({ "error": "forbidden", "message": reason }));
+                    Err(actix_web::error::InternalError::from_response(reason, response).into())
+                }
+                Err(e) => {
+                    error_levels.log(&e, "authorization engine failed");
                     Err(actix_web::error::ErrorInternalServerError(e.to_string()))
                 }
             }
         })
     }
 }
+
+/// An extractor that authenticates with the `Guard` like [`Auth<I>`], then
+/// checks that the identity belongs to the tenant the request addresses.
+///
+/// The [`TenantBinding`](authkestra_resource::TenantBinding) `B` names the
+/// identity's tenant claim and the path parameter or header naming the
+/// request's tenant (`tenant_id` for both by default). Unauthenticated
+/// requests are rejected with `401`, requests naming no tenant with `400`,
+/// and requests for another tenant with `403` and a JSON body
+/// `{"error": "forbidden", "message": "..."}`.
+#[cfg(feature = "resource")]
+pub struct TenantAuth<I, B = authkestra_resource::PathTenant>(
+    pub I,
+    pub std::marker::PhantomData<B>,
+);
+
+#[cfg(feature = "resource")]
+impl<I, B> FromRequest for TenantAuth<I, B>
+where
+    I: serde::Serialize + Send + Sync + 'static,
+    B: authkestra_resource::TenantBinding,
+{
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let tenant = match B::SOURCE {
+            authkestra_resource::TenantSource::Path(name) => req.match_info().get(name),
+            authkestra_resource::TenantSource::Header(name) => req
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok()),
+        }
+        .filter(|tenant| !tenant.is_empty())
+        .map(str::to_owned);
+        let auth = Auth::<I>::from_request(req, payload);
+
+        Box::pin(async move {
+            let Auth(identity) = auth.await?;
+            let tenant = tenant.ok_or_else(|| {
+                tracing::warn!(source = ?B::SOURCE, "request does not name a tenant");
+                actix_web::error::ErrorBadRequest("Missing tenant id")
+            })?;
+            if !authkestra_resource::check_tenant::<B>(&identity, &tenant) {
+                let message = "Identity does not belong to this tenant";
+                let response = actix_web::HttpResponse::Forbidden()
+                    .json(serde_json::json!({ "error": "forbidden", "message": message }));
+                return Err(
+                    actix_web::error::InternalError::from_response(message, response).into(),
+                );
+            }
+            Ok(TenantAuth(identity, std::marker::PhantomData))
+        })
+    }
+}
+
+/// The extractor for the client IP address.
+///
+/// Forwarding headers are only honoured when the socket peer is one of the
+/// `TrustedProxies` registered as `web::Data<TrustedProxies>`. Without it, no
+/// proxy is trusted and the socket address is used.
+#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientIp(pub std::net::IpAddr);
+
+#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
+impl actix_web::FromRequest for ClientIp {
+    type Error = actix_web::Error;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let Some(peer) = req.peer_addr().map(|addr| addr.ip()) else {
+            tracing::error!("peer address not available on actix request");
+            return std::future::ready(Err(actix_web::error::ErrorInternalServerError(
+                "Peer address not available",
+            )));
+        };
+
+        let values = |name: &str| -> Vec<&str> {
+            req.headers()
+                .get_all(name)
+                .filter_map(|v| v.to_str().ok())
+                .collect()
+        };
+        let forwarded = values("forwarded");
+        let x_forwarded_for = values("x-forwarded-for");
+
+        let ip = match req.app_data::<actix_web::web::Data<TrustedProxies>>() {
+            Some(trusted) => trusted.resolve(peer, &forwarded, &x_forwarded_for),
+            None => peer,
+        };
+        tracing::debug!(%peer, client_ip = %ip, "resolved client IP");
+        std::future::ready(Ok(ClientIp(ip)))
+    }
+}
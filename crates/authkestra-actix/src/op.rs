@@ -15,7 +15,7 @@ use std::sync::Arc;
 
 pub async fn actix_jwks_handler(token_manager: web::Data<Arc<TokenManager>>) -> impl Responder {
     tracing::debug!("Handling JWKS request (actix)");
-    let resp = JwksResponse::new(token_manager.public_jwk());
+    let resp = JwksResponse::from_keys(token_manager.jwks());
     HttpResponse::Ok().json(resp)
 }
 
@@ -0,0 +1,126 @@
+//! A single piece of app data for the actix extractors.
+
+#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
+use actix_web::{web, HttpRequest};
+use authkestra_engine::{
+    auth::{ErrorLevels, SessionStore},
+    Configured, Engine, Missing, SessionConfig, TokenManager,
+};
+use std::sync::Arc;
+
+/// The components of an [`Engine`] read by the extractors, registered once as
+/// `web::Data<AuthkestraState>`.
+///
+/// actix looks app data up by type, so without it `AuthSession`, `ValidCsrf`
+/// and `AuthToken` each need their component registered separately
+/// (`web::Data<Arc<dyn SessionStore>>`, `web::Data<SessionConfig>`,
+/// `web::Data<Arc<TokenManager>>`). With it, one registration covers them all,
+/// like `FromRef` does for axum:
+///
+/// ```rust,ignore
+/// App::new()
+///     .app_data(web::Data::new(AuthkestraState::from(&engine)))
+///     .service(engine.actix_scope())
+/// ```
+///
+/// Separately registered components take precedence over this state.
+#[derive(Clone)]
+pub struct AuthkestraState {
+    session_store: Option<Arc<dyn SessionStore>>,
+    session_config: SessionConfig,
+    token_manager: Option<Arc<TokenManager>>,
+    error_levels: ErrorLevels,
+}
+
+impl AuthkestraState {
+    /// The session store, if the engine has one.
+    pub fn session_store(&self) -> Option<Arc<dyn SessionStore>> {
+        self.session_store.clone()
+    }
+
+    /// The session cookie configuration.
+    pub fn session_config(&self) -> &SessionConfig {
+        &self.session_config
+    }
+
+    /// The token manager, if the engine has one.
+    pub fn token_manager(&self) -> Option<Arc<TokenManager>> {
+        self.token_manager.clone()
+    }
+
+    /// The levels errors are logged at.
+    pub fn error_levels(&self) -> &ErrorLevels {
+        &self.error_levels
+    }
+}
+
+impl<S: EngineComponent<Arc<dyn SessionStore>>, T: EngineComponent<Arc<TokenManager>>>
+    From<&Engine<S, T>> for AuthkestraState
+{
+    fn from(engine: &Engine<S, T>) -> Self {
+        Self {
+            session_store: engine.session_store.component(),
+            session_config: engine.session_config.clone(),
+            token_manager: engine.token_manager.component(),
+            error_levels: engine.error_levels.clone(),
+        }
+    }
+}
+
+/// A typestate slot of an [`Engine`]: [`Missing`] or [`Configured`].
+pub trait EngineComponent<C> {
+    /// The configured component, if any.
+    fn component(&self) -> Option<C>;
+}
+
+impl<C> EngineComponent<C> for Missing {
+    fn component(&self) -> Option<C> {
+        None
+    }
+}
+
+impl<C: Clone> EngineComponent<C> for Configured<C> {
+    fn component(&self) -> Option<C> {
+        Some(self.0.clone())
+    }
+}
+
+/// The session store registered on its own or through [`AuthkestraState`].
+#[cfg(feature = "session")]
+pub(crate) fn session_store(req: &HttpRequest) -> Option<Arc<dyn SessionStore>> {
+    req.app_data::<web::Data<Arc<dyn SessionStore>>>()
+        .map(|store| store.get_ref().clone())
+        .or_else(|| state(req).and_then(AuthkestraState::session_store))
+}
+
+/// The session config registered on its own or through [`AuthkestraState`].
+#[cfg(feature = "session")]
+pub(crate) fn session_config(req: &HttpRequest) -> Option<SessionConfig> {
+    req.app_data::<web::Data<SessionConfig>>()
+        .map(|config| config.get_ref().clone())
+        .or_else(|| state(req).map(|state| state.session_config.clone()))
+}
+
+/// The token manager registered on its own or through [`AuthkestraState`].
+#[cfg(feature = "token")]
+pub(crate) fn token_manager(req: &HttpRequest) -> Option<Arc<TokenManager>> {
+    req.app_data::<web::Data<Arc<TokenManager>>>()
+        .map(|manager| manager.get_ref().clone())
+        .or_else(|| state(req).and_then(AuthkestraState::token_manager))
+}
+
+/// The error levels registered on their own or through [`AuthkestraState`],
+/// or the defaults.
+#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
+pub(crate) fn error_levels(req: &HttpRequest) -> ErrorLevels {
+    req.app_data::<web::Data<ErrorLevels>>()
+        .map(|levels| levels.get_ref().clone())
+        .or_else(|| state(req).map(|state| state.error_levels.clone()))
+        .unwrap_or_default()
+}
+
+#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
+fn state(req: &HttpRequest) -> Option<&AuthkestraState> {
+    req.app_data::<web::Data<AuthkestraState>>()
+        .map(|state| state.get_ref())
+}
@@ -0,0 +1,132 @@
+//! Cookie access abstraction used by the session extractor and OAuth helpers.
+//!
+//! Two implementations are provided:
+//!
+//! - [`tower_cookies::Cookies`]: requires `tower_cookies::CookieManagerLayer` to be
+//!   installed on the router. Changes are written to the response by the layer.
+//! - [`HeaderCookies`]: reads the raw `Cookie` request header and does **not** require
+//!   any layer. Pending changes must be returned from the handler (it implements
+//!   [`IntoResponseParts`]) so they are emitted as `Set-Cookie` headers.
+
+use axum::extract::FromRequestParts;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::{IntoResponseParts, ResponseParts};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use tower_cookies::{Cookie, Cookies};
+
+/// Read/write access to request and response cookies.
+pub trait CookieAccess: Send + Sync {
+    /// Get the value of a request cookie by name.
+    fn get_cookie(&self, name: &str) -> Option<String>;
+    /// Queue a cookie to be set on the response.
+    fn add_cookie(&self, cookie: Cookie<'static>);
+    /// Queue removal of a cookie on the response.
+    fn remove_cookie(&self, cookie: Cookie<'static>);
+}
+
+/// Requires `tower_cookies::CookieManagerLayer`.
+impl CookieAccess for Cookies {
+    fn get_cookie(&self, name: &str) -> Option<String> {
+        self.get(name).map(|c| c.value().to_string())
+    }
+
+    fn add_cookie(&self, cookie: Cookie<'static>) {
+        self.add(cookie);
+    }
+
+    fn remove_cookie(&self, cookie: Cookie<'static>) {
+        self.remove(cookie);
+    }
+}
+
+impl<C: CookieAccess + ?Sized> CookieAccess for &C {
+    fn get_cookie(&self, name: &str) -> Option<String> {
+        (**self).get_cookie(name)
+    }
+
+    fn add_cookie(&self, cookie: Cookie<'static>) {
+        (**self).add_cookie(cookie)
+    }
+
+    fn remove_cookie(&self, cookie: Cookie<'static>) {
+        (**self).remove_cookie(cookie)
+    }
+}
+
+/// A cookie jar backed by the raw `Cookie` request header.
+///
+/// Does not require `CookieManagerLayer`. Return it from the handler (e.g.
+/// `(jar, Redirect::to("/"))`) to emit queued changes as `Set-Cookie` headers.
+#[derive(Clone, Debug, Default)]
+pub struct HeaderCookies {
+    request: Vec<Cookie<'static>>,
+    pending: Arc<Mutex<Vec<Cookie<'static>>>>,
+}
+
+impl HeaderCookies {
+    /// Parse the cookies sent in the request headers.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let request = headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| Cookie::split_parse_encoded(value.to_string()))
+            .filter_map(|cookie| cookie.ok())
+            .map(|cookie| cookie.into_owned())
+            .collect();
+
+        Self {
+            request,
+            pending: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Cookies queued to be written to the response.
+    pub fn pending(&self) -> Vec<Cookie<'static>> {
+        self.pending.lock().unwrap().clone()
+    }
+}
+
+impl CookieAccess for HeaderCookies {
+    fn get_cookie(&self, name: &str) -> Option<String> {
+        self.request
+            .iter()
+            .find(|c| c.name() == name)
+            .map(|c| c.value().to_string())
+    }
+
+    fn add_cookie(&self, cookie: Cookie<'static>) {
+        self.pending.lock().unwrap().push(cookie);
+    }
+
+    fn remove_cookie(&self, mut cookie: Cookie<'static>) {
+        cookie.set_value("");
+        cookie.make_removal();
+        self.pending.lock().unwrap().push(cookie);
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for HeaderCookies {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+impl IntoResponseParts for HeaderCookies {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        for cookie in self.pending() {
+            if let Ok(value) = HeaderValue::from_str(&cookie.encoded().to_string()) {
+                res.headers_mut().append(header::SET_COOKIE, value);
+            }
+        }
+        Ok(res)
+    }
+}
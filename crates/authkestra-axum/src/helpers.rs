@@ -1,5 +1,9 @@
+#[cfg(any(feature = "flow", feature = "session"))]
+pub use crate::cookies::{CookieAccess, HeaderCookies};
 #[cfg(feature = "session")]
 pub use authkestra_engine::auth::{Session, SessionConfig, SessionStore};
+#[cfg(any(feature = "session", feature = "token"))]
+use authkestra_engine::ErrorLevels;
 #[cfg(feature = "token")]
 use authkestra_engine::TokenManager;
 #[cfg(any(feature = "flow", feature = "session", feature = "token"))]
@@ -14,8 +18,8 @@ use axum::Json;
 #[allow(unused_imports)]
 use axum::{
     extract::{Path, Query},
-    http::StatusCode,
-    response::{IntoResponse, Redirect},
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect, Response},
 };
 #[allow(unused_imports)]
 use std::sync::Arc;
@@ -34,6 +38,27 @@ pub struct OAuthCallbackParams {
 pub struct OAuthLoginParams {
     pub scope: Option<String>,
     pub success_url: Option<String>,
+    /// OIDC `prompt` value, e.g. `login` to force re-authentication.
+    pub prompt: Option<String>,
+    /// OIDC `login_hint` value, e.g. the user's email address.
+    pub login_hint: Option<String>,
+    /// `true` to create a long-lived session using `SessionConfig::remember_max_age`.
+    pub remember: Option<bool>,
+}
+
+#[cfg(feature = "flow")]
+impl OAuthLoginParams {
+    /// Validates `prompt` and collects the extra authorization parameters.
+    pub fn authorization_params(
+        &self,
+    ) -> Result<authkestra_engine::AuthorizationParams, authkestra_engine::AuthError> {
+        Ok(authkestra_engine::AuthorizationParams {
+            prompt: self.prompt.as_deref().map(str::parse).transpose()?,
+            login_hint: self.login_hint.clone(),
+            remember: self.remember.unwrap_or(false),
+            correlation_id: None,
+        })
+    }
 }
 
 #[cfg(any(feature = "flow", feature = "session"))]
@@ -52,7 +77,8 @@ pub fn create_axum_cookie<'a>(config: &SessionConfig, value: String) -> Cookie<'
     cookie.set_secure(config.secure);
     cookie.set_http_only(config.http_only);
     cookie.set_same_site(to_axum_same_site(config.same_site));
-    if let Some(max_age) = config.max_age {
+    cookie.set_partitioned(config.partitioned);
+    if let Some(max_age) = config.cookie_lifetime() {
         cookie.set_max_age(Some(tower_cookies::cookie::time::Duration::seconds(
             max_age.num_seconds(),
         )));
@@ -66,16 +92,45 @@ pub fn create_axum_cookie<'a>(config: &SessionConfig, value: String) -> Cookie<'
 #[cfg(feature = "flow")]
 pub fn initiate_oauth_login(
     flow: &dyn ErasedOAuthFlow,
-    cookies: &Cookies,
+    cookies: &impl CookieAccess,
     scopes: &[&str],
     config: &SessionConfig,
     success_url: Option<String>,
 ) -> Redirect {
+    initiate_oauth_login_with_params(
+        flow,
+        cookies,
+        scopes,
+        config,
+        success_url,
+        &authkestra_engine::AuthorizationParams::default(),
+    )
+    .expect("default authorization parameters leave the URL unchanged")
+}
+
+/// Like [`initiate_oauth_login`], appending `prompt`/`login_hint` to the authorization URL
+/// and recording `remember` and `correlation_id` in the state cookie.
+///
+/// Fails with [`AuthError::Config`](authkestra_engine::AuthError::Config) if
+/// the provider's authorization URL cannot be parsed to append them.
+#[cfg(feature = "flow")]
+pub fn initiate_oauth_login_with_params(
+    flow: &dyn ErasedOAuthFlow,
+    cookies: &impl CookieAccess,
+    scopes: &[&str],
+    config: &SessionConfig,
+    success_url: Option<String>,
+    params: &authkestra_engine::AuthorizationParams,
+) -> Result<Redirect, authkestra_engine::AuthError> {
     let pkce = Pkce::new();
     let (url, mut auth_state) = flow.initiate_login(scopes, Some(&pkce.code_challenge));
+    let url = params.apply(&url)?;
 
     auth_state.code_verifier = Some(pkce.code_verifier);
     auth_state.success_url = success_url;
+    auth_state.remember = params.remember;
+    auth_state.correlation_id = params.correlation_id.clone();
+    auth_state.expires_at = chrono::Utc::now().timestamp() + config.flow_state_ttl.num_seconds();
 
     let encrypted = auth_state
         .encrypt(&config.state_encryption_key)
@@ -88,52 +143,59 @@ pub fn initiate_oauth_login(
     cookie.set_http_only(true);
     cookie.set_same_site(SameSite::Lax);
     cookie.set_secure(true);
-    cookie.set_max_age(Some(tower_cookies::cookie::time::Duration::minutes(15)));
+    cookie.set_max_age(Some(tower_cookies::cookie::time::Duration::seconds(
+        config.flow_state_ttl.num_seconds(),
+    )));
+
+    cookies.add_cookie(cookie);
+
+    Ok(Redirect::to(&url))
+}
 
-    cookies.add(cookie);
+/// Reads and decrypts the `ak_state` cookie set by the login leg.
+#[cfg(feature = "flow")]
+fn decrypt_state_cookie(
+    cookies: &impl CookieAccess,
+    config: &SessionConfig,
+) -> Result<OAuth2State, (StatusCode, String)> {
+    let encrypted_state = cookies.get_cookie("ak_state").ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            "CSRF validation failed or session expired".to_string(),
+        )
+    })?;
 
-    Redirect::to(&url)
+    OAuth2State::decrypt(&encrypted_state, &config.state_encryption_key).map_err(|e| {
+        (
+            StatusCode::UNAUTHORIZED,
+            format!("Invalid state cookie: {e}"),
+        )
+    })
 }
 
 /// Internal helper to finalize the OAuth flow by validating state and exchanging the code.
 #[cfg(feature = "flow")]
 async fn finalize_callback_erased(
     flow: &dyn ErasedOAuthFlow,
-    cookies: &Cookies,
+    cookies: &impl CookieAccess,
     params: &OAuthCallbackParams,
-    config: &SessionConfig,
+    expected_state: OAuth2State,
+    levels: &ErrorLevels,
 ) -> Result<(Identity, OAuthToken, OAuth2State), (StatusCode, String)> {
     let cookie_name = "ak_state";
 
-    let encrypted_state = cookies
-        .get(cookie_name)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| {
-            (
-                StatusCode::UNAUTHORIZED,
-                "CSRF validation failed or session expired".to_string(),
-            )
-        })?;
-
-    let expected_state = OAuth2State::decrypt(&encrypted_state, &config.state_encryption_key)
-        .map_err(|e| {
-            (
-                StatusCode::UNAUTHORIZED,
-                format!("Invalid state cookie: {e}"),
-            )
-        })?;
-
     // Remove cookie after use
     let mut remove_cookie = Cookie::new(cookie_name, "");
     remove_cookie.set_path("/");
     remove_cookie.set_secure(true);
 
-    cookies.remove(remove_cookie);
+    cookies.remove_cookie(remove_cookie);
 
     let (identity, token) = flow
         .finalize_login(&params.code, &params.state, &expected_state)
         .await
         .map_err(|e| {
+            levels.log(&e, "OAuth callback failed");
             (
                 StatusCode::UNAUTHORIZED,
                 format!("Authentication failed: {e}"),
@@ -144,84 +206,148 @@ async fn finalize_callback_erased(
 }
 
 /// Helper to handle the OAuth2 callback and create a server-side session.
+///
+/// The scopes granted by the login are reported to `consent`; pass
+/// `engine.consent_sink.as_ref()`, or `&()` to discard them.
 #[cfg(all(feature = "flow", feature = "session"))]
 pub async fn handle_oauth_callback_erased(
     flow: &dyn ErasedOAuthFlow,
-    cookies: Cookies,
+    cookies: impl CookieAccess,
     params: OAuthCallbackParams,
     store: Arc<dyn SessionStore>,
     config: SessionConfig,
+    consent: &dyn authkestra_engine::ConsentSink,
     _success_url: &str,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let expected_state = decrypt_state_cookie(&cookies, &config)?;
+    complete_oauth_callback(
+        flow,
+        cookies,
+        params,
+        expected_state,
+        store,
+        config,
+        consent,
+        &ErrorLevels::default(),
+    )
+    .await
+}
+
+/// Finalizes the login, reports the granted scopes to `consent` and creates the session.
+#[cfg(all(feature = "flow", feature = "session"))]
+#[allow(clippy::too_many_arguments)]
+async fn complete_oauth_callback(
+    flow: &dyn ErasedOAuthFlow,
+    cookies: impl CookieAccess,
+    params: OAuthCallbackParams,
+    expected_state: OAuth2State,
+    store: Arc<dyn SessionStore>,
+    config: SessionConfig,
+    consent: &dyn authkestra_engine::ConsentSink,
+    levels: &ErrorLevels,
+) -> Result<Response, (StatusCode, String)> {
     let (mut identity, token, auth_state) =
-        finalize_callback_erased(flow, &cookies, &params, &config).await?;
+        finalize_callback_erased(flow, &cookies, &params, expected_state, levels).await?;
 
-    // Store tokens in identity attributes for convenience
-    identity
-        .attributes
-        .insert("access_token".to_string(), token.access_token);
+    consent
+        .record(authkestra_engine::ConsentRecord::from_login(
+            &identity,
+            &auth_state,
+            &token,
+        ))
+        .await;
 
-    if let Some(expires_in) = token.expires_in {
-        let expires_at = chrono::Utc::now().timestamp() + expires_in as i64;
+    // Store tokens in identity attributes for convenience
+    if let Some(expires_at) = token.expires_at() {
         identity
             .attributes
-            .insert("expires_at".to_string(), expires_at.to_string());
+            .insert("expires_at".to_string(), expires_at.timestamp().to_string());
     }
+    identity
+        .attributes
+        .insert("access_token".to_string(), token.access_token);
 
     if let Some(rt) = token.refresh_token {
         identity.attributes.insert("refresh_token".to_string(), rt);
     }
 
-    let session_duration = config.max_age.unwrap_or(chrono::Duration::hours(24));
+    let config = if auth_state.remember {
+        config.remembered()
+    } else {
+        config
+    };
     let session = Session {
         id: uuid::Uuid::new_v4().to_string(),
         identity,
-        expires_at: chrono::Utc::now() + session_duration,
+        expires_at: chrono::Utc::now() + config.session_lifetime(),
+        created_at: chrono::Utc::now(),
     };
 
-    store.save_session(&session).await.map_err(|e| {
+    let session_id = store.create_session(&session).await.map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Failed to save session: {e}"),
         )
     })?;
 
-    let cookie = create_axum_cookie(&config, session.id);
-    cookies.add(cookie);
+    let cookie = create_axum_cookie(&config, session_id);
+    cookies.add_cookie(cookie);
 
-    let redirect_url = auth_state.success_url.unwrap_or_else(|| "/".to_string());
+    let redirect_url = config.safe_redirect(auth_state.success_url.as_deref());
     Ok(Redirect::to(&redirect_url).into_response())
 }
 
 /// Helper to handle the OAuth2 callback and create a server-side session.
+///
+/// See [`handle_oauth_callback_erased`].
 #[cfg(all(feature = "flow", feature = "session"))]
 pub async fn handle_oauth_callback<P, M>(
     flow: &OAuth2Flow<P, M>,
-    cookies: Cookies,
+    cookies: impl CookieAccess,
     params: OAuthCallbackParams,
     store: Arc<dyn SessionStore>,
     config: SessionConfig,
+    consent: &dyn authkestra_engine::ConsentSink,
     success_url: &str,
 ) -> Result<impl IntoResponse, (StatusCode, String)>
 where
     P: authkestra_engine::OAuthProvider + Send + Sync + 'static,
     M: authkestra_engine::UserMapper + Send + Sync + 'static,
 {
-    handle_oauth_callback_erased(flow, cookies, params, store, config, success_url).await
+    handle_oauth_callback_erased(flow, cookies, params, store, config, consent, success_url).await
 }
 
 /// Helper to handle the OAuth2 callback and return a JWT for stateless auth.
+///
+/// The scopes granted by the login are reported to `consent`; pass
+/// `engine.consent_sink.as_ref()`, or `&()` to discard them.
 #[cfg(all(feature = "flow", feature = "token"))]
 pub async fn handle_oauth_callback_jwt_erased(
     flow: &dyn ErasedOAuthFlow,
-    cookies: Cookies,
+    cookies: impl CookieAccess,
     params: OAuthCallbackParams,
     token_manager: Arc<TokenManager>,
     expires_in_secs: u64,
     config: SessionConfig,
+    consent: &dyn authkestra_engine::ConsentSink,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let (identity, _token, _auth_state) =
-        finalize_callback_erased(flow, &cookies, &params, &config).await?;
+    let expected_state = decrypt_state_cookie(&cookies, &config)?;
+    let (identity, token, auth_state) = finalize_callback_erased(
+        flow,
+        &cookies,
+        &params,
+        expected_state,
+        &ErrorLevels::default(),
+    )
+    .await?;
+
+    consent
+        .record(authkestra_engine::ConsentRecord::from_login(
+            &identity,
+            &auth_state,
+            &token,
+        ))
+        .await;
 
     let jwt = token_manager
         .issue_user_token(identity, expires_in_secs, None, None)
@@ -240,14 +366,17 @@ pub async fn handle_oauth_callback_jwt_erased(
 }
 
 /// Helper to handle the OAuth2 callback and return a JWT for stateless auth.
+///
+/// See [`handle_oauth_callback_jwt_erased`].
 #[cfg(all(feature = "flow", feature = "token"))]
 pub async fn handle_oauth_callback_jwt<P, M>(
     flow: &OAuth2Flow<P, M>,
-    cookies: Cookies,
+    cookies: impl CookieAccess,
     params: OAuthCallbackParams,
     token_manager: Arc<TokenManager>,
     expires_in_secs: u64,
     config: SessionConfig,
+    consent: &dyn authkestra_engine::ConsentSink,
 ) -> Result<impl IntoResponse, (StatusCode, String)>
 where
     P: authkestra_engine::OAuthProvider + Send + Sync + 'static,
@@ -260,110 +389,291 @@ where
         token_manager,
         expires_in_secs,
         config,
+        consent,
     )
     .await
 }
 
+/// Mint a JWT for the identity of `session`, e.g. for an SPA calling an API
+/// directly while the web UI uses the session.
+///
+/// Returns the same JSON as [`handle_oauth_callback_jwt`]: `access_token`,
+/// `token_type` and `expires_in`. Every token issued is reported as a security
+/// event: an `INFO` event with target `authkestra::security` and
+/// `event = "session_token_issued"`. Keep `expires_in_secs` short, since the
+/// token outlives a logout of the session.
+#[cfg(all(feature = "session", feature = "token"))]
+pub fn issue_session_token(
+    session: &Session,
+    token_manager: &TokenManager,
+    expires_in_secs: u64,
+) -> Result<Json<serde_json::Value>, crate::AxumError> {
+    let jwt = token_manager
+        .issue_user_token(session.identity.clone(), expires_in_secs, None, None)
+        .map_err(|e| {
+            e.log("failed to issue session token");
+            crate::AxumError::Internal(e.to_string())
+        })?;
+
+    tracing::info!(
+        target: "authkestra::security",
+        event = "session_token_issued",
+        session_id = %session.id,
+        user_id = %session.identity.external_id,
+        expires_in = expires_in_secs,
+        "issued a token for a session"
+    );
+    Ok(Json(serde_json::json!({
+        "access_token": jwt,
+        "token_type": "Bearer",
+        "expires_in": expires_in_secs
+    })))
+}
+
+/// A router with `POST /auth/token`, which exchanges the session of the request
+/// for a JWT valid for `expires_in_secs` (see [`issue_session_token`]).
+///
+/// Requests without a valid session are rejected with `401`.
+///
+/// ```rust,ignore
+/// let app = Router::new()
+///     .merge(helpers::session_token_router(300))
+///     .with_state(AxumState::from(engine));
+/// ```
+#[cfg(all(feature = "session", feature = "token"))]
+pub fn session_token_router<AppState>(expires_in_secs: u64) -> axum::Router<AppState>
+where
+    AppState: Clone + Send + Sync + 'static,
+    SessionConfig: axum::extract::FromRef<AppState>,
+    Result<Arc<dyn SessionStore>, crate::AxumError>: axum::extract::FromRef<AppState>,
+    Result<Arc<TokenManager>, crate::AxumError>: axum::extract::FromRef<AppState>,
+    ErrorLevels: axum::extract::FromRef<AppState>,
+{
+    use axum::extract::{FromRef, State};
+    axum::Router::new().route(
+        "/auth/token",
+        axum::routing::post(
+            move |State(state): State<AppState>,
+                  crate::AuthSession(session): crate::AuthSession| async move {
+                let token_manager =
+                    <Result<Arc<TokenManager>, crate::AxumError>>::from_ref(&state)?;
+                issue_session_token(&session, &token_manager, expires_in_secs)
+            },
+        ),
+    )
+}
+
 /// Helper to handle logout by deleting the session from the store and clearing the cookie.
 ///
+/// Sessions found under fallback cookie names are deleted and their cookies cleared too.
+///
 /// Returns a redirect to the specified URL.
 #[cfg(feature = "session")]
 pub async fn logout(
-    cookies: Cookies,
+    cookies: impl CookieAccess,
     store: Arc<dyn SessionStore>,
     config: SessionConfig,
     redirect_to: &str,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let session_id = cookies
-        .get(&config.cookie_name)
-        .map(|c| c.value().to_string());
+    for name in config.lookup_cookie_names() {
+        let session_id = cookies.get_cookie(name);
+        if let Some(id) = &session_id {
+            store
+                .delete_session(id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        } else if name != config.cookie_name {
+            continue;
+        }
 
-    if let Some(id) = session_id {
-        store
-            .delete_session(&id)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let mut cookie = create_axum_cookie(&config, "".to_string());
+        cookie.set_name(name.to_string());
+        cookie.set_max_age(Some(tower_cookies::cookie::time::Duration::ZERO));
+        cookies.remove_cookie(cookie);
     }
 
-    let mut cookie = create_axum_cookie(&config, "".to_string());
-    cookie.set_max_age(Some(tower_cookies::cookie::time::Duration::ZERO));
-    cookies.remove(cookie);
-
     Ok(Redirect::to(redirect_to))
 }
 
+/// Login route handler.
+///
+/// Runs in a span carrying the `X-Request-Id` of the request (generated if
+/// absent), which is echoed on the response and carried to the callback in the
+/// state cookie.
 #[cfg(feature = "flow")]
 pub async fn axum_login_handler<AppState, S, T>(
     Path(provider): Path<String>,
     axum::extract::State(state): axum::extract::State<AppState>,
     Query(params): Query<OAuthLoginParams>,
+    headers: axum::http::HeaderMap,
     cookies: Cookies,
-) -> Result<impl IntoResponse, AxumError>
+) -> Response
 where
     AppState: Clone + Send + Sync + 'static,
     Engine<S, T>: axum::extract::FromRef<AppState>,
     SessionConfig: axum::extract::FromRef<AppState>,
+{
+    use tracing::Instrument;
+    let request_id = request_id(&headers);
+    let span = tracing::info_span!("oauth_login", %provider, request_id = %request_id);
+    let response = login::<AppState, S, T>(provider, state, params, cookies, request_id.clone())
+        .instrument(span)
+        .await;
+    with_request_id(response.into_response(), &request_id)
+}
+
+#[cfg(feature = "flow")]
+async fn login<AppState, S, T>(
+    provider: String,
+    state: AppState,
+    params: OAuthLoginParams,
+    cookies: Cookies,
+    request_id: String,
+) -> Result<Response, AxumError>
+where
+    Engine<S, T>: axum::extract::FromRef<AppState>,
+    SessionConfig: axum::extract::FromRef<AppState>,
 {
     use axum::extract::FromRef;
     let authkestra = Engine::<S, T>::from_ref(&state);
     let session_config = SessionConfig::from_ref(&state);
 
-    let flow: &Arc<dyn ErasedOAuthFlow> = match authkestra.providers.get(&provider) {
+    let flow: Arc<dyn ErasedOAuthFlow> = match authkestra.providers.get(&provider) {
         Some(f) => f,
         None => {
-            return Err(AxumError::Internal("Provider not found".to_string()));
+            tracing::warn!(%provider, "unknown provider requested");
+            return Ok(unknown_provider_response(&authkestra, &provider));
         }
     };
 
+    let mut authorization_params = params.authorization_params().map_err(|e| {
+        tracing::warn!(error = %e, "rejected login request parameters");
+        AxumError::BadRequest(e.to_string())
+    })?;
+    authorization_params.correlation_id = Some(request_id);
+
     let scopes_str = params.scope.unwrap_or_default();
     let scopes: Vec<&str> = scopes_str
         .split(|c: char| [' ', ','].contains(&c))
         .filter(|s| !s.is_empty())
         .collect();
 
-    let redirect = initiate_oauth_login(
+    let redirect = initiate_oauth_login_with_params(
         flow.as_ref(),
         &cookies,
         &scopes,
         &session_config,
         params.success_url,
-    );
+        &authorization_params,
+    )
+    .map_err(|e| {
+        authkestra
+            .error_levels
+            .log(&e, "failed to build the authorization URL");
+        AxumError::Internal(e.to_string())
+    })?;
+
+    Ok(redirect.into_response())
+}
 
-    Ok(redirect)
+/// The correlation id of a request, from its `X-Request-Id` header or generated.
+#[cfg(feature = "flow")]
+fn request_id(headers: &axum::http::HeaderMap) -> String {
+    authkestra_engine::correlation_id(
+        headers
+            .get(authkestra_engine::REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok()),
+    )
 }
 
+/// Echoes the correlation id on the response.
+#[cfg(feature = "flow")]
+fn with_request_id(mut response: Response, request_id: &str) -> Response {
+    if let Ok(value) = header::HeaderValue::from_str(request_id) {
+        response
+            .headers_mut()
+            .insert(authkestra_engine::REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(feature = "flow")]
+fn unknown_provider_response<S, T>(authkestra: &Engine<S, T>, provider: &str) -> Response {
+    let (status, content_type, body) = authkestra.unknown_provider_response(provider);
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_REQUEST);
+    (status, [(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+/// Callback route handler.
+///
+/// Runs in a span carrying the correlation id of the login leg, read from the
+/// state cookie. Falls back to the request's own `X-Request-Id`.
 #[cfg(all(feature = "flow", feature = "session"))]
 pub async fn axum_callback_handler<AppState, S, T>(
     Path(provider): Path<String>,
     axum::extract::State(state): axum::extract::State<AppState>,
     Query(params): Query<OAuthCallbackParams>,
+    headers: axum::http::HeaderMap,
     cookies: Cookies,
-) -> Result<impl IntoResponse, AxumError>
+) -> Response
 where
     AppState: Clone + Send + Sync + 'static,
     Engine<S, T>: axum::extract::FromRef<AppState>,
     SessionConfig: axum::extract::FromRef<AppState>,
     Result<Arc<dyn SessionStore>, AxumError>: axum::extract::FromRef<AppState>,
+{
+    use axum::extract::FromRef;
+    use tracing::Instrument;
+    let session_config = SessionConfig::from_ref(&state);
+    let expected_state = decrypt_state_cookie(&cookies, &session_config);
+    let request_id = expected_state
+        .as_ref()
+        .ok()
+        .and_then(|state| state.correlation_id.clone())
+        .unwrap_or_else(|| request_id(&headers));
+    let span = tracing::info_span!("oauth_callback", %provider, request_id = %request_id);
+    let response = callback::<AppState, S, T>(provider, state, params, expected_state, cookies)
+        .instrument(span)
+        .await;
+    with_request_id(response.into_response(), &request_id)
+}
+
+#[cfg(all(feature = "flow", feature = "session"))]
+async fn callback<AppState, S, T>(
+    provider: String,
+    state: AppState,
+    params: OAuthCallbackParams,
+    expected_state: Result<OAuth2State, (StatusCode, String)>,
+    cookies: Cookies,
+) -> Result<Response, AxumError>
+where
+    Engine<S, T>: axum::extract::FromRef<AppState>,
+    SessionConfig: axum::extract::FromRef<AppState>,
+    Result<Arc<dyn SessionStore>, AxumError>: axum::extract::FromRef<AppState>,
 {
     use axum::extract::FromRef;
     let authkestra = Engine::<S, T>::from_ref(&state);
     let session_config = SessionConfig::from_ref(&state);
     let session_store = <Result<Arc<dyn SessionStore>, AxumError>>::from_ref(&state)?;
 
-    let flow: &Arc<dyn ErasedOAuthFlow> = match authkestra.providers.get(&provider) {
+    let flow: Arc<dyn ErasedOAuthFlow> = match authkestra.providers.get(&provider) {
         Some(f) => f,
         None => {
-            return Err(AxumError::Internal("Provider not found".to_string()));
+            tracing::warn!(%provider, "unknown provider requested");
+            return Ok(unknown_provider_response(&authkestra, &provider));
         }
     };
 
-    handle_oauth_callback_erased(
+    let expected_state = expected_state.map_err(|(_, msg)| AxumError::Unauthorized(msg))?;
+    complete_oauth_callback(
         flow.as_ref(),
         cookies,
         params,
+        expected_state,
         session_store,
         session_config,
-        "",
+        authkestra.consent_sink.as_ref(),
+        &authkestra.error_levels,
     )
     .await
     .map_err(|(status, msg)| {
@@ -375,10 +685,25 @@ where
     })
 }
 
+/// Form fields of the logout route.
+#[derive(serde::Deserialize)]
+pub struct LogoutParams {
+    /// The CSRF token from [`SessionConfig::logout_token`].
+    pub logout_token: Option<String>,
+}
+
+/// Logout route handler.
+///
+/// Expects a `POST` whose form carries the `logout_token` of the current
+/// session (see [`SessionConfig::logout_token`]), so a cross-site request
+/// cannot log users out. Requests without a session cookie are let through,
+/// as there is nothing to log out. The `unprotected-logout` feature skips the
+/// check and also accepts `GET`.
 #[cfg(all(feature = "flow", feature = "session"))]
 pub async fn axum_logout_handler<AppState, S, T>(
     axum::extract::State(state): axum::extract::State<AppState>,
     cookies: Cookies,
+    form: Result<axum::Form<LogoutParams>, axum::extract::rejection::FormRejection>,
 ) -> Result<impl IntoResponse, AxumError>
 where
     AppState: Clone + Send + Sync + 'static,
@@ -389,6 +714,24 @@ where
     let session_config = SessionConfig::from_ref(&state);
     let session_store = <Result<Arc<dyn SessionStore>, AxumError>>::from_ref(&state)?;
 
+    #[cfg(not(feature = "unprotected-logout"))]
+    {
+        let token = form.ok().and_then(|form| form.0.logout_token);
+        let session_id = session_config
+            .lookup_cookie_names()
+            .find_map(|name| cookies.get_cookie(name));
+        if let Some(session_id) = session_id {
+            if !token.is_some_and(|token| session_config.verify_logout_token(&session_id, &token)) {
+                tracing::warn!("rejected logout without a valid logout token");
+                return Err(AxumError::Forbidden(
+                    "Missing or invalid logout token".to_string(),
+                ));
+            }
+        }
+    }
+    #[cfg(feature = "unprotected-logout")]
+    let _ = form;
+
     logout(cookies, session_store, session_config, "/")
         .await
         .map_err(|(status, msg)| {
@@ -400,9 +743,47 @@ where
         })
 }
 
+/// The request header carrying the CSRF token of scripted requests.
+#[cfg(feature = "session")]
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Verify the CSRF `token` submitted with a form against the session cookie.
+///
+/// Issue the token with `session_config.csrf().issue(&session.id)` and render it
+/// into the form; pass the submitted field here. Fails with
+/// [`AxumError::Forbidden`] when the request has no session cookie or the token
+/// is missing or was not issued for that session. See [`crate::ValidCsrf`] for
+/// requests sending the token in the [`CSRF_HEADER`] header.
+#[cfg(feature = "session")]
+pub fn verify_csrf(
+    session_config: &SessionConfig,
+    cookies: &impl CookieAccess,
+    token: Option<&str>,
+) -> Result<(), AxumError> {
+    let csrf = session_config.csrf();
+    let valid = token.is_some_and(|token| {
+        session_config
+            .lookup_cookie_names()
+            .filter_map(|name| cookies.get_cookie(name))
+            .any(|session_id| csrf.verify(&session_id, token))
+    });
+    if valid {
+        Ok(())
+    } else {
+        tracing::warn!("rejected request without a valid CSRF token");
+        Err(AxumError::Forbidden(
+            "Missing or invalid CSRF token".to_string(),
+        ))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum AxumError {
     Unauthorized(String),
+    /// The request was refused, e.g. a logout without a valid CSRF token.
+    Forbidden(String),
+    /// The request was malformed, e.g. an invalid query parameter.
+    BadRequest(String),
     Internal(String),
     /// A required component (e.g., SessionManager, TokenManager) is missing
     ComponentMissing(String),
@@ -412,62 +793,536 @@ impl std::fmt::Display for AxumError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             AxumError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            AxumError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            AxumError::BadRequest(msg) => write!(f, "Bad Request: {}", msg),
             AxumError::Internal(msg) => write!(f, "Internal Error: {}", msg),
             AxumError::ComponentMissing(msg) => write!(f, "Component Missing: {}", msg),
         }
     }
 }
 
+impl std::error::Error for AxumError {}
+
+impl AxumError {
+    /// The HTTP status code of the error response.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AxumError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AxumError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AxumError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AxumError::Internal(_) | AxumError::ComponentMissing(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// A stable, machine-readable error code, used as `error` in JSON bodies.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AxumError::Unauthorized(_) => "unauthorized",
+            AxumError::Forbidden(_) => "forbidden",
+            AxumError::BadRequest(_) => "bad_request",
+            AxumError::Internal(_) => "internal_error",
+            AxumError::ComponentMissing(_) => "component_missing",
+        }
+    }
+
+    /// The human-readable error message.
+    pub fn message(&self) -> &str {
+        match self {
+            AxumError::Unauthorized(msg)
+            | AxumError::Forbidden(msg)
+            | AxumError::BadRequest(msg)
+            | AxumError::Internal(msg)
+            | AxumError::ComponentMissing(msg) => msg,
+        }
+    }
+}
+
+/// Renders the response with [`ErrorRenderer::Json`].
+///
+/// The error is also stored in the response extensions, so [`render_errors`]
+/// can re-render it in another format.
 impl IntoResponse for AxumError {
     fn into_response(self) -> axum::response::Response {
-        let (status, message) = match self {
-            AxumError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
-            AxumError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            AxumError::ComponentMissing(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+        let (content_type, body) = ErrorRenderer::Json.render(&self, None);
+        let mut response =
+            (self.status(), [(header::CONTENT_TYPE, content_type)], body).into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// The format of [`AxumError`] response bodies.
+///
+/// Install it with [`render_errors`]:
+///
+/// ```rust,ignore
+/// use axum::middleware::from_fn_with_state;
+/// use authkestra_axum::helpers::{render_errors, ErrorRenderer};
+///
+/// let app = app.layer(from_fn_with_state(ErrorRenderer::Auto, render_errors));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorRenderer {
+    /// `{"error": "<code>", "message": "<message>"}` as `application/json`.
+    #[default]
+    Json,
+    /// The message as `text/plain`.
+    Plain,
+    /// A minimal `text/html` page.
+    Html,
+    /// Picks JSON, plain text or HTML from the request's `Accept` header,
+    /// falling back to JSON.
+    Auto,
+}
+
+impl ErrorRenderer {
+    /// Renders `error`, returning the content type and body.
+    ///
+    /// `accept` is the request's `Accept` header, only used by [`ErrorRenderer::Auto`].
+    pub fn render(self, error: &AxumError, accept: Option<&str>) -> (&'static str, String) {
+        match self {
+            ErrorRenderer::Json => (
+                "application/json",
+                serde_json::json!({ "error": error.code(), "message": error.message() })
+                    .to_string(),
+            ),
+            ErrorRenderer::Plain => ("text/plain; charset=utf-8", error.message().to_string()),
+            ErrorRenderer::Html => {
+                let status = error.status();
+                let title = format!(
+                    "{} {}",
+                    status.as_u16(),
+                    status.canonical_reason().unwrap_or("Error")
+                );
+                (
+                    "text/html; charset=utf-8",
+                    format!(
+                        "<!DOCTYPE html><html><head><title>{title}</title></head><body><h1>{title}</h1><p>{}</p></body></html>",
+                        escape_html(error.message())
+                    ),
+                )
+            }
+            ErrorRenderer::Auto => Self::negotiate(accept).render(error, None),
+        }
+    }
+
+    /// Picks the supported media type with the highest `q` in `accept`.
+    /// Ties go to the earlier entry; no usable entry means JSON.
+    fn negotiate(accept: Option<&str>) -> ErrorRenderer {
+        let mut best = (ErrorRenderer::Json, 0.0_f32);
+        for range in accept.unwrap_or_default().split(',') {
+            let mut params = range.split(';');
+            let renderer = match params.next().unwrap_or_default().trim() {
+                "application/json" | "application/*" => ErrorRenderer::Json,
+                "text/html" => ErrorRenderer::Html,
+                "text/plain" | "text/*" => ErrorRenderer::Plain,
+                "*/*" => ErrorRenderer::Json,
+                _ => continue,
+            };
+            let q = params
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())
+                .unwrap_or(0.0);
+            if q > best.1 {
+                best = (renderer, q);
+            }
+        }
+        best.0
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Middleware re-rendering [`AxumError`] responses with the given [`ErrorRenderer`].
+///
+/// Use it with `axum::middleware::from_fn_with_state`. Other responses, and
+/// headers set alongside the error (e.g. cookies), are left untouched.
+pub async fn render_errors(
+    axum::extract::State(renderer): axum::extract::State<ErrorRenderer>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Response {
+    let accept = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let response = next.run(request).await;
+    let Some(error) = response.extensions().get::<AxumError>().cloned() else {
+        return response;
+    };
+
+    let (content_type, body) = renderer.render(&error, accept.as_deref());
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static(content_type),
+    );
+    Response::from_parts(parts, axum::body::Body::from(body))
+}
+
+/// Where [`redirect_to_login`] sends unauthenticated browser requests.
+#[derive(Clone, Debug)]
+pub struct LoginRedirect {
+    login_url: String,
+    next_param: String,
+}
+
+impl LoginRedirect {
+    /// Redirect to `login_url`, passing the requested path and query in the
+    /// `next` query parameter.
+    pub fn new(login_url: impl Into<String>) -> Self {
+        Self {
+            login_url: login_url.into(),
+            next_param: "next".to_string(),
+        }
+    }
+
+    /// Set the query parameter carrying the requested path. Use `success_url`
+    /// to redirect straight to the built-in `/auth/login/{provider}` route,
+    /// which returns there after login.
+    pub fn next_param(mut self, name: impl Into<String>) -> Self {
+        self.next_param = name.into();
+        self
+    }
+
+    /// The login URL for a request to `next`.
+    pub fn location(&self, next: &str) -> String {
+        let separator = if self.login_url.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        let next: String = url::form_urlencoded::byte_serialize(next.as_bytes()).collect();
+        format!("{}{separator}{}={next}", self.login_url, self.next_param)
+    }
+}
+
+/// Middleware redirecting browsers to a login page instead of answering
+/// `401 Unauthorized`.
+///
+/// Use it with `axum::middleware::from_fn_with_state`:
+///
+/// ```rust,ignore
+/// use axum::middleware::from_fn_with_state;
+/// use authkestra_axum::helpers::{redirect_to_login, LoginRedirect};
+///
+/// let login = LoginRedirect::new("/auth/login/github").next_param("success_url");
+/// let app = app.layer(from_fn_with_state(login, redirect_to_login));
+/// ```
+///
+/// An [`AxumError::Unauthorized`] response to a `GET` or `HEAD` request
+/// whose `Accept` header prefers `text/html` becomes a `303 See Other` to the
+/// login URL. API clients (e.g. `Accept: application/json`) and other
+/// methods still get the `401`, as do rejections that are not an
+/// [`AxumError`], such as the `JwtRejection` of the `Jwt` extractor.
+pub async fn redirect_to_login(
+    axum::extract::State(login): axum::extract::State<LoginRedirect>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Response {
+    let browser = matches!(
+        *request.method(),
+        axum::http::Method::GET | axum::http::Method::HEAD
+    ) && ErrorRenderer::negotiate(
+        request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok()),
+    ) == ErrorRenderer::Html;
+    let target = request
+        .uri()
+        .path_and_query()
+        .map_or("/", |p| p.as_str())
+        .to_owned();
+
+    let response = next.run(request).await;
+    match response.extensions().get::<AxumError>() {
+        Some(AxumError::Unauthorized(_)) if browser => {
+            tracing::debug!(next = %target, "redirecting unauthenticated browser to login");
+            Redirect::to(&login.location(&target)).into_response()
+        }
+        _ => response,
+    }
+}
+
+/// Middleware rejecting requests the [`Guard`](authkestra_resource::Guard)
+/// does not authenticate, e.g. for every route of a router.
+///
+/// Use it with `axum::middleware::from_fn_with_state`:
+///
+/// ```rust,ignore
+/// use axum::middleware::from_fn_with_state;
+/// use authkestra_axum::helpers::require_auth;
+///
+/// let app = app.layer(from_fn_with_state(guard.clone(), require_auth::<User>));
+/// ```
+///
+/// The identity is cached in the request extensions, so an [`Auth<I>`](crate::Auth)
+/// or [`Authz<I, P>`](crate::Authz) extractor in the handler reuses it
+/// instead of running the guard again.
+#[cfg(feature = "resource")]
+pub async fn require_auth<I>(
+    axum::extract::State(guard): axum::extract::State<Arc<authkestra_resource::Guard<I>>>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Response
+where
+    I: Clone + Send + Sync + 'static,
+{
+    let (mut parts, body) = request.into_parts();
+    if let Err(e) = crate::resolve_identity(&mut parts, &guard).await {
+        return e.into_response();
+    }
+    next.run(axum::extract::Request::from_parts(parts, body))
+        .await
+}
+
+/// The rejection of the [`Jwt`](crate::Jwt) extractor: a `401` with an
+/// RFC 6750 `WWW-Authenticate: Bearer` challenge.
+///
+/// The body is negotiated from the request's `Accept` header: an HTML page
+/// for browsers, otherwise the OAuth 2.0 error JSON
+/// `{"error": "invalid_token", "error_description": "..."}`. It is not an
+/// [`AxumError`], so [`render_errors`] leaves it as it is.
+#[cfg(feature = "resource")]
+#[derive(Debug, Clone)]
+pub struct JwtRejection {
+    error: &'static str,
+    description: String,
+    html: bool,
+}
+
+#[cfg(feature = "resource")]
+impl JwtRejection {
+    /// The request carried no usable bearer token.
+    pub(crate) fn invalid_request(description: impl Into<String>, accept: Option<&str>) -> Self {
+        Self::new("invalid_request", description.into(), accept)
+    }
+
+    /// The bearer token was malformed, expired or otherwise invalid.
+    pub(crate) fn invalid_token(description: impl Into<String>, accept: Option<&str>) -> Self {
+        Self::new("invalid_token", description.into(), accept)
+    }
+
+    fn new(error: &'static str, description: String, accept: Option<&str>) -> Self {
+        Self {
+            error,
+            description,
+            html: ErrorRenderer::negotiate(accept) == ErrorRenderer::Html,
+        }
+    }
+
+    /// The OAuth 2.0 error code, `invalid_request` or `invalid_token`.
+    pub fn error(&self) -> &'static str {
+        self.error
+    }
+
+    /// The human-readable error description.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The `WWW-Authenticate` challenge. A request without credentials gets
+    /// no error code, as RFC 6750 section 3.1 recommends.
+    fn challenge(&self) -> String {
+        if self.error != "invalid_token" {
+            return "Bearer".to_string();
+        }
+        // Keep the description a valid quoted-string.
+        let description: String = self
+            .description
+            .chars()
+            .filter(|c| c.is_ascii() && !c.is_ascii_control() && *c != '"' && *c != '\\')
+            .collect();
+        format!(
+            r#"Bearer error="{}", error_description="{description}""#,
+            self.error
+        )
+    }
+}
+
+#[cfg(feature = "resource")]
+impl std::fmt::Display for JwtRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.error, self.description)
+    }
+}
+
+#[cfg(feature = "resource")]
+impl std::error::Error for JwtRejection {}
+
+#[cfg(feature = "resource")]
+impl IntoResponse for JwtRejection {
+    fn into_response(self) -> Response {
+        let (content_type, body) = if self.html {
+            ErrorRenderer::Html.render(&AxumError::Unauthorized(self.description.clone()), None)
+        } else {
+            (
+                "application/json",
+                serde_json::json!({ "error": self.error, "error_description": self.description })
+                    .to_string(),
+            )
         };
-        (status, message).into_response()
+        let challenge = header::HeaderValue::from_str(&self.challenge())
+            .unwrap_or_else(|_| header::HeaderValue::from_static("Bearer"));
+        (
+            StatusCode::UNAUTHORIZED,
+            [
+                (
+                    header::CONTENT_TYPE,
+                    header::HeaderValue::from_static(content_type),
+                ),
+                (header::WWW_AUTHENTICATE, challenge),
+            ],
+            body,
+        )
+            .into_response()
     }
 }
 
 #[cfg(feature = "session")]
-#[tracing::instrument(skip(store, cookies))]
 pub async fn get_session(
     store: &Arc<dyn SessionStore>,
     config: &SessionConfig,
-    cookies: &Cookies,
+    cookies: &impl CookieAccess,
+) -> Result<Session, AxumError> {
+    load_session(store, config, cookies, &ErrorLevels::default()).await
+}
+
+/// [`get_session`], logging store failures at `levels`.
+#[cfg(feature = "session")]
+#[tracing::instrument(skip(store, cookies, levels))]
+pub(crate) async fn load_session(
+    store: &Arc<dyn SessionStore>,
+    config: &SessionConfig,
+    cookies: &impl CookieAccess,
+    levels: &ErrorLevels,
 ) -> Result<Session, AxumError> {
     tracing::debug!("getting session from cookies");
-    let session_id = cookies
-        .get(&config.cookie_name)
-        .map(|c| c.value().to_string())
-        .ok_or_else(|| {
-            tracing::warn!("missing session cookie in request");
-            AxumError::Unauthorized("Missing session cookie".to_string())
-        })?;
+    let session_ids: Vec<String> = config
+        .lookup_cookie_names()
+        .filter_map(|name| cookies.get_cookie(name))
+        .collect();
+    if session_ids.is_empty() {
+        tracing::warn!("missing session cookie in request");
+        return Err(AxumError::Unauthorized(
+            "Missing session cookie".to_string(),
+        ));
+    }
 
-    let session = store
-        .load_session(&session_id)
-        .await
-        .map_err(|e| {
-            tracing::error!(error = %e, "failed to load session from store");
+    let mut found = None;
+    for session_id in &session_ids {
+        let session = store.load_session(session_id).await.map_err(|e| {
+            levels.log(&e, "failed to load session from store");
             AxumError::Internal(e.to_string())
-        })?
-        .ok_or_else(|| {
-            tracing::warn!("session not found or invalid");
-            AxumError::Unauthorized("Invalid session".to_string())
         })?;
+        if let Some(session) = session.filter(|session| config.is_active(session)) {
+            found = Some(session);
+            break;
+        }
+    }
+    let session = found.ok_or_else(|| {
+        tracing::warn!("session not found, invalid or expired");
+        AxumError::Unauthorized("Invalid session".to_string())
+    })?;
 
     tracing::info!(session_id = %session.id, user_id = %session.identity.external_id, "successfully retrieved session");
     Ok(session)
 }
 
+/// Seconds before `expires_at` at which a stored access token is treated as expired.
+#[cfg(feature = "flow")]
+const TOKEN_REFRESH_LEEWAY_SECS: i64 = 30;
+
+/// Ensures the upstream access token stored in the session is not expired.
+///
+/// If the `expires_at` attribute has passed, the `refresh_token` attribute is
+/// exchanged with the session's provider and the updated session is saved.
+/// Returns `Ok(true)` if the token is stale: it expired and could not be refreshed
+/// (no refresh token, unknown provider, or the provider rejected the refresh).
+#[cfg(feature = "flow")]
+pub async fn refresh_session_token(
+    session: &mut Session,
+    providers: &authkestra_engine::ProviderRegistry,
+    store: &Arc<dyn SessionStore>,
+) -> Result<bool, AxumError> {
+    refresh_upstream_token(session, providers, store, &ErrorLevels::default()).await
+}
+
+/// [`refresh_session_token`], logging failures at `levels`.
+#[cfg(feature = "flow")]
+#[tracing::instrument(skip_all, fields(session_id = %session.id))]
+pub(crate) async fn refresh_upstream_token(
+    session: &mut Session,
+    providers: &authkestra_engine::ProviderRegistry,
+    store: &Arc<dyn SessionStore>,
+    levels: &ErrorLevels,
+) -> Result<bool, AxumError> {
+    let attributes = &session.identity.attributes;
+    let expired = attributes
+        .get("expires_at")
+        .and_then(|v| v.parse::<i64>().ok())
+        .is_some_and(|exp| exp <= chrono::Utc::now().timestamp() + TOKEN_REFRESH_LEEWAY_SECS);
+
+    if !expired {
+        tracing::debug!("stored access token is still valid");
+        return Ok(false);
+    }
+
+    let Some(refresh_token) = attributes.get("refresh_token").cloned() else {
+        tracing::warn!("stored access token expired and no refresh token is available");
+        return Ok(true);
+    };
+
+    let Some(flow) = providers.get(&session.identity.provider_id) else {
+        tracing::warn!(provider = %session.identity.provider_id, "provider not registered, cannot refresh access token");
+        return Ok(true);
+    };
+
+    let token = match flow.refresh_token(&refresh_token).await {
+        Ok(token) => token,
+        Err(e) => {
+            levels.log(&e, "failed to refresh access token");
+            return Ok(true);
+        }
+    };
+
+    let attributes = &mut session.identity.attributes;
+    match token.expires_at() {
+        Some(expires_at) => {
+            attributes.insert("expires_at".to_string(), expires_at.timestamp().to_string());
+        }
+        None => {
+            attributes.remove("expires_at");
+        }
+    }
+    attributes.insert("access_token".to_string(), token.access_token);
+    if let Some(rt) = token.refresh_token {
+        attributes.insert("refresh_token".to_string(), rt);
+    }
+
+    store.save_session(session).await.map_err(|e| {
+        levels.log(&e, "failed to save session after token refresh");
+        AxumError::Internal(e.to_string())
+    })?;
+
+    tracing::info!("refreshed upstream access token");
+    Ok(false)
+}
+
+/// The token of the `Authorization: Bearer <token>` header.
 #[cfg(feature = "token")]
-#[tracing::instrument(skip_all)]
-pub async fn get_token(
-    parts: &axum::http::request::Parts,
-    token_manager: &TokenManager,
-) -> Result<authkestra_engine::Claims, AxumError> {
-    tracing::debug!("getting token from request parts");
+pub(crate) fn bearer_token(parts: &axum::http::request::Parts) -> Result<&str, AxumError> {
     let auth_header = parts
         .headers
         .get(axum::http::header::AUTHORIZATION)
@@ -484,9 +1339,29 @@ pub async fn get_token(
         ));
     }
 
-    let token = &auth_header[7..];
+    Ok(&auth_header[7..])
+}
+
+#[cfg(feature = "token")]
+#[tracing::instrument(skip_all)]
+pub async fn get_token(
+    parts: &axum::http::request::Parts,
+    token_manager: &TokenManager,
+) -> Result<authkestra_engine::Claims, AxumError> {
+    validate_bearer_token(parts, token_manager, &ErrorLevels::default())
+}
+
+/// [`get_token`], logging failures at `levels`.
+#[cfg(feature = "token")]
+pub(crate) fn validate_bearer_token(
+    parts: &axum::http::request::Parts,
+    token_manager: &TokenManager,
+    levels: &ErrorLevels,
+) -> Result<authkestra_engine::Claims, AxumError> {
+    tracing::debug!("getting token from request parts");
+    let token = bearer_token(parts)?;
     let claims = token_manager.validate_token(token, None).map_err(|e| {
-        tracing::error!(error = %e, "failed to validate token");
+        levels.log(&e, "failed to validate token");
         AxumError::Unauthorized(format!("Invalid token: {e}"))
     })?;
 
@@ -1,28 +1,49 @@
+#[cfg(any(feature = "session", feature = "token"))]
+use authkestra_engine::ErrorLevels;
+#[cfg(feature = "session")]
+pub use authkestra_engine::SessionConfig;
 #[cfg(feature = "token")]
 pub use authkestra_engine::TokenManager;
+#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
+pub use authkestra_engine::TrustedProxies;
 #[cfg(feature = "flow")]
-pub use authkestra_engine::{Engine, Missing, SessionConfig};
+pub use authkestra_engine::{Engine, Missing};
 #[cfg(feature = "resource")]
-pub use authkestra_resource::Guard;
+pub use authkestra_resource::{
+    AuthorizationEngine, Decision, Guard, LocalRules, PathTenant, Policy, RequireScopes,
+    ScopeRequirement, TenantBinding, TenantSource,
+};
 #[allow(unused_imports)]
 use axum::extract::FromRef;
-#[cfg(feature = "session")]
+#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
 use axum::extract::FromRequestParts;
 #[cfg(any(feature = "session", feature = "token", feature = "resource"))]
 use std::sync::Arc;
 
+#[cfg(any(feature = "flow", feature = "session"))]
+pub mod cookies;
 pub mod helpers;
 
 #[cfg(feature = "op")]
 pub mod op;
 
-pub use helpers::AxumError;
+#[cfg(feature = "tower-sessions")]
+pub mod tower_sessions;
+
+#[cfg(any(feature = "flow", feature = "session"))]
+pub use cookies::{CookieAccess, HeaderCookies};
+#[cfg(feature = "resource")]
+pub use helpers::JwtRejection;
+pub use helpers::{redirect_to_login, render_errors, AxumError, ErrorRenderer, LoginRedirect};
 #[cfg(feature = "session")]
 pub use helpers::{Session, SessionStore};
 
 #[cfg(feature = "op")]
 pub use op::OpExt;
 
+#[cfg(feature = "tower-sessions")]
+pub use tower_sessions::TowerSessionStore;
+
 #[cfg(feature = "macros")]
 extern crate self as authkestra_axum;
 
@@ -44,6 +65,9 @@ impl<S, T> From<Engine<S, T>> for AxumState<S, T> {
 }
 
 /// The extractor for a validated session.
+///
+/// The session cookie is read from the raw `Cookie` header, so this extractor
+/// does not require `tower_cookies::CookieManagerLayer`.
 #[cfg(feature = "session")]
 pub struct AuthSession(pub Session);
 
@@ -53,6 +77,7 @@ where
     S: Send + Sync,
     Result<Arc<dyn SessionStore>, AxumError>: FromRef<S>,
     SessionConfig: FromRef<S>,
+    ErrorLevels: FromRef<S>,
 {
     type Rejection = AxumError;
 
@@ -61,18 +86,13 @@ where
         parts: &mut axum::http::request::Parts,
         state: &S,
     ) -> Result<Self, Self::Rejection> {
-        use tower_cookies::Cookies;
         tracing::debug!("extracting AuthSession from request");
         let session_store = <Result<Arc<dyn SessionStore>, AxumError>>::from_ref(state)?;
         let session_config = SessionConfig::from_ref(state);
-        let cookies = Cookies::from_request_parts(parts, state)
-            .await
-            .map_err(|e| {
-                tracing::error!(error = %e.1, "failed to extract cookies");
-                AxumError::Internal(e.1.to_string())
-            })?;
+        let cookies = HeaderCookies::from_headers(&parts.headers);
 
-        let session = helpers::get_session(&session_store, &session_config, &cookies)
+        let levels = ErrorLevels::from_ref(state);
+        let session = helpers::load_session(&session_store, &session_config, &cookies, &levels)
             .await
             .map_err(|e| {
                 tracing::error!(error = %e, "failed to get session from store");
@@ -84,6 +104,58 @@ where
     }
 }
 
+/// The extractor for a validated session with a non-expired upstream access token.
+///
+/// Behaves like [`AuthSession`], then refreshes the access token stored in the
+/// session if it has expired and re-saves the session. If the refresh fails, the
+/// session is still returned with `token_stale` set.
+#[cfg(feature = "flow")]
+pub struct AuthSessionWithToken {
+    pub session: Session,
+    /// The upstream access token, if the session holds one.
+    pub access_token: Option<String>,
+    /// `true` if the access token has expired and could not be refreshed.
+    pub token_stale: bool,
+}
+
+#[cfg(feature = "flow")]
+impl<S> FromRequestParts<S> for AuthSessionWithToken
+where
+    S: Send + Sync,
+    Result<Arc<dyn SessionStore>, AxumError>: FromRef<S>,
+    SessionConfig: FromRef<S>,
+    ErrorLevels: FromRef<S>,
+    authkestra_engine::ProviderRegistry: FromRef<S>,
+{
+    type Rejection = AxumError;
+
+    #[tracing::instrument(skip_all)]
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        tracing::debug!("extracting AuthSessionWithToken from request");
+        let AuthSession(mut session) = AuthSession::from_request_parts(parts, state).await?;
+        let session_store = <Result<Arc<dyn SessionStore>, AxumError>>::from_ref(state)?;
+        let providers = authkestra_engine::ProviderRegistry::from_ref(state);
+
+        let levels = ErrorLevels::from_ref(state);
+        let token_stale =
+            helpers::refresh_upstream_token(&mut session, &providers, &session_store, &levels)
+                .await?;
+        if token_stale {
+            tracing::warn!(session_id = %session.id, "returning session with stale access token");
+        }
+
+        let access_token = session.identity.attributes.get("access_token").cloned();
+        Ok(AuthSessionWithToken {
+            session,
+            access_token,
+            token_stale,
+        })
+    }
+}
+
 /// The extractor for a validated JWT.
 ///
 /// Expects an `Authorization: Bearer <token>` header.
@@ -95,6 +167,7 @@ impl<S> FromRequestParts<S> for AuthToken
 where
     S: Send + Sync,
     Result<Arc<TokenManager>, AxumError>: FromRef<S>,
+    ErrorLevels: FromRef<S>,
 {
     type Rejection = AxumError;
 
@@ -105,9 +178,9 @@ where
     ) -> Result<Self, Self::Rejection> {
         tracing::debug!("extracting AuthToken from request");
         let token_manager = <Result<Arc<TokenManager>, AxumError>>::from_ref(state)?;
-        let token = helpers::get_token(parts, &token_manager)
-            .await
-            .map_err(|e| {
+        let levels = ErrorLevels::from_ref(state);
+        let token =
+            helpers::validate_bearer_token(parts, &token_manager, &levels).map_err(|e| {
                 tracing::error!(error = %e, "failed to get and validate token");
                 e
             })?;
@@ -116,9 +189,125 @@ where
     }
 }
 
+/// The extractor for a step-up token of at least assurance level `LEVEL`,
+/// issued with [`TokenManager::issue_stepup_token`].
+///
+/// Expects an `Authorization: Bearer <token>` header. A missing or forged
+/// token is rejected with `401 Unauthorized`; a genuine token below `LEVEL`
+/// or expired is rejected with `403 Forbidden` and a `step-up required`
+/// message, so the client can send the user through step-up again.
+///
+/// ```rust,ignore
+/// async fn delete_account(StepUp(claims): StepUp<2>) { /* ... */ }
+/// ```
+#[cfg(feature = "token")]
+pub struct StepUp<const LEVEL: u32>(pub authkestra_engine::Claims);
+
+#[cfg(feature = "token")]
+impl<S, const LEVEL: u32> FromRequestParts<S> for StepUp<LEVEL>
+where
+    S: Send + Sync,
+    Result<Arc<TokenManager>, AxumError>: FromRef<S>,
+    ErrorLevels: FromRef<S>,
+{
+    type Rejection = AxumError;
+
+    #[tracing::instrument(skip_all, fields(level = LEVEL))]
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let token_manager = <Result<Arc<TokenManager>, AxumError>>::from_ref(state)?;
+        let token = helpers::bearer_token(parts)?;
+        match token_manager.validate_stepup_token(token, LEVEL) {
+            Ok(claims) => Ok(StepUp(claims)),
+            Err(authkestra_engine::AuthError::StepUpRequired) => {
+                tracing::info!("step-up required");
+                Err(AxumError::Forbidden("step-up required".to_string()))
+            }
+            Err(e) => {
+                ErrorLevels::from_ref(state).log(&e, "invalid step-up token");
+                Err(AxumError::Unauthorized(format!("Invalid token: {e}")))
+            }
+        }
+    }
+}
+
+/// A session and a JWT that must belong to the same user.
+///
+/// When the request carries both a session cookie and an `Authorization`
+/// header, both are validated as by [`AuthSession`] and [`AuthToken`] and the
+/// binding check `B` must pass; a mismatch is rejected with
+/// `401 Unauthorized` and reported as a security event (see
+/// [`check_token_binding`](authkestra_engine::auth::check_token_binding)).
+/// When only one is present, this behaves as the corresponding extractor and
+/// the other field is `None`.
+#[cfg(all(feature = "session", feature = "token"))]
+pub struct BoundAuth<B = authkestra_engine::auth::SameSubject> {
+    pub session: Option<Session>,
+    pub claims: Option<authkestra_engine::Claims>,
+    binding: std::marker::PhantomData<B>,
+}
+
+#[cfg(all(feature = "session", feature = "token"))]
+impl<S, B> FromRequestParts<S> for BoundAuth<B>
+where
+    S: Send + Sync,
+    Result<Arc<dyn SessionStore>, AxumError>: FromRef<S>,
+    Result<Arc<TokenManager>, AxumError>: FromRef<S>,
+    SessionConfig: FromRef<S>,
+    ErrorLevels: FromRef<S>,
+    B: authkestra_engine::auth::TokenBinding,
+{
+    type Rejection = AxumError;
+
+    #[tracing::instrument(skip_all)]
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let session_config = SessionConfig::from_ref(state);
+        let cookies = HeaderCookies::from_headers(&parts.headers);
+        let has_session = session_config
+            .lookup_cookie_names()
+            .any(|name| cookies.get_cookie(name).is_some());
+        let has_token = parts
+            .headers
+            .contains_key(axum::http::header::AUTHORIZATION);
+
+        // Without either, the session extractor reports the missing cookie.
+        let session = if has_session || !has_token {
+            Some(AuthSession::from_request_parts(parts, state).await?.0)
+        } else {
+            None
+        };
+        let claims = if has_token {
+            Some(AuthToken::from_request_parts(parts, state).await?.0)
+        } else {
+            None
+        };
+
+        if let (Some(session), Some(claims)) = (&session, &claims) {
+            let token_manager = <Result<Arc<TokenManager>, AxumError>>::from_ref(state)?;
+            if !authkestra_engine::auth::check_token_binding::<B>(session, claims, &token_manager) {
+                return Err(AxumError::Unauthorized(
+                    "Token does not belong to the session".to_string(),
+                ));
+            }
+        }
+
+        Ok(BoundAuth {
+            session,
+            claims,
+            binding: std::marker::PhantomData,
+        })
+    }
+}
+
 /// A generic JWT extractor for resource server validation.
 ///
 /// Validates a Bearer token against a configured `JwksCache` and `JwtValidation`.
+/// Failures are rejected with a [`JwtRejection`](helpers::JwtRejection).
 #[cfg(feature = "resource")]
 pub struct Jwt<T>(pub T);
 
@@ -130,7 +319,7 @@ where
     jsonwebtoken::Validation: FromRef<S>,
     T: for<'de> serde::Deserialize<'de> + 'static,
 {
-    type Rejection = AxumError;
+    type Rejection = helpers::JwtRejection;
 
     async fn from_request_parts(
         parts: &mut axum::http::request::Parts,
@@ -138,16 +327,23 @@ where
     ) -> Result<Self, Self::Rejection> {
         let cache = Arc::<authkestra_resource::jwt::JwksCache>::from_ref(state);
         let validation = jsonwebtoken::Validation::from_ref(state);
+        let accept = parts
+            .headers
+            .get(axum::http::header::ACCEPT)
+            .and_then(|h| h.to_str().ok());
 
         let auth_header = parts
             .headers
             .get(axum::http::header::AUTHORIZATION)
             .and_then(|h| h.to_str().ok())
-            .ok_or_else(|| AxumError::Unauthorized("Missing Authorization header".to_string()))?;
+            .ok_or_else(|| {
+                helpers::JwtRejection::invalid_request("Missing Authorization header", accept)
+            })?;
 
         if !auth_header.starts_with("Bearer ") {
-            return Err(AxumError::Unauthorized(
-                "Invalid Authorization header".to_string(),
+            return Err(helpers::JwtRejection::invalid_request(
+                "Invalid Authorization header",
+                accept,
             ));
         }
 
@@ -155,7 +351,7 @@ where
         let claims =
             authkestra_resource::jwt::validate_jwt_generic::<T>(token, &cache, &validation)
                 .await
-                .map_err(|e| AxumError::Unauthorized(format!("Invalid token: {e}")))?;
+                .map_err(|e| helpers::JwtRejection::invalid_token(e.to_string(), accept))?;
 
         Ok(Jwt(claims))
     }
@@ -164,15 +360,56 @@ where
 /// A unified extractor for authentication.
 ///
 /// It uses the `Guard` from the application state to validate the request.
+/// The identity is cached in the request extensions, so the guard runs at
+/// most once per request, even when [`helpers::require_auth`] or another
+/// extractor already authenticated it.
 #[cfg(feature = "resource")]
 pub struct Auth<I>(pub I);
 
+/// An identity resolved by the `Guard` for the current request.
+#[cfg(feature = "resource")]
+#[derive(Clone)]
+struct Resolved<I>(I);
+
+/// Returns the identity cached in `parts`, or authenticates with `guard` and
+/// caches the result.
+#[cfg(feature = "resource")]
+pub(crate) async fn resolve_identity<I>(
+    parts: &mut axum::http::request::Parts,
+    guard: &authkestra_resource::Guard<I>,
+) -> Result<I, AxumError>
+where
+    I: Clone + Send + Sync + 'static,
+{
+    if let Some(Resolved(identity)) = parts.extensions.get::<Resolved<I>>() {
+        tracing::debug!("reusing identity resolved earlier in the request");
+        return Ok(identity.clone());
+    }
+    match guard.authenticate(parts).await {
+        Ok(Some(identity)) => {
+            tracing::info!("successfully authenticated request via Guard");
+            parts.extensions.insert(Resolved(identity.clone()));
+            Ok(identity)
+        }
+        Ok(None) => {
+            tracing::warn!("authentication failed: no identity returned");
+            Err(AxumError::Unauthorized("Authentication failed".to_string()))
+        }
+        Err(e) => {
+            guard
+                .error_levels()
+                .log(&e, "internal error during authentication");
+            Err(AxumError::Internal(e.to_string()))
+        }
+    }
+}
+
 #[cfg(feature = "resource")]
 impl<S, I> FromRequestParts<S> for Auth<I>
 where
     S: Send + Sync,
     Arc<authkestra_resource::Guard<I>>: FromRef<S>,
-    I: Send + Sync + 'static,
+    I: Clone + Send + Sync + 'static,
 {
     type Rejection = AxumError;
 
@@ -183,23 +420,335 @@ where
     ) -> Result<Self, Self::Rejection> {
         tracing::debug!("extracting generic Auth from request via Guard");
         let guard = Arc::<authkestra_resource::Guard<I>>::from_ref(state);
-        match guard.authenticate(parts).await {
+        resolve_identity(parts, &guard).await.map(Auth)
+    }
+}
+
+/// An extractor that authenticates with the `Guard` like [`Auth<I>`], then
+/// authorizes the identity with the [`Policy`](authkestra_resource::Policy) `P`.
+///
+/// Unauthenticated requests are rejected with `401`; authenticated requests
+/// the policy denies are rejected with `403` and the policy's reason as the
+/// error message.
+#[cfg(feature = "resource")]
+pub struct Authz<I, P>(pub I, pub std::marker::PhantomData<P>);
+
+#[cfg(feature = "resource")]
+impl<S, I, P> FromRequestParts<S> for Authz<I, P>
+where
+    S: Send + Sync,
+    Arc<authkestra_resource::Guard<I>>: FromRef<S>,
+    I: Clone + Send + Sync + 'static,
+    P: authkestra_resource::Policy<I>,
+{
+    type Rejection = AxumError;
+
+    #[tracing::instrument(skip_all)]
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let Auth(identity) = Auth::<I>::from_request_parts(parts, state).await?;
+        match P::evaluate(&identity) {
+            authkestra_resource::Decision::Allow => Ok(Authz(identity, std::marker::PhantomData)),
+            authkestra_resource::Decision::Deny(reason) => {
+                tracing::warn!(%reason, "authorization denied by policy");
+                Err(AxumError::Forbidden(reason))
+            }
+        }
+    }
+}
+
+/// An extractor that authenticates with the `Guard` like [`Auth<I>`], then
+/// asks the [`AuthorizationEngine`](authkestra_resource::AuthorizationEngine)
+/// from the application state whether the identity may perform the request's
+/// method on its path. The path is the full request path, including the
+/// prefix of any [`Router::nest`](axum::Router::nest) the route is mounted under.
+///
+/// Unauthenticated requests are rejected with `401`, denied requests with
+/// `403` and the engine's reason as the error message, and engine errors with
+/// `500`.
+#[cfg(feature = "resource")]
+pub struct Authorized<I>(pub I);
+
+#[cfg(feature = "resource")]
+impl<S, I> FromRequestParts<S> for Authorized<I>
+where
+    S: Send + Sync,
+    Arc<authkestra_resource::Guard<I>>: FromRef<S>,
+    Arc<dyn authkestra_resource::AuthorizationEngine<I>>: FromRef<S>,
+    I: Clone + Send + Sync + 'static,
+{
+    type Rejection = AxumError;
+
+    #[tracing::instrument(skip_all, fields(method = %parts.method))]
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let Auth(identity) = Auth::<I>::from_request_parts(parts, state).await?;
+        let engine = Arc::<dyn authkestra_resource::AuthorizationEngine<I>>::from_ref(state);
+        // Nested routers strip their prefix from `parts.uri`.
+        let path = parts
+            .extensions
+            .get::<axum::extract::OriginalUri>()
+            .map_or(parts.uri.path(), |uri| uri.path());
+        match engine
+            .authorize(&identity, parts.method.as_str(), path)
+            .await
+        {
+            Ok(authkestra_resource::Decision::Allow) => Ok(Authorized(identity)),
+            Ok(authkestra_resource::Decision::Deny(reason)) => {
+                tracing::warn!(%reason, %path, "authorization denied by engine");
+                Err(AxumError::Forbidden(reason))
+            }
+            Err(e) => {
+                Arc::<authkestra_resource::Guard<I>>::from_ref(state)
+                    .error_levels()
+                    .log(&e, "authorization engine failed");
+                Err(AxumError::Internal(e.to_string()))
+            }
+        }
+    }
+}
+
+/// An extractor that authenticates with the `Guard` like [`Auth<I>`], then
+/// checks that the identity belongs to the tenant the request addresses.
+///
+/// The [`TenantBinding`](authkestra_resource::TenantBinding) `B` names the
+/// identity's tenant claim and the path parameter or header naming the
+/// request's tenant (`tenant_id` for both by default). Unauthenticated
+/// requests are rejected with `401`, requests naming no tenant with `400`,
+/// and requests for another tenant with `403`.
+#[cfg(feature = "resource")]
+pub struct TenantAuth<I, B = authkestra_resource::PathTenant>(
+    pub I,
+    pub std::marker::PhantomData<B>,
+);
+
+#[cfg(feature = "resource")]
+impl<S, I, B> FromRequestParts<S> for TenantAuth<I, B>
+where
+    S: Send + Sync,
+    Arc<authkestra_resource::Guard<I>>: FromRef<S>,
+    I: Clone + serde::Serialize + Send + Sync + 'static,
+    B: authkestra_resource::TenantBinding,
+{
+    type Rejection = AxumError;
+
+    #[tracing::instrument(skip_all, fields(claim = B::CLAIM))]
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let Auth(identity) = Auth::<I>::from_request_parts(parts, state).await?;
+        let tenant = match B::SOURCE {
+            authkestra_resource::TenantSource::Path(name) => {
+                axum::extract::RawPathParams::from_request_parts(parts, state)
+                    .await
+                    .ok()
+                    .and_then(|params| {
+                        params
+                            .iter()
+                            .find(|(key, _)| *key == name)
+                            .map(|(_, value)| value.to_owned())
+                    })
+            }
+            authkestra_resource::TenantSource::Header(name) => parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+        }
+        .filter(|tenant| !tenant.is_empty())
+        .ok_or_else(|| {
+            tracing::warn!(source = ?B::SOURCE, "request does not name a tenant");
+            AxumError::BadRequest("Missing tenant id".to_string())
+        })?;
+
+        if !authkestra_resource::check_tenant::<B>(&identity, &tenant) {
+            return Err(AxumError::Forbidden(
+                "Identity does not belong to this tenant".to_string(),
+            ));
+        }
+        Ok(TenantAuth(identity, std::marker::PhantomData))
+    }
+}
+
+/// Names the query parameter [`WsAuth`] reads the token from.
+#[cfg(feature = "resource")]
+pub trait TokenQueryParam: Send + Sync + 'static {
+    /// The query parameter name.
+    const NAME: &'static str;
+}
+
+/// The standard `access_token` query parameter.
+#[cfg(feature = "resource")]
+pub struct AccessTokenParam;
+
+#[cfg(feature = "resource")]
+impl TokenQueryParam for AccessTokenParam {
+    const NAME: &'static str = "access_token";
+}
+
+/// An extractor for endpoints that cannot send an `Authorization` header, such as
+/// `EventSource` (SSE) and WebSocket upgrades.
+///
+/// The token is read from the query parameter named by `P` (`access_token` by
+/// default) and validated by the same `Guard` as [`Auth<I>`], as if it had been sent
+/// as `Authorization: Bearer <token>`. Missing or invalid tokens are rejected with
+/// `401` before the handler (and therefore the upgrade) runs.
+///
+/// Query strings end up in access logs, browser history and proxies, so only use
+/// short-lived tokens here, e.g. a dedicated token issued just before connecting.
+#[cfg(feature = "resource")]
+pub struct WsAuth<I, P = AccessTokenParam>(pub I, pub std::marker::PhantomData<P>);
+
+#[cfg(feature = "resource")]
+impl<S, I, P> FromRequestParts<S> for WsAuth<I, P>
+where
+    S: Send + Sync,
+    Arc<authkestra_resource::Guard<I>>: FromRef<S>,
+    I: Send + Sync + 'static,
+    P: TokenQueryParam,
+{
+    type Rejection = AxumError;
+
+    #[tracing::instrument(skip_all, fields(param = P::NAME))]
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        tracing::debug!("extracting WsAuth from query token");
+        let token = authkestra_engine::strategy::utils::extract_query_param(parts, P::NAME)
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| {
+                tracing::warn!("missing token query parameter");
+                AxumError::Unauthorized(format!("Missing {} query parameter", P::NAME))
+            })?;
+
+        let bearer =
+            axum::http::HeaderValue::from_str(&format!("Bearer {token}")).map_err(|_| {
+                tracing::warn!("token query parameter is not a valid header value");
+                AxumError::Unauthorized("Invalid token".to_string())
+            })?;
+
+        // Present the token to the guard as a bearer header on a copy of the request.
+        let mut request = axum::http::Request::new(());
+        *request.method_mut() = parts.method.clone();
+        *request.uri_mut() = parts.uri.clone();
+        *request.headers_mut() = parts.headers.clone();
+        request
+            .headers_mut()
+            .insert(axum::http::header::AUTHORIZATION, bearer);
+        let (bearer_parts, _) = request.into_parts();
+
+        let guard = Arc::<authkestra_resource::Guard<I>>::from_ref(state);
+        match guard.authenticate(&bearer_parts).await {
             Ok(Some(identity)) => {
-                tracing::info!("successfully authenticated request via Guard");
-                Ok(Auth(identity))
+                tracing::info!("successfully authenticated request via query token");
+                Ok(WsAuth(identity, std::marker::PhantomData))
             }
             Ok(None) => {
                 tracing::warn!("authentication failed: no identity returned");
                 Err(AxumError::Unauthorized("Authentication failed".to_string()))
             }
+            Err(
+                e @ (authkestra_engine::AuthError::Token(_)
+                | authkestra_engine::AuthError::InvalidToken(_)
+                | authkestra_engine::AuthError::InvalidCredentials),
+            ) => {
+                guard.error_levels().log(&e, "rejected query token");
+                Err(AxumError::Unauthorized(e.to_string()))
+            }
             Err(e) => {
-                tracing::error!(error = %e, "internal error during authentication");
+                guard
+                    .error_levels()
+                    .log(&e, "internal error during authentication");
                 Err(AxumError::Internal(e.to_string()))
             }
         }
     }
 }
 
+/// The extractor for the client IP address.
+///
+/// Forwarding headers are only honoured when the socket peer is one of the
+/// [`TrustedProxies`] in the state; otherwise the socket address is used. The
+/// socket address comes from `ConnectInfo<SocketAddr>`, so serve the app with
+/// `into_make_service_with_connect_info::<SocketAddr>()`.
+#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientIp(pub std::net::IpAddr);
+
+#[cfg(any(feature = "session", feature = "token", feature = "resource"))]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+    TrustedProxies: FromRef<S>,
+{
+    type Rejection = AxumError;
+
+    #[tracing::instrument(skip_all)]
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
+            .map(|info| info.0.ip())
+            .ok_or_else(|| {
+                tracing::error!("missing ConnectInfo<SocketAddr> request extension");
+                AxumError::ComponentMissing("ConnectInfo<SocketAddr>".to_string())
+            })?;
+        let trusted = TrustedProxies::from_ref(state);
+        let ip = trusted.client_ip(&parts.headers, peer);
+        tracing::debug!(%peer, client_ip = %ip, "resolved client IP");
+        Ok(ClientIp(ip))
+    }
+}
+
+/// Guards a handler against cross-site requests.
+///
+/// Requires the [`helpers::CSRF_HEADER`] header to carry the token issued with
+/// `session_config.csrf().issue(&session.id)` for the session cookie of the
+/// request, and rejects the request with `403 Forbidden` otherwise. Forms that
+/// post the token as a field can call [`helpers::verify_csrf`] instead.
+#[cfg(feature = "session")]
+pub struct ValidCsrf;
+
+#[cfg(feature = "session")]
+impl<S> FromRequestParts<S> for ValidCsrf
+where
+    S: Send + Sync,
+    SessionConfig: FromRef<S>,
+{
+    type Rejection = AxumError;
+
+    #[tracing::instrument(skip_all)]
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let session_config = SessionConfig::from_ref(state);
+        let cookies = HeaderCookies::from_headers(&parts.headers);
+        let token = parts
+            .headers
+            .get(helpers::CSRF_HEADER)
+            .and_then(|value| value.to_str().ok());
+        helpers::verify_csrf(&session_config, &cookies, token)?;
+        Ok(ValidCsrf)
+    }
+}
+
+/// Mounts the login, callback and logout routes.
+///
+/// These routes extract `tower_cookies::Cookies`, so the router must be wrapped in
+/// `tower_cookies::CookieManagerLayer`. Use the helpers in [`helpers`] with
+/// [`HeaderCookies`] to build layer-free handlers.
+///
+/// The routes log errors at the engine's `error_levels`.
 #[cfg(all(feature = "flow", feature = "session"))]
 pub trait AxumExt<S, T> {
     fn axum_router<AppState>(&self) -> axum::Router<AppState>
@@ -208,6 +757,16 @@ pub trait AxumExt<S, T> {
         Engine<S, T>: FromRef<AppState>,
         SessionConfig: FromRef<AppState>,
         Result<Arc<dyn SessionStore>, AxumError>: FromRef<AppState>;
+
+    /// The routes of [`axum_router`](Self::axum_router) with this engine as
+    /// their [`AxumState`], wrapped in `tower_cookies::CookieManagerLayer`.
+    ///
+    /// The result needs no state of its own, so a minimal app is
+    /// `app.merge(engine.into_router())`. Use `axum_router` to share an
+    /// application state with the auth routes instead.
+    fn into_router(self) -> axum::Router
+    where
+        S: authkestra_engine::SessionStoreState;
 }
 
 #[cfg(all(feature = "flow", feature = "session"))]
@@ -222,6 +781,11 @@ impl<S: Clone + Send + Sync + 'static, T: Clone + Send + Sync + 'static> AxumExt
         Result<Arc<dyn SessionStore>, AxumError>: FromRef<AppState>,
     {
         use axum::routing::get;
+        #[cfg(not(feature = "unprotected-logout"))]
+        let logout = axum::routing::post(helpers::axum_logout_handler::<AppState, S, T>);
+        #[cfg(feature = "unprotected-logout")]
+        let logout = get(helpers::axum_logout_handler::<AppState, S, T>)
+            .post(helpers::axum_logout_handler::<AppState, S, T>);
         axum::Router::new()
             .route(
                 "/auth/login/{provider}",
@@ -231,9 +795,127 @@ impl<S: Clone + Send + Sync + 'static, T: Clone + Send + Sync + 'static> AxumExt
                 "/auth/callback/{provider}",
                 get(helpers::axum_callback_handler::<AppState, S, T>),
             )
-            .route(
-                "/auth/logout",
-                get(helpers::axum_logout_handler::<AppState, S, T>),
-            )
+            .route("/auth/logout", logout)
+    }
+
+    fn into_router(self) -> axum::Router
+    where
+        S: authkestra_engine::SessionStoreState,
+    {
+        self.axum_router::<AxumState<S, T>>()
+            .layer(tower_cookies::CookieManagerLayer::new())
+            .with_state(AxumState::from(self))
+    }
+}
+
+#[cfg(all(test, feature = "session"))]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use authkestra_engine::{AuthError, Identity};
+    use axum::http::{header, Request};
+    use std::collections::HashMap;
+
+    struct MockSessionStore;
+
+    #[async_trait]
+    impl SessionStore for MockSessionStore {
+        async fn load_session(&self, id: &str) -> Result<Option<Session>, AuthError> {
+            if id != "session-123" {
+                return Ok(None);
+            }
+            Ok(Some(Session {
+                id: id.to_string(),
+                identity: Identity {
+                    provider_id: "mock".to_string(),
+                    external_id: "user123".to_string(),
+                    email: None,
+                    email_verified: None,
+                    username: None,
+                    attributes: HashMap::new(),
+                    attributes_multi: HashMap::new(),
+                },
+                expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+                created_at: chrono::Utc::now(),
+            }))
+        }
+        async fn save_session(&self, _session: &Session) -> Result<(), AuthError> {
+            Ok(())
+        }
+        async fn delete_session(&self, _id: &str) -> Result<(), AuthError> {
+            Ok(())
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestState {
+        store: Arc<dyn SessionStore>,
+        config: SessionConfig,
+    }
+
+    impl FromRef<TestState> for Result<Arc<dyn SessionStore>, AxumError> {
+        fn from_ref(state: &TestState) -> Self {
+            Ok(state.store.clone())
+        }
+    }
+
+    impl FromRef<TestState> for SessionConfig {
+        fn from_ref(state: &TestState) -> Self {
+            state.config.clone()
+        }
+    }
+
+    impl FromRef<TestState> for ErrorLevels {
+        fn from_ref(_state: &TestState) -> Self {
+            ErrorLevels::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auth_session_from_raw_cookie_header() {
+        let state = TestState {
+            store: Arc::new(MockSessionStore),
+            config: SessionConfig::default(),
+        };
+
+        // No CookieManagerLayer is involved: the extractor reads the raw header.
+        let (mut parts, _) = Request::builder()
+            .header(header::COOKIE, "other=1; authkestra_session=session-123")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let AuthSession(session) = AuthSession::from_request_parts(&mut parts, &state)
+            .await
+            .unwrap();
+        assert_eq!(session.identity.external_id, "user123");
+
+        let (mut parts, _) = Request::builder().body(()).unwrap().into_parts();
+        let result = AuthSession::from_request_parts(&mut parts, &state).await;
+        assert!(matches!(result, Err(AxumError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_header_cookies_emit_set_cookie() {
+        use axum::response::IntoResponse;
+
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(header::COOKIE, "ak_state=abc".parse().unwrap());
+        let jar = HeaderCookies::from_headers(&headers);
+        assert_eq!(jar.get_cookie("ak_state").as_deref(), Some("abc"));
+
+        jar.add_cookie(tower_cookies::Cookie::new("authkestra_session", "xyz"));
+        jar.remove_cookie(tower_cookies::Cookie::new("ak_state", ""));
+
+        let response = (jar, "ok").into_response();
+        let set_cookies: Vec<_> = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|v| v.to_str().unwrap().to_string())
+            .collect();
+        assert_eq!(set_cookies.len(), 2);
+        assert!(set_cookies[0].starts_with("authkestra_session=xyz"));
+        assert!(set_cookies[1].starts_with("ak_state=;"));
     }
 }
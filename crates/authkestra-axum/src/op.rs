@@ -32,7 +32,7 @@ where
     tracing::debug!("Handling JWKS request");
     (
         StatusCode::OK,
-        Json(JwksResponse::new(token_manager.public_jwk())),
+        Json(JwksResponse::from_keys(token_manager.jwks())),
     )
         .into_response()
 }
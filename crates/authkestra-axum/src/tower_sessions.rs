@@ -0,0 +1,174 @@
+//! Authkestra sessions stored in a `tower-sessions` backend.
+
+use async_trait::async_trait;
+use authkestra_engine::{AuthError, Session, SessionStore};
+use std::collections::HashMap;
+use tower_sessions_core::session::{Id, Record};
+
+/// A [`SessionStore`] backed by a [`tower_sessions_core::SessionStore`].
+///
+/// Lets apps already using `tower-sessions` keep Authkestra sessions in the
+/// same backend. Each Authkestra session is one record: the record id is the
+/// session id, the record expiry is `Session::expires_at`, and the session is
+/// serialized under [`TowerSessionStore::DATA_KEY`].
+///
+/// Session ids are `tower-sessions` ids, so sessions must be created through
+/// [`SessionStore::create_session`] (as `Engine::create_session` does).
+#[derive(Clone, Debug)]
+pub struct TowerSessionStore<S> {
+    inner: S,
+}
+
+impl<S> TowerSessionStore<S> {
+    /// The record data key holding the serialized [`Session`].
+    pub const DATA_KEY: &'static str = "authkestra.session";
+
+    /// Wrap a `tower-sessions` store.
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    /// The wrapped `tower-sessions` store.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: tower_sessions_core::SessionStore> TowerSessionStore<S> {
+    fn record(id: Id, session: &Session) -> Result<Record, AuthError> {
+        let value = serde_json::to_value(session)
+            .map_err(|e| AuthError::Session(format!("Failed to serialize session: {e}")))?;
+        let expiry_date = time::OffsetDateTime::from_unix_timestamp(session.expires_at.timestamp())
+            .map_err(|e| AuthError::Session(format!("Invalid session expiry: {e}")))?;
+        Ok(Record {
+            id,
+            data: HashMap::from([(Self::DATA_KEY.to_string(), value)]),
+            expiry_date,
+        })
+    }
+}
+
+fn store_error(e: tower_sessions_core::session_store::Error) -> AuthError {
+    AuthError::Session(e.to_string())
+}
+
+#[async_trait]
+impl<S: tower_sessions_core::SessionStore> SessionStore for TowerSessionStore<S> {
+    async fn load_session(&self, id: &str) -> Result<Option<Session>, AuthError> {
+        let Ok(record_id) = id.parse::<Id>() else {
+            return Ok(None);
+        };
+        let Some(record) = self.inner.load(&record_id).await.map_err(store_error)? else {
+            return Ok(None);
+        };
+        let Some(value) = record.data.get(Self::DATA_KEY) else {
+            return Ok(None);
+        };
+        let mut session: Session = serde_json::from_value(value.clone())
+            .map_err(|e| AuthError::Session(format!("Failed to deserialize session: {e}")))?;
+        // The store may have replaced the id on a collision in `create`.
+        session.id = record.id.to_string();
+        if session.is_expired() {
+            return Ok(None);
+        }
+        Ok(Some(session))
+    }
+
+    async fn save_session(&self, session: &Session) -> Result<(), AuthError> {
+        let id = session
+            .id
+            .parse::<Id>()
+            .map_err(|e| AuthError::Session(format!("Not a tower-sessions session id: {e}")))?;
+        let record = Self::record(id, session)?;
+        self.inner.save(&record).await.map_err(store_error)
+    }
+
+    async fn delete_session(&self, id: &str) -> Result<(), AuthError> {
+        let Ok(record_id) = id.parse::<Id>() else {
+            return Ok(());
+        };
+        self.inner.delete(&record_id).await.map_err(store_error)
+    }
+
+    async fn create_session(&self, session: &Session) -> Result<String, AuthError> {
+        let mut record = Self::record(Id::default(), session)?;
+        self.inner.create(&mut record).await.map_err(store_error)?;
+        Ok(record.id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use authkestra_engine::state::Identity;
+    use tower_sessions_memory_store::MemoryStore;
+
+    fn session(expires_in: chrono::Duration) -> Session {
+        Session {
+            id: "ignored".to_string(),
+            identity: Identity {
+                provider_id: "mock".to_string(),
+                external_id: "user123".to_string(),
+                email: None,
+                email_verified: None,
+                username: None,
+                attributes: HashMap::new(),
+                attributes_multi: HashMap::new(),
+            },
+            expires_at: chrono::Utc::now() + expires_in,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let inner = MemoryStore::default();
+        let store = TowerSessionStore::new(inner.clone());
+
+        let mut session = session(chrono::Duration::hours(1));
+        session.id = store.create_session(&session).await.unwrap();
+
+        let loaded = store.load_session(&session.id).await.unwrap().unwrap();
+        assert_eq!(loaded.id, session.id);
+        assert_eq!(loaded.identity.external_id, "user123");
+        assert_eq!(
+            loaded.expires_at.timestamp(),
+            session.expires_at.timestamp()
+        );
+
+        // The record is visible to tower-sessions with the same id and expiry.
+        let record = tower_sessions_core::SessionStore::load(&inner, &session.id.parse().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            record.expiry_date.unix_timestamp(),
+            session.expires_at.timestamp()
+        );
+
+        session.identity.username = Some("alice".to_string());
+        store.save_session(&session).await.unwrap();
+        let loaded = store.load_session(&session.id).await.unwrap().unwrap();
+        assert_eq!(loaded.identity.username.as_deref(), Some("alice"));
+
+        store.delete_session(&session.id).await.unwrap();
+        assert!(store.load_session(&session.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_and_expired_sessions_are_not_loaded() {
+        let store = TowerSessionStore::new(MemoryStore::default());
+        assert!(store.load_session("not-an-id").await.unwrap().is_none());
+        assert!(store
+            .load_session(&Id::default().to_string())
+            .await
+            .unwrap()
+            .is_none());
+
+        let id = store
+            .create_session(&session(-chrono::Duration::seconds(1)))
+            .await
+            .unwrap();
+        assert!(store.load_session(&id).await.unwrap().is_none());
+    }
+}
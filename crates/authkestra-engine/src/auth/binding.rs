@@ -0,0 +1,150 @@
+//! Binding a request's JWT to its session.
+//!
+//! Apps that issue both a session cookie and a JWT can require that a request
+//! carrying both presents them for the same user, so a token leaked from one
+//! account cannot be replayed alongside another account's cookie. The
+//! framework adapters run the check in their `BoundAuth` extractors.
+
+use crate::auth::session::Session;
+use crate::token::{Claims, TokenManager};
+
+/// Decides whether a session and a token belong together.
+pub trait TokenBinding: Send + Sync + 'static {
+    /// Whether `claims`, validated by `tokens`, may be used alongside `session`.
+    fn is_bound(session: &Session, claims: &Claims, tokens: &TokenManager) -> bool;
+}
+
+/// Binds a token to a session of the same user.
+///
+/// The token's `sub` must equal the subject the token manager's
+/// [`SubjectSource`](crate::token::SubjectSource) derives from the session's
+/// identity, as [`TokenManager::issue_user_token`] would put it. Tokens
+/// carrying the identity, as issued ones do, must also have the same
+/// provider-qualified [`Identity::subject`](crate::auth::Identity::subject),
+/// so a `google:123` token is not accepted alongside a `github:123` session.
+pub struct SameSubject;
+
+impl TokenBinding for SameSubject {
+    fn is_bound(session: &Session, claims: &Claims, tokens: &TokenManager) -> bool {
+        let same_sub = tokens
+            .subject_for(&session.identity)
+            .is_ok_and(|sub| sub == claims.sub);
+        same_sub
+            && claims
+                .identity
+                .as_ref()
+                .is_none_or(|identity| identity.subject() == session.identity.subject())
+    }
+}
+
+/// Run the binding check `B` on a session and token presented together.
+///
+/// A mismatch is reported as a security event: a `WARN` event with target
+/// `authkestra::security` and `event = "token_binding_mismatch"`, carrying the
+/// session id and both subjects, for subscribers that alert on it.
+pub fn check_token_binding<B: TokenBinding>(
+    session: &Session,
+    claims: &Claims,
+    tokens: &TokenManager,
+) -> bool {
+    if B::is_bound(session, claims, tokens) {
+        return true;
+    }
+    tracing::warn!(
+        target: "authkestra::security",
+        event = "token_binding_mismatch",
+        session_id = %session.id,
+        session_subject = %session.identity.subject(),
+        token_subject = %claims.sub,
+        "session and token belong to different subjects"
+    );
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Identity;
+    use std::collections::HashMap;
+
+    fn identity(provider_id: &str, external_id: &str) -> Identity {
+        Identity {
+            provider_id: provider_id.to_string(),
+            external_id: external_id.to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        }
+    }
+
+    fn session(external_id: &str) -> Session {
+        Session {
+            id: "sid".to_string(),
+            identity: identity("github", external_id),
+            expires_at: chrono::Utc::now(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    fn tokens() -> TokenManager {
+        TokenManager::new(b"secret", None)
+    }
+
+    #[test]
+    fn test_same_subject() {
+        let claims: Claims =
+            serde_json::from_value(serde_json::json!({ "sub": "alice", "exp": 0, "iat": 0 }))
+                .unwrap();
+        assert!(check_token_binding::<SameSubject>(
+            &session("alice"),
+            &claims,
+            &tokens()
+        ));
+        assert!(!check_token_binding::<SameSubject>(
+            &session("bob"),
+            &claims,
+            &tokens()
+        ));
+    }
+
+    #[test]
+    fn test_same_subject_uses_the_subject_source() {
+        let tokens = tokens().with_subject_source(crate::token::SubjectSource::Subject);
+        let claims: Claims = serde_json::from_value(
+            serde_json::json!({ "sub": "github:alice", "exp": 0, "iat": 0 }),
+        )
+        .unwrap();
+        assert!(check_token_binding::<SameSubject>(
+            &session("alice"),
+            &claims,
+            &tokens
+        ));
+        assert!(!check_token_binding::<SameSubject>(
+            &session("bob"),
+            &claims,
+            &tokens
+        ));
+    }
+
+    #[test]
+    fn test_same_subject_compares_the_provider() {
+        let mut claims: Claims =
+            serde_json::from_value(serde_json::json!({ "sub": "123", "exp": 0, "iat": 0 }))
+                .unwrap();
+        claims.identity = Some(identity("github", "123"));
+        assert!(check_token_binding::<SameSubject>(
+            &session("123"),
+            &claims,
+            &tokens()
+        ));
+
+        claims.identity = Some(identity("google", "123"));
+        assert!(!check_token_binding::<SameSubject>(
+            &session("123"),
+            &claims,
+            &tokens()
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+//! A read-through cache in front of a [`SessionStore`].
+
+use crate::auth::error::AuthError;
+use crate::auth::session::{Session, SessionStore};
+use crate::store::KvStore;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Caches the sessions loaded from another [`SessionStore`].
+///
+/// Useful in front of a SQL store that would otherwise be queried on every
+/// authenticated request. Sessions are cached in a [`KvStore`] (a
+/// `MemoryStore` per instance, or Redis shared between instances) for the
+/// configured TTL, or until the session expires if that is sooner. Missing
+/// sessions are not cached.
+///
+/// Writes go to the inner store first, then the cached copy is dropped, so
+/// the next load reads the new state. [`SessionStore::delete_sessions_before`]
+/// cannot tell which cached entries it removed, so those sessions keep
+/// loading from a per-instance cache until their entry expires; keep the TTL
+/// short.
+pub struct CachedSessionStore {
+    inner: Arc<dyn SessionStore>,
+    cache: Box<dyn KvStore<Session>>,
+    ttl: Duration,
+}
+
+impl CachedSessionStore {
+    /// Default time a loaded session is reused for.
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+    /// Wrap `inner`, caching its sessions in `cache`.
+    pub fn new(inner: Arc<dyn SessionStore>, cache: impl KvStore<Session>) -> Self {
+        Self {
+            inner,
+            cache: Box::new(cache),
+            ttl: Self::DEFAULT_TTL,
+        }
+    }
+
+    /// Set how long a loaded session is reused for.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn cache_key(id: &str) -> String {
+        format!("session_cache:{id}")
+    }
+
+    async fn invalidate(&self, id: &str) -> Result<(), AuthError> {
+        self.cache
+            .delete(&Self::cache_key(id))
+            .await
+            .map_err(|e| AuthError::Session(format!("Failed to invalidate cached session: {e}")))
+    }
+}
+
+#[async_trait]
+impl SessionStore for CachedSessionStore {
+    #[tracing::instrument(skip(self))]
+    async fn load_session(&self, id: &str) -> Result<Option<Session>, AuthError> {
+        let key = Self::cache_key(id);
+        match self.cache.get(&key).await {
+            Ok(Some(session)) => {
+                tracing::debug!("session served from cache");
+                return Ok(Some(session));
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(error = %e, "session cache lookup failed"),
+        }
+
+        let session = self.inner.load_session(id).await?;
+        if let Some(session) = &session {
+            // Expired sessions get a zero TTL and are not cached.
+            let ttl = session
+                .remaining_ttl()
+                .and_then(|ttl| ttl.to_std().ok())
+                .unwrap_or_default()
+                .min(self.ttl);
+            if !ttl.is_zero() {
+                if let Err(e) = self.cache.set(&key, session.clone(), ttl).await {
+                    tracing::warn!(error = %e, "failed to write session cache");
+                }
+            }
+        }
+        Ok(session)
+    }
+
+    async fn save_session(&self, session: &Session) -> Result<(), AuthError> {
+        self.inner.save_session(session).await?;
+        self.invalidate(&session.id).await
+    }
+
+    async fn delete_session(&self, id: &str) -> Result<(), AuthError> {
+        self.inner.delete_session(id).await?;
+        self.invalidate(id).await
+    }
+
+    async fn create_session(&self, session: &Session) -> Result<String, AuthError> {
+        let id = self.inner.create_session(session).await?;
+        self.invalidate(&id).await?;
+        Ok(id)
+    }
+
+    async fn delete_sessions_before(
+        &self,
+        cutoff: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, AuthError> {
+        self.inner.delete_sessions_before(cutoff).await
+    }
+
+    async fn ping(&self) -> Result<(), AuthError> {
+        self.inner.ping().await
+    }
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+    use super::*;
+    use crate::auth::Identity;
+    use crate::store::memory::MemoryStore;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Stands in for a SQL store, counting how often sessions are loaded.
+    #[derive(Default)]
+    struct CountingStore {
+        sessions: MemoryStore<Session>,
+        loads: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SessionStore for CountingStore {
+        async fn load_session(&self, id: &str) -> Result<Option<Session>, AuthError> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            self.sessions.load_session(id).await
+        }
+
+        async fn save_session(&self, session: &Session) -> Result<(), AuthError> {
+            self.sessions.save_session(session).await
+        }
+
+        async fn delete_session(&self, id: &str) -> Result<(), AuthError> {
+            self.sessions.delete_session(id).await
+        }
+    }
+
+    fn session(id: &str, expires_in: chrono::Duration) -> Session {
+        Session {
+            id: id.to_string(),
+            identity: Identity {
+                provider_id: "mock".to_string(),
+                external_id: "alice".to_string(),
+                email: None,
+                email_verified: None,
+                username: None,
+                attributes: HashMap::new(),
+                attributes_multi: HashMap::new(),
+            },
+            expires_at: chrono::Utc::now() + expires_in,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    fn stores() -> (Arc<CountingStore>, CachedSessionStore) {
+        let inner = Arc::new(CountingStore::default());
+        let cached = CachedSessionStore::new(inner.clone(), MemoryStore::<Session>::new())
+            .with_ttl(Duration::from_secs(60));
+        (inner, cached)
+    }
+
+    #[tokio::test]
+    async fn test_repeated_loads_hit_the_inner_store_once() {
+        let (inner, cached) = stores();
+        cached
+            .save_session(&session("sid", chrono::Duration::hours(1)))
+            .await
+            .unwrap();
+
+        for _ in 0..3 {
+            let loaded = cached.load_session("sid").await.unwrap().unwrap();
+            assert_eq!(loaded.identity.external_id, "alice");
+        }
+        assert_eq!(inner.loads.load(Ordering::SeqCst), 1);
+
+        // Missing sessions are not cached.
+        for _ in 0..2 {
+            assert!(cached.load_session("unknown").await.unwrap().is_none());
+        }
+        assert_eq!(inner.loads.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_writes_invalidate_the_cache() {
+        let (inner, cached) = stores();
+        let mut stored = session("sid", chrono::Duration::hours(1));
+        cached.save_session(&stored).await.unwrap();
+        cached.load_session("sid").await.unwrap().unwrap();
+
+        stored.identity.username = Some("alice2".to_string());
+        cached.save_session(&stored).await.unwrap();
+        let loaded = cached.load_session("sid").await.unwrap().unwrap();
+        assert_eq!(loaded.identity.username.as_deref(), Some("alice2"));
+        assert_eq!(inner.loads.load(Ordering::SeqCst), 2);
+
+        cached.delete_session("sid").await.unwrap();
+        assert!(cached.load_session("sid").await.unwrap().is_none());
+        assert!(inner.load_session("sid").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl_is_bounded_by_the_session_expiry() {
+        let (inner, cached) = stores();
+        cached
+            .save_session(&session("sid", chrono::Duration::milliseconds(200)))
+            .await
+            .unwrap();
+
+        assert!(cached.load_session("sid").await.unwrap().is_some());
+        assert!(cached.load_session("sid").await.unwrap().is_some());
+        assert_eq!(inner.loads.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        // The cached entry expired with the session, so the inner store is asked again.
+        assert!(cached.load_session("sid").await.unwrap().is_none());
+        assert_eq!(inner.loads.load(Ordering::SeqCst), 2);
+    }
+}
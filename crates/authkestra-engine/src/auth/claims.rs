@@ -0,0 +1,124 @@
+//! Accessors shared by claim types so scope checks and subject extraction
+//! work the same for [`Claims`](crate::token::Claims) and user-defined structs.
+//!
+//! Custom claim structs usually get these from `#[derive(JwtClaims)]` in
+//! `authkestra-macros`.
+
+/// Claims that carry granted OAuth2 scopes.
+pub trait HasScopes {
+    /// The granted scopes, in the order they appear in the token.
+    fn scopes(&self) -> Vec<&str>;
+
+    /// Whether `scope` was granted.
+    fn has_scope(&self, scope: &str) -> bool {
+        self.scopes().contains(&scope)
+    }
+
+    /// Whether every scope in `required` was granted.
+    fn has_all_scopes(&self, required: &[&str]) -> bool {
+        let granted = self.scopes();
+        required.iter().all(|scope| granted.contains(scope))
+    }
+}
+
+/// Claims that identify a subject (the `sub` claim).
+pub trait HasSubject {
+    /// The subject the token was issued for.
+    fn subject(&self) -> &str;
+}
+
+/// Claims that identify a single token (the `jti` and `exp` claims).
+pub trait HasTokenId {
+    /// The token's unique id, if it carries one.
+    fn token_id(&self) -> Option<&str>;
+
+    /// When the token expires, in seconds since the Unix epoch.
+    fn expires_at(&self) -> u64;
+}
+
+/// Field types accepted by `#[jwt(scopes)]`.
+///
+/// Strings are treated as space-delimited (RFC 6749 §3.3); lists are taken as is.
+#[doc(hidden)]
+pub trait ScopeField {
+    /// The scopes held by the field.
+    fn scope_list(&self) -> Vec<&str>;
+}
+
+impl ScopeField for String {
+    fn scope_list(&self) -> Vec<&str> {
+        self.split_whitespace().collect()
+    }
+}
+
+impl ScopeField for Vec<String> {
+    fn scope_list(&self) -> Vec<&str> {
+        self.iter().map(String::as_str).collect()
+    }
+}
+
+impl<T: ScopeField> ScopeField for Option<T> {
+    fn scope_list(&self) -> Vec<&str> {
+        self.as_ref()
+            .map(ScopeField::scope_list)
+            .unwrap_or_default()
+    }
+}
+
+impl HasScopes for crate::token::Claims {
+    fn scopes(&self) -> Vec<&str> {
+        self.scope.scope_list()
+    }
+}
+
+impl HasSubject for crate::token::Claims {
+    fn subject(&self) -> &str {
+        &self.sub
+    }
+}
+
+impl HasTokenId for crate::token::Claims {
+    fn token_id(&self) -> Option<&str> {
+        self.jti.as_deref()
+    }
+
+    fn expires_at(&self) -> u64 {
+        self.exp as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_fields() {
+        assert_eq!(
+            "read  write".to_string().scope_list(),
+            vec!["read", "write"]
+        );
+        assert_eq!(
+            vec!["read".to_string(), "admin:all".to_string()].scope_list(),
+            vec!["read", "admin:all"]
+        );
+        assert!(None::<String>.scope_list().is_empty());
+        assert_eq!(Some("read".to_string()).scope_list(), vec!["read"]);
+    }
+
+    #[test]
+    fn test_has_all_scopes() {
+        struct Granted(Vec<String>);
+        impl HasScopes for Granted {
+            fn scopes(&self) -> Vec<&str> {
+                self.0.scope_list()
+            }
+        }
+
+        let granted = Granted(vec!["read".to_string(), "write".to_string()]);
+        assert!(granted.has_scope("read"));
+        assert!(!granted.has_scope("admin"));
+        assert!(granted.has_all_scopes(&["read", "write"]));
+        assert!(!granted.has_all_scopes(&["read", "admin"]));
+        assert!(granted.has_all_scopes(&[]));
+    }
+}
@@ -0,0 +1,290 @@
+//! Client IP resolution behind reverse proxies.
+//!
+//! Forwarding headers are only honoured when the connection comes from a
+//! trusted proxy. The chain is then walked from the right, skipping trusted
+//! hops, and the first untrusted address is taken as the client. Anything to
+//! its left was supplied by the client and may be spoofed.
+
+use crate::auth::AuthError;
+use http::HeaderMap;
+use std::net::IpAddr;
+
+/// An address or CIDR range of trusted reverse proxies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    fn parse(s: &str) -> Result<Self, AuthError> {
+        let invalid = || AuthError::Config(format!("Invalid trusted proxy: {s}"));
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.trim().parse().map_err(|_| invalid())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.trim().parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`) are treated as IPv4.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6
+            .to_ipv4_mapped()
+            .map(IpAddr::V4)
+            .unwrap_or(IpAddr::V6(v6)),
+        v4 => v4,
+    }
+}
+
+/// The reverse proxies whose `Forwarded` and `X-Forwarded-For` headers are trusted.
+///
+/// The default trusts no one, so the socket address is always used.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+    networks: Vec<Network>,
+}
+
+impl TrustedProxies {
+    /// Trust the given addresses or CIDR ranges, e.g. `"10.0.0.0/8"` or `"::1"`.
+    pub fn new<I, S>(proxies: I) -> Result<Self, AuthError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let networks = proxies
+            .into_iter()
+            .map(|p| Network::parse(p.as_ref()))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { networks })
+    }
+
+    /// Whether `ip` belongs to a trusted proxy.
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|n| n.contains(ip))
+    }
+
+    /// Resolve the client address of a request received from `peer`.
+    ///
+    /// `Forwarded` (RFC 7239) is used when present, otherwise `X-Forwarded-For`.
+    /// If every hop is trusted, the leftmost address is returned. A malformed
+    /// hop stops the walk at the last valid address.
+    pub fn client_ip(&self, headers: &HeaderMap, peer: IpAddr) -> IpAddr {
+        let values = |name| -> Vec<&str> {
+            headers
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .collect()
+        };
+        self.resolve(
+            peer,
+            &values(http::header::FORWARDED.as_str()),
+            &values("x-forwarded-for"),
+        )
+    }
+
+    /// Like [`TrustedProxies::client_ip`], taking the raw `Forwarded` and
+    /// `X-Forwarded-For` header values, in the order received.
+    pub fn resolve(&self, peer: IpAddr, forwarded: &[&str], x_forwarded_for: &[&str]) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+
+        let mut client = peer;
+        for hop in forwarded_chain(forwarded, x_forwarded_for).iter().rev() {
+            let Some(ip) = hop else {
+                break;
+            };
+            client = *ip;
+            if !self.is_trusted(client) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// The forwarding chain, left to right. Unparseable hops are `None`.
+fn forwarded_chain(forwarded: &[&str], x_forwarded_for: &[&str]) -> Vec<Option<IpAddr>> {
+    if !forwarded.is_empty() {
+        return forwarded
+            .iter()
+            .flat_map(|v| v.split(','))
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(k, _)| k.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, v)| parse_node(v.trim().trim_matches('"')))
+            })
+            .collect();
+    }
+
+    x_forwarded_for
+        .iter()
+        .flat_map(|v| v.split(','))
+        .map(|hop| parse_node(hop.trim()))
+        .collect()
+}
+
+/// Parse `1.2.3.4`, `1.2.3.4:80`, `::1` or `[::1]:80`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(ip) = node.parse() {
+        return Some(ip);
+    }
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    let (ip, port) = node.rsplit_once(':')?;
+    port.parse::<u16>().ok()?;
+    ip.parse::<std::net::Ipv4Addr>().ok().map(IpAddr::V4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(
+                http::header::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                value.parse().unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_untrusted_peer_ignores_headers() {
+        let proxies = TrustedProxies::default();
+        let spoofed = headers(&[("x-forwarded-for", "1.1.1.1")]);
+        assert_eq!(
+            proxies.client_ip(&spoofed, ip("203.0.113.7")),
+            ip("203.0.113.7")
+        );
+
+        let proxies = TrustedProxies::new(["10.0.0.0/8"]).unwrap();
+        assert_eq!(
+            proxies.client_ip(&spoofed, ip("203.0.113.7")),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn test_rightmost_untrusted_hop_wins() {
+        let proxies = TrustedProxies::new(["10.0.0.0/8", "192.168.1.1"]).unwrap();
+        // The client prepended a fake address; the proxies appended the real one.
+        let spoofed = headers(&[("x-forwarded-for", "1.1.1.1, 198.51.100.23, 192.168.1.1")]);
+        assert_eq!(
+            proxies.client_ip(&spoofed, ip("10.0.0.2")),
+            ip("198.51.100.23")
+        );
+
+        // Every hop trusted: the leftmost one is the client.
+        let internal = headers(&[("x-forwarded-for", "10.1.1.1, 192.168.1.1")]);
+        assert_eq!(proxies.client_ip(&internal, ip("10.0.0.2")), ip("10.1.1.1"));
+
+        // No header: the trusted proxy itself.
+        assert_eq!(
+            proxies.client_ip(&HeaderMap::new(), ip("10.0.0.2")),
+            ip("10.0.0.2")
+        );
+    }
+
+    #[test]
+    fn test_multiple_xff_headers_are_concatenated() {
+        let proxies = TrustedProxies::new(["10.0.0.0/8"]).unwrap();
+        let split = headers(&[
+            ("x-forwarded-for", "1.1.1.1"),
+            ("x-forwarded-for", "198.51.100.23, 10.0.0.5"),
+        ]);
+        assert_eq!(
+            proxies.client_ip(&split, ip("10.0.0.2")),
+            ip("198.51.100.23")
+        );
+    }
+
+    #[test]
+    fn test_malformed_hop_stops_the_walk() {
+        let proxies = TrustedProxies::new(["10.0.0.0/8"]).unwrap();
+        let garbage = headers(&[("x-forwarded-for", "1.1.1.1, not-an-ip, 10.0.0.5")]);
+        assert_eq!(proxies.client_ip(&garbage, ip("10.0.0.2")), ip("10.0.0.5"));
+    }
+
+    #[test]
+    fn test_forwarded_header_takes_precedence() {
+        let proxies = TrustedProxies::new(["10.0.0.0/8", "2001:db8::/32"]).unwrap();
+        let both = headers(&[
+            ("x-forwarded-for", "1.1.1.1"),
+            (
+                "forwarded",
+                "for=1.1.1.1, for=\"[2001:db8:cafe::17]:4711\";proto=https, For=198.51.100.23:8080;by=10.0.0.1",
+            ),
+        ]);
+        assert_eq!(
+            proxies.client_ip(&both, ip("10.0.0.2")),
+            ip("198.51.100.23")
+        );
+
+        let v6 = headers(&[("forwarded", "for=1.1.1.1, for=\"[2001:db8:cafe::17]:4711\"")]);
+        assert_eq!(proxies.client_ip(&v6, ip("10.0.0.2")), ip("1.1.1.1"));
+
+        let obfuscated = headers(&[("forwarded", "for=1.1.1.1, for=_hidden, for=10.0.0.5")]);
+        assert_eq!(
+            proxies.client_ip(&obfuscated, ip("10.0.0.2")),
+            ip("10.0.0.5")
+        );
+    }
+
+    #[test]
+    fn test_trusted_proxy_parsing() {
+        assert!(matches!(
+            TrustedProxies::new(["10.0.0.0/33"]),
+            Err(AuthError::Config(_))
+        ));
+        assert!(matches!(
+            TrustedProxies::new(["not-an-ip"]),
+            Err(AuthError::Config(_))
+        ));
+        assert!(TrustedProxies::new(["::/0"]).is_ok());
+
+        let proxies = TrustedProxies::new(["127.0.0.1", "0.0.0.0/0"]).unwrap();
+        assert!(proxies.is_trusted(ip("8.8.8.8")));
+        assert!(proxies.is_trusted(ip("::ffff:8.8.8.8")));
+        assert!(!proxies.is_trusted(ip("::1")));
+    }
+}
@@ -0,0 +1,119 @@
+//! Audit records of the scopes users consented to.
+//!
+//! After a successful OAuth login the callback builds a [`ConsentRecord`] and
+//! hands it to the engine's [`ConsentSink`], so applications can keep an audit
+//! trail of the data access each user granted. The default sink, `()`,
+//! discards the records.
+
+use crate::auth::scopes::Scopes;
+use crate::auth::state::{Identity, OAuth2State, OAuthToken};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The scopes granted to the application by one login.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsentRecord {
+    /// The user, as [`Identity::subject`].
+    pub subject: String,
+    /// The provider the user logged in with.
+    pub provider: String,
+    /// The scopes the provider granted.
+    pub scopes: Scopes,
+    /// Scopes requested at login that the provider did not grant.
+    pub denied: Scopes,
+    /// When the login completed.
+    pub granted_at: DateTime<Utc>,
+}
+
+impl ConsentRecord {
+    /// Build the record for a completed login.
+    ///
+    /// The granted scopes come from the token response. Providers may omit
+    /// `scope` when it equals the request (RFC 6749 §5.1), in which case the
+    /// requested scopes recorded in `state` are taken as granted.
+    pub fn from_login(identity: &Identity, state: &OAuth2State, token: &OAuthToken) -> Self {
+        let scopes = match &token.scope {
+            Some(scope) => Scopes::parse(scope),
+            None => state.scopes.clone(),
+        };
+        Self {
+            subject: identity.subject(),
+            provider: state.provider_id.clone(),
+            denied: state.scopes.difference(&scopes),
+            scopes,
+            granted_at: Utc::now(),
+        }
+    }
+}
+
+/// Receives a [`ConsentRecord`] for every successful OAuth login.
+///
+/// Sinks cannot fail the login: implementations should handle and log their
+/// own errors.
+#[async_trait]
+pub trait ConsentSink: Send + Sync {
+    /// Record the consent of one login.
+    async fn record(&self, record: ConsentRecord);
+}
+
+/// Discards consent records.
+#[async_trait]
+impl ConsentSink for () {
+    async fn record(&self, _record: ConsentRecord) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn login(requested: &str, granted: Option<&str>) -> ConsentRecord {
+        let identity = Identity {
+            provider_id: "github".to_string(),
+            external_id: "123".to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        };
+        let state = OAuth2State {
+            state: "state".to_string(),
+            nonce: None,
+            code_verifier: None,
+            success_url: None,
+            remember: false,
+            correlation_id: None,
+            scopes: Scopes::parse(requested),
+            provider_id: "github".to_string(),
+            expires_at: 0,
+        };
+        let token = OAuthToken {
+            access_token: "access".to_string(),
+            token_type: "Bearer".to_string(),
+            expires_in: None,
+            issued_at: chrono::Utc::now(),
+            refresh_token: None,
+            scope: granted.map(str::to_string),
+            id_token: None,
+        };
+        ConsentRecord::from_login(&identity, &state, &token)
+    }
+
+    #[test]
+    fn test_record_captures_denied_scopes() {
+        let record = login("read:user repo", Some("read:user"));
+        assert_eq!(record.subject, "github:123");
+        assert_eq!(record.provider, "github");
+        assert_eq!(record.scopes, Scopes::parse("read:user"));
+        assert_eq!(record.denied, Scopes::parse("repo"));
+    }
+
+    #[test]
+    fn test_omitted_scope_means_requested_scopes_were_granted() {
+        let record = login("read:user repo", None);
+        assert_eq!(record.scopes, Scopes::parse("read:user repo"));
+        assert!(record.denied.is_empty());
+    }
+}
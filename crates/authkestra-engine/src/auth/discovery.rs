@@ -28,7 +28,7 @@ impl ProviderMetadata {
     /// Also returns the parsed max-age from the Cache-Control header if present.
     pub async fn discover(
         issuer_url: &str,
-        client: reqwest::Client,
+        client: impl Into<crate::auth::http_client::HttpExecutor>,
     ) -> Result<(Self, Option<std::time::Duration>), AuthError> {
         let mut url = url::Url::parse(issuer_url)
             .map_err(|e| AuthError::Discovery(format!("Invalid issuer URL: {e}")))?;
@@ -40,10 +40,11 @@ impl ProviderMetadata {
         }
 
         let response = client
+            .into()
             .get(url)
             .send()
             .await
-            .map_err(|_| AuthError::Network)?;
+            .map_err(|e| crate::auth::http_client::map_error(&e))?;
 
         let mut cache_max_age = None;
         if let Some(cache_control) = response.headers().get(reqwest::header::CACHE_CONTROL) {
@@ -59,10 +60,11 @@ impl ProviderMetadata {
             }
         }
 
-        let metadata = response
-            .json::<ProviderMetadata>()
-            .await
-            .map_err(|e| AuthError::Discovery(format!("Failed to parse metadata: {e}")))?;
+        let metadata = response.json::<ProviderMetadata>().await.map_err(|e| {
+            crate::auth::http_client::map_body_error(&e, |e| {
+                AuthError::Discovery(format!("Failed to parse metadata: {e}"))
+            })
+        })?;
 
         Ok((metadata, cache_max_age))
     }
@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::Level;
 
 /// Errors that can occur during the authentication process.
 #[derive(Debug, thiserror::Error)]
@@ -15,12 +18,28 @@ pub enum AuthError {
     /// A network error occurred during communication with the provider
     #[error("Network error")]
     Network,
+    /// A request to the provider did not complete within the configured timeout
+    #[error("Upstream request timed out")]
+    Timeout,
     /// An error occurred during session management
     #[error("Session error: {0}")]
     Session(String),
     /// An error occurred during token processing
     #[error("Token error: {0}")]
     Token(String),
+    /// The presented token is not valid, e.g. a bad signature, an expired
+    /// token or an unknown signing key. Strategies treat it as no credentials.
+    #[error("Invalid token: {0}")]
+    InvalidToken(String),
+    /// The operation needs a recent step-up: the token's `acr` is too low or it has expired
+    #[error("Step-up required")]
+    StepUpRequired,
+    /// A one-time token was presented again
+    #[error("Token has already been used")]
+    TokenReplayed,
+    /// The provider returned an email address it has not verified
+    #[error("Email address is not verified")]
+    UnverifiedEmail,
     /// The CSRF state parameter does not match the expected value
     #[error("CSRF state mismatch")]
     CsrfMismatch,
@@ -30,6 +49,218 @@ pub enum AuthError {
     /// A required component (e.g., SessionManager, TokenManager) is missing
     #[error("Missing component: {0}")]
     ComponentMissing(String),
+    /// The library or a provider is misconfigured, e.g. an unparsable URL
+    #[error("Configuration error: {0}")]
+    Config(String),
+    /// A request parameter supplied by the client is invalid
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    /// A password could not be hashed, or a stored hash could not be read
+    #[error("Password hashing error: {0}")]
+    PasswordHash(String),
+}
+
+impl AuthError {
+    /// The kind of the error, without its message.
+    ///
+    /// Logged as the `kind` field and used as the key of [`ErrorLevels`].
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            AuthError::Provider(_) => ErrorKind::Provider,
+            AuthError::InvalidCredentials => ErrorKind::InvalidCredentials,
+            AuthError::InvalidCode => ErrorKind::InvalidCode,
+            AuthError::Network => ErrorKind::Network,
+            AuthError::Timeout => ErrorKind::Timeout,
+            AuthError::Session(_) => ErrorKind::Session,
+            AuthError::Token(_) => ErrorKind::Token,
+            AuthError::InvalidToken(_) => ErrorKind::InvalidToken,
+            AuthError::StepUpRequired => ErrorKind::StepUpRequired,
+            AuthError::TokenReplayed => ErrorKind::TokenReplayed,
+            AuthError::UnverifiedEmail => ErrorKind::UnverifiedEmail,
+            AuthError::CsrfMismatch => ErrorKind::CsrfMismatch,
+            AuthError::Discovery(_) => ErrorKind::Discovery,
+            AuthError::ComponentMissing(_) => ErrorKind::ComponentMissing,
+            AuthError::Config(_) => ErrorKind::Config,
+            AuthError::InvalidRequest(_) => ErrorKind::InvalidRequest,
+            AuthError::PasswordHash(_) => ErrorKind::PasswordHash,
+        }
+    }
+
+    /// The default level this error is logged at; see [`ErrorKind::default_level`].
+    pub fn log_level(&self) -> Level {
+        self.kind().default_level()
+    }
+
+    /// Logs `message` with this error at its default [`log_level`](Self::log_level),
+    /// with `error` and `kind` fields.
+    ///
+    /// For components used without an [`Engine`](crate::Engine). Code holding
+    /// an engine logs through its [`ErrorLevels`] instead, so overrides apply.
+    pub fn log(&self, message: impl std::fmt::Display) {
+        ErrorLevels::default().log(self, message);
+    }
+}
+
+/// The kind of an [`AuthError`], as returned by [`AuthError::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// [`AuthError::Provider`]
+    Provider,
+    /// [`AuthError::InvalidCredentials`]
+    InvalidCredentials,
+    /// [`AuthError::InvalidCode`]
+    InvalidCode,
+    /// [`AuthError::Network`]
+    Network,
+    /// [`AuthError::Timeout`]
+    Timeout,
+    /// [`AuthError::Session`]
+    Session,
+    /// [`AuthError::Token`]
+    Token,
+    /// [`AuthError::InvalidToken`]
+    InvalidToken,
+    /// [`AuthError::StepUpRequired`]
+    StepUpRequired,
+    /// [`AuthError::TokenReplayed`]
+    TokenReplayed,
+    /// [`AuthError::UnverifiedEmail`]
+    UnverifiedEmail,
+    /// [`AuthError::CsrfMismatch`]
+    CsrfMismatch,
+    /// [`AuthError::Discovery`]
+    Discovery,
+    /// [`AuthError::ComponentMissing`]
+    ComponentMissing,
+    /// [`AuthError::Config`]
+    Config,
+    /// [`AuthError::InvalidRequest`]
+    InvalidRequest,
+    /// [`AuthError::PasswordHash`]
+    PasswordHash,
+}
+
+impl ErrorKind {
+    /// A stable, snake_case name for the kind, e.g. `"csrf_mismatch"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::Provider => "provider",
+            ErrorKind::InvalidCredentials => "invalid_credentials",
+            ErrorKind::InvalidCode => "invalid_code",
+            ErrorKind::Network => "network",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Session => "session",
+            ErrorKind::Token => "token",
+            ErrorKind::InvalidToken => "invalid_token",
+            ErrorKind::StepUpRequired => "step_up_required",
+            ErrorKind::TokenReplayed => "token_replayed",
+            ErrorKind::UnverifiedEmail => "unverified_email",
+            ErrorKind::CsrfMismatch => "csrf_mismatch",
+            ErrorKind::Discovery => "discovery",
+            ErrorKind::ComponentMissing => "component_missing",
+            ErrorKind::Config => "config",
+            ErrorKind::InvalidRequest => "invalid_request",
+            ErrorKind::PasswordHash => "password_hash",
+        }
+    }
+
+    /// The level errors of this kind are logged at unless overridden.
+    ///
+    /// Failures caused by the client (bad credentials, codes or tokens, a
+    /// CSRF mismatch) are `INFO`, since they are expected during attacks and
+    /// expiry; upstream failures (network, timeouts, provider and discovery
+    /// errors) and token errors are `WARN`; store and configuration errors
+    /// are `ERROR`.
+    pub fn default_level(self) -> Level {
+        match self {
+            ErrorKind::InvalidCredentials
+            | ErrorKind::InvalidCode
+            | ErrorKind::InvalidToken
+            | ErrorKind::StepUpRequired
+            | ErrorKind::UnverifiedEmail
+            | ErrorKind::CsrfMismatch
+            | ErrorKind::InvalidRequest => Level::INFO,
+            ErrorKind::Provider
+            | ErrorKind::Network
+            | ErrorKind::Timeout
+            | ErrorKind::Token
+            | ErrorKind::TokenReplayed
+            | ErrorKind::Discovery => Level::WARN,
+            ErrorKind::Session
+            | ErrorKind::ComponentMissing
+            | ErrorKind::Config
+            | ErrorKind::PasswordHash => Level::ERROR,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Overrides the level [`AuthError`]s are logged at, by [`ErrorKind`].
+///
+/// Set it on the engine with
+/// [`EngineBuilder::error_levels`](crate::EngineBuilder::error_levels); the
+/// engine and the framework adapters log through it:
+///
+/// ```rust
+/// use authkestra_engine::auth::{ErrorKind, ErrorLevels};
+/// use authkestra_engine::Engine;
+/// use tracing::Level;
+///
+/// let engine = Engine::builder()
+///     .error_levels(
+///         ErrorLevels::new()
+///             .with_level(ErrorKind::Network, Level::ERROR)
+///             .with_level(ErrorKind::CsrfMismatch, Level::DEBUG),
+///     )
+///     .build();
+/// assert_eq!(engine.error_levels.level(ErrorKind::Network), Level::ERROR);
+/// ```
+///
+/// Cloning is cheap: the mapping is shared between clones.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorLevels {
+    levels: Arc<BTreeMap<ErrorKind, Level>>,
+}
+
+impl ErrorLevels {
+    /// An empty mapping: every kind keeps its default level.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log errors of `kind` at `level`.
+    pub fn with_level(mut self, kind: ErrorKind, level: Level) -> Self {
+        Arc::make_mut(&mut self.levels).insert(kind, level);
+        self
+    }
+
+    /// The level errors of `kind` are logged at: the override, or
+    /// [`ErrorKind::default_level`].
+    pub fn level(&self, kind: ErrorKind) -> Level {
+        self.levels
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| kind.default_level())
+    }
+
+    /// Logs `message` with `error` at the level of its kind, with `error`
+    /// and `kind` fields.
+    pub fn log(&self, error: &AuthError, message: impl std::fmt::Display) {
+        let kind = error.kind().as_str();
+        match self.level(error.kind()) {
+            Level::ERROR => tracing::error!(error = %error, kind, "{message}"),
+            Level::WARN => tracing::warn!(error = %error, kind, "{message}"),
+            Level::INFO => tracing::info!(error = %error, kind, "{message}"),
+            Level::DEBUG => tracing::debug!(error = %error, kind, "{message}"),
+            _ => tracing::trace!(error = %error, kind, "{message}"),
+        }
+    }
 }
 
 /// Represents an error response from an OAuth2 provider.
@@ -0,0 +1,332 @@
+//! Outbound HTTP client defaults shared by providers, discovery and JWKS fetching.
+
+use crate::auth::error::AuthError;
+use std::time::Duration;
+
+/// Default timeout applied to every outbound request (connect through response body).
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default maximum size, in bytes, of a response body read with [`read_limited`].
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 256 * 1024;
+
+/// Build a `reqwest::Client` whose requests fail after `timeout`.
+///
+/// Building only fails if the TLS backend cannot be initialized. That is
+/// logged at `ERROR`, and the client falls back to `reqwest::Client::new()`,
+/// which applies no timeout.
+pub fn with_timeout(timeout: Duration) -> reqwest::Client {
+    builder(timeout).build().unwrap_or_else(|e| {
+        tracing::error!(
+            error = %e,
+            ?timeout,
+            "failed to build HTTP client; falling back to a client without timeout"
+        );
+        reqwest::Client::new()
+    })
+}
+
+/// A `reqwest::ClientBuilder` preconfigured with `timeout`, for callers that need extra settings.
+pub fn builder(timeout: Duration) -> reqwest::ClientBuilder {
+    reqwest::Client::builder().timeout(timeout)
+}
+
+/// Map a failed request to [`AuthError::Timeout`] if it timed out, otherwise [`AuthError::Network`].
+pub fn map_error(err: &impl RequestError) -> AuthError {
+    if err.is_timeout() {
+        AuthError::Timeout
+    } else {
+        AuthError::Network
+    }
+}
+
+/// Map a failure to read or decode a response body to [`AuthError::Timeout`]
+/// if the read timed out, otherwise to `otherwise(err)`.
+pub fn map_body_error<E: RequestError>(
+    err: &E,
+    otherwise: impl FnOnce(&E) -> AuthError,
+) -> AuthError {
+    if err.is_timeout() {
+        AuthError::Timeout
+    } else {
+        otherwise(err)
+    }
+}
+
+/// Errors that tell whether the request timed out, for [`map_error`].
+pub trait RequestError: std::fmt::Display {
+    /// Whether the request failed because it timed out.
+    fn is_timeout(&self) -> bool;
+}
+
+impl RequestError for reqwest::Error {
+    fn is_timeout(&self) -> bool {
+        reqwest::Error::is_timeout(self)
+    }
+}
+
+/// Reads the body of `response`, failing with [`BodyError::TooLarge`] once it
+/// exceeds `max_bytes`.
+///
+/// Both the `Content-Length` header and the streamed body are checked, so a
+/// missing or wrong header cannot be used to send an unbounded body.
+pub async fn read_limited(
+    mut response: reqwest::Response,
+    max_bytes: usize,
+) -> Result<Vec<u8>, BodyError> {
+    if response
+        .content_length()
+        .is_some_and(|len| len > max_bytes as u64)
+    {
+        return Err(BodyError::TooLarge { max: max_bytes });
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(BodyError::TooLarge { max: max_bytes });
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// A response body that [`read_limited`] could not read.
+#[derive(Debug, thiserror::Error)]
+pub enum BodyError {
+    /// The body is larger than the limit.
+    #[error("Response body exceeds maximum size of {max} bytes")]
+    TooLarge {
+        /// The limit, in bytes.
+        max: usize,
+    },
+    /// Reading the body failed.
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+}
+
+impl RequestError for BodyError {
+    fn is_timeout(&self) -> bool {
+        matches!(self, BodyError::Reqwest(e) if e.is_timeout())
+    }
+}
+
+/// Sends the outbound requests of providers, discovery and JWKS caches.
+///
+/// Wraps a plain `reqwest::Client` or, with the `reqwest-middleware` feature,
+/// a `reqwest_middleware::ClientWithMiddleware`, so middleware (tracing
+/// headers, retries) applies to every request to the identity provider.
+/// Both convert with `.into()`.
+#[derive(Clone, Debug)]
+pub struct HttpExecutor(Executor);
+
+#[derive(Clone, Debug)]
+enum Executor {
+    Plain(reqwest::Client),
+    #[cfg(feature = "reqwest-middleware")]
+    Middleware(reqwest_middleware::ClientWithMiddleware),
+}
+
+impl HttpExecutor {
+    /// An executor whose requests fail after `timeout`.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        with_timeout(timeout).into()
+    }
+
+    /// Start a `GET` request to `url`.
+    pub fn get<U: reqwest::IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(reqwest::Method::GET, url)
+    }
+
+    /// Start a `POST` request to `url`.
+    pub fn post<U: reqwest::IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(reqwest::Method::POST, url)
+    }
+
+    fn request<U: reqwest::IntoUrl>(&self, method: reqwest::Method, url: U) -> RequestBuilder {
+        RequestBuilder(match &self.0 {
+            Executor::Plain(client) => Builder::Plain(client.request(method, url)),
+            #[cfg(feature = "reqwest-middleware")]
+            Executor::Middleware(client) => Builder::Middleware(client.request(method, url)),
+        })
+    }
+}
+
+impl Default for HttpExecutor {
+    fn default() -> Self {
+        Self::with_timeout(DEFAULT_TIMEOUT)
+    }
+}
+
+impl From<reqwest::Client> for HttpExecutor {
+    fn from(client: reqwest::Client) -> Self {
+        Self(Executor::Plain(client))
+    }
+}
+
+#[cfg(feature = "reqwest-middleware")]
+impl From<reqwest_middleware::ClientWithMiddleware> for HttpExecutor {
+    fn from(client: reqwest_middleware::ClientWithMiddleware) -> Self {
+        Self(Executor::Middleware(client))
+    }
+}
+
+/// A request being built by an [`HttpExecutor`].
+#[derive(Debug)]
+pub struct RequestBuilder(Builder);
+
+#[derive(Debug)]
+enum Builder {
+    Plain(reqwest::RequestBuilder),
+    #[cfg(feature = "reqwest-middleware")]
+    Middleware(reqwest_middleware::RequestBuilder),
+}
+
+impl RequestBuilder {
+    /// Add a header to the request.
+    pub fn header<K, V>(self, key: K, value: V) -> Self
+    where
+        reqwest::header::HeaderName: TryFrom<K>,
+        <reqwest::header::HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        reqwest::header::HeaderValue: TryFrom<V>,
+        <reqwest::header::HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        Self(match self.0 {
+            Builder::Plain(builder) => Builder::Plain(builder.header(key, value)),
+            #[cfg(feature = "reqwest-middleware")]
+            Builder::Middleware(builder) => Builder::Middleware(builder.header(key, value)),
+        })
+    }
+
+    /// Send `form` as an `application/x-www-form-urlencoded` body.
+    pub fn form<T: serde::Serialize + ?Sized>(self, form: &T) -> Self {
+        Self(match self.0 {
+            Builder::Plain(builder) => Builder::Plain(builder.form(form)),
+            #[cfg(feature = "reqwest-middleware")]
+            Builder::Middleware(builder) => Builder::Middleware(builder.form(form)),
+        })
+    }
+
+    /// Send the request.
+    pub async fn send(self) -> Result<reqwest::Response, HttpError> {
+        match self.0 {
+            Builder::Plain(builder) => builder.send().await.map_err(HttpError::from),
+            #[cfg(feature = "reqwest-middleware")]
+            Builder::Middleware(builder) => builder.send().await.map_err(HttpError::Middleware),
+        }
+    }
+}
+
+/// A request sent by an [`HttpExecutor`] that failed.
+#[derive(Debug, thiserror::Error)]
+pub enum HttpError {
+    /// The HTTP request failed.
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+    /// The request failed in the middleware chain.
+    #[cfg(feature = "reqwest-middleware")]
+    #[error(transparent)]
+    Middleware(reqwest_middleware::Error),
+}
+
+impl RequestError for HttpError {
+    fn is_timeout(&self) -> bool {
+        match self {
+            HttpError::Reqwest(e) => e.is_timeout(),
+            #[cfg(feature = "reqwest-middleware")]
+            HttpError::Middleware(reqwest_middleware::Error::Reqwest(e)) => e.is_timeout(),
+            #[cfg(feature = "reqwest-middleware")]
+            HttpError::Middleware(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{discovery::ProviderMetadata, ClientCredentialsFlow};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn slow_server(http_method: &str, endpoint: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method(http_method))
+            .and(path(endpoint))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials_timeout() {
+        let server = slow_server("POST", "/token").await;
+        let flow = ClientCredentialsFlow::new(
+            "client".to_string(),
+            "secret".to_string(),
+            format!("{}/token", server.uri()),
+        )
+        .with_timeout(Duration::from_millis(200));
+
+        let result = flow.get_token(None).await;
+        assert!(matches!(result, Err(AuthError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_discovery_timeout() {
+        let server = slow_server("GET", "/.well-known/openid-configuration").await;
+        let client = with_timeout(Duration::from_millis(200));
+
+        let result = ProviderMetadata::discover(&server.uri(), client).await;
+        assert!(matches!(result, Err(AuthError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_host_is_network_error() {
+        let client = with_timeout(Duration::from_millis(200));
+
+        let result = ProviderMetadata::discover("http://127.0.0.1:9", client).await;
+        assert!(matches!(result, Err(AuthError::Network)));
+    }
+
+    /// Answers every connection with response headers and the first byte of a
+    /// JSON body, then stalls, so only reading the body can time out.
+    async fn stalled_body_server() -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0; 4096];
+                    let _ = socket.read(&mut request).await;
+                    let _ = socket
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 100\r\n\r\n{",
+                        )
+                        .await;
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                });
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_stalled_response_body_is_timeout() {
+        let base = stalled_body_server().await;
+
+        let client = with_timeout(Duration::from_millis(200));
+        let result = ProviderMetadata::discover(&base, client).await;
+        assert!(matches!(result, Err(AuthError::Timeout)), "{result:?}");
+
+        let flow = ClientCredentialsFlow::new(
+            "client".to_string(),
+            "secret".to_string(),
+            format!("{base}/token"),
+        )
+        .with_timeout(Duration::from_millis(200));
+        let result = flow.get_token(None).await;
+        assert!(matches!(result, Err(AuthError::Timeout)), "{result:?}");
+    }
+}
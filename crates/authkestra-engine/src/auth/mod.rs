@@ -16,19 +16,77 @@ pub mod strategy;
 
 /// Errors that can occur during the authentication process.
 pub mod error;
-pub use error::AuthError;
+pub use error::{AuthError, ErrorKind, ErrorLevels};
 
 /// A unified identity structure returned by all providers.
 pub mod state;
-pub use state::{Identity, OAuth2State, OAuthToken};
+pub use state::{Identity, OAuth2State, OAuthToken, RedactedOAuthToken};
 
 /// Discovery utilities for OAuth2 providers.
+#[cfg(feature = "http")]
 pub mod discovery;
 
+/// Outbound HTTP client defaults.
+#[cfg(feature = "http")]
+pub mod http_client;
+
+/// Client IP resolution behind trusted reverse proxies.
+pub mod client_ip;
+pub use client_ip::TrustedProxies;
+
+/// Scope and subject accessors for token claims.
+pub mod claims;
+pub use claims::{HasScopes, HasSubject, HasTokenId};
+
+/// A typed set of OAuth2 scopes.
+pub mod scopes;
+pub use scopes::Scopes;
+
+/// Audit records of granted scopes.
+pub mod consent;
+pub use consent::{ConsentRecord, ConsentSink};
+
 /// Session management traits and types.
 pub mod session;
 pub use session::{Session, SessionConfig, SessionStore};
 
+/// Binding a request's JWT to its session.
+pub mod binding;
+pub use binding::{check_token_binding, SameSubject, TokenBinding};
+
+/// A read-through cache in front of a session store.
+pub mod cached_session;
+pub use cached_session::CachedSessionStore;
+
+/// Session persistence with native `async fn` in traits.
+#[cfg(feature = "native-async")]
+pub mod native_session;
+#[cfg(feature = "native-async")]
+pub use native_session::{DynCompat, NativeSessionStore};
+
+/// Per-session CSRF tokens for forms.
+pub mod csrf;
+pub use csrf::CsrfService;
+
+/// Signed, store-less sessions.
+pub mod stateless;
+pub use stateless::StatelessSession;
+
+/// KDF-agnostic password hashing.
+pub mod password;
+pub use password::{
+    PasswordCredentials, PasswordCredentialsProvider, PasswordHasher, PasswordRecord,
+    PasswordRepository,
+};
+
+/// A short-lived cache of userinfo responses.
+pub mod userinfo_cache;
+pub use userinfo_cache::UserInfoCache;
+
+/// Just-in-time provisioning of local users.
+pub mod provisioning;
+pub use provisioning::{ProvisioningUserMapper, UserRepository};
+
 /// Represents the input for an authentication method.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
@@ -93,12 +151,120 @@ pub enum SameSite {
     None,
 }
 
+/// The OIDC `prompt` authorization parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Prompt {
+    /// Do not display any authentication or consent UI.
+    None,
+    /// Force the user to re-authenticate.
+    Login,
+    /// Force the consent screen to be shown.
+    Consent,
+    /// Ask the user to select an account.
+    SelectAccount,
+}
+
+impl Prompt {
+    /// The wire value of the parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Prompt::None => "none",
+            Prompt::Login => "login",
+            Prompt::Consent => "consent",
+            Prompt::SelectAccount => "select_account",
+        }
+    }
+}
+
+impl std::str::FromStr for Prompt {
+    type Err = AuthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Prompt::None),
+            "login" => Ok(Prompt::Login),
+            "consent" => Ok(Prompt::Consent),
+            "select_account" => Ok(Prompt::SelectAccount),
+            other => Err(AuthError::InvalidRequest(format!(
+                "Invalid prompt value: {other}"
+            ))),
+        }
+    }
+}
+
+/// Optional parameters appended to the authorization request.
+#[derive(Clone, Debug, Default)]
+pub struct AuthorizationParams {
+    /// The `prompt` parameter.
+    pub prompt: Option<Prompt>,
+    /// The `login_hint` parameter, e.g. the user's email address.
+    pub login_hint: Option<String>,
+    /// Request a "remember me" session. Carried in the encrypted state cookie,
+    /// never sent to the provider.
+    pub remember: bool,
+    /// Correlation id of the login request. Carried in the encrypted state cookie,
+    /// never sent to the provider.
+    pub correlation_id: Option<String>,
+}
+
+impl AuthorizationParams {
+    /// Appends the configured parameters to an authorization URL.
+    ///
+    /// Without parameters to add, the URL is returned unchanged. Otherwise an
+    /// unparsable URL is an [`AuthError::Config`] error.
+    pub fn apply(&self, authorization_url: &str) -> Result<String, AuthError> {
+        if self.prompt.is_none() && self.login_hint.is_none() {
+            return Ok(authorization_url.to_string());
+        }
+
+        let mut url = url::Url::parse(authorization_url).map_err(|e| {
+            AuthError::Config(format!(
+                "Invalid authorization URL {authorization_url}: {e}"
+            ))
+        })?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(prompt) = self.prompt {
+                query.append_pair("prompt", prompt.as_str());
+            }
+            if let Some(login_hint) = &self.login_hint {
+                query.append_pair("login_hint", login_hint);
+            }
+        }
+        Ok(url.to_string())
+    }
+}
+
+/// The header carrying a request's correlation id.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Returns `incoming` if it is a usable correlation id, otherwise a new random one.
+///
+/// Usable ids are 1 to 128 visible ASCII characters, so they are safe to log and
+/// echo back in a header.
+pub fn correlation_id(incoming: Option<&str>) -> String {
+    match incoming.map(str::trim) {
+        Some(id) if (1..=128).contains(&id.len()) && id.bytes().all(|b| b.is_ascii_graphic()) => {
+            id.to_string()
+        }
+        _ => uuid::Uuid::new_v4().to_string(),
+    }
+}
+
 /// Trait for an OAuth2-compatible provider.
 #[async_trait]
 pub trait OAuthProvider: Provider {
     /// Get the provider identifier.
     fn provider_id(&self) -> &str;
 
+    /// The scopes requested when neither the login request nor
+    /// [`OAuth2Flow::with_scopes`](crate::flow::OAuth2Flow::with_scopes)
+    /// names any. Defaults to none.
+    fn default_scopes(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
     /// Helper to get the authorization URL.
     fn get_authorization_url(
         &self,
@@ -116,6 +282,28 @@ pub trait OAuthProvider: Provider {
         nonce: Option<&str>,
     ) -> Result<(Identity, OAuthToken), AuthError>;
 
+    /// Exchange an authorization code for an Identity, sending one RFC 8707
+    /// `resource` parameter per entry of `resources` with the token request.
+    ///
+    /// Defaults to [`exchange_code_for_identity`](Self::exchange_code_for_identity)
+    /// when `resources` is empty and fails otherwise, so a provider that
+    /// cannot send them never silently issues an unrestricted token.
+    async fn exchange_code_for_identity_with_resources(
+        &self,
+        code: &str,
+        code_verifier: Option<&str>,
+        nonce: Option<&str>,
+        resources: &[String],
+    ) -> Result<(Identity, OAuthToken), AuthError> {
+        if !resources.is_empty() {
+            return Err(AuthError::Provider(
+                "Resource indicators not supported by this provider".into(),
+            ));
+        }
+        self.exchange_code_for_identity(code, code_verifier, nonce)
+            .await
+    }
+
     /// Refresh an access token using a refresh token.
     async fn refresh_token(&self, _refresh_token: &str) -> Result<OAuthToken, AuthError> {
         Err(AuthError::Provider(
@@ -129,6 +317,16 @@ pub trait OAuthProvider: Provider {
             "Token revocation not supported by this provider".into(),
         ))
     }
+
+    /// The redirect URI sent to the provider, if it has a fixed one.
+    fn redirect_uri(&self) -> Option<&str> {
+        None
+    }
+
+    /// The URL of the provider's JWKS, if it signs tokens.
+    fn jwks_uri(&self) -> Option<String> {
+        None
+    }
 }
 
 /// Trait for a Credentials-based provider (e.g., Email/Password).
@@ -170,6 +368,20 @@ pub trait ErasedOAuthFlow: Send + Sync {
         received_state: &str,
         expected_state: &OAuth2State,
     ) -> Result<(Identity, OAuthToken), AuthError>;
+    /// Refresh an access token using a refresh token.
+    async fn refresh_token(&self, _refresh_token: &str) -> Result<OAuthToken, AuthError> {
+        Err(AuthError::Provider(
+            "Token refresh not supported by this provider".into(),
+        ))
+    }
+    /// The redirect URI of the provider; see [`OAuthProvider::redirect_uri`].
+    fn redirect_uri(&self) -> Option<String> {
+        None
+    }
+    /// The JWKS URL of the provider; see [`OAuthProvider::jwks_uri`].
+    fn jwks_uri(&self) -> Option<String> {
+        None
+    }
 }
 
 #[async_trait]
@@ -204,6 +416,18 @@ impl<T: ErasedOAuthFlow + ?Sized> ErasedOAuthFlow for std::sync::Arc<T> {
             .finalize_login(code, received_state, expected_state)
             .await
     }
+
+    async fn refresh_token(&self, refresh_token: &str) -> Result<OAuthToken, AuthError> {
+        (**self).refresh_token(refresh_token).await
+    }
+
+    fn redirect_uri(&self) -> Option<String> {
+        (**self).redirect_uri()
+    }
+
+    fn jwks_uri(&self) -> Option<String> {
+        (**self).jwks_uri()
+    }
 }
 
 #[async_trait]
@@ -230,4 +454,16 @@ impl<T: ErasedOAuthFlow + ?Sized> ErasedOAuthFlow for Box<T> {
             .finalize_login(code, received_state, expected_state)
             .await
     }
+
+    async fn refresh_token(&self, refresh_token: &str) -> Result<OAuthToken, AuthError> {
+        (**self).refresh_token(refresh_token).await
+    }
+
+    fn redirect_uri(&self) -> Option<String> {
+        (**self).redirect_uri()
+    }
+
+    fn jwks_uri(&self) -> Option<String> {
+        (**self).jwks_uri()
+    }
 }
@@ -0,0 +1,83 @@
+//! A [`SessionStore`] variant using native `async fn` in traits.
+
+use crate::auth::error::AuthError;
+use crate::auth::session::{Session, SessionStore};
+use async_trait::async_trait;
+use std::future::Future;
+
+/// Session persistence without boxed futures.
+///
+/// The methods of [`SessionStore`] return `Pin<Box<dyn Future>>` (through
+/// `#[async_trait]`), which costs one heap allocation per call. This trait
+/// returns `impl Future` instead, so code generic over `S: NativeSessionStore`
+/// calls the store without allocating for the future.
+///
+/// The tradeoff is object safety: a trait returning `impl Future` is not
+/// `dyn`-compatible, so it cannot be stored as `Arc<dyn NativeSessionStore>`.
+/// The engine and the framework adapters hold their store as
+/// `Arc<dyn SessionStore>`; wrap a native store in [`DynCompat`] to hand it to
+/// them. Calls through the wrapper box the future again, so the saving only
+/// applies to code calling the native store directly.
+pub trait NativeSessionStore: Send + Sync + 'static {
+    /// Load a session by its ID.
+    fn load_session(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = Result<Option<Session>, AuthError>> + Send;
+    /// Save or update a session.
+    fn save_session(&self, session: &Session)
+        -> impl Future<Output = Result<(), AuthError>> + Send;
+    /// Delete a session by its ID.
+    fn delete_session(&self, id: &str) -> impl Future<Output = Result<(), AuthError>> + Send;
+    /// Delete every session created before `cutoff`, returning how many were
+    /// removed. See [`SessionStore::delete_sessions_before`].
+    ///
+    /// Stores that cannot enumerate sessions by creation time return an error.
+    fn delete_sessions_before(
+        &self,
+        cutoff: chrono::DateTime<chrono::Utc>,
+    ) -> impl Future<Output = Result<u64, AuthError>> + Send {
+        let _ = cutoff;
+        async {
+            Err(AuthError::Session(
+                "Batch session deletion is not supported by this store".to_string(),
+            ))
+        }
+    }
+    /// Check that the store is reachable. See [`SessionStore::ping`].
+    ///
+    /// Defaults to looking up a session id that is never issued.
+    fn ping(&self) -> impl Future<Output = Result<(), AuthError>> + Send {
+        async { self.load_session("authkestra-ping").await.map(|_| ()) }
+    }
+}
+
+/// Adapts a [`NativeSessionStore`] to the `dyn`-compatible [`SessionStore`].
+#[derive(Clone, Debug, Default)]
+pub struct DynCompat<S>(pub S);
+
+#[async_trait]
+impl<S: NativeSessionStore> SessionStore for DynCompat<S> {
+    async fn load_session(&self, id: &str) -> Result<Option<Session>, AuthError> {
+        self.0.load_session(id).await
+    }
+
+    async fn save_session(&self, session: &Session) -> Result<(), AuthError> {
+        self.0.save_session(session).await
+    }
+
+    async fn delete_session(&self, id: &str) -> Result<(), AuthError> {
+        self.0.delete_session(id).await
+    }
+
+    async fn delete_sessions_before(
+        &self,
+        cutoff: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, AuthError> {
+        self.0.delete_sessions_before(cutoff).await
+    }
+
+    async fn ping(&self) -> Result<(), AuthError> {
+        self.0.ping().await
+    }
+}
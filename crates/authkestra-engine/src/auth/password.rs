@@ -0,0 +1,565 @@
+//! Password hashing behind a KDF-agnostic trait.
+//!
+//! [`PasswordHasher`] hides the key derivation function, so a credentials
+//! provider can hold a `Box<dyn PasswordHasher>` and the application picks
+//! the algorithm. Argon2id ([`Argon2idHasher`], feature `argon2`) and bcrypt
+//! ([`BcryptHasher`], feature `bcrypt`) are provided.
+//!
+//! [`PasswordCredentialsProvider`] plugs a hasher into
+//! [`CredentialsFlow`](crate::flow::CredentialsFlow): it looks the stored hash
+//! up in a [`PasswordRepository`], verifies the password and, when
+//! [`needs_rehash`](PasswordHasher::needs_rehash) says so, stores a fresh
+//! [`hash`](PasswordHasher::hash) so users move to new parameters or a new
+//! algorithm as they log in. Hashes of a previous algorithm are verified by
+//! the hashers registered with
+//! [`with_legacy_hasher`](PasswordCredentialsProvider::with_legacy_hasher).
+
+use crate::auth::{AuthError, CredentialsProvider, Identity};
+use async_trait::async_trait;
+
+/// Hashes and verifies passwords with one key derivation function.
+pub trait PasswordHasher: Send + Sync {
+    /// Hash `password` with a fresh salt, in the algorithm's PHC or modular crypt format.
+    fn hash(&self, password: &str) -> Result<String, AuthError>;
+
+    /// Whether `password` matches `hash`.
+    ///
+    /// Returns `Ok(false)` for a wrong password and an error if `hash` is not
+    /// a hash this hasher understands.
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, AuthError>;
+
+    /// Whether `hash` was produced by another algorithm or with other
+    /// parameters than this hasher's, and should be replaced.
+    fn needs_rehash(&self, hash: &str) -> bool;
+}
+
+/// A username (or email) and password pair, the credentials of a
+/// [`PasswordCredentialsProvider`].
+#[derive(Clone)]
+pub struct PasswordCredentials {
+    /// The username or email.
+    pub identifier: String,
+    /// The secret password.
+    pub password: String,
+}
+
+impl std::fmt::Debug for PasswordCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PasswordCredentials")
+            .field("identifier", &self.identifier)
+            .field("password", &"[REDACTED]")
+            .finish()
+    }
+}
+
+/// A stored password hash and the identity it authenticates.
+#[derive(Debug, Clone)]
+pub struct PasswordRecord {
+    /// The identity returned on a successful login.
+    pub identity: Identity,
+    /// The password hash, in the format of the configured [`PasswordHasher`].
+    pub hash: String,
+}
+
+/// Storage for password hashes, keyed by the login identifier.
+#[async_trait]
+pub trait PasswordRepository: Send + Sync {
+    /// Find the record for `identifier`, if the account exists.
+    async fn find_password(&self, identifier: &str) -> Result<Option<PasswordRecord>, AuthError>;
+
+    /// Replace the stored hash for `identifier` after a rehash.
+    async fn update_password_hash(&self, identifier: &str, hash: &str) -> Result<(), AuthError>;
+}
+
+/// A [`CredentialsProvider`] checking passwords against a [`PasswordRepository`].
+///
+/// Unknown identifiers and wrong passwords both fail with
+/// [`AuthError::InvalidCredentials`], and an unknown identifier still costs a
+/// hash so response times do not reveal which accounts exist.
+///
+/// New hashes always use the current hasher. A stored hash the current
+/// hasher does not understand is verified by the legacy hashers, in the order
+/// they were added, and replaced by a current hash once the login succeeds.
+pub struct PasswordCredentialsProvider<R> {
+    repository: R,
+    hasher: Box<dyn PasswordHasher>,
+    legacy: Vec<Box<dyn PasswordHasher>>,
+}
+
+impl<R: PasswordRepository> PasswordCredentialsProvider<R> {
+    /// Create a provider verifying passwords from `repository` with `hasher`.
+    pub fn new(repository: R, hasher: Box<dyn PasswordHasher>) -> Self {
+        Self {
+            repository,
+            hasher,
+            legacy: Vec::new(),
+        }
+    }
+
+    /// Also verify hashes of a previous algorithm with `hasher`, e.g. bcrypt
+    /// hashes after moving to Argon2id. Such hashes are rehashed with the
+    /// current hasher on login.
+    pub fn with_legacy_hasher(mut self, hasher: Box<dyn PasswordHasher>) -> Self {
+        self.legacy.push(hasher);
+        self
+    }
+
+    /// The underlying repository.
+    pub fn repository(&self) -> &R {
+        &self.repository
+    }
+
+    /// Verify with the current hasher, then with each legacy hasher for as
+    /// long as the hash's format is not understood.
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, AuthError> {
+        let mut result = self.hasher.verify(password, hash);
+        for legacy in &self.legacy {
+            if !matches!(result, Err(AuthError::PasswordHash(_))) {
+                break;
+            }
+            result = legacy.verify(password, hash);
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<R: PasswordRepository> CredentialsProvider for PasswordCredentialsProvider<R> {
+    type Credentials = PasswordCredentials;
+
+    #[tracing::instrument(skip_all, fields(identifier = %creds.identifier))]
+    async fn authenticate(&self, creds: PasswordCredentials) -> Result<Identity, AuthError> {
+        let Some(record) = self.repository.find_password(&creds.identifier).await? else {
+            // Spend the same work as a verification before rejecting.
+            let _ = self.hasher.hash(&creds.password);
+            tracing::info!("password login for unknown identifier");
+            return Err(AuthError::InvalidCredentials);
+        };
+
+        if !self.verify(&creds.password, &record.hash)? {
+            tracing::info!("password login with wrong password");
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        if self.hasher.needs_rehash(&record.hash) {
+            tracing::debug!("rehashing password with current parameters");
+            let hash = self.hasher.hash(&creds.password)?;
+            if let Err(e) = self
+                .repository
+                .update_password_hash(&creds.identifier, &hash)
+                .await
+            {
+                // The login itself succeeded; the next one retries the rehash.
+                e.log("failed to store rehashed password");
+            }
+        }
+
+        Ok(record.identity)
+    }
+}
+
+#[cfg(feature = "argon2")]
+pub use self::argon2_hasher::Argon2idHasher;
+
+#[cfg(feature = "argon2")]
+mod argon2_hasher {
+    use super::PasswordHasher;
+    use crate::auth::AuthError;
+    use argon2::password_hash::{
+        rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString,
+    };
+    use argon2::{Algorithm, Argon2, Params, Version};
+
+    /// Argon2id with configurable memory, time and parallelism costs.
+    #[derive(Clone, Debug)]
+    pub struct Argon2idHasher {
+        params: Params,
+    }
+
+    impl Default for Argon2idHasher {
+        /// The `argon2` crate defaults: 19 MiB, 2 iterations, 1 lane.
+        fn default() -> Self {
+            Self {
+                params: Params::default(),
+            }
+        }
+    }
+
+    impl Argon2idHasher {
+        /// A hasher using `memory_kib` KiB of memory, `iterations` passes and
+        /// `parallelism` lanes.
+        pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self, AuthError> {
+            let params = Params::new(memory_kib, iterations, parallelism, None)
+                .map_err(|e| AuthError::Config(format!("Invalid Argon2 parameters: {e}")))?;
+            Ok(Self { params })
+        }
+
+        fn argon2(&self) -> Argon2<'_> {
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+        }
+    }
+
+    impl PasswordHasher for Argon2idHasher {
+        fn hash(&self, password: &str) -> Result<String, AuthError> {
+            let salt = SaltString::generate(&mut OsRng);
+            self.argon2()
+                .hash_password(password.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+                .map_err(|e| AuthError::PasswordHash(format!("Failed to hash password: {e}")))
+        }
+
+        fn verify(&self, password: &str, hash: &str) -> Result<bool, AuthError> {
+            let parsed = PasswordHash::new(hash)
+                .map_err(|e| AuthError::PasswordHash(format!("Invalid Argon2 hash: {e}")))?;
+            if parsed.algorithm != Algorithm::Argon2id.ident() {
+                return Err(AuthError::PasswordHash(format!(
+                    "Expected an argon2id hash, got {}",
+                    parsed.algorithm
+                )));
+            }
+            // The parameters stored in the hash are used, not `self.params`.
+            match self.argon2().verify_password(password.as_bytes(), &parsed) {
+                Ok(()) => Ok(true),
+                Err(argon2::password_hash::Error::Password) => Ok(false),
+                Err(e) => Err(AuthError::PasswordHash(format!(
+                    "Failed to verify password: {e}"
+                ))),
+            }
+        }
+
+        fn needs_rehash(&self, hash: &str) -> bool {
+            let Ok(parsed) = PasswordHash::new(hash) else {
+                return true;
+            };
+            if parsed.algorithm != Algorithm::Argon2id.ident()
+                || parsed.version != Some(Version::V0x13.into())
+            {
+                return true;
+            }
+            match Params::try_from(&parsed) {
+                Ok(params) => {
+                    params.m_cost() != self.params.m_cost()
+                        || params.t_cost() != self.params.t_cost()
+                        || params.p_cost() != self.params.p_cost()
+                }
+                Err(_) => true,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "bcrypt")]
+pub use self::bcrypt_hasher::BcryptHasher;
+
+#[cfg(feature = "bcrypt")]
+mod bcrypt_hasher {
+    use super::PasswordHasher;
+    use crate::auth::AuthError;
+
+    /// bcrypt with a configurable cost.
+    ///
+    /// bcrypt only uses the first 72 bytes of a password.
+    #[derive(Clone, Debug)]
+    pub struct BcryptHasher {
+        cost: u32,
+    }
+
+    impl Default for BcryptHasher {
+        /// Cost 12.
+        fn default() -> Self {
+            Self {
+                cost: bcrypt::DEFAULT_COST,
+            }
+        }
+    }
+
+    impl BcryptHasher {
+        /// A hasher using `cost` (4 to 31) rounds as the base-2 logarithm.
+        pub fn new(cost: u32) -> Self {
+            Self { cost }
+        }
+    }
+
+    impl PasswordHasher for BcryptHasher {
+        fn hash(&self, password: &str) -> Result<String, AuthError> {
+            bcrypt::hash(password, self.cost)
+                .map_err(|e| AuthError::PasswordHash(format!("Failed to hash password: {e}")))
+        }
+
+        fn verify(&self, password: &str, hash: &str) -> Result<bool, AuthError> {
+            bcrypt::verify(password, hash)
+                .map_err(|e| AuthError::PasswordHash(format!("Invalid bcrypt hash: {e}")))
+        }
+
+        fn needs_rehash(&self, hash: &str) -> bool {
+            match hash.parse::<bcrypt::HashParts>() {
+                Ok(parts) => parts.get_cost() != self.cost,
+                Err(_) => true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Cheap parameters to keep the tests fast.
+    #[cfg(feature = "argon2")]
+    fn argon2() -> Argon2idHasher {
+        Argon2idHasher::new(1024, 1, 1).unwrap()
+    }
+
+    #[cfg(any(feature = "argon2", feature = "bcrypt"))]
+    fn assert_round_trip(hasher: &dyn PasswordHasher) {
+        let hash = hasher.hash("hunter2").unwrap();
+        assert!(hasher.verify("hunter2", &hash).unwrap());
+        assert!(!hasher.verify("hunter3", &hash).unwrap());
+        assert!(!hasher.needs_rehash(&hash));
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn test_argon2_round_trip() {
+        assert_round_trip(&argon2());
+    }
+
+    #[cfg(feature = "bcrypt")]
+    #[test]
+    fn test_bcrypt_round_trip() {
+        assert_round_trip(&BcryptHasher::new(4));
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn test_argon2_errors_are_typed() {
+        assert!(matches!(
+            Argon2idHasher::new(1, 1, 1),
+            Err(AuthError::Config(_))
+        ));
+        assert!(matches!(
+            argon2().verify("hunter2", "not a hash"),
+            Err(AuthError::PasswordHash(_))
+        ));
+    }
+
+    #[cfg(feature = "bcrypt")]
+    #[test]
+    fn test_bcrypt_errors_are_typed() {
+        assert!(matches!(
+            BcryptHasher::new(4).verify("hunter2", "not a hash"),
+            Err(AuthError::PasswordHash(_))
+        ));
+    }
+
+    #[cfg(all(feature = "argon2", feature = "bcrypt"))]
+    #[test]
+    fn test_hash_from_other_algorithm_needs_rehash() {
+        let argon2 = argon2();
+        let bcrypt = BcryptHasher::new(4);
+        let argon2_hash = argon2.hash("hunter2").unwrap();
+        let bcrypt_hash = bcrypt.hash("hunter2").unwrap();
+
+        assert!(argon2.needs_rehash(&bcrypt_hash));
+        assert!(bcrypt.needs_rehash(&argon2_hash));
+        assert!(argon2.verify("hunter2", &bcrypt_hash).is_err());
+        assert!(bcrypt.verify("hunter2", &argon2_hash).is_err());
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn test_changed_argon2_params_need_rehash() {
+        let hash = argon2().hash("hunter2").unwrap();
+        let stronger = Argon2idHasher::new(2048, 2, 1).unwrap();
+        assert!(stronger.needs_rehash(&hash));
+        // Old hashes still verify with the new parameters.
+        assert!(stronger.verify("hunter2", &hash).unwrap());
+    }
+
+    #[cfg(feature = "bcrypt")]
+    #[test]
+    fn test_changed_bcrypt_cost_needs_rehash() {
+        let hash = BcryptHasher::new(4).hash("hunter2").unwrap();
+        assert!(BcryptHasher::new(5).needs_rehash(&hash));
+        assert!(BcryptHasher::new(5).verify("hunter2", &hash).unwrap());
+    }
+
+    /// A reversible "hash" tagged with a version, so the provider tests run
+    /// without either KDF feature.
+    struct TaggedHasher(&'static str);
+
+    impl PasswordHasher for TaggedHasher {
+        fn hash(&self, password: &str) -> Result<String, AuthError> {
+            Ok(format!("{}${password}", self.0))
+        }
+
+        fn verify(&self, password: &str, hash: &str) -> Result<bool, AuthError> {
+            let (_, stored) = hash
+                .split_once('$')
+                .ok_or_else(|| AuthError::PasswordHash("malformed hash".to_string()))?;
+            Ok(stored == password)
+        }
+
+        fn needs_rehash(&self, hash: &str) -> bool {
+            !hash.starts_with(&format!("{}$", self.0))
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryPasswords(Mutex<HashMap<String, String>>);
+
+    #[async_trait]
+    impl PasswordRepository for MemoryPasswords {
+        async fn find_password(
+            &self,
+            identifier: &str,
+        ) -> Result<Option<PasswordRecord>, AuthError> {
+            Ok(self
+                .0
+                .lock()
+                .unwrap()
+                .get(identifier)
+                .map(|hash| PasswordRecord {
+                    identity: Identity {
+                        provider_id: "password".to_string(),
+                        external_id: identifier.to_string(),
+                        email: Some(identifier.to_string()),
+                        email_verified: None,
+                        username: None,
+                        attributes: HashMap::new(),
+                        attributes_multi: HashMap::new(),
+                    },
+                    hash: hash.clone(),
+                }))
+        }
+
+        async fn update_password_hash(
+            &self,
+            identifier: &str,
+            hash: &str,
+        ) -> Result<(), AuthError> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(identifier.to_string(), hash.to_string());
+            Ok(())
+        }
+    }
+
+    fn creds(identifier: &str, password: &str) -> PasswordCredentials {
+        PasswordCredentials {
+            identifier: identifier.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_credentials_flow_verifies_password() {
+        let repository = MemoryPasswords::default();
+        repository
+            .0
+            .lock()
+            .unwrap()
+            .insert("alice@example.com".to_string(), "v1$hunter2".to_string());
+        let flow = crate::flow::CredentialsFlow::new(PasswordCredentialsProvider::new(
+            repository,
+            Box::new(TaggedHasher("v2")),
+        ));
+
+        assert!(matches!(
+            flow.authenticate(creds("alice@example.com", "hunter3"))
+                .await,
+            Err(AuthError::InvalidCredentials)
+        ));
+        assert!(matches!(
+            flow.authenticate(creds("bob@example.com", "hunter2")).await,
+            Err(AuthError::InvalidCredentials)
+        ));
+
+        let (identity, _) = flow
+            .authenticate(creds("alice@example.com", "hunter2"))
+            .await
+            .unwrap();
+        assert_eq!(identity.subject(), "password:alice@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_outdated_hash_is_replaced_on_login() {
+        let provider = PasswordCredentialsProvider::new(
+            MemoryPasswords::default(),
+            Box::new(TaggedHasher("v2")),
+        );
+        provider
+            .repository()
+            .0
+            .lock()
+            .unwrap()
+            .insert("alice@example.com".to_string(), "v1$hunter2".to_string());
+
+        provider
+            .authenticate(creds("alice@example.com", "hunter2"))
+            .await
+            .unwrap();
+        assert_eq!(
+            provider.repository().0.lock().unwrap()["alice@example.com"],
+            "v2$hunter2"
+        );
+    }
+
+    #[cfg(all(feature = "argon2", feature = "bcrypt"))]
+    #[tokio::test]
+    async fn test_legacy_bcrypt_hash_is_upgraded_to_argon2_on_login() {
+        let bcrypt_hash = BcryptHasher::new(4).hash("hunter2").unwrap();
+        let provider =
+            PasswordCredentialsProvider::new(MemoryPasswords::default(), Box::new(argon2()))
+                .with_legacy_hasher(Box::new(BcryptHasher::new(4)));
+        provider
+            .repository()
+            .0
+            .lock()
+            .unwrap()
+            .insert("alice@example.com".to_string(), bcrypt_hash.clone());
+
+        assert!(matches!(
+            provider
+                .authenticate(creds("alice@example.com", "hunter3"))
+                .await,
+            Err(AuthError::InvalidCredentials)
+        ));
+        provider
+            .authenticate(creds("alice@example.com", "hunter2"))
+            .await
+            .unwrap();
+
+        let stored = provider.repository().0.lock().unwrap()["alice@example.com"].clone();
+        assert_ne!(stored, bcrypt_hash);
+        assert!(stored.starts_with("$argon2id$"));
+        assert!(argon2().verify("hunter2", &stored).unwrap());
+    }
+
+    #[cfg(all(feature = "argon2", feature = "bcrypt"))]
+    #[tokio::test]
+    async fn test_hash_of_unregistered_algorithm_fails_the_login() {
+        let provider =
+            PasswordCredentialsProvider::new(MemoryPasswords::default(), Box::new(argon2()));
+        provider.repository().0.lock().unwrap().insert(
+            "alice@example.com".to_string(),
+            BcryptHasher::new(4).hash("hunter2").unwrap(),
+        );
+
+        assert!(matches!(
+            provider
+                .authenticate(creds("alice@example.com", "hunter2"))
+                .await,
+            Err(AuthError::PasswordHash(_))
+        ));
+    }
+
+    #[test]
+    fn test_credentials_debug_redacts_password() {
+        let debug = format!("{:?}", creds("alice@example.com", "hunter2"));
+        assert!(!debug.contains("hunter2"));
+    }
+}
@@ -0,0 +1,65 @@
+//! Just-in-time provisioning of local user accounts.
+//!
+//! [`ProvisioningUserMapper`] implements [`UserMapper`] on top of a
+//! [`UserRepository`]: the first login for a subject creates the local user
+//! and later logins update it. Pass it to `OAuth2Flow::with_mapper` or
+//! `CredentialsFlow::with_mapper` so both login paths share the same logic.
+
+use crate::auth::{AuthError, Identity, UserMapper};
+use async_trait::async_trait;
+
+/// Storage for local user accounts, keyed by [`Identity::subject`].
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    /// The type of the local user object.
+    type User: Send + Sync;
+
+    /// Find the user provisioned for `subject`, if any.
+    async fn find_by_subject(&self, subject: &str) -> Result<Option<Self::User>, AuthError>;
+
+    /// Create a user for an identity seen for the first time.
+    async fn create_from_identity(&self, identity: &Identity) -> Result<Self::User, AuthError>;
+
+    /// Refresh an existing user's profile from the identity of a later login.
+    async fn update_from_identity(
+        &self,
+        user: Self::User,
+        identity: &Identity,
+    ) -> Result<Self::User, AuthError>;
+}
+
+/// A [`UserMapper`] that creates or updates local users in a [`UserRepository`].
+pub struct ProvisioningUserMapper<R> {
+    repository: R,
+}
+
+impl<R> ProvisioningUserMapper<R> {
+    /// Create a new `ProvisioningUserMapper` backed by `repository`.
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// The underlying repository.
+    pub fn repository(&self) -> &R {
+        &self.repository
+    }
+}
+
+#[async_trait]
+impl<R: UserRepository> UserMapper for ProvisioningUserMapper<R> {
+    type LocalUser = R::User;
+
+    #[tracing::instrument(skip_all, fields(subject = %identity.subject()))]
+    async fn map_user(&self, identity: &Identity) -> Result<Self::LocalUser, AuthError> {
+        match self.repository.find_by_subject(&identity.subject()).await? {
+            Some(user) => {
+                tracing::debug!("updating provisioned user");
+                self.repository.update_from_identity(user, identity).await
+            }
+            None => {
+                tracing::info!("provisioning new user");
+                self.repository.create_from_identity(identity).await
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! A typed set of OAuth2 scopes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// A set of OAuth2 scope tokens, kept sorted and deduplicated.
+///
+/// Parses from and displays as the space-delimited form of RFC 6749 §3.3.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Scopes(BTreeSet<String>);
+
+impl Scopes {
+    /// Parse a space-delimited scope string, e.g. the `scope` of a token response.
+    pub fn parse(scope: &str) -> Self {
+        scope.split_whitespace().collect()
+    }
+
+    /// Whether `scope` is in the set.
+    pub fn contains(&self, scope: &str) -> bool {
+        self.0.contains(scope)
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The number of scopes in the set.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The scopes in sorted order.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    /// The scopes in `self` that are not in `other`.
+    pub fn difference(&self, other: &Scopes) -> Scopes {
+        Scopes(self.0.difference(&other.0).cloned().collect())
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for Scopes {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        Scopes(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl std::fmt::Display for Scopes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut scopes = self.iter();
+        if let Some(first) = scopes.next() {
+            f.write_str(first)?;
+        }
+        for scope in scopes {
+            write!(f, " {scope}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let scopes = Scopes::parse("  profile openid  email openid ");
+        assert_eq!(scopes.len(), 3);
+        assert!(scopes.contains("openid"));
+        assert_eq!(scopes.to_string(), "email openid profile");
+        assert!(Scopes::parse("").is_empty());
+    }
+
+    #[test]
+    fn test_difference() {
+        let requested = Scopes::parse("openid email repo");
+        let granted = Scopes::parse("openid email");
+        assert_eq!(requested.difference(&granted), Scopes::parse("repo"));
+        assert!(granted.difference(&requested).is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::auth::csrf::CsrfService;
 use crate::auth::error::AuthError;
 use crate::auth::state::Identity;
 use crate::auth::SameSite;
@@ -9,16 +10,45 @@ use serde::{Deserialize, Serialize};
 pub struct SessionConfig {
     /// The name of the session cookie.
     pub cookie_name: String,
+    /// Legacy cookie names still accepted for session lookup, tried in order
+    /// after `cookie_name`. Sessions are only ever written under `cookie_name`,
+    /// so this allows renaming the cookie without logging everyone out.
+    pub fallback_cookie_names: Vec<String>,
     /// Whether the cookie should only be sent over HTTPS.
     pub secure: bool,
     /// Whether the cookie should be inaccessible to client-side scripts.
     pub http_only: bool,
     /// The `SameSite` attribute for the cookie.
     pub same_site: SameSite,
+    /// Whether to set the `Partitioned` attribute (CHIPS), so the cookie keeps
+    /// working when the app is embedded in a third-party iframe.
+    ///
+    /// Requires `same_site: SameSite::None` and `secure: true`.
+    pub partitioned: bool,
     /// The path for which the cookie is valid.
     pub path: String,
-    /// The maximum age of the session.
+    /// The maximum age of the session, used for both `session_ttl` and
+    /// `cookie_max_age` unless they are set.
     pub max_age: Option<chrono::Duration>,
+    /// How long the server-side session lives, i.e. its `expires_at`. `None`
+    /// uses `max_age`, or 24 hours if that is unset too.
+    pub session_ttl: Option<chrono::Duration>,
+    /// The `Max-Age` of the session cookie, which may be shorter or longer
+    /// than `session_ttl`. `None` uses `max_age`; without either the cookie
+    /// lasts until the browser is closed.
+    pub cookie_max_age: Option<chrono::Duration>,
+    /// The maximum age of sessions created with "remember me" requested at login.
+    pub remember_max_age: Option<chrono::Duration>,
+    /// The maximum lifetime of a session, counted from its creation. Sliding
+    /// expiration never extends a session past it. `None` disables the cap.
+    pub absolute_max_age: Option<chrono::Duration>,
+    /// Origins (e.g. `https://app.example.com`) a `success_url` may point to
+    /// besides same-origin relative paths. Empty by default, so only paths
+    /// like `/dashboard` are followed after login.
+    pub allowed_redirect_origins: Vec<String>,
+    /// How long a login may take: the lifetime of the OAuth state cookie and
+    /// of the state it carries, independent of `max_age`.
+    pub flow_state_ttl: chrono::Duration,
     /// Key used to encrypt intermediate OAuth state cookies.
     /// Must be 32 bytes for AES-256-GCM.
     pub state_encryption_key: [u8; 32],
@@ -34,16 +64,161 @@ impl Default for SessionConfig {
 
         Self {
             cookie_name: "authkestra_session".to_string(),
+            fallback_cookie_names: Vec::new(),
             secure: true,
             http_only: true,
             same_site: SameSite::Lax,
+            partitioned: false,
             path: "/".to_string(),
             max_age: Some(chrono::Duration::hours(24)),
+            session_ttl: None,
+            cookie_max_age: None,
+            remember_max_age: Some(chrono::Duration::days(30)),
+            absolute_max_age: None,
+            allowed_redirect_origins: Vec::new(),
+            flow_state_ttl: chrono::Duration::minutes(10),
             state_encryption_key: key,
         }
     }
 }
 
+impl SessionConfig {
+    /// The cookie names to read a session from: `cookie_name` first, then the fallbacks.
+    pub fn lookup_cookie_names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.cookie_name.as_str())
+            .chain(self.fallback_cookie_names.iter().map(String::as_str))
+    }
+
+    /// This config with `max_age` replaced by `remember_max_age`, if one is
+    /// set, for both the session and the cookie.
+    pub fn remembered(&self) -> Self {
+        match self.remember_max_age {
+            Some(remember_max_age) => Self {
+                max_age: Some(remember_max_age),
+                session_ttl: None,
+                cookie_max_age: None,
+                ..self.clone()
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// The lifetime of new server-side sessions: `session_ttl`, else
+    /// `max_age`, else 24 hours.
+    pub fn session_lifetime(&self) -> chrono::Duration {
+        self.session_ttl
+            .or(self.max_age)
+            .unwrap_or(chrono::Duration::hours(24))
+    }
+
+    /// The `Max-Age` of the session cookie: `cookie_max_age`, else `max_age`.
+    /// `None` means a cookie that lasts until the browser is closed.
+    pub fn cookie_lifetime(&self) -> Option<chrono::Duration> {
+        self.cookie_max_age.or(self.max_age)
+    }
+
+    /// The URL to redirect to after login: `url` if it is a same-origin
+    /// relative path or on one of `allowed_redirect_origins`, `/` otherwise.
+    ///
+    /// Protocol-relative (`//evil.com`) and backslash (`/\evil.com`) paths are
+    /// rejected, since browsers resolve them to another host.
+    pub fn safe_redirect(&self, url: Option<&str>) -> String {
+        url.filter(|url| self.is_allowed_redirect(url))
+            .unwrap_or("/")
+            .to_string()
+    }
+
+    fn is_allowed_redirect(&self, url: &str) -> bool {
+        // Browsers strip tabs and newlines from URLs and treat `\` as `/`.
+        if url.chars().any(|c| c.is_control() || c == '\\') {
+            return false;
+        }
+        if url.starts_with('/') {
+            return !url.starts_with("//");
+        }
+        let Ok(parsed) = url::Url::parse(url) else {
+            return false;
+        };
+        let origin = parsed.origin().ascii_serialization();
+        matches!(parsed.scheme(), "http" | "https")
+            && self
+                .allowed_redirect_origins
+                .iter()
+                .any(|allowed| allowed.trim_end_matches('/') == origin)
+    }
+
+    /// Whether `session` may be used: it has not expired and is younger than
+    /// `absolute_max_age`.
+    pub fn is_active(&self, session: &Session) -> bool {
+        self.is_active_at(session, chrono::Utc::now())
+    }
+
+    /// Slide the expiry of `session` to the
+    /// [session lifetime](Self::session_lifetime) from now, capped at
+    /// `absolute_max_age` after its creation.
+    ///
+    /// Returns `false` and leaves the session untouched if it is no longer
+    /// [active](Self::is_active).
+    pub fn touch(&self, session: &mut Session) -> bool {
+        self.touch_at(session, chrono::Utc::now())
+    }
+
+    fn is_active_at(&self, session: &Session, now: chrono::DateTime<chrono::Utc>) -> bool {
+        !session.is_expired_at(now)
+            && self
+                .absolute_max_age
+                .is_none_or(|cap| now < session.created_at + cap)
+    }
+
+    fn touch_at(&self, session: &mut Session, now: chrono::DateTime<chrono::Utc>) -> bool {
+        if !self.is_active_at(session, now) {
+            return false;
+        }
+        session.expires_at = self.expiry(session.created_at, now);
+        true
+    }
+
+    /// The session lifetime from `now`, capped at `absolute_max_age` after `created_at`.
+    pub(crate) fn expiry(
+        &self,
+        created_at: chrono::DateTime<chrono::Utc>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> chrono::DateTime<chrono::Utc> {
+        let expires_at = now + self.session_lifetime();
+        match self.absolute_max_age {
+            Some(cap) => expires_at.min(created_at + cap),
+            None => expires_at,
+        }
+    }
+
+    /// The CSRF token the logout route requires for `session_id`.
+    ///
+    /// Render it into the logout form as a hidden `logout_token` field. The token
+    /// is an HMAC of the session id keyed with the [CSRF key](Self::csrf), so it
+    /// is only valid for that session and needs no server-side storage.
+    pub fn logout_token(&self, session_id: &str) -> String {
+        self.csrf().with_scope("logout").issue(session_id)
+    }
+
+    /// Whether `token` is the logout token for `session_id`, compared in constant time.
+    pub fn verify_logout_token(&self, session_id: &str, token: &str) -> bool {
+        self.csrf().with_scope("logout").verify(session_id, token)
+    }
+
+    /// The CSRF service for the app's own forms.
+    ///
+    /// Its key is derived from `state_encryption_key` with HKDF-SHA256 under the
+    /// `authkestra-csrf` label, so tokens never use the state cookie key itself.
+    /// The axum and actix CSRF helpers verify tokens against this service.
+    pub fn csrf(&self) -> CsrfService {
+        let mut key = [0u8; 32];
+        hkdf::Hkdf::<sha2::Sha256>::new(None, &self.state_encryption_key)
+            .expand(b"authkestra-csrf", &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        CsrfService::new(key.to_vec())
+    }
+}
+
 /// Represents an active user session.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Session {
@@ -53,30 +228,102 @@ pub struct Session {
     pub identity: Identity,
     /// When the session expires.
     pub expires_at: chrono::DateTime<chrono::Utc>,
+    /// When the session was created. Sessions stored before this field existed
+    /// load with the Unix epoch, so `absolute_max_age` rejects them.
+    #[serde(default)]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Session {
+    /// Whether the session has expired. A session expiring exactly now is expired.
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(chrono::Utc::now())
+    }
+
+    /// The time left before the session expires, or `None` if it has expired.
+    pub fn remaining_ttl(&self) -> Option<chrono::Duration> {
+        self.remaining_ttl_at(chrono::Utc::now())
+    }
+
+    /// Push the expiry back by `duration`.
+    pub fn extend(&mut self, duration: chrono::Duration) {
+        self.expires_at += duration;
+    }
+
+    fn is_expired_at(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.expires_at <= now
+    }
+
+    fn remaining_ttl_at(&self, now: chrono::DateTime<chrono::Utc>) -> Option<chrono::Duration> {
+        if self.is_expired_at(now) {
+            None
+        } else {
+            Some(self.expires_at - now)
+        }
+    }
 }
 
 /// Trait for implementing session persistence.
+///
+/// `load_session` must keep "no such session" and "the backend failed" apart:
+/// `Ok(None)` means the session does not exist (never created, deleted,
+/// expired or, for stateless stores, invalid), while a failure to reach or
+/// read the backend is an `Err(AuthError::Session)`. The adapters answer
+/// `Ok(None)` with `401` and an error with `500`, so a store that swallows a
+/// connection error into `Ok(None)` logs users out on every backend blip.
 #[async_trait]
 pub trait SessionStore: Send + Sync + 'static {
-    /// Load a session by its ID.
+    /// Load a session by its ID: `Ok(None)` only if it does not exist, an
+    /// error if the backend could not be queried.
     async fn load_session(&self, id: &str) -> Result<Option<Session>, AuthError>;
     /// Save or update a session.
     async fn save_session(&self, session: &Session) -> Result<(), AuthError>;
     /// Delete a session by its ID.
     async fn delete_session(&self, id: &str) -> Result<(), AuthError>;
+    /// Persist a new session and return the ID its cookie should carry.
+    ///
+    /// Defaults to saving the session under `session.id`. Stateless stores
+    /// return a signed token encoding the session instead.
+    async fn create_session(&self, session: &Session) -> Result<String, AuthError> {
+        self.save_session(session).await?;
+        Ok(session.id.clone())
+    }
+    /// Delete every session created before `cutoff`, returning how many were
+    /// removed. Useful for forcing re-login after a credential leak.
+    ///
+    /// Stores that cannot enumerate sessions by creation time return an error.
+    async fn delete_sessions_before(
+        &self,
+        cutoff: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, AuthError> {
+        let _ = cutoff;
+        Err(AuthError::Session(
+            "Batch session deletion is not supported by this store".to_string(),
+        ))
+    }
+    /// Check that the store is reachable, e.g. before serving traffic.
+    ///
+    /// Defaults to looking up a session id that is never issued.
+    async fn ping(&self) -> Result<(), AuthError> {
+        self.load_session("authkestra-ping").await.map(|_| ())
+    }
 }
 
 #[async_trait]
 impl<S: crate::store::KvStore<Session>> SessionStore for S {
     async fn load_session(&self, id: &str) -> Result<Option<Session>, AuthError> {
-        self.get(id)
-            .await
-            .map_err(|e| AuthError::Session(e.to_string()))
+        match self.get(id).await {
+            Ok(session) => Ok(session),
+            Err(crate::store::StoreError::NotFound) => Ok(None),
+            Err(e) => Err(AuthError::Session(e.to_string())),
+        }
     }
 
     async fn save_session(&self, session: &Session) -> Result<(), AuthError> {
-        let ttl_secs = (session.expires_at - chrono::Utc::now()).num_seconds();
-        let ttl = std::time::Duration::from_secs(if ttl_secs > 0 { ttl_secs as u64 } else { 0 });
+        let ttl = session
+            .remaining_ttl()
+            .and_then(|ttl| ttl.to_std().ok())
+            .unwrap_or_default();
         self.set(&session.id, session.clone(), ttl)
             .await
             .map_err(|e| AuthError::Session(e.to_string()))
@@ -87,4 +334,231 @@ impl<S: crate::store::KvStore<Session>> SessionStore for S {
             .await
             .map_err(|e| AuthError::Session(e.to_string()))
     }
+
+    #[tracing::instrument(skip(self))]
+    async fn delete_sessions_before(
+        &self,
+        cutoff: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, AuthError> {
+        let deleted = self
+            .delete_created_before(cutoff)
+            .await
+            .map_err(|e| AuthError::Session(e.to_string()))?;
+        tracing::info!(deleted, "deleted sessions created before cutoff");
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use std::collections::HashMap;
+
+    /// Fails every lookup, except `missing`, which it reports as not found.
+    struct FailingStore;
+
+    #[async_trait]
+    impl crate::store::KvStore<Session> for FailingStore {
+        async fn get(&self, key: &str) -> Result<Option<Session>, crate::store::StoreError> {
+            match key {
+                "missing" => Err(crate::store::StoreError::NotFound),
+                _ => Err(crate::store::StoreError::Internal(
+                    "connection reset".to_string(),
+                )),
+            }
+        }
+
+        async fn set(
+            &self,
+            _key: &str,
+            _value: Session,
+            _ttl: std::time::Duration,
+        ) -> Result<(), crate::store::StoreError> {
+            Ok(())
+        }
+
+        async fn delete(&self, _key: &str) -> Result<(), crate::store::StoreError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backend_errors_are_not_reported_as_missing_sessions() {
+        assert!(matches!(
+            FailingStore.load_session("session-1").await,
+            Err(AuthError::Session(_))
+        ));
+        assert!(matches!(
+            FailingStore.ping().await,
+            Err(AuthError::Session(_))
+        ));
+        assert!(FailingStore
+            .load_session("missing")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_logout_token_is_bound_to_session_and_key() {
+        let config = SessionConfig::default();
+        let token = config.logout_token("session-1");
+
+        assert!(config.verify_logout_token("session-1", &token));
+        assert!(!config.verify_logout_token("session-2", &token));
+        assert!(!config.verify_logout_token("session-1", ""));
+        assert!(!config.verify_logout_token("session-1", "not base64!"));
+
+        let other = SessionConfig {
+            state_encryption_key: *b"another-key-of-exactly-32-bytes!",
+            ..Default::default()
+        };
+        assert!(!other.verify_logout_token("session-1", &token));
+    }
+
+    #[test]
+    fn test_csrf_key_is_derived_from_state_key() {
+        let config = SessionConfig::default();
+        let token = config.csrf().issue("session-1");
+
+        assert!(config.csrf().verify("session-1", &token));
+        let raw = crate::auth::CsrfService::new(config.state_encryption_key.to_vec());
+        assert!(!raw.verify("session-1", &token));
+        assert!(!raw
+            .with_scope("logout")
+            .verify("session-1", &config.logout_token("session-1")));
+    }
+
+    fn session(expires_at: chrono::DateTime<Utc>) -> Session {
+        Session {
+            id: "session-1".to_string(),
+            identity: Identity {
+                provider_id: "mock".to_string(),
+                external_id: "user123".to_string(),
+                email: None,
+                email_verified: None,
+                username: None,
+                attributes: HashMap::new(),
+                attributes_multi: HashMap::new(),
+            },
+            expires_at,
+            created_at: expires_at - Duration::hours(1),
+        }
+    }
+
+    #[test]
+    fn test_session_expiry_boundary() {
+        let now = Utc::now();
+
+        let at_now = session(now);
+        assert!(at_now.is_expired_at(now));
+        assert_eq!(at_now.remaining_ttl_at(now), None);
+
+        let past = session(now - Duration::seconds(1));
+        assert!(past.is_expired_at(now));
+        assert_eq!(past.remaining_ttl_at(now), None);
+
+        let future = session(now + Duration::seconds(1));
+        assert!(!future.is_expired_at(now));
+        assert_eq!(future.remaining_ttl_at(now), Some(Duration::seconds(1)));
+    }
+
+    #[test]
+    fn test_session_expiry_uses_current_time() {
+        assert!(session(Utc::now() - Duration::seconds(1)).is_expired());
+        assert_eq!(
+            session(Utc::now() - Duration::seconds(1)).remaining_ttl(),
+            None
+        );
+
+        let live = session(Utc::now() + Duration::hours(1));
+        assert!(!live.is_expired());
+        assert!(live.remaining_ttl().unwrap() > Duration::minutes(59));
+    }
+
+    #[test]
+    fn test_session_extend() {
+        let now = Utc::now();
+        let mut expired = session(now - Duration::seconds(1));
+        expired.extend(Duration::seconds(2));
+        assert_eq!(expired.expires_at, now + Duration::seconds(1));
+        assert!(!expired.is_expired_at(now));
+    }
+
+    #[test]
+    fn test_touch_slides_expiry_up_to_the_absolute_cap() {
+        let config = SessionConfig {
+            max_age: Some(Duration::hours(1)),
+            absolute_max_age: Some(Duration::hours(12)),
+            ..Default::default()
+        };
+        let created_at = Utc::now();
+        let mut touched = session(created_at + Duration::hours(1));
+        touched.created_at = created_at;
+
+        // Touched every 30 minutes, the session never idles out...
+        let mut now = created_at;
+        while config.touch_at(&mut touched, now) {
+            assert!(touched.expires_at <= created_at + Duration::hours(12));
+            now += Duration::minutes(30);
+        }
+        // ...but is rejected once it reaches the absolute cap.
+        assert_eq!(now, created_at + Duration::hours(12));
+        assert_eq!(touched.expires_at, created_at + Duration::hours(12));
+        assert!(!config.is_active_at(&touched, now));
+    }
+
+    #[test]
+    fn test_session_and_cookie_lifetimes() {
+        let default = SessionConfig::default();
+        assert_eq!(default.session_lifetime(), Duration::hours(24));
+        assert_eq!(default.cookie_lifetime(), Some(Duration::hours(24)));
+
+        let split = SessionConfig {
+            session_ttl: Some(Duration::days(7)),
+            cookie_max_age: Some(Duration::minutes(10)),
+            ..Default::default()
+        };
+        assert_eq!(split.session_lifetime(), Duration::days(7));
+        assert_eq!(split.cookie_lifetime(), Some(Duration::minutes(10)));
+        let now = Utc::now();
+        assert_eq!(split.expiry(now, now), now + Duration::days(7));
+
+        let browser_session = SessionConfig {
+            max_age: None,
+            ..Default::default()
+        };
+        assert_eq!(browser_session.session_lifetime(), Duration::hours(24));
+        assert_eq!(browser_session.cookie_lifetime(), None);
+
+        // "Remember me" applies to both.
+        let remembered = split.remembered();
+        assert_eq!(remembered.session_lifetime(), Duration::days(30));
+        assert_eq!(remembered.cookie_lifetime(), Some(Duration::days(30)));
+    }
+
+    #[test]
+    fn test_absolute_cap_rejects_unexpired_session() {
+        let now = Utc::now();
+        let mut old = session(now + Duration::hours(1));
+        old.created_at = now - Duration::hours(13);
+
+        assert!(SessionConfig::default().is_active_at(&old, now));
+        let capped = SessionConfig {
+            absolute_max_age: Some(Duration::hours(12)),
+            ..Default::default()
+        };
+        assert!(!capped.is_active_at(&old, now));
+        assert!(!capped.touch_at(&mut old, now));
+        assert_eq!(old.expires_at, now + Duration::hours(1));
+    }
+
+    #[test]
+    fn test_created_at_defaults_to_epoch() {
+        let mut value = serde_json::to_value(session(Utc::now())).unwrap();
+        value.as_object_mut().unwrap().remove("created_at");
+        let legacy: Session = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.created_at, chrono::DateTime::UNIX_EPOCH);
+    }
 }
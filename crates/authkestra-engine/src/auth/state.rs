@@ -2,8 +2,14 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+/// Placeholder printed by `Debug` implementations in place of secret values.
+pub const REDACTED: &str = "[redacted]";
+
+/// Identity attributes that hold upstream tokens and are redacted from `Debug` output.
+const SECRET_ATTRIBUTES: &[&str] = &["access_token", "refresh_token", "id_token"];
+
 /// A unified identity structure returned by all providers.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Identity {
     /// The provider identifier (e.g., "github", "google")
     pub provider_id: String,
@@ -11,14 +17,60 @@ pub struct Identity {
     pub external_id: String,
     /// The user's email address, if available and authorized
     pub email: Option<String>,
+    /// Whether the provider verified `email`, or `None` if it did not say
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
     /// The user's username or display name, if available
     pub username: Option<String>,
     /// Additional provider-specific attributes
     pub attributes: HashMap<String, String>,
+    /// Multi-valued provider-specific attributes, such as group memberships.
+    ///
+    /// Arrays belong here rather than JSON-encoded in `attributes`, so they
+    /// serialize as JSON arrays. Identities serialized before this field
+    /// existed deserialize with it empty.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub attributes_multi: HashMap<String, Vec<String>>,
+}
+
+impl Identity {
+    /// Canonical key for the user across providers, formatted as `"{provider_id}:{external_id}"`.
+    pub fn subject(&self) -> String {
+        format!("{}:{}", self.provider_id, self.external_id)
+    }
+}
+
+impl std::fmt::Debug for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let attributes: HashMap<&str, &str> = self
+            .attributes
+            .iter()
+            .map(|(k, v)| {
+                if SECRET_ATTRIBUTES.contains(&k.as_str()) {
+                    (k.as_str(), REDACTED)
+                } else {
+                    (k.as_str(), v.as_str())
+                }
+            })
+            .collect();
+
+        f.debug_struct("Identity")
+            .field("provider_id", &self.provider_id)
+            .field("external_id", &self.external_id)
+            .field("email", &self.email)
+            .field("email_verified", &self.email_verified)
+            .field("username", &self.username)
+            .field("attributes", &attributes)
+            .field("attributes_multi", &self.attributes_multi)
+            .finish()
+    }
 }
 
 /// Represents the tokens returned by an OAuth2 provider.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// The token values are redacted from `Debug` output. Serialize
+/// [`OAuthToken::redacted`] to log or export a token without them.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct OAuthToken {
     /// The access token used for API requests
     pub access_token: String,
@@ -27,6 +79,12 @@ pub struct OAuthToken {
     /// Seconds until the access token expires
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub expires_in: Option<u64>,
+    /// When the token response was received; `expires_in` counts from here.
+    ///
+    /// Defaults to the time of deserialization, so tokens parsed straight
+    /// from a token response get the fetch time.
+    #[serde(default = "chrono::Utc::now")]
+    pub issued_at: chrono::DateTime<chrono::Utc>,
     /// The refresh token used to obtain new access tokens
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub refresh_token: Option<String>,
@@ -38,6 +96,58 @@ pub struct OAuthToken {
     pub id_token: Option<String>,
 }
 
+impl std::fmt::Debug for OAuthToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuthToken")
+            .field("access_token", &REDACTED)
+            .field("token_type", &self.token_type)
+            .field("expires_in", &self.expires_in)
+            .field("issued_at", &self.issued_at)
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_ref().map(|_| REDACTED),
+            )
+            .field("scope", &self.scope)
+            .field("id_token", &self.id_token.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
+impl OAuthToken {
+    /// When the access token expires: `issued_at + expires_in`, if the
+    /// provider reported a lifetime.
+    pub fn expires_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.expires_in
+            .map(|secs| self.issued_at + chrono::Duration::seconds(secs as i64))
+    }
+
+    /// A view of the token metadata (`token_type`, `expires_in`, `issued_at`,
+    /// `scope`) without the token values, for logging or export.
+    pub fn redacted(&self) -> RedactedOAuthToken<'_> {
+        RedactedOAuthToken {
+            token_type: &self.token_type,
+            expires_in: self.expires_in,
+            issued_at: self.issued_at,
+            scope: self.scope.as_deref(),
+        }
+    }
+}
+
+/// The metadata of an [`OAuthToken`], returned by [`OAuthToken::redacted`].
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactedOAuthToken<'a> {
+    /// The type of token (usually "Bearer")
+    pub token_type: &'a str,
+    /// Seconds until the access token expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_in: Option<u64>,
+    /// When the token response was received
+    pub issued_at: chrono::DateTime<chrono::Utc>,
+    /// The scopes granted by the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<&'a str>,
+}
+
 /// Intermediate state for OAuth2/OIDC flows, stored in an encrypted cookie.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OAuth2State {
@@ -52,6 +162,15 @@ pub struct OAuth2State {
     /// Optional redirect URL to go back to after flow completion
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub success_url: Option<String>,
+    /// Whether the user asked for a long-lived ("remember me") session
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub remember: bool,
+    /// Correlation id of the login request, so the callback can log under the same id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// The scopes requested at login, compared against the granted scopes on callback
+    #[serde(default, skip_serializing_if = "crate::auth::Scopes::is_empty")]
+    pub scopes: crate::auth::Scopes,
     /// The provider identifier
     pub provider_id: String,
     /// Expiration timestamp (seconds since epoch)
@@ -0,0 +1,431 @@
+//! Signed, store-less sessions.
+//!
+//! [`StatelessSession`] is a [`SessionStore`] that keeps nothing server-side:
+//! the session cookie is an HS256-signed token carrying the subject, the expiry
+//! and a short allowlist of identity claims. Loading a session only verifies
+//! the signature, so there is no store round-trip.
+//!
+//! The tradeoff is revocation. Logging out clears the cookie, but a copied
+//! token stays valid until it expires, and deleting sessions server-side
+//! (`delete_session`, `delete_sessions_before`) has no effect. Use a short
+//! `SessionConfig::session_ttl`, and replace the secret to invalidate every
+//! session at once. [`StatelessSession::rotate`] instead keeps accepting the
+//! old secret for a grace period. Updates to a loaded session (e.g. a
+//! refreshed upstream access token) are not persisted either.
+
+use crate::auth::error::AuthError;
+use crate::auth::session::{Session, SessionStore};
+use crate::auth::state::Identity;
+use async_trait::async_trait;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Minimum secret length, matching the HS256 output size.
+const MIN_SECRET_LEN: usize = 32;
+
+#[derive(Serialize, Deserialize)]
+struct StatelessClaims {
+    sub: String,
+    exp: i64,
+    /// Session creation time; tokens signed before it was added have none.
+    #[serde(default)]
+    iat: i64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    claims: BTreeMap<String, String>,
+}
+
+/// A secret that no longer signs, but still verifies until `expires_at`.
+#[derive(Clone)]
+struct PreviousSecret {
+    decoding_key: DecodingKey,
+    expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PreviousSecret {
+    fn is_active(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// A [`SessionStore`] issuing signed session cookies instead of storing sessions.
+///
+/// ```rust,ignore
+/// let store = StatelessSession::new(secret)?.with_claims(["email", "role"]);
+/// let engine = Engine::builder()
+///     .session_store(Arc::new(store))
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct StatelessSession {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    previous: Vec<PreviousSecret>,
+    claims: Vec<String>,
+}
+
+impl std::fmt::Debug for StatelessSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StatelessSession")
+            .field("claims", &self.claims)
+            .field("previous_secrets", &self.previous.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl StatelessSession {
+    /// Create a store signing cookies with `secret`, which must be at least 32 bytes.
+    ///
+    /// Every instance serving the same users must share the secret.
+    pub fn new(secret: &[u8]) -> Result<Self, AuthError> {
+        check_secret(secret)?;
+        Ok(Self {
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            previous: Vec::new(),
+            claims: Vec::new(),
+        })
+    }
+
+    /// Rotates to a new signing secret.
+    ///
+    /// The current secret no longer signs, but still verifies cookies for
+    /// `overlap`, so sessions survive the rotation. Previous secrets that have
+    /// already expired are dropped.
+    pub fn rotate(self, secret: &[u8], overlap: chrono::Duration) -> Result<Self, AuthError> {
+        let next = Self::new(secret)?;
+        let now = chrono::Utc::now();
+        let mut previous: Vec<PreviousSecret> = self
+            .previous
+            .into_iter()
+            .filter(|k| k.is_active(now))
+            .collect();
+        previous.push(PreviousSecret {
+            decoding_key: self.decoding_key,
+            expires_at: now + overlap,
+        });
+
+        Ok(Self {
+            previous,
+            claims: self.claims,
+            ..next
+        })
+    }
+
+    /// Adds a previous secret that verifies cookies until `expires_at`, e.g.
+    /// the old secret restored after a restart.
+    ///
+    /// Secrets with `expires_at` in the past are ignored.
+    pub fn with_previous_secret(
+        mut self,
+        secret: &[u8],
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Self, AuthError> {
+        check_secret(secret)?;
+        if chrono::Utc::now() < expires_at {
+            self.previous.push(PreviousSecret {
+                decoding_key: DecodingKey::from_secret(secret),
+                expires_at,
+            });
+        }
+        Ok(self)
+    }
+
+    /// Copy these identity fields into the cookie: `email`, `username`, or the
+    /// name of an identity attribute. Nothing else survives the round-trip.
+    pub fn with_claims<I, S>(mut self, claims: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.claims = claims.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sign `session` into a cookie value.
+    pub fn sign(&self, session: &Session) -> Result<String, AuthError> {
+        let identity = &session.identity;
+        let claims = self
+            .claims
+            .iter()
+            .filter_map(|name| {
+                let value = match name.as_str() {
+                    "email" => identity.email.clone(),
+                    "username" => identity.username.clone(),
+                    _ => identity.attributes.get(name).cloned(),
+                };
+                value.map(|value| (name.clone(), value))
+            })
+            .collect();
+
+        self.encode(&StatelessClaims {
+            sub: identity.subject(),
+            exp: session.expires_at.timestamp(),
+            iat: session.created_at.timestamp(),
+            claims,
+        })
+    }
+
+    /// Verify a cookie value, rejecting tampered and expired tokens.
+    ///
+    /// The returned session's `id` is the token itself. A token verified with
+    /// a previous secret comes back re-signed with the current one, so setting
+    /// the session cookie from the loaded session (e.g. after
+    /// `Engine::touch_session`) moves it to the current secret.
+    pub fn verify(&self, token: &str) -> Result<Session, AuthError> {
+        let (payload, id) = self.decode(token)?;
+        let expires_at = chrono::DateTime::from_timestamp(payload.exp, 0)
+            .ok_or_else(|| AuthError::Session("Invalid stateless session expiry".to_string()))?;
+        let created_at = chrono::DateTime::from_timestamp(payload.iat, 0).ok_or_else(|| {
+            AuthError::Session("Invalid stateless session creation time".to_string())
+        })?;
+
+        let (provider_id, external_id) = payload
+            .sub
+            .split_once(':')
+            .ok_or_else(|| AuthError::Session("Invalid stateless session subject".to_string()))?;
+        let mut claims = payload.claims;
+        let identity = Identity {
+            provider_id: provider_id.to_string(),
+            external_id: external_id.to_string(),
+            email: claims.remove("email"),
+            email_verified: None,
+            username: claims.remove("username"),
+            attributes: claims.into_iter().collect(),
+            attributes_multi: Default::default(),
+        };
+
+        Ok(Session {
+            id,
+            identity,
+            expires_at,
+            created_at,
+        })
+    }
+
+    fn encode(&self, payload: &StatelessClaims) -> Result<String, AuthError> {
+        jsonwebtoken::encode(&Header::new(Algorithm::HS256), payload, &self.encoding_key)
+            .map_err(|e| AuthError::Session(e.to_string()))
+    }
+
+    /// Decodes `token` with the current secret, then with the previous ones.
+    /// Returns the payload and the token signed with the current secret.
+    fn decode(&self, token: &str) -> Result<(StatelessClaims, String), AuthError> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.leeway = 0;
+        validation.validate_aud = false;
+        validation.set_required_spec_claims(&["exp", "sub"]);
+        let decode = |key: &DecodingKey| {
+            jsonwebtoken::decode::<StatelessClaims>(token, key, &validation).map(|t| t.claims)
+        };
+
+        let mut error = match decode(&self.decoding_key) {
+            Ok(payload) => return Ok((payload, token.to_string())),
+            Err(e) => e,
+        };
+        let now = chrono::Utc::now();
+        for previous in self.previous.iter().filter(|k| k.is_active(now)) {
+            if *error.kind() != ErrorKind::InvalidSignature {
+                break;
+            }
+            match decode(&previous.decoding_key) {
+                Ok(payload) => {
+                    tracing::debug!("re-signing stateless session verified with a previous secret");
+                    let id = self.encode(&payload)?;
+                    return Ok((payload, id));
+                }
+                Err(e) => error = e,
+            }
+        }
+        Err(AuthError::Session(format!(
+            "Invalid stateless session: {error}"
+        )))
+    }
+}
+
+fn check_secret(secret: &[u8]) -> Result<(), AuthError> {
+    if secret.len() < MIN_SECRET_LEN {
+        return Err(AuthError::Session(format!(
+            "Stateless session secret must be at least {MIN_SECRET_LEN} bytes"
+        )));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl SessionStore for StatelessSession {
+    /// Verifies the token. Invalid or expired tokens load as no session.
+    async fn load_session(&self, id: &str) -> Result<Option<Session>, AuthError> {
+        match self.verify(id) {
+            Ok(session) => Ok(Some(session)),
+            Err(e) => {
+                tracing::warn!(error = %e, "rejected stateless session");
+                Ok(None)
+            }
+        }
+    }
+
+    /// No-op: there is nothing to update server-side.
+    async fn save_session(&self, _session: &Session) -> Result<(), AuthError> {
+        Ok(())
+    }
+
+    /// No-op: stateless sessions cannot be revoked before they expire.
+    async fn delete_session(&self, _id: &str) -> Result<(), AuthError> {
+        Ok(())
+    }
+
+    async fn create_session(&self, session: &Session) -> Result<String, AuthError> {
+        self.sign(session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use std::collections::HashMap;
+
+    const SECRET: &[u8] = b"an-example-secret-of-32-bytes!!!";
+
+    fn session(expires_at: chrono::DateTime<Utc>) -> Session {
+        Session {
+            id: "ignored".to_string(),
+            identity: Identity {
+                provider_id: "github".to_string(),
+                external_id: "user:123".to_string(),
+                email: Some("user@example.com".to_string()),
+                email_verified: None,
+                username: Some("user".to_string()),
+                attributes: HashMap::from([
+                    ("role".to_string(), "admin".to_string()),
+                    ("access_token".to_string(), "secret-token".to_string()),
+                ]),
+                attributes_multi: HashMap::new(),
+            },
+            expires_at,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_valid_session_round_trips_minimal_claims() {
+        let store = StatelessSession::new(SECRET)
+            .unwrap()
+            .with_claims(["email", "role"]);
+        let original = session(Utc::now() + Duration::hours(1));
+
+        let token = store.create_session(&original).await.unwrap();
+        let loaded = store.load_session(&token).await.unwrap().unwrap();
+
+        assert_eq!(loaded.id, token);
+        assert_eq!(loaded.identity.subject(), "github:user:123");
+        assert_eq!(loaded.identity.email.as_deref(), Some("user@example.com"));
+        assert_eq!(loaded.identity.username, None);
+        assert_eq!(
+            loaded.identity.attributes,
+            HashMap::from([("role".to_string(), "admin".to_string())])
+        );
+        assert_eq!(
+            loaded.expires_at.timestamp(),
+            original.expires_at.timestamp()
+        );
+        assert_eq!(
+            loaded.created_at.timestamp(),
+            original.created_at.timestamp()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_is_rejected() {
+        let store = StatelessSession::new(SECRET).unwrap();
+        let token = store
+            .sign(&session(Utc::now() - Duration::seconds(1)))
+            .unwrap();
+
+        assert!(store.verify(&token).is_err());
+        assert!(store.load_session(&token).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tampered_session_is_rejected() {
+        let store = StatelessSession::new(SECRET).unwrap();
+        let token = store
+            .sign(&session(Utc::now() + Duration::hours(1)))
+            .unwrap();
+
+        // Swap in a payload for another user, keeping the original signature.
+        let parts: Vec<&str> = token.split('.').collect();
+        let forged = StatelessClaims {
+            sub: "github:attacker".to_string(),
+            exp: (Utc::now() + Duration::hours(1)).timestamp(),
+            iat: Utc::now().timestamp(),
+            claims: BTreeMap::new(),
+        };
+        use base64::Engine as _;
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(&forged).unwrap());
+        let tampered = format!("{}.{}.{}", parts[0], payload, parts[2]);
+        assert!(store.load_session(&tampered).await.unwrap().is_none());
+
+        // A token signed with another secret.
+        let other = StatelessSession::new(b"another-secret-of-at-least-32-b!").unwrap();
+        let foreign = other
+            .sign(&session(Utc::now() + Duration::hours(1)))
+            .unwrap();
+        assert!(store.load_session(&foreign).await.unwrap().is_none());
+
+        assert!(store.load_session("not-a-token").await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_short_secret_is_rejected() {
+        assert!(StatelessSession::new(b"too-short").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rotated_secret_verifies_and_resigns() {
+        const NEXT: &[u8] = b"the-next-secret-of-at-least-32-b";
+        let old = StatelessSession::new(SECRET).unwrap();
+        let token = old.sign(&session(Utc::now() + Duration::hours(1))).unwrap();
+
+        let rotated = old.rotate(NEXT, Duration::hours(1)).unwrap();
+        let loaded = rotated.load_session(&token).await.unwrap().unwrap();
+        assert_eq!(loaded.identity.subject(), "github:user:123");
+
+        // The loaded id is signed with the new secret alone.
+        assert_ne!(loaded.id, token);
+        let next = StatelessSession::new(NEXT).unwrap();
+        assert!(next.verify(&token).is_err());
+        assert_eq!(next.verify(&loaded.id).unwrap().id, loaded.id);
+        assert_eq!(rotated.verify(&loaded.id).unwrap().id, loaded.id);
+    }
+
+    #[tokio::test]
+    async fn test_previous_secret_is_dropped_after_its_grace_period() {
+        const NEXT: &[u8] = b"the-next-secret-of-at-least-32-b";
+        let old = StatelessSession::new(SECRET).unwrap();
+        let token = old.sign(&session(Utc::now() + Duration::hours(1))).unwrap();
+
+        let expired = old.clone().rotate(NEXT, Duration::zero()).unwrap();
+        assert!(expired.load_session(&token).await.unwrap().is_none());
+
+        let restored = StatelessSession::new(NEXT)
+            .unwrap()
+            .with_previous_secret(SECRET, Utc::now() + Duration::hours(1))
+            .unwrap();
+        assert!(restored.load_session(&token).await.unwrap().is_some());
+        let lapsed = StatelessSession::new(NEXT)
+            .unwrap()
+            .with_previous_secret(SECRET, Utc::now() - Duration::seconds(1))
+            .unwrap();
+        assert!(lapsed.load_session(&token).await.unwrap().is_none());
+
+        // An expired token stays rejected whichever secret signed it.
+        let stale = old
+            .sign(&session(Utc::now() - Duration::seconds(1)))
+            .unwrap();
+        assert!(restored.verify(&stale).is_err());
+    }
+}
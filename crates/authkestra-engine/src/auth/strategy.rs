@@ -1,6 +1,11 @@
+use crate::auth::claims::HasTokenId;
 use crate::error::AuthError;
+use crate::store::{AtomicInsert, KvStore};
 use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
 use http::request::Parts;
+use sha2::{Digest, Sha256};
 use std::marker::PhantomData;
 
 /// Trait for an authentication strategy.
@@ -71,20 +76,58 @@ pub trait TokenValidator: Send + Sync {
     async fn validate(&self, token: &str) -> Result<Option<Self::Identity>, AuthError>;
 }
 
+/// A location a bearer token can be read from.
+///
+/// [`TokenSource::Header`] is the default and the recommended source. Query string
+/// tokens end up in server logs, browser history, proxies and `Referer` headers, so
+/// only enable [`TokenSource::Query`] for clients that cannot set headers (SSE,
+/// download links, websockets), and prefer short-lived tokens there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenSource {
+    /// The `Authorization: Bearer <token>` header.
+    Header,
+    /// A query string parameter, e.g. `?access_token=<token>`.
+    Query(String),
+    /// A cookie with the given name.
+    Cookie(String),
+}
+
+impl TokenSource {
+    /// The standard `access_token` query parameter (RFC 6750, section 2.3).
+    pub fn query() -> Self {
+        Self::Query("access_token".to_string())
+    }
+
+    /// A cookie with the given name.
+    pub fn cookie(name: impl Into<String>) -> Self {
+        Self::Cookie(name.into())
+    }
+}
+
 /// Strategy for Token (Bearer) authentication.
 pub struct TokenStrategy<V, I> {
     validator: V,
+    sources: Vec<TokenSource>,
     _marker: PhantomData<I>,
 }
 
 impl<V, I> TokenStrategy<V, I> {
     /// Create a new TokenStrategy with the given validator.
+    ///
+    /// Tokens are read from the `Authorization` header only.
     pub fn new(validator: V) -> Self {
         Self {
             validator,
+            sources: vec![TokenSource::Header],
             _marker: PhantomData,
         }
     }
+
+    /// Set the sources the token is read from, in order of precedence.
+    pub fn with_sources(mut self, sources: Vec<TokenSource>) -> Self {
+        self.sources = sources;
+        self
+    }
 }
 
 #[async_trait]
@@ -94,24 +137,80 @@ where
     I: Send + Sync + 'static,
 {
     async fn authenticate(&self, parts: &Parts) -> Result<Option<I>, AuthError> {
-        if let Some(token) = utils::extract_bearer_token(&parts.headers) {
-            self.validator.validate(token).await
+        if let Some(token) = utils::extract_token(parts, &self.sources) {
+            self.validator.validate(&token).await
         } else {
             Ok(None)
         }
     }
 }
 
+/// Trait for a validator that verifies the value of a custom header.
+///
+/// Implemented for closures `Fn(String) -> impl Future` returning
+/// `Result<Option<I>, AuthError>`. Implement it on a struct instead when the
+/// validator holds state (a database pool, an HTTP client) that the future
+/// should borrow rather than clone.
+#[async_trait]
+pub trait HeaderValidator<I>: Send + Sync {
+    /// Validate the header value.
+    async fn validate(&self, value: String) -> Result<Option<I>, AuthError>;
+}
+
+#[async_trait]
+impl<F, Fut, I> HeaderValidator<I> for F
+where
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: std::future::Future<Output = Result<Option<I>, AuthError>> + Send,
+    I: Send + 'static,
+{
+    async fn validate(&self, value: String) -> Result<Option<I>, AuthError> {
+        self(value).await
+    }
+}
+
+/// A [`HeaderValidator`] calling a closure with a clone of its state.
+///
+/// Built by [`HeaderStrategy::with_state`].
+pub struct WithState<S, F> {
+    state: S,
+    validator: F,
+}
+
+#[async_trait]
+impl<S, F, Fut, I> HeaderValidator<I> for WithState<S, F>
+where
+    S: Clone + Send + Sync,
+    F: Fn(S, String) -> Fut + Send + Sync,
+    Fut: std::future::Future<Output = Result<Option<I>, AuthError>> + Send,
+    I: Send + 'static,
+{
+    async fn validate(&self, value: String) -> Result<Option<I>, AuthError> {
+        (self.validator)(self.state.clone(), value).await
+    }
+}
+
 /// Strategy for custom header authentication.
-pub struct HeaderStrategy<F, I> {
+///
+/// Strategies are shared across request tasks, so the validator must be
+/// `Send + Sync` and the future it returns must be `Send`. A closure
+/// capturing a `reqwest::Client`, an `Arc` or a `sqlx` pool meets both;
+/// state that is not `Sync` (a `RefCell`, a `Cell`) has to be wrapped in a
+/// `Mutex` first.
+///
+/// The future returned by a `Fn(String) -> Fut` closure cannot borrow what
+/// the closure captured, so captured state has to be cloned into it on every
+/// call. [`HeaderStrategy::with_state`] does that clone for you, or implement
+/// [`HeaderValidator`] on your own type to borrow the state instead.
+pub struct HeaderStrategy<V, I> {
     header_name: http::header::HeaderName,
-    validator: F,
+    validator: V,
     _marker: PhantomData<I>,
 }
 
-impl<F, I> HeaderStrategy<F, I> {
+impl<V, I> HeaderStrategy<V, I> {
     /// Create a new HeaderStrategy.
-    pub fn new(header_name: http::header::HeaderName, validator: F) -> Self {
+    pub fn new(header_name: http::header::HeaderName, validator: V) -> Self {
         Self {
             header_name,
             validator,
@@ -120,23 +219,161 @@ impl<F, I> HeaderStrategy<F, I> {
     }
 }
 
+impl<S, F, I> HeaderStrategy<WithState<S, F>, I> {
+    /// Create a HeaderStrategy whose validator receives a clone of `state`
+    /// along with the header value, e.g. an `Arc`-wrapped pool or a
+    /// `reqwest::Client`.
+    pub fn with_state(header_name: http::header::HeaderName, state: S, validator: F) -> Self {
+        Self::new(header_name, WithState { state, validator })
+    }
+}
+
 #[async_trait]
-impl<F, I, Fut> AuthenticationStrategy<I> for HeaderStrategy<F, I>
+impl<V, I> AuthenticationStrategy<I> for HeaderStrategy<V, I>
 where
-    F: Fn(String) -> Fut + Send + Sync,
-    Fut: std::future::Future<Output = Result<Option<I>, AuthError>> + Send,
+    V: HeaderValidator<I>,
     I: Send + Sync + 'static,
 {
     async fn authenticate(&self, parts: &Parts) -> Result<Option<I>, AuthError> {
         if let Some(value) = parts.headers.get(&self.header_name) {
             if let Ok(value_str) = value.to_str() {
-                return (self.validator)(value_str.to_string()).await;
+                return self.validator.validate(value_str.to_string()).await;
             }
         }
         Ok(None)
     }
 }
 
+/// Authenticates gateway traffic from a header set by a trusted edge proxy,
+/// falling back to another strategy for direct callers.
+///
+/// The trusted header takes precedence: when upstream trust is enabled and the
+/// header is present, its [`HeaderStrategy`] runs first and, if it yields an
+/// identity, the fallback (typically a `JwtStrategy`) is not run at all. If it
+/// yields none, or the header is absent, the fallback authenticates the request.
+///
+/// Upstream trust is off by default, since anyone can set the header on a
+/// request that does not pass through the proxy. While it is off the header is
+/// ignored and a present one is reported as a security event: a `WARN` event
+/// with target `authkestra::security` and `event = "untrusted_upstream_header"`.
+/// Only call [`GatewayStrategy::trust_upstream`] when every request passes
+/// through a proxy that strips the header from client requests.
+///
+/// ```rust,ignore
+/// let gateway = GatewayStrategy::new(
+///     HeaderStrategy::new(HeaderName::from_static("x-user-id"), load_user),
+///     JwtStrategy::new(jwks_url, validation),
+/// )
+/// .trust_upstream(config.behind_proxy);
+/// ```
+pub struct GatewayStrategy<I> {
+    header_name: http::header::HeaderName,
+    trusted: Box<dyn AuthenticationStrategy<I>>,
+    fallback: Box<dyn AuthenticationStrategy<I>>,
+    trust_upstream: bool,
+}
+
+impl<I> GatewayStrategy<I> {
+    /// Combine the `trusted` header strategy with the `fallback` for direct callers.
+    pub fn new<V>(
+        trusted: HeaderStrategy<V, I>,
+        fallback: impl AuthenticationStrategy<I> + 'static,
+    ) -> Self
+    where
+        HeaderStrategy<V, I>: AuthenticationStrategy<I> + 'static,
+    {
+        Self {
+            header_name: trusted.header_name.clone(),
+            trusted: Box::new(trusted),
+            fallback: Box::new(fallback),
+            trust_upstream: false,
+        }
+    }
+
+    /// Whether the trusted header is honoured. Off by default.
+    pub fn trust_upstream(mut self, trust: bool) -> Self {
+        self.trust_upstream = trust;
+        self
+    }
+}
+
+#[async_trait]
+impl<I> AuthenticationStrategy<I> for GatewayStrategy<I>
+where
+    I: Send + Sync + 'static,
+{
+    async fn authenticate(&self, parts: &Parts) -> Result<Option<I>, AuthError> {
+        if parts.headers.contains_key(&self.header_name) {
+            if self.trust_upstream {
+                if let Some(identity) = self.trusted.authenticate(parts).await? {
+                    tracing::debug!("identity taken from trusted upstream header");
+                    return Ok(Some(identity));
+                }
+            } else {
+                tracing::warn!(
+                    target: "authkestra::security",
+                    event = "untrusted_upstream_header",
+                    header = %self.header_name,
+                    "ignoring upstream identity header, upstream trust is disabled"
+                );
+            }
+        }
+        self.fallback.authenticate(parts).await
+    }
+}
+
+/// Converts the identities of another strategy, so strategies producing
+/// different types can share one `Guard`.
+///
+/// ```rust,ignore
+/// let guard = Guard::<AppUser>::builder()
+///     .strategy(MapIdentity::new(jwt_strategy, |claims: Claims| AppUser::from(claims)))
+///     .strategy(MapIdentity::try_new(session_strategy, AppUser::try_from))
+///     .build();
+/// ```
+///
+/// A failed conversion is an error of the strategy, handled by the guard's
+/// policy like any other. `Ok(None)` from the inner strategy is passed on.
+pub struct MapIdentity<S, A, B> {
+    inner: S,
+    map: Box<dyn Fn(A) -> Result<B, AuthError> + Send + Sync>,
+}
+
+impl<S, A, B> MapIdentity<S, A, B> {
+    /// Convert the identities of `inner` with `map`.
+    pub fn new(inner: S, map: impl Fn(A) -> B + Send + Sync + 'static) -> Self {
+        Self::try_new(inner, move |identity| Ok(map(identity)))
+    }
+
+    /// Convert the identities of `inner` with a fallible `map`, e.g. a
+    /// `TryFrom` implementation with `AuthError` as its error.
+    pub fn try_new(
+        inner: S,
+        map: impl Fn(A) -> Result<B, AuthError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            inner,
+            map: Box::new(map),
+        }
+    }
+}
+
+#[async_trait]
+impl<S, A, B> AuthenticationStrategy<B> for MapIdentity<S, A, B>
+where
+    S: AuthenticationStrategy<A>,
+    A: Send + 'static,
+    B: Send + Sync + 'static,
+{
+    async fn authenticate(&self, parts: &Parts) -> Result<Option<B>, AuthError> {
+        self.inner
+            .authenticate(parts)
+            .await?
+            .map(&self.map)
+            .transpose()
+    }
+}
+
 /// Trait for a session store that can load an identity.
 #[async_trait]
 pub trait SessionProvider: Send + Sync {
@@ -150,6 +387,7 @@ pub trait SessionProvider: Send + Sync {
 pub struct SessionStrategy<P, I> {
     provider: P,
     cookie_name: String,
+    fallback_cookie_names: Vec<String>,
     _marker: PhantomData<I>,
 }
 
@@ -159,9 +397,16 @@ impl<P, I> SessionStrategy<P, I> {
         Self {
             provider,
             cookie_name: cookie_name.into(),
+            fallback_cookie_names: Vec::new(),
             _marker: PhantomData,
         }
     }
+
+    /// Also look sessions up under these legacy cookie names, in order, after the primary one.
+    pub fn with_fallback_cookies(mut self, names: Vec<String>) -> Self {
+        self.fallback_cookie_names = names;
+        self
+    }
 }
 
 #[async_trait]
@@ -171,17 +416,368 @@ where
     I: Send + Sync + 'static,
 {
     async fn authenticate(&self, parts: &Parts) -> Result<Option<I>, AuthError> {
-        if let Some(session_id) = utils::extract_cookie(&parts.headers, &self.cookie_name) {
-            self.provider.load_session(session_id).await
-        } else {
-            Ok(None)
+        let names = std::iter::once(&self.cookie_name).chain(&self.fallback_cookie_names);
+        for name in names {
+            if let Some(session_id) = utils::extract_cookie(&parts.headers, name) {
+                if let Some(identity) = self.provider.load_session(session_id).await? {
+                    return Ok(Some(identity));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Returns when a cached identity stops being valid, if known.
+pub type ExpiryFn<I> = Box<dyn Fn(&I) -> Option<chrono::DateTime<chrono::Utc>> + Send + Sync>;
+
+/// Caches identities produced by another strategy, keyed by a hash of the credential.
+///
+/// Useful in front of strategies that introspect opaque tokens or hit a database
+/// on every request. The credential is read from the configured [`TokenSource`]s
+/// (the `Authorization` header by default) and only its SHA-256 digest is used as
+/// the cache key. Requests without a credential go straight to the inner strategy,
+/// and only successful authentications are cached.
+///
+/// Entries live for the configured TTL, or until the identity's own expiry when
+/// [`CachingStrategy::with_expiry`] is set, whichever comes first. A revoked token
+/// keeps authenticating until its entry expires, so keep the TTL short.
+pub struct CachingStrategy<I> {
+    inner: Box<dyn AuthenticationStrategy<I>>,
+    store: Box<dyn KvStore<I>>,
+    ttl: std::time::Duration,
+    sources: Vec<TokenSource>,
+    expires_at: Option<ExpiryFn<I>>,
+}
+
+impl<I> CachingStrategy<I> {
+    /// Default time a cached identity is reused for.
+    pub const DEFAULT_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
+    /// Wrap `inner`, caching its identities in `store`.
+    pub fn new(inner: impl AuthenticationStrategy<I> + 'static, store: impl KvStore<I>) -> Self {
+        Self {
+            inner: Box::new(inner),
+            store: Box::new(store),
+            ttl: Self::DEFAULT_TTL,
+            sources: vec![TokenSource::Header],
+            expires_at: None,
+        }
+    }
+
+    /// Set how long a cached identity is reused for.
+    pub fn with_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set the sources the credential is read from. Match the inner strategy's sources.
+    pub fn with_sources(mut self, sources: Vec<TokenSource>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Cap cache entries at the expiry reported for each identity.
+    pub fn with_expiry(
+        mut self,
+        expires_at: impl Fn(&I) -> Option<chrono::DateTime<chrono::Utc>> + Send + Sync + 'static,
+    ) -> Self {
+        self.expires_at = Some(Box::new(expires_at));
+        self
+    }
+
+    fn cache_key(token: &str) -> String {
+        let digest = Sha256::digest(token.as_bytes());
+        format!("strategy_cache:{}", URL_SAFE_NO_PAD.encode(digest))
+    }
+
+    /// The TTL for `identity`, or `None` if it is already expired.
+    fn entry_ttl(&self, identity: &I) -> Option<std::time::Duration> {
+        let Some(expires_at) = self.expires_at.as_ref().and_then(|f| f(identity)) else {
+            return Some(self.ttl);
+        };
+        let remaining = (expires_at - chrono::Utc::now()).to_std().ok()?;
+        (!remaining.is_zero()).then(|| remaining.min(self.ttl))
+    }
+}
+
+#[async_trait]
+impl<I> AuthenticationStrategy<I> for CachingStrategy<I>
+where
+    I: Clone + Send + Sync + 'static,
+{
+    #[tracing::instrument(skip_all)]
+    async fn authenticate(&self, parts: &Parts) -> Result<Option<I>, AuthError> {
+        let Some(token) = utils::extract_token(parts, &self.sources) else {
+            return self.inner.authenticate(parts).await;
+        };
+        let key = Self::cache_key(&token);
+
+        match self.store.get(&key).await {
+            Ok(Some(identity)) => {
+                tracing::debug!("identity served from strategy cache");
+                return Ok(Some(identity));
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(error = %e, "strategy cache lookup failed"),
+        }
+
+        let identity = self.inner.authenticate(parts).await?;
+        if let Some(identity) = &identity {
+            match self.entry_ttl(identity) {
+                Some(ttl) => {
+                    if let Err(e) = self.store.set(&key, identity.clone(), ttl).await {
+                        tracing::warn!(error = %e, "failed to write strategy cache");
+                    }
+                }
+                None => tracing::debug!("identity already expired, not caching"),
+            }
+        }
+        Ok(identity)
+    }
+}
+
+/// Accepts each token of another strategy at most once, by its `jti`.
+///
+/// Meant for high-value operations that take single-use tokens. The first
+/// request with a token records its `jti` in the store until the token's
+/// `exp`; any later request with the same `jti` fails with
+/// [`AuthError::TokenReplayed`], even if the token is otherwise valid. This is
+/// stricter than revocation: the token is spent by its first use. Tokens
+/// without a `jti` are rejected with [`AuthError::Token`].
+///
+/// The `jti` is recorded with [`AtomicInsert::insert_if_absent`], so two
+/// concurrent requests with the same token cannot both succeed. Share the
+/// store between instances (e.g. Redis or SQL) to enforce this across them.
+pub struct OneTimeTokenStrategy<I> {
+    inner: Box<dyn AuthenticationStrategy<I>>,
+    store: Box<dyn AtomicInsert<()>>,
+}
+
+impl<I> OneTimeTokenStrategy<I> {
+    /// Wrap `inner`, recording the `jti`s of its identities in `store`.
+    pub fn new(
+        inner: impl AuthenticationStrategy<I> + 'static,
+        store: impl AtomicInsert<()>,
+    ) -> Self {
+        Self {
+            inner: Box::new(inner),
+            store: Box::new(store),
         }
     }
 }
 
+#[async_trait]
+impl<I> AuthenticationStrategy<I> for OneTimeTokenStrategy<I>
+where
+    I: HasTokenId + Send + Sync + 'static,
+{
+    #[tracing::instrument(skip_all)]
+    async fn authenticate(&self, parts: &Parts) -> Result<Option<I>, AuthError> {
+        let Some(identity) = self.inner.authenticate(parts).await? else {
+            return Ok(None);
+        };
+        let jti = identity
+            .token_id()
+            .filter(|jti| !jti.is_empty())
+            .ok_or_else(|| AuthError::Token("Missing jti claim".to_string()))?;
+
+        let remaining = identity
+            .expires_at()
+            .saturating_sub(chrono::Utc::now().timestamp().max(0) as u64);
+        if remaining == 0 {
+            return Err(AuthError::Token("Token has expired".to_string()));
+        }
+
+        let key = format!("one_time_token:{jti}");
+        let ttl = std::time::Duration::from_secs(remaining);
+        let first_use = self
+            .store
+            .insert_if_absent(&key, (), ttl)
+            .await
+            .map_err(|e| AuthError::Token(format!("Failed to record token use: {e}")))?;
+        if !first_use {
+            tracing::warn!(
+                target: "authkestra::security",
+                event = "token_replayed",
+                jti = %jti,
+                "one-time token presented again"
+            );
+            return Err(AuthError::TokenReplayed);
+        }
+        Ok(Some(identity))
+    }
+}
+
+/// A client allowed to sign requests for [`HmacSignatureStrategy`].
+pub struct HmacClient<I> {
+    /// The shared secret the client signs with.
+    pub secret: Vec<u8>,
+    /// The identity authenticated by a valid signature.
+    pub identity: I,
+}
+
+/// Trait for a store that looks up signing clients by ID.
+#[async_trait]
+pub trait HmacClientStore: Send + Sync {
+    /// The type of identity returned by this store.
+    type Identity;
+    /// Load the client with the given ID.
+    async fn load_client(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<HmacClient<Self::Identity>>, AuthError>;
+}
+
+/// The SHA-256 digest of a request body, for [`HmacSignatureStrategy`].
+///
+/// Strategies only see the request head, so the body has to be hashed by
+/// whoever buffers it (a middleware) and the digest inserted into the request
+/// extensions. Without it, the digest of an empty body is assumed, so a signed
+/// request with a body fails verification instead of passing unchecked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyDigest([u8; 32]);
+
+impl BodyDigest {
+    /// The digest of `body`.
+    pub fn of(body: &[u8]) -> Self {
+        Self(Sha256::digest(body).into())
+    }
+}
+
+/// Strategy for APIs authenticating each request with an HMAC-SHA256 signature.
+///
+/// The client sends its ID in `X-Client-Id`, the Unix time in seconds in
+/// `X-Timestamp`, and in `X-Signature` the base64 HMAC, keyed with its secret,
+/// of:
+///
+/// ```text
+/// {timestamp}\n{METHOD}\n{path and query}\n{base64 SHA-256 of the body}
+/// ```
+///
+/// See [`HmacSignatureStrategy::sign`]. Requests without `X-Signature` are
+/// left to other strategies. Requests whose timestamp is further than the
+/// allowed skew (5 minutes by default) from the server clock are rejected;
+/// within that window a captured request can be replayed as is, so pair the
+/// strategy with a nonce check for non-idempotent endpoints.
+pub struct HmacSignatureStrategy<I> {
+    clients: Box<dyn HmacClientStore<Identity = I>>,
+    max_skew: std::time::Duration,
+}
+
+impl<I> HmacSignatureStrategy<I> {
+    /// The header carrying the client ID.
+    pub const CLIENT_ID_HEADER: &'static str = "x-client-id";
+    /// The header carrying the Unix timestamp, in seconds.
+    pub const TIMESTAMP_HEADER: &'static str = "x-timestamp";
+    /// The header carrying the signature.
+    pub const SIGNATURE_HEADER: &'static str = "x-signature";
+    /// Default tolerated difference between the request timestamp and the server clock.
+    pub const DEFAULT_MAX_SKEW: std::time::Duration = std::time::Duration::from_secs(300);
+
+    /// Create a new HmacSignatureStrategy looking clients up in `clients`.
+    pub fn new(clients: impl HmacClientStore<Identity = I> + 'static) -> Self {
+        Self {
+            clients: Box::new(clients),
+            max_skew: Self::DEFAULT_MAX_SKEW,
+        }
+    }
+
+    /// Set the tolerated difference between the request timestamp and the server clock.
+    pub fn with_max_skew(mut self, max_skew: std::time::Duration) -> Self {
+        self.max_skew = max_skew;
+        self
+    }
+
+    /// The `X-Signature` value for a request, as computed by the client.
+    pub fn sign(
+        secret: &[u8],
+        method: &http::Method,
+        path_and_query: &str,
+        timestamp: i64,
+        body: &[u8],
+    ) -> String {
+        let mac = Self::mac(
+            secret,
+            method,
+            path_and_query,
+            timestamp,
+            &BodyDigest::of(body),
+        );
+        base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+    }
+
+    fn mac(
+        secret: &[u8],
+        method: &http::Method,
+        path_and_query: &str,
+        timestamp: i64,
+        body: &BodyDigest,
+    ) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        let body = base64::engine::general_purpose::STANDARD.encode(body.0);
+        mac.update(format!("{timestamp}\n{method}\n{path_and_query}\n{body}").as_bytes());
+        mac
+    }
+}
+
+#[async_trait]
+impl<I> AuthenticationStrategy<I> for HmacSignatureStrategy<I>
+where
+    I: Send + Sync + 'static,
+{
+    #[tracing::instrument(skip_all)]
+    async fn authenticate(&self, parts: &Parts) -> Result<Option<I>, AuthError> {
+        let header = |name: &str| parts.headers.get(name).and_then(|v| v.to_str().ok());
+        let Some(signature) = header(Self::SIGNATURE_HEADER) else {
+            return Ok(None);
+        };
+        let signature = base64::engine::general_purpose::STANDARD
+            .decode(signature)
+            .map_err(|_| AuthError::InvalidCredentials)?;
+        let (Some(client_id), Some(timestamp)) = (
+            header(Self::CLIENT_ID_HEADER),
+            header(Self::TIMESTAMP_HEADER).and_then(|t| t.parse::<i64>().ok()),
+        ) else {
+            tracing::debug!("signed request without a client ID or timestamp");
+            return Err(AuthError::InvalidCredentials);
+        };
+
+        let skew = chrono::Utc::now().timestamp().abs_diff(timestamp);
+        if skew > self.max_skew.as_secs() {
+            tracing::debug!(skew, "signed request timestamp outside the allowed window");
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        let Some(client) = self.clients.load_client(client_id).await? else {
+            tracing::debug!("signed request from an unknown client");
+            return Err(AuthError::InvalidCredentials);
+        };
+        let path_and_query = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+        let body = parts
+            .extensions
+            .get::<BodyDigest>()
+            .copied()
+            .unwrap_or_else(|| BodyDigest::of(b""));
+        Self::mac(
+            &client.secret,
+            &parts.method,
+            path_and_query,
+            timestamp,
+            &body,
+        )
+        .verify_slice(&signature)
+        .map_err(|_| AuthError::InvalidCredentials)?;
+        Ok(Some(client.identity))
+    }
+}
+
 /// Utility functions for common authentication tasks.
 pub mod utils {
+    use super::TokenSource;
     use http::header::{HeaderMap, AUTHORIZATION};
+    use http::request::Parts;
 
     /// Extract the Bearer token from the Authorization header.
     pub fn extract_bearer_token(headers: &HeaderMap) -> Option<&str> {
@@ -193,33 +789,679 @@ pub mod utils {
             .map(|s| s.trim())
     }
 
+    /// Extract a query string parameter by name, percent-decoded.
+    pub fn extract_query_param(parts: &Parts, name: &str) -> Option<String> {
+        url::form_urlencoded::parse(parts.uri.query()?.as_bytes())
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    }
+
+    /// Extract a non-empty token from the first of `sources` that provides one.
+    pub fn extract_token(parts: &Parts, sources: &[TokenSource]) -> Option<String> {
+        sources.iter().find_map(|source| {
+            let token = match source {
+                TokenSource::Header => extract_bearer_token(&parts.headers).map(str::to_string),
+                TokenSource::Query(name) => extract_query_param(parts, name),
+                TokenSource::Cookie(name) => {
+                    extract_cookie(&parts.headers, name).map(str::to_string)
+                }
+            };
+            token.filter(|t| !t.is_empty())
+        })
+    }
+
     /// Extract Basic credentials from the Authorization header.
+    ///
+    /// The credentials are decoded as standard base64, with or without padding,
+    /// falling back to the URL-safe alphabet for clients that send base64url. They
+    /// are split on the first `:`, so the password may contain colons.
     pub fn extract_basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+        use base64::engine::general_purpose::{
+            STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD,
+        };
+
         let auth_header = headers.get(AUTHORIZATION)?.to_str().ok()?;
-        if !auth_header.starts_with("Basic ") {
-            return None;
-        }
         let encoded = auth_header.strip_prefix("Basic ")?.trim();
-        let decoded =
-            base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded).ok()?;
+        let decoded = [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD]
+            .iter()
+            .find_map(|engine| base64::Engine::decode(engine, encoded).ok())?;
         let decoded_str = String::from_utf8(decoded).ok()?;
-        let mut parts = decoded_str.splitn(2, ':');
-        let username = parts.next()?.to_string();
-        let password = parts.next()?.to_string();
-        Some((username, password))
+        let (username, password) = decoded_str.split_once(':')?;
+        Some((username.to_string(), password.to_string()))
     }
 
     /// Extract a cookie value by name.
+    ///
+    /// Follows RFC 6265: pairs are split on the first `=`, whitespace around names
+    /// and values is trimmed and a value wrapped in double quotes is unquoted.
+    /// Segments without `=` or with an empty name are skipped. All `Cookie`
+    /// headers are searched, in order.
     pub fn extract_cookie<'a>(headers: &'a http::HeaderMap, name: &str) -> Option<&'a str> {
-        let cookie_header = headers.get(http::header::COOKIE)?.to_str().ok()?;
-        for cookie in cookie_header.split(';') {
-            let mut parts = cookie.splitn(2, '=');
-            let k = parts.next()?.trim();
-            let v = parts.next()?.trim();
-            if k == name {
-                return Some(v);
+        if name.is_empty() {
+            return None;
+        }
+        headers
+            .get_all(http::header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|header| header.split(';'))
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(k, _)| k.trim() == name)
+            .map(|(_, v)| {
+                let v = v.trim();
+                v.strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(v)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EchoValidator;
+
+    #[async_trait]
+    impl TokenValidator for EchoValidator {
+        type Identity = String;
+
+        async fn validate(&self, token: &str) -> Result<Option<String>, AuthError> {
+            Ok(Some(token.to_string()))
+        }
+    }
+
+    fn parts(uri: &str, headers: &[(&str, &str)]) -> Parts {
+        let mut builder = http::Request::builder().uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    fn all_sources() -> Vec<TokenSource> {
+        vec![
+            TokenSource::Header,
+            TokenSource::query(),
+            TokenSource::cookie("ak_token"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_token_strategy_defaults_to_header_only() {
+        let strategy = TokenStrategy::new(EchoValidator);
+
+        let from_header = parts("/", &[("authorization", "Bearer header-token")]);
+        assert_eq!(
+            strategy.authenticate(&from_header).await.unwrap(),
+            Some("header-token".to_string())
+        );
+
+        let from_query = parts("/?access_token=query-token", &[]);
+        assert_eq!(strategy.authenticate(&from_query).await.unwrap(), None);
+
+        let from_cookie = parts("/", &[("cookie", "ak_token=cookie-token")]);
+        assert_eq!(strategy.authenticate(&from_cookie).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_token_strategy_reads_query() {
+        let strategy = TokenStrategy::new(EchoValidator).with_sources(vec![TokenSource::query()]);
+        let parts = parts("/events?foo=bar&access_token=abc%2Edef", &[]);
+        assert_eq!(
+            strategy.authenticate(&parts).await.unwrap(),
+            Some("abc.def".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_token_strategy_reads_cookie() {
+        let strategy =
+            TokenStrategy::new(EchoValidator).with_sources(vec![TokenSource::cookie("ak_token")]);
+        let parts = parts("/", &[("cookie", "other=1; ak_token=cookie-token")]);
+        assert_eq!(
+            strategy.authenticate(&parts).await.unwrap(),
+            Some("cookie-token".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_token_strategy_source_precedence() {
+        let strategy = TokenStrategy::new(EchoValidator).with_sources(all_sources());
+        let all = parts(
+            "/?access_token=query-token",
+            &[
+                ("authorization", "Bearer header-token"),
+                ("cookie", "ak_token=cookie-token"),
+            ],
+        );
+        assert_eq!(
+            strategy.authenticate(&all).await.unwrap(),
+            Some("header-token".to_string())
+        );
+
+        let query_and_cookie = parts(
+            "/?access_token=query-token",
+            &[("cookie", "ak_token=cookie-token")],
+        );
+        assert_eq!(
+            strategy.authenticate(&query_and_cookie).await.unwrap(),
+            Some("query-token".to_string())
+        );
+
+        let reversed = TokenStrategy::new(EchoValidator)
+            .with_sources(vec![TokenSource::cookie("ak_token"), TokenSource::Header]);
+        let header_and_cookie = parts(
+            "/",
+            &[
+                ("authorization", "Bearer header-token"),
+                ("cookie", "ak_token=cookie-token"),
+            ],
+        );
+        assert_eq!(
+            reversed.authenticate(&header_and_cookie).await.unwrap(),
+            Some("cookie-token".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_token_strategy_skips_empty_values() {
+        let strategy = TokenStrategy::new(EchoValidator).with_sources(all_sources());
+        let parts = parts("/?access_token=", &[("cookie", "ak_token=cookie-token")]);
+        assert_eq!(
+            strategy.authenticate(&parts).await.unwrap(),
+            Some("cookie-token".to_string())
+        );
+    }
+
+    struct KnownSessions;
+
+    #[async_trait]
+    impl SessionProvider for KnownSessions {
+        type Identity = String;
+
+        async fn load_session(&self, session_id: &str) -> Result<Option<String>, AuthError> {
+            Ok(session_id.strip_prefix("sid-").map(|user| user.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_strategy_falls_back_to_legacy_cookies() {
+        let strategy = SessionStrategy::new(KnownSessions, "authkestra_session")
+            .with_fallback_cookies(vec!["authly_session".to_string()]);
+
+        let legacy = parts("/", &[("cookie", "authly_session=sid-alice")]);
+        assert_eq!(
+            strategy.authenticate(&legacy).await.unwrap(),
+            Some("alice".to_string())
+        );
+
+        let both = parts(
+            "/",
+            &[(
+                "cookie",
+                "authly_session=sid-alice; authkestra_session=sid-bob",
+            )],
+        );
+        assert_eq!(
+            strategy.authenticate(&both).await.unwrap(),
+            Some("bob".to_string())
+        );
+
+        let stale_primary = parts(
+            "/",
+            &[(
+                "cookie",
+                "authkestra_session=gone; authly_session=sid-alice",
+            )],
+        );
+        assert_eq!(
+            strategy.authenticate(&stale_primary).await.unwrap(),
+            Some("alice".to_string())
+        );
+    }
+
+    /// Stands in for a database pool: cheap to clone, queried asynchronously.
+    #[derive(Clone)]
+    struct Pool(std::sync::Arc<std::collections::HashMap<String, String>>);
+
+    impl Pool {
+        fn with_key(key: &str, user: &str) -> Self {
+            Self(std::sync::Arc::new(
+                [(key.to_string(), user.to_string())].into(),
+            ))
+        }
+
+        async fn user_for_key(&self, key: &str) -> Result<Option<String>, AuthError> {
+            Ok(self.0.get(key).cloned())
+        }
+    }
+
+    async fn authenticate_key<V: HeaderValidator<String>>(
+        strategy: &HeaderStrategy<V, String>,
+        key: &str,
+    ) -> Option<String> {
+        let parts = parts("/", &[("x-api-key", key)]);
+        strategy.authenticate(&parts).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_header_strategy_closure_capturing_a_pool() {
+        let pool = Pool::with_key("key-1", "alice");
+        let strategy = HeaderStrategy::new(
+            http::header::HeaderName::from_static("x-api-key"),
+            move |key: String| {
+                let pool = pool.clone();
+                async move { pool.user_for_key(&key).await }
+            },
+        );
+
+        assert_eq!(
+            authenticate_key(&strategy, "key-1").await,
+            Some("alice".to_string())
+        );
+        assert_eq!(authenticate_key(&strategy, "key-2").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_header_strategy_with_state() {
+        let strategy = HeaderStrategy::with_state(
+            http::header::HeaderName::from_static("x-api-key"),
+            Pool::with_key("key-1", "alice"),
+            |pool: Pool, key: String| async move { pool.user_for_key(&key).await },
+        );
+
+        assert_eq!(
+            authenticate_key(&strategy, "key-1").await,
+            Some("alice".to_string())
+        );
+        let boxed: Box<dyn AuthenticationStrategy<String>> = Box::new(strategy);
+        let parts = parts("/", &[]);
+        assert_eq!(boxed.authenticate(&parts).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_header_strategy_with_validator_borrowing_its_state() {
+        struct ApiKeys(Pool);
+
+        #[async_trait]
+        impl HeaderValidator<String> for ApiKeys {
+            async fn validate(&self, key: String) -> Result<Option<String>, AuthError> {
+                self.0.user_for_key(&key).await
             }
         }
-        None
+
+        let strategy = HeaderStrategy::new(
+            http::header::HeaderName::from_static("x-api-key"),
+            ApiKeys(Pool::with_key("key-1", "alice")),
+        );
+        assert_eq!(
+            authenticate_key(&strategy, "key-1").await,
+            Some("alice".to_string())
+        );
+    }
+
+    /// Accepts only `valid-token`, as the caller `direct`.
+    struct StrictValidator;
+
+    #[async_trait]
+    impl TokenValidator for StrictValidator {
+        type Identity = String;
+
+        async fn validate(&self, token: &str) -> Result<Option<String>, AuthError> {
+            Ok((token == "valid-token").then(|| "direct".to_string()))
+        }
+    }
+
+    fn gateway(trust_upstream: bool) -> GatewayStrategy<String> {
+        GatewayStrategy::new(
+            HeaderStrategy::new(
+                http::header::HeaderName::from_static("x-user-id"),
+                |user: String| async move { Ok(Some(format!("upstream:{user}"))) },
+            ),
+            TokenStrategy::new(StrictValidator),
+        )
+        .trust_upstream(trust_upstream)
+    }
+
+    #[tokio::test]
+    async fn test_gateway_strategy_prefers_trusted_header() {
+        let strategy = gateway(true);
+
+        // The header wins and the bearer token is never validated.
+        let internal = parts(
+            "/",
+            &[("x-user-id", "alice"), ("authorization", "Bearer garbage")],
+        );
+        assert_eq!(
+            strategy.authenticate(&internal).await.unwrap(),
+            Some("upstream:alice".to_string())
+        );
+
+        let external = parts("/", &[("authorization", "Bearer valid-token")]);
+        assert_eq!(
+            strategy.authenticate(&external).await.unwrap(),
+            Some("direct".to_string())
+        );
+        let invalid = parts("/", &[("authorization", "Bearer garbage")]);
+        assert_eq!(strategy.authenticate(&invalid).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_gateway_strategy_ignores_header_without_upstream_trust() {
+        let strategy = gateway(false);
+
+        let spoofed = parts("/", &[("x-user-id", "admin")]);
+        assert_eq!(strategy.authenticate(&spoofed).await.unwrap(), None);
+
+        let spoofed_with_token = parts(
+            "/",
+            &[
+                ("x-user-id", "admin"),
+                ("authorization", "Bearer valid-token"),
+            ],
+        );
+        assert_eq!(
+            strategy.authenticate(&spoofed_with_token).await.unwrap(),
+            Some("direct".to_string())
+        );
+    }
+
+    struct Partners;
+
+    #[async_trait]
+    impl HmacClientStore for Partners {
+        type Identity = String;
+
+        async fn load_client(
+            &self,
+            client_id: &str,
+        ) -> Result<Option<HmacClient<String>>, AuthError> {
+            Ok((client_id == "acme").then(|| HmacClient {
+                secret: b"acme-secret".to_vec(),
+                identity: "acme".to_string(),
+            }))
+        }
+    }
+
+    fn signed(timestamp: i64, signature: &str, body: &[u8]) -> Parts {
+        let timestamp = timestamp.to_string();
+        let mut parts = parts(
+            "/orders?page=2",
+            &[
+                ("x-client-id", "acme"),
+                ("x-timestamp", &timestamp),
+                ("x-signature", signature),
+            ],
+        );
+        parts.method = http::Method::POST;
+        parts.extensions.insert(BodyDigest::of(body));
+        parts
+    }
+
+    fn sign(timestamp: i64, body: &[u8]) -> String {
+        HmacSignatureStrategy::<String>::sign(
+            b"acme-secret",
+            &http::Method::POST,
+            "/orders?page=2",
+            timestamp,
+            body,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_hmac_strategy_accepts_a_valid_signature() {
+        let strategy = HmacSignatureStrategy::new(Partners);
+        let now = chrono::Utc::now().timestamp();
+
+        let valid = signed(now, &sign(now, b"{\"qty\":1}"), b"{\"qty\":1}");
+        assert_eq!(
+            strategy.authenticate(&valid).await.unwrap(),
+            Some("acme".to_string())
+        );
+        let unsigned = parts("/orders", &[("x-client-id", "acme")]);
+        assert_eq!(strategy.authenticate(&unsigned).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_hmac_strategy_rejects_tampered_requests() {
+        let strategy = HmacSignatureStrategy::new(Partners);
+        let now = chrono::Utc::now().timestamp();
+        let signature = sign(now, b"{\"qty\":1}");
+
+        let tampered_body = signed(now, &signature, b"{\"qty\":100}");
+        assert!(strategy.authenticate(&tampered_body).await.is_err());
+
+        let mut tampered_path = signed(now, &signature, b"{\"qty\":1}");
+        tampered_path.uri = "/orders?page=3".parse().unwrap();
+        assert!(strategy.authenticate(&tampered_path).await.is_err());
+
+        let mut unknown_client = signed(now, &signature, b"{\"qty\":1}");
+        unknown_client
+            .headers
+            .insert("x-client-id", "globex".parse().unwrap());
+        assert!(strategy.authenticate(&unknown_client).await.is_err());
+
+        // Without a `BodyDigest`, the body is taken to be empty.
+        let mut undigested = signed(now, &signature, b"{\"qty\":1}");
+        undigested.extensions.clear();
+        assert!(strategy.authenticate(&undigested).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hmac_strategy_rejects_stale_timestamps() {
+        let strategy =
+            HmacSignatureStrategy::new(Partners).with_max_skew(std::time::Duration::from_secs(60));
+        let stale = chrono::Utc::now().timestamp() - 120;
+
+        let parts = signed(stale, &sign(stale, b""), b"");
+        assert!(matches!(
+            strategy.authenticate(&parts).await,
+            Err(AuthError::InvalidCredentials)
+        ));
+    }
+
+    fn cookie(header: &str, name: &str) -> Option<String> {
+        let parts = parts("/", &[("cookie", header)]);
+        utils::extract_cookie(&parts.headers, name).map(str::to_string)
+    }
+
+    #[test]
+    fn test_extract_cookie_splits_on_first_equals() {
+        assert_eq!(
+            cookie("sid=abc==; other=x", "sid").as_deref(),
+            Some("abc==")
+        );
+        assert_eq!(cookie("sid=a=b=c", "sid").as_deref(), Some("a=b=c"));
+        assert_eq!(cookie("sid=", "sid").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_extract_cookie_unquotes_and_trims() {
+        assert_eq!(cookie("sid=\"abc\"", "sid").as_deref(), Some("abc"));
+        assert_eq!(cookie("  sid  =  abc  ;x=1", "sid").as_deref(), Some("abc"));
+        assert_eq!(cookie("sid=\"abc", "sid").as_deref(), Some("\"abc"));
+        assert_eq!(cookie("sid=\"", "sid").as_deref(), Some("\""));
+    }
+
+    #[test]
+    fn test_extract_cookie_skips_garbage_segments() {
+        assert_eq!(
+            cookie("garbage; ;;=orphan; =; sid=abc", "sid").as_deref(),
+            Some("abc")
+        );
+        assert_eq!(cookie("sidabc; sid", "sid"), None);
+        assert_eq!(cookie("=abc", ""), None);
+        assert_eq!(cookie("", "sid"), None);
+    }
+
+    #[test]
+    fn test_extract_cookie_reads_all_cookie_headers() {
+        let parts = parts("/", &[("cookie", "a=1"), ("cookie", "sid=abc")]);
+        assert_eq!(utils::extract_cookie(&parts.headers, "sid"), Some("abc"));
+    }
+
+    fn basic(encoded: &str) -> Option<(String, String)> {
+        let parts = parts("/", &[("authorization", &format!("Basic {encoded}"))]);
+        utils::extract_basic_credentials(&parts.headers)
+    }
+
+    fn credentials(username: &str, password: &str) -> Option<(String, String)> {
+        Some((username.to_string(), password.to_string()))
+    }
+
+    #[test]
+    fn test_basic_credentials_padded_and_unpadded() {
+        // "user:pass1" needs padding in base64.
+        assert_eq!(basic("dXNlcjpwYXNzMQ=="), credentials("user", "pass1"));
+        assert_eq!(basic("dXNlcjpwYXNzMQ"), credentials("user", "pass1"));
+    }
+
+    #[test]
+    fn test_basic_credentials_url_safe() {
+        // "user:~~~>" encodes to "+" and "/" in the standard alphabet.
+        assert_eq!(basic("dXNlcjp+fn4+"), credentials("user", "~~~>"));
+        assert_eq!(basic("dXNlcjp-fn4-"), credentials("user", "~~~>"));
+        assert_eq!(basic("dXNlcjo_Pz8"), credentials("user", "???"));
+    }
+
+    #[test]
+    fn test_basic_credentials_split_on_first_colon() {
+        // "user:pa:ss:"
+        assert_eq!(basic("dXNlcjpwYTpzczo="), credentials("user", "pa:ss:"));
+        // "nocolon"
+        assert_eq!(basic("bm9jb2xvbg=="), None);
+        assert_eq!(basic("!!!"), None);
+    }
+
+    #[cfg(feature = "memory")]
+    mod one_time {
+        use super::*;
+        use crate::auth::Identity;
+        use crate::store::memory::MemoryStore;
+        use crate::{Claims, TokenManager};
+        use std::collections::HashMap;
+        use std::sync::Arc;
+
+        /// Validates tokens issued by the test's `TokenManager`.
+        struct ClaimsValidator(Arc<TokenManager>);
+
+        #[async_trait]
+        impl TokenValidator for ClaimsValidator {
+            type Identity = Claims;
+
+            async fn validate(&self, token: &str) -> Result<Option<Claims>, AuthError> {
+                Ok(self.0.validate_token(token, None).ok())
+            }
+        }
+
+        fn one_time(
+            manager: &Arc<TokenManager>,
+            store: MemoryStore<()>,
+        ) -> OneTimeTokenStrategy<Claims> {
+            OneTimeTokenStrategy::new(TokenStrategy::new(ClaimsValidator(manager.clone())), store)
+        }
+
+        fn user_token(manager: &TokenManager) -> String {
+            let identity = Identity {
+                provider_id: "mock".to_string(),
+                external_id: "alice".to_string(),
+                email: None,
+                email_verified: None,
+                username: None,
+                attributes: HashMap::new(),
+                attributes_multi: HashMap::new(),
+            };
+            manager.issue_user_token(identity, 300, None, None).unwrap()
+        }
+
+        fn bearer(token: &str) -> Parts {
+            parts("/", &[("authorization", &format!("Bearer {token}"))])
+        }
+
+        #[tokio::test]
+        async fn test_one_time_token_is_accepted_once() {
+            let manager = Arc::new(TokenManager::new(
+                b"a-test-secret-of-at-least-32-bytes!!",
+                None,
+            ));
+            let strategy = one_time(&manager, Default::default());
+            let first = user_token(&manager);
+
+            let claims = strategy
+                .authenticate(&bearer(&first))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(claims.sub, "alice");
+
+            let replayed = strategy.authenticate(&bearer(&first)).await;
+            assert!(
+                matches!(replayed, Err(AuthError::TokenReplayed)),
+                "{replayed:?}"
+            );
+
+            // Other tokens of the same user are unaffected.
+            let second = user_token(&manager);
+            assert!(strategy
+                .authenticate(&bearer(&second))
+                .await
+                .unwrap()
+                .is_some());
+        }
+
+        #[tokio::test]
+        async fn test_one_time_token_store_is_shared_between_instances() {
+            let manager = Arc::new(TokenManager::new(
+                b"a-test-secret-of-at-least-32-bytes!!",
+                None,
+            ));
+            let store = MemoryStore::new();
+            let token = user_token(&manager);
+
+            let first = one_time(&manager, store.clone());
+            let second = one_time(&manager, store);
+            assert!(first.authenticate(&bearer(&token)).await.unwrap().is_some());
+            assert!(matches!(
+                second.authenticate(&bearer(&token)).await,
+                Err(AuthError::TokenReplayed)
+            ));
+        }
+
+        /// Accepts any token, returning claims without a `jti`.
+        struct NoJtiValidator;
+
+        #[async_trait]
+        impl TokenValidator for NoJtiValidator {
+            type Identity = Claims;
+
+            async fn validate(&self, _token: &str) -> Result<Option<Claims>, AuthError> {
+                let exp = chrono::Utc::now().timestamp() + 300;
+                Ok(Some(
+                    serde_json::from_value(
+                        serde_json::json!({ "sub": "alice", "exp": exp, "iat": 0 }),
+                    )
+                    .unwrap(),
+                ))
+            }
+        }
+
+        #[tokio::test]
+        async fn test_one_time_tokens_without_jti_are_rejected() {
+            let strategy =
+                OneTimeTokenStrategy::new(TokenStrategy::new(NoJtiValidator), MemoryStore::new());
+            let result = strategy.authenticate(&bearer("anything")).await;
+            assert!(
+                matches!(&result, Err(AuthError::Token(message)) if message.contains("jti")),
+                "{result:?}"
+            );
+
+            // Requests without a token are left to other strategies.
+            assert!(strategy
+                .authenticate(&parts("/", &[]))
+                .await
+                .unwrap()
+                .is_none());
+        }
     }
 }
@@ -0,0 +1,94 @@
+//! A short-lived cache of userinfo responses.
+
+use crate::auth::state::Identity;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Caches the identity fetched from a provider's userinfo endpoint, keyed by
+/// the access token it was fetched with.
+///
+/// Keys are SHA-256 hashes of the token; the raw token is never stored. An
+/// entry lives for the cache's TTL, or until the token expires if that is
+/// sooner. Expired entries are pruned on insert.
+#[derive(Debug)]
+pub struct UserInfoCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<[u8; 32], (Identity, Instant)>>,
+}
+
+impl UserInfoCache {
+    /// A cache keeping entries for at most `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The identity cached for `access_token`, if it has not expired.
+    pub fn get(&self, access_token: &str) -> Option<Identity> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(&Self::key(access_token))
+            .filter(|(_, expires_at)| Instant::now() < *expires_at)
+            .map(|(identity, _)| identity.clone())
+    }
+
+    /// Cache `identity` for `access_token`, which expires in `expires_in`
+    /// seconds if known. Tokens expiring immediately are not cached.
+    pub fn insert(&self, access_token: &str, identity: Identity, expires_in: Option<u64>) {
+        let ttl = expires_in
+            .map(Duration::from_secs)
+            .map_or(self.ttl, |token_ttl| token_ttl.min(self.ttl));
+        if ttl.is_zero() {
+            return;
+        }
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (_, expires_at)| now < *expires_at);
+        entries.insert(Self::key(access_token), (identity, now + ttl));
+    }
+
+    fn key(access_token: &str) -> [u8; 32] {
+        Sha256::digest(access_token.as_bytes()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity() -> Identity {
+        Identity {
+            provider_id: "mock".to_string(),
+            external_id: "alice".to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_entries_are_keyed_by_token_and_bounded_by_its_expiry() {
+        let cache = UserInfoCache::new(Duration::from_secs(60));
+        cache.insert("token-a", identity(), Some(3600));
+        cache.insert("token-b", identity(), Some(0));
+
+        assert_eq!(cache.get("token-a").unwrap().external_id, "alice");
+        assert!(cache.get("token-b").is_none());
+        assert!(cache.get("token-c").is_none());
+    }
+
+    #[test]
+    fn test_raw_token_is_not_stored() {
+        let cache = UserInfoCache::new(Duration::from_secs(60));
+        cache.insert("secret-token", identity(), None);
+
+        assert!(cache.get("secret-token").is_some());
+        assert!(!format!("{cache:?}").contains("secret-token"));
+    }
+}
@@ -1,9 +1,10 @@
 use crate::auth::session::{Session, SessionConfig, SessionStore};
-use crate::auth::{AuthError, ErasedOAuthFlow, Identity};
+use crate::auth::{AuthError, ConsentSink, ErasedOAuthFlow, ErrorLevels, Identity, SameSite};
 #[cfg(feature = "token")]
 use crate::token::TokenManager;
+use async_trait::async_trait;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 /// Marker for a missing component in the typestate pattern.
 #[derive(Clone, Default, Debug)]
@@ -13,6 +14,225 @@ pub struct Missing;
 #[derive(Clone, Debug)]
 pub struct Configured<T>(pub T);
 
+/// Marker for a JWT issuer waiting for its token manager in the typestate pattern.
+///
+/// [`EngineBuilder::jwt_issuer`] called before `jwt_secret` or `token_manager`
+/// leaves the builder in this state, which cannot be built:
+///
+/// ```compile_fail
+/// authkestra_engine::Engine::builder()
+///     .jwt_issuer("https://issuer.example")
+///     .build();
+/// ```
+#[cfg(feature = "token")]
+#[derive(Clone, Default, Debug)]
+pub struct PendingIssuer;
+
+mod sealed {
+    /// Token typestates an [`Engine`](super::Engine) can be built from.
+    pub trait Buildable {}
+
+    impl Buildable for super::Missing {}
+
+    #[cfg(feature = "token")]
+    impl Buildable for super::Configured<std::sync::Arc<super::TokenManager>> {}
+}
+
+/// A builder configuration that cannot produce a working [`Engine`].
+#[derive(Debug, thiserror::Error)]
+pub enum EngineBuildError {
+    /// `SessionConfig::partitioned` was set on a cookie browsers would reject.
+    #[error("partitioned session cookies require `same_site: SameSite::None` and `secure: true`")]
+    InvalidPartitionedCookie,
+    /// `UnknownProviderResponse::status` is not a 4xx or 5xx status code.
+    #[error("unknown provider response status `{0}` is not a 4xx or 5xx status code")]
+    InvalidUnknownProviderStatus(u16),
+}
+
+/// A configuration problem found by [`Engine::validate`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ConfigIssue {
+    /// A provider's redirect URI is not an absolute URL.
+    #[error("provider `{provider}` has a redirect URI that is not absolute: `{uri}`")]
+    RelativeRedirectUri { provider: String, uri: String },
+    /// A provider's JWKS could not be fetched.
+    #[error("JWKS of provider `{provider}` at `{uri}` is unreachable: {reason}")]
+    UnreachableJwks {
+        provider: String,
+        uri: String,
+        reason: String,
+    },
+    /// The token manager was created with an empty secret.
+    #[error("the token manager has no signing key")]
+    MissingSigningKey,
+    /// The session store failed [`SessionStore::ping`].
+    #[error("the session store is not responding: {0}")]
+    SessionStoreUnavailable(AuthError),
+}
+
+/// An `Engine` component checked by [`Engine::validate`]. A [`Missing`]
+/// component has nothing to check.
+#[async_trait]
+pub trait ValidateComponent: Sync {
+    /// Add the problems found with this component to `issues`.
+    async fn validate(&self, issues: &mut Vec<ConfigIssue>);
+}
+
+#[async_trait]
+impl ValidateComponent for Missing {
+    async fn validate(&self, _issues: &mut Vec<ConfigIssue>) {}
+}
+
+#[async_trait]
+impl ValidateComponent for Configured<Arc<dyn SessionStore>> {
+    async fn validate(&self, issues: &mut Vec<ConfigIssue>) {
+        if let Err(e) = self.0.ping().await {
+            issues.push(ConfigIssue::SessionStoreUnavailable(e));
+        }
+    }
+}
+
+#[cfg(feature = "token")]
+#[async_trait]
+impl ValidateComponent for Configured<Arc<TokenManager>> {
+    async fn validate(&self, issues: &mut Vec<ConfigIssue>) {
+        if !self.0.has_signing_key() {
+            issues.push(ConfigIssue::MissingSigningKey);
+        }
+    }
+}
+
+/// Body format of the response for an unknown provider.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum UnknownProviderBody {
+    /// `{ "error": "unknown_provider", "valid": [...] }` listing the registered provider ids.
+    #[default]
+    Json,
+    /// Plain text `Provider {id} not found`.
+    Text,
+}
+
+/// Response returned by the login and callback routes when the requested
+/// provider is not registered.
+///
+/// Defaults to `400 Bad Request` with a JSON body. Use
+/// `UnknownProviderResponse { status: 404, body: UnknownProviderBody::Text }`
+/// for a plain `404 Not Found`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownProviderResponse {
+    /// HTTP status code of the response, a 4xx or 5xx code.
+    pub status: u16,
+    /// Body format of the response.
+    pub body: UnknownProviderBody,
+}
+
+impl Default for UnknownProviderResponse {
+    fn default() -> Self {
+        Self {
+            status: 400,
+            body: UnknownProviderBody::Json,
+        }
+    }
+}
+
+impl UnknownProviderResponse {
+    /// Render the response as `(status, content type, body)`.
+    pub fn render(&self, provider: &str, valid: &[String]) -> (u16, &'static str, String) {
+        match self.body {
+            UnknownProviderBody::Json => (
+                self.status,
+                "application/json",
+                serde_json::json!({ "error": "unknown_provider", "valid": valid }).to_string(),
+            ),
+            UnknownProviderBody::Text => (
+                self.status,
+                "text/plain; charset=utf-8",
+                format!("Provider {provider} not found"),
+            ),
+        }
+    }
+}
+
+/// The OAuth providers registered on an [`Engine`], keyed by provider id.
+///
+/// Cloning the registry shares it: providers added or removed through one
+/// clone are seen by all of them, including the clones handed to a running
+/// server. Lookups return the flow's `Arc`, so a request that already holds a
+/// provider finishes with it even if the provider is removed meanwhile.
+#[derive(Clone, Default)]
+pub struct ProviderRegistry {
+    flows: Arc<RwLock<HashMap<String, Arc<dyn ErasedOAuthFlow>>>>,
+}
+
+impl ProviderRegistry {
+    /// The flow registered under `id`.
+    pub fn get(&self, id: &str) -> Option<Arc<dyn ErasedOAuthFlow>> {
+        self.read().get(id).cloned()
+    }
+
+    /// Whether a provider is registered under `id`.
+    pub fn contains(&self, id: &str) -> bool {
+        self.read().contains_key(id)
+    }
+
+    /// The registered provider ids, sorted.
+    pub fn ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.read().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// The registered providers, sorted by id.
+    pub fn flows(&self) -> Vec<(String, Arc<dyn ErasedOAuthFlow>)> {
+        let mut flows: Vec<_> = self
+            .read()
+            .iter()
+            .map(|(id, flow)| (id.clone(), flow.clone()))
+            .collect();
+        flows.sort_by(|a, b| a.0.cmp(&b.0));
+        flows
+    }
+
+    /// The number of registered providers.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Whether no provider is registered.
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Register `flow` under its provider id, returning `true` if it replaced
+    /// a provider with the same id.
+    pub fn insert(&self, flow: Arc<dyn ErasedOAuthFlow>) -> bool {
+        let id = flow.provider_id();
+        self.write().insert(id, flow).is_some()
+    }
+
+    /// Remove the provider registered under `id`, returning whether there was one.
+    pub fn remove(&self, id: &str) -> bool {
+        self.write().remove(id).is_some()
+    }
+
+    // The map is only touched inside these guards, so a poisoned lock still
+    // holds a consistent map.
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<String, Arc<dyn ErasedOAuthFlow>>> {
+        self.flows.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<String, Arc<dyn ErasedOAuthFlow>>> {
+        self.flows.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for ProviderRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.ids()).finish()
+    }
+}
+
 /// Trait for the session store state in the `Engine`.
 pub trait SessionStoreState: Send + Sync + Clone {
     /// Returns the session store if configured.
@@ -45,8 +265,11 @@ impl TokenManagerState for Configured<Arc<TokenManager>> {
 /// It is constructed using the [`EngineBuilder`] which uses the Typestate pattern
 /// to ensure that certain methods are only available when the necessary components are configured.
 pub struct Engine<S = Missing, T = Missing> {
-    /// Map of registered OAuth providers.
-    pub providers: HashMap<String, Arc<dyn ErasedOAuthFlow>>,
+    /// Registered OAuth providers.
+    ///
+    /// The registry is shared between clones of the engine, so providers
+    /// registered or removed at runtime are seen by every router serving it.
+    pub providers: ProviderRegistry,
     /// The session storage backend.
     pub session_store: S,
     /// Configuration for session cookies.
@@ -54,6 +277,12 @@ pub struct Engine<S = Missing, T = Missing> {
     /// Manager for JWT signing and verification.
     #[cfg(feature = "token")]
     pub token_manager: T,
+    /// Response used by the routes when a provider is not registered.
+    pub unknown_provider: UnknownProviderResponse,
+    /// Receives a consent record for every successful OAuth login.
+    pub consent_sink: Arc<dyn ConsentSink>,
+    /// The levels errors are logged at by the engine and the adapters.
+    pub error_levels: ErrorLevels,
 }
 
 impl<S, T> Clone for Engine<S, T>
@@ -68,6 +297,9 @@ where
             session_config: self.session_config.clone(),
             #[cfg(feature = "token")]
             token_manager: self.token_manager.clone(),
+            unknown_provider: self.unknown_provider.clone(),
+            consent_sink: self.consent_sink.clone(),
+            error_levels: self.error_levels.clone(),
         }
     }
 }
@@ -81,6 +313,11 @@ impl Engine<Missing, Missing> {
             session_config: SessionConfig::default(),
             #[cfg(feature = "token")]
             token_manager: Missing,
+            #[cfg(feature = "token")]
+            jwt_issuer: None,
+            unknown_provider: UnknownProviderResponse::default(),
+            consent_sink: Arc::new(()),
+            error_levels: ErrorLevels::default(),
         }
     }
 }
@@ -92,6 +329,12 @@ pub struct EngineBuilder<S = Missing, T = Missing> {
     session_config: SessionConfig,
     #[cfg(feature = "token")]
     token_manager: T,
+    /// Issuer waiting for a token manager to be configured.
+    #[cfg(feature = "token")]
+    jwt_issuer: Option<String>,
+    unknown_provider: UnknownProviderResponse,
+    consent_sink: Arc<dyn ConsentSink>,
+    error_levels: ErrorLevels,
 }
 
 impl<S, T> EngineBuilder<S, T> {
@@ -116,20 +359,35 @@ impl<S, T> EngineBuilder<S, T> {
             session_config: self.session_config,
             #[cfg(feature = "token")]
             token_manager: self.token_manager,
+            #[cfg(feature = "token")]
+            jwt_issuer: self.jwt_issuer,
+            unknown_provider: self.unknown_provider,
+            consent_sink: self.consent_sink,
+            error_levels: self.error_levels,
         }
     }
 
     /// Set the token manager.
+    ///
+    /// An issuer set earlier with `jwt_issuer` is applied to `manager`.
     #[cfg(feature = "token")]
     pub fn token_manager(
         self,
         manager: Arc<TokenManager>,
     ) -> EngineBuilder<S, Configured<Arc<TokenManager>>> {
+        let manager = match self.jwt_issuer {
+            Some(issuer) => Arc::new((*manager).clone().with_issuer(issuer)),
+            None => manager,
+        };
         EngineBuilder {
             providers: self.providers,
             session_store: self.session_store,
             session_config: self.session_config,
             token_manager: Configured(manager),
+            jwt_issuer: None,
+            unknown_provider: self.unknown_provider,
+            consent_sink: self.consent_sink,
+            error_levels: self.error_levels,
         }
     }
 
@@ -145,18 +403,213 @@ impl<S, T> EngineBuilder<S, T> {
         self
     }
 
+    /// Set the response returned for unknown providers.
+    pub fn unknown_provider_response(mut self, response: UnknownProviderResponse) -> Self {
+        self.unknown_provider = response;
+        self
+    }
+
+    /// Set the sink receiving a [`ConsentRecord`](crate::auth::ConsentRecord)
+    /// for every successful OAuth login. Records are discarded by default.
+    pub fn consent_sink(mut self, sink: Arc<dyn ConsentSink>) -> Self {
+        self.consent_sink = sink;
+        self
+    }
+
+    /// Set the levels errors are logged at. Every kind keeps its default
+    /// level unless overridden.
+    pub fn error_levels(mut self, levels: ErrorLevels) -> Self {
+        self.error_levels = levels;
+        self
+    }
+}
+
+impl<S, T: sealed::Buildable> EngineBuilder<S, T> {
     /// Build the `Engine`.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the configurations [`try_build`](Self::try_build) rejects.
     pub fn build(self) -> Engine<S, T> {
-        Engine {
-            providers: self.providers,
+        self.try_build()
+            .unwrap_or_else(|e| panic!("invalid Engine configuration: {e}"))
+    }
+
+    /// Build the `Engine`, or report why the configuration cannot work.
+    ///
+    /// Fails with [`EngineBuildError::InvalidPartitionedCookie`] if the session
+    /// cookie is partitioned but not `SameSite=None; Secure`, and with
+    /// [`EngineBuildError::InvalidUnknownProviderStatus`] if the unknown-provider
+    /// status is not an error status.
+    pub fn try_build(self) -> Result<Engine<S, T>, EngineBuildError> {
+        let config = &self.session_config;
+        if config.partitioned && !(config.same_site == SameSite::None && config.secure) {
+            return Err(EngineBuildError::InvalidPartitionedCookie);
+        }
+        if !(400..=599).contains(&self.unknown_provider.status) {
+            return Err(EngineBuildError::InvalidUnknownProviderStatus(
+                self.unknown_provider.status,
+            ));
+        }
+        let providers = ProviderRegistry::default();
+        for flow in self.providers.into_values() {
+            providers.insert(flow);
+        }
+        Ok(Engine {
+            providers,
             session_store: self.session_store,
             session_config: self.session_config,
             #[cfg(feature = "token")]
             token_manager: self.token_manager,
+            unknown_provider: self.unknown_provider,
+            consent_sink: self.consent_sink,
+            error_levels: self.error_levels,
+        })
+    }
+}
+
+#[cfg(feature = "token")]
+impl<S> EngineBuilder<S, Missing> {
+    /// Set the `iss` claim of issued tokens.
+    ///
+    /// Applied to the token manager configured next with `jwt_secret` or
+    /// `token_manager`; the builder cannot be built until then.
+    pub fn jwt_issuer(self, issuer: impl Into<String>) -> EngineBuilder<S, PendingIssuer> {
+        EngineBuilder {
+            providers: self.providers,
+            session_store: self.session_store,
+            session_config: self.session_config,
+            token_manager: PendingIssuer,
+            jwt_issuer: Some(issuer.into()),
+            unknown_provider: self.unknown_provider,
+            consent_sink: self.consent_sink,
+            error_levels: self.error_levels,
         }
     }
 }
 
+#[cfg(feature = "token")]
+impl<S> EngineBuilder<S, PendingIssuer> {
+    /// Replace the pending `iss` claim.
+    pub fn jwt_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.jwt_issuer = Some(issuer.into());
+        self
+    }
+}
+
+#[cfg(feature = "token")]
+impl<S> EngineBuilder<S, Configured<Arc<TokenManager>>> {
+    /// Set the `iss` claim of tokens issued by the configured token manager.
+    pub fn jwt_issuer(mut self, issuer: impl Into<String>) -> Self {
+        let manager = (*self.token_manager.0).clone().with_issuer(issuer.into());
+        self.token_manager = Configured(Arc::new(manager));
+        self
+    }
+}
+
+impl<S, T> Engine<S, T> {
+    /// Register an OAuth provider flow on an already-built `Engine`.
+    ///
+    /// Replaces any provider with the same id and keeps the `S`/`T` typestate.
+    /// The provider registry is shared between clones of the engine, so a
+    /// provider registered while serving is immediately routable.
+    #[tracing::instrument(skip(self, flow), fields(provider_id = %flow.provider_id()))]
+    pub fn register_provider<F>(&self, flow: F)
+    where
+        F: ErasedOAuthFlow + 'static,
+    {
+        if self.providers.insert(Arc::new(flow)) {
+            tracing::debug!("replaced existing provider");
+        } else {
+            tracing::debug!("registered provider");
+        }
+    }
+
+    /// Remove the provider registered under `id`, returning whether there was one.
+    ///
+    /// Later login and callback requests for `id` get the unknown-provider
+    /// response; requests that already resolved the provider complete. A login
+    /// started before the removal cannot complete its callback.
+    #[tracing::instrument(skip(self))]
+    pub fn remove_provider(&self, id: &str) -> bool {
+        let removed = self.providers.remove(id);
+        if removed {
+            tracing::debug!("removed provider");
+        }
+        removed
+    }
+
+    /// The ids of the registered providers, sorted.
+    pub fn provider_ids(&self) -> Vec<String> {
+        self.providers.ids()
+    }
+
+    /// Render the configured unknown-provider response as `(status, content type, body)`.
+    ///
+    /// The JSON body lists the registered provider ids in sorted order.
+    pub fn unknown_provider_response(&self, provider: &str) -> (u16, &'static str, String) {
+        self.unknown_provider
+            .render(provider, &self.providers.ids())
+    }
+}
+
+impl<S: ValidateComponent, T: ValidateComponent> Engine<S, T> {
+    /// Check that the configuration is coherent, e.g. before serving traffic.
+    ///
+    /// Every provider's redirect URI must be an absolute URL, the token manager
+    /// must have a signing key and the session store must answer
+    /// [`SessionStore::ping`]. With `network` and the `http` feature, every
+    /// provider's JWKS is also fetched. All problems are returned, not just the
+    /// first.
+    pub async fn validate(&self, network: bool) -> Result<(), Vec<ConfigIssue>> {
+        let mut issues = Vec::new();
+        #[cfg(not(feature = "http"))]
+        let _ = network;
+
+        for (provider, flow) in self.providers.flows() {
+            if let Some(uri) = flow.redirect_uri() {
+                if !url::Url::parse(&uri).is_ok_and(|url| url.has_host()) {
+                    issues.push(ConfigIssue::RelativeRedirectUri {
+                        provider: provider.clone(),
+                        uri,
+                    });
+                }
+            }
+            #[cfg(feature = "http")]
+            if let Some(uri) = flow.jwks_uri().filter(|_| network) {
+                if let Err(e) = fetch(&uri).await {
+                    issues.push(ConfigIssue::UnreachableJwks {
+                        provider: provider.clone(),
+                        uri,
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        self.session_store.validate(&mut issues).await;
+        #[cfg(feature = "token")]
+        self.token_manager.validate(&mut issues).await;
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+/// GETs `uri`, failing on a non-success status.
+#[cfg(feature = "http")]
+async fn fetch(uri: &str) -> Result<(), reqwest::Error> {
+    crate::auth::http_client::with_timeout(crate::auth::http_client::DEFAULT_TIMEOUT)
+        .get(uri)
+        .send()
+        .await?
+        .error_for_status()
+        .map(|_| ())
+}
+
 // Methods available only when a session store is present
 impl<T> Engine<Configured<Arc<dyn SessionStore>>, T> {
     /// Get the session store.
@@ -167,30 +620,80 @@ impl<T> Engine<Configured<Arc<dyn SessionStore>>, T> {
     /// Create a new session for the given identity.
     #[tracing::instrument(skip(self, identity), fields(user_id = %identity.external_id))]
     pub async fn create_session(&self, identity: Identity) -> Result<Session, AuthError> {
-        let session_duration = self
-            .session_config
-            .max_age
-            .unwrap_or(chrono::Duration::hours(24));
-        let session = Session {
+        let now = chrono::Utc::now();
+        let mut session = Session {
             id: uuid::Uuid::new_v4().to_string(),
             identity,
-            expires_at: chrono::Utc::now() + session_duration,
+            expires_at: self.session_config.expiry(now, now),
+            created_at: now,
         };
 
         tracing::debug!(session_id = %session.id, "creating new session");
 
-        self.session_store
+        session.id = self
+            .session_store
             .0
-            .save_session(&session)
+            .create_session(&session)
             .await
             .map_err(|e| {
-                tracing::error!(error = %e, "failed to save session");
-                AuthError::Session(e.to_string())
+                let e = AuthError::Session(e.to_string());
+                self.error_levels.log(&e, "failed to save session");
+                e
             })?;
 
         tracing::info!(session_id = %session.id, "session created successfully");
         Ok(session)
     }
+
+    /// Sliding expiration: push the expiry of the session `session_id` back to
+    /// the [session lifetime](SessionConfig::session_lifetime) from now and save it.
+    ///
+    /// The expiry never moves past `absolute_max_age` after the session's
+    /// creation. Returns `None` for a missing, expired or too old session,
+    /// which is then left as it is.
+    #[tracing::instrument(skip(self, session_id))]
+    pub async fn touch_session(&self, session_id: &str) -> Result<Option<Session>, AuthError> {
+        let Some(mut session) = self.session_store.0.load_session(session_id).await? else {
+            return Ok(None);
+        };
+        if !self.session_config.touch(&mut session) {
+            tracing::info!(session_id = %session.id, "declined to extend an inactive session");
+            return Ok(None);
+        }
+        self.session_store.0.save_session(&session).await?;
+        tracing::debug!(session_id = %session.id, expires_at = %session.expires_at, "session touched");
+        Ok(Some(session))
+    }
+
+    /// Replace the session `old_id` with a new session for `identity`.
+    ///
+    /// Call this whenever a request gains privileges (an anonymous session
+    /// logging in, a step-up login, a credentials login reusing a session
+    /// cookie), and set the returned session's id as the new cookie. A session
+    /// id planted in the browser beforehand is then useless to an attacker.
+    ///
+    /// The new session is created before the old one is deleted; if the delete
+    /// fails the new session is removed again and the error is returned, so
+    /// the caller never ends up with both ids valid. Stateless stores cannot
+    /// revoke the old token, which stays valid until it expires.
+    #[tracing::instrument(skip(self, old_id, identity), fields(user_id = %identity.external_id))]
+    pub async fn regenerate_session_id(
+        &self,
+        old_id: &str,
+        identity: Identity,
+    ) -> Result<Session, AuthError> {
+        let session = self.create_session(identity).await?;
+
+        if let Err(e) = self.session_store.0.delete_session(old_id).await {
+            self.error_levels
+                .log(&e, "failed to delete old session, discarding the new one");
+            let _ = self.session_store.0.delete_session(&session.id).await;
+            return Err(e);
+        }
+
+        tracing::info!(session_id = %session.id, "session id regenerated");
+        Ok(session)
+    }
 }
 
 #[cfg(feature = "token")]
@@ -212,8 +715,9 @@ impl<S> Engine<S, Configured<Arc<TokenManager>>> {
             .0
             .issue_user_token(identity, expires_in_secs, None, None)
             .map_err(|e| {
-                tracing::error!(error = %e, "failed to issue token");
-                AuthError::Token(e.to_string())
+                let e = AuthError::Token(e.to_string());
+                self.error_levels.log(&e, "failed to issue token");
+                e
             })
             .inspect(|_| {
                 tracing::info!("token issued successfully");
@@ -1,4 +1,5 @@
-use crate::auth::{error::AuthError, state::OAuthToken};
+use crate::auth::{error::AuthError, http_client, state::OAuthToken};
+use std::time::Duration;
 
 /// Orchestrates the Client Credentials Flow (RFC 6749 Section 4.4).
 ///
@@ -8,7 +9,17 @@ pub struct ClientCredentialsFlow {
     client_id: String,
     client_secret: String,
     token_url: String,
-    http_client: reqwest::Client,
+    http_client: http_client::HttpExecutor,
+}
+
+impl std::fmt::Debug for ClientCredentialsFlow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientCredentialsFlow")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &crate::auth::state::REDACTED)
+            .field("token_url", &self.token_url)
+            .finish_non_exhaustive()
+    }
 }
 
 impl ClientCredentialsFlow {
@@ -24,10 +35,25 @@ impl ClientCredentialsFlow {
             client_id,
             client_secret,
             token_url,
-            http_client: reqwest::Client::new(),
+            http_client: http_client::HttpExecutor::default(),
         }
     }
 
+    /// Set the timeout for requests to the token endpoint.
+    ///
+    /// Requests that exceed it fail with [`AuthError::Timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http_client = http_client::HttpExecutor::with_timeout(timeout);
+        self
+    }
+
+    /// Send requests through `client`, e.g. a `reqwest_middleware::ClientWithMiddleware`
+    /// adding tracing headers. Replaces the client set by [`with_timeout`](Self::with_timeout).
+    pub fn with_http_client(mut self, client: impl Into<http_client::HttpExecutor>) -> Self {
+        self.http_client = client.into();
+        self
+    }
+
     /// Obtains an access token using the client credentials.
     ///
     /// # Arguments
@@ -57,7 +83,7 @@ impl ClientCredentialsFlow {
             .form(&params)
             .send()
             .await
-            .map_err(|_| AuthError::Network)?;
+            .map_err(|e| http_client::map_error(&e))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -66,9 +92,10 @@ impl ClientCredentialsFlow {
             )));
         }
 
-        response
-            .json::<OAuthToken>()
-            .await
-            .map_err(|e| AuthError::Provider(format!("Failed to parse token response: {e}")))
+        response.json::<OAuthToken>().await.map_err(|e| {
+            http_client::map_body_error(&e, |e| {
+                AuthError::Provider(format!("Failed to parse token response: {e}"))
+            })
+        })
     }
 }
@@ -1,5 +1,6 @@
 use crate::auth::{
     error::{AuthError, OAuthErrorResponse},
+    http_client,
     state::OAuthToken,
 };
 use serde::{Deserialize, Serialize};
@@ -24,6 +25,59 @@ pub struct DeviceAuthorizationResponse {
     /// The minimum amount of time in seconds that the client SHOULD wait
     /// between polling requests to the token endpoint.
     pub interval: Option<u64>,
+    /// When the response was received; `expires_in` counts from here.
+    ///
+    /// Defaults to the time of deserialization, so responses parsed straight
+    /// from the device authorization endpoint get the fetch time.
+    #[serde(default = "chrono::Utc::now")]
+    pub received_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl DeviceAuthorizationResponse {
+    /// When the codes expire: `received_at + expires_in`.
+    pub fn expires_at(&self) -> chrono::DateTime<chrono::Utc> {
+        self.received_at + chrono::Duration::seconds(self.expires_in as i64)
+    }
+
+    /// The time left before the codes expire, or `None` if they have expired.
+    pub fn remaining_ttl(&self) -> Option<chrono::Duration> {
+        self.remaining_ttl_at(chrono::Utc::now())
+    }
+
+    /// Instructions for the user, ready to print to a terminal.
+    ///
+    /// Names the verification URI and the user code, then the complete URI
+    /// if the server sent one.
+    pub fn instructions(&self) -> String {
+        let mut instructions = format!(
+            "Open {} in a browser and enter the code: {}",
+            self.verification_uri, self.user_code
+        );
+        if let Some(complete) = &self.verification_uri_complete {
+            instructions.push_str(&format!("\nOr open {complete} to skip entering the code."));
+        }
+        instructions
+    }
+
+    /// `verification_uri_complete` as a QR code drawn with Unicode half
+    /// blocks, for scanning off a terminal. `None` if the server sent no
+    /// complete URI.
+    #[cfg(feature = "qrcode")]
+    pub fn qr_code(&self) -> Option<String> {
+        let uri = self.verification_uri_complete.as_deref()?;
+        let code = qrcode::QrCode::new(uri.as_bytes()).ok()?;
+        Some(
+            code.render::<qrcode::render::unicode::Dense1x2>()
+                .dark_color(qrcode::render::unicode::Dense1x2::Light)
+                .light_color(qrcode::render::unicode::Dense1x2::Dark)
+                .build(),
+        )
+    }
+
+    fn remaining_ttl_at(&self, now: chrono::DateTime<chrono::Utc>) -> Option<chrono::Duration> {
+        let expires_at = self.expires_at();
+        (expires_at > now).then(|| expires_at - now)
+    }
 }
 
 /// Orchestrates the Device Authorization Flow (RFC 8628).
@@ -31,7 +85,7 @@ pub struct DeviceFlow {
     client_id: String,
     device_authorization_url: String,
     token_url: String,
-    http_client: reqwest::Client,
+    http_client: http_client::HttpExecutor,
 }
 
 impl DeviceFlow {
@@ -41,10 +95,25 @@ impl DeviceFlow {
             client_id,
             device_authorization_url,
             token_url,
-            http_client: reqwest::Client::new(),
+            http_client: http_client::HttpExecutor::default(),
         }
     }
 
+    /// Set the timeout for each request to the authorization and token endpoints.
+    ///
+    /// Requests that exceed it fail with [`AuthError::Timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http_client = http_client::HttpExecutor::with_timeout(timeout);
+        self
+    }
+
+    /// Send requests through `client`, e.g. a `reqwest_middleware::ClientWithMiddleware`
+    /// adding tracing headers. Replaces the client set by [`with_timeout`](Self::with_timeout).
+    pub fn with_http_client(mut self, client: impl Into<http_client::HttpExecutor>) -> Self {
+        self.http_client = client.into();
+        self
+    }
+
     /// Initiates the device authorization request.
     /// Returns a `DeviceAuthorizationResponse` which contains the codes and URIs
     /// to be displayed to the user.
@@ -61,7 +130,7 @@ impl DeviceFlow {
             .form(&[("client_id", &self.client_id), ("scope", &scope_param)])
             .send()
             .await
-            .map_err(|_| AuthError::Network)?;
+            .map_err(|e| http_client::map_error(&e))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -71,9 +140,11 @@ impl DeviceFlow {
         }
 
         let response_text = response.text().await.map_err(|e| {
-            AuthError::Provider(format!(
-                "Failed to read device authorization response body: {e}"
-            ))
+            http_client::map_body_error(&e, |e| {
+                AuthError::Provider(format!(
+                    "Failed to read device authorization response body: {e}"
+                ))
+            })
         })?;
 
         println!("Raw device authorization response body: {response_text}");
@@ -110,10 +181,12 @@ impl DeviceFlow {
                 ])
                 .send()
                 .await
-                .map_err(|_| AuthError::Network)?;
+                .map_err(|e| http_client::map_error(&e))?;
 
             let response_text = response.text().await.map_err(|e| {
-                AuthError::Provider(format!("Failed to read token response body: {e}"))
+                http_client::map_body_error(&e, |e| {
+                    AuthError::Provider(format!("Failed to read token response body: {e}"))
+                })
             })?;
 
             // Attempt to deserialize as OAuthErrorResponse first
@@ -156,3 +229,57 @@ impl DeviceFlow {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(verification_uri_complete: Option<&str>) -> DeviceAuthorizationResponse {
+        serde_json::from_value(serde_json::json!({
+            "device_code": "device-123",
+            "user_code": "WDJB-MJHT",
+            "verification_uri": "https://example.com/device",
+            "verification_uri_complete": verification_uri_complete,
+            "expires_in": 900,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_instructions_include_user_code_and_complete_uri() {
+        let complete = "https://example.com/device?user_code=WDJB-MJHT";
+        let instructions = response(Some(complete)).instructions();
+        assert!(instructions.contains("https://example.com/device "));
+        assert!(instructions.contains("WDJB-MJHT"));
+        assert!(instructions.contains(complete));
+
+        let instructions = response(None).instructions();
+        assert!(instructions.contains("WDJB-MJHT"));
+        assert_eq!(instructions.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_remaining_ttl_counts_from_receipt() {
+        let response = response(None);
+        let now = response.received_at;
+        assert_eq!(
+            response.remaining_ttl_at(now),
+            Some(chrono::Duration::seconds(900))
+        );
+        assert_eq!(
+            response.remaining_ttl_at(now + chrono::Duration::seconds(900)),
+            None
+        );
+        assert!(response.remaining_ttl().unwrap() > chrono::Duration::seconds(890));
+    }
+
+    #[cfg(feature = "qrcode")]
+    #[test]
+    fn test_qr_code_renders_complete_uri() {
+        let qr = response(Some("https://example.com/device?user_code=WDJB-MJHT"))
+            .qr_code()
+            .unwrap();
+        assert!(qr.lines().count() > 10);
+        assert!(response(None).qr_code().is_none());
+    }
+}
@@ -55,15 +55,19 @@ use std::collections::HashMap;
 pub use crate::engine::{Configured, Engine, EngineBuilder, Missing};
 
 /// Client Credentials flow implementation.
+#[cfg(feature = "http")]
 pub mod client_credentials_flow;
 /// Device Authorization flow implementation.
+#[cfg(feature = "http")]
 pub mod device_flow;
 /// OAuth2 Authorization Code flow implementation.
 pub mod oauth2;
 
+#[cfg(feature = "http")]
 pub use client_credentials_flow::ClientCredentialsFlow;
+#[cfg(feature = "http")]
 pub use device_flow::{DeviceAuthorizationResponse, DeviceFlow};
-pub use oauth2::OAuth2Flow;
+pub use oauth2::{AuthorizationRequest, IdentityTransform, OAuth2Flow};
 
 /// Orchestrates a direct credentials flow.
 pub struct CredentialsFlow<P: CredentialsProvider, M: UserMapper = ()> {
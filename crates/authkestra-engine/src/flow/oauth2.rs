@@ -4,6 +4,85 @@ use crate::auth::{
 };
 use crate::flow::{Flow, FlowContext, FlowResult};
 use async_trait::async_trait;
+use std::collections::BTreeMap;
+
+/// Post-processes the identity returned by the provider.
+pub type IdentityTransform = Box<dyn Fn(Identity) -> Result<Identity, AuthError> + Send + Sync>;
+
+/// An authorization request, split into its parts for inspection.
+///
+/// Built by [`OAuth2Flow::build_authorization_request`]. The parameters are
+/// parsed back from [`url`](Self::url) as the provider generated it, so a
+/// parameter the provider left out is `None` here too.
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    /// The URL the user is redirected to.
+    pub url: String,
+    /// The authorization endpoint: `url` without its query and fragment.
+    pub endpoint: String,
+    /// The `client_id` parameter.
+    pub client_id: Option<String>,
+    /// The `redirect_uri` parameter.
+    pub redirect_uri: Option<String>,
+    /// The `scope` parameter, space-delimited.
+    pub scope: Option<String>,
+    /// The `state` parameter.
+    pub state: Option<String>,
+    /// The `code_challenge` parameter (PKCE).
+    pub code_challenge: Option<String>,
+    /// The `code_challenge_method` parameter (PKCE).
+    pub code_challenge_method: Option<String>,
+    /// The `resource` parameters (RFC 8707), in order.
+    pub resources: Vec<String>,
+    /// Every other parameter, e.g. `response_type`, `nonce` or `prompt`.
+    pub extra: BTreeMap<String, String>,
+    /// The state to keep until the callback, as returned by
+    /// [`OAuth2Flow::initiate_login`].
+    pub flow_state: OAuth2State,
+}
+
+impl AuthorizationRequest {
+    fn parse(url: String, flow_state: OAuth2State) -> Self {
+        let mut request = Self {
+            endpoint: url.split(['?', '#']).next().unwrap_or_default().to_string(),
+            client_id: None,
+            redirect_uri: None,
+            scope: None,
+            state: None,
+            code_challenge: None,
+            code_challenge_method: None,
+            resources: Vec::new(),
+            extra: BTreeMap::new(),
+            flow_state,
+            url,
+        };
+        let Ok(parsed) = url::Url::parse(&request.url) else {
+            tracing::warn!(url = %request.url, "authorization URL is not absolute");
+            return request;
+        };
+        for (name, value) in parsed.query_pairs() {
+            let value = value.into_owned();
+            let field = match name.as_ref() {
+                "client_id" => &mut request.client_id,
+                "redirect_uri" => &mut request.redirect_uri,
+                "scope" => &mut request.scope,
+                "state" => &mut request.state,
+                "code_challenge" => &mut request.code_challenge,
+                "code_challenge_method" => &mut request.code_challenge_method,
+                "resource" => {
+                    request.resources.push(value);
+                    continue;
+                }
+                _ => {
+                    request.extra.insert(name.into_owned(), value);
+                    continue;
+                }
+            };
+            *field = Some(value);
+        }
+        request
+    }
+}
 
 /// Orchestrates the standard OAuth2 Authorization Code flow.
 pub struct OAuth2Flow<P: OAuthProvider, M: UserMapper = ()> {
@@ -11,6 +90,9 @@ pub struct OAuth2Flow<P: OAuthProvider, M: UserMapper = ()> {
     mapper: Option<M>,
     scopes: Vec<String>,
     use_pkce: bool,
+    require_verified_email: bool,
+    identity_transform: Option<IdentityTransform>,
+    resources: Vec<String>,
 }
 
 #[async_trait]
@@ -56,17 +138,7 @@ impl<P: OAuthProvider + 'static, M: UserMapper + 'static> ErasedOAuthFlow for OA
         scopes: &[&str],
         pkce_challenge: Option<&str>,
     ) -> (String, OAuth2State) {
-        let effective_scopes = if !scopes.is_empty() {
-            scopes
-        } else {
-            &self
-                .scopes
-                .iter()
-                .map(|s| s.as_str())
-                .collect::<Vec<&str>>()
-        };
-
-        self.initiate_login(effective_scopes, pkce_challenge)
+        self.initiate_login(scopes, pkce_challenge)
     }
 
     async fn finalize_login(
@@ -80,6 +152,18 @@ impl<P: OAuthProvider + 'static, M: UserMapper + 'static> ErasedOAuthFlow for OA
             .await?;
         Ok((identity, token))
     }
+
+    async fn refresh_token(&self, refresh_token: &str) -> Result<OAuthToken, AuthError> {
+        self.refresh_access_token(refresh_token).await
+    }
+
+    fn redirect_uri(&self) -> Option<String> {
+        self.provider.redirect_uri().map(str::to_string)
+    }
+
+    fn jwks_uri(&self) -> Option<String> {
+        self.provider.jwks_uri()
+    }
 }
 
 impl<P: OAuthProvider> OAuth2Flow<P, ()> {
@@ -90,6 +174,9 @@ impl<P: OAuthProvider> OAuth2Flow<P, ()> {
             mapper: None,
             scopes: Vec::new(),
             use_pkce: true,
+            require_verified_email: false,
+            identity_transform: None,
+            resources: Vec::new(),
         }
     }
 }
@@ -102,10 +189,17 @@ impl<P: OAuthProvider, M: UserMapper> OAuth2Flow<P, M> {
             mapper: Some(mapper),
             scopes: Vec::new(),
             use_pkce: true,
+            require_verified_email: false,
+            identity_transform: None,
+            resources: Vec::new(),
         }
     }
 
     /// Set the scopes for the OAuth2 flow.
+    ///
+    /// Scopes passed to [`initiate_login`](Self::initiate_login) take
+    /// precedence over these, which in turn take precedence over the
+    /// provider's [`default_scopes`](OAuthProvider::default_scopes).
     pub fn with_scopes(mut self, scopes: Vec<impl Into<String>>) -> Self {
         self.scopes = scopes.into_iter().map(|s| s.into()).collect();
         self
@@ -117,50 +211,125 @@ impl<P: OAuthProvider, M: UserMapper> OAuth2Flow<P, M> {
         self
     }
 
+    /// Request tokens restricted to the given resource servers (RFC 8707).
+    ///
+    /// Each entry, an absolute URI such as `https://api.example.com`, is sent
+    /// as a `resource` parameter in both the authorization URL and the token
+    /// request. A provider without
+    /// [`exchange_code_for_identity_with_resources`](OAuthProvider::exchange_code_for_identity_with_resources)
+    /// support fails the login rather than drop them.
+    pub fn with_resources(mut self, resources: Vec<impl Into<String>>) -> Self {
+        self.resources = resources.into_iter().map(|r| r.into()).collect();
+        self
+    }
+
+    /// Reject logins whose identity carries an email the provider has not verified.
+    ///
+    /// An email with [`Identity::email_verified`] other than `Some(true)` fails
+    /// [`finalize_login`](Self::finalize_login) with [`AuthError::UnverifiedEmail`],
+    /// so an attacker cannot claim an account by registering its address
+    /// unverified with another provider. Identities without an email are accepted.
+    pub fn with_require_verified_email(mut self, require: bool) -> Self {
+        self.require_verified_email = require;
+        self
+    }
+
+    /// Post-process every identity before it reaches the mapper.
+    ///
+    /// `transform` runs in [`finalize_login`](Self::finalize_login) right after
+    /// the code exchange, e.g. to normalize usernames or derive roles from the
+    /// email domain. An error from it fails the login. The
+    /// [verified email check](Self::with_require_verified_email) sees the
+    /// identity as the provider returned it.
+    pub fn with_identity_transform(
+        mut self,
+        transform: impl Fn(Identity) -> Result<Identity, AuthError> + Send + Sync + 'static,
+    ) -> Self {
+        self.identity_transform = Some(Box::new(transform));
+        self
+    }
+
     /// Generates the redirect URL and CSRF state.
-    #[tracing::instrument(skip(self), fields(provider_id = %self.provider.provider_id()))]
+    ///
+    /// An empty `scopes` falls back to the [builder scopes](Self::with_scopes),
+    /// then to the provider's [`default_scopes`](OAuthProvider::default_scopes).
     pub fn initiate_login(
         &self,
         scopes: &[&str],
         pkce_challenge: Option<&str>,
     ) -> (String, OAuth2State) {
+        let request = self.build_authorization_request(scopes, pkce_challenge);
+        (request.url, request.flow_state)
+    }
+
+    /// Generates the authorization request with its parameters broken out,
+    /// e.g. to debug redirect URI mismatches or to assert on single
+    /// parameters in tests.
+    #[tracing::instrument(skip(self), fields(provider_id = %self.provider.provider_id()))]
+    pub fn build_authorization_request(
+        &self,
+        scopes: &[&str],
+        pkce_challenge: Option<&str>,
+    ) -> AuthorizationRequest {
         let state = uuid::Uuid::new_v4().to_string();
         let nonce = Some(uuid::Uuid::new_v4().to_string());
 
-        let effective_scopes = if !scopes.is_empty() {
-            scopes
+        let effective_scopes: Vec<&str> = if !scopes.is_empty() {
+            scopes.to_vec()
+        } else if !self.scopes.is_empty() {
+            self.scopes.iter().map(String::as_str).collect()
         } else {
-            &self
-                .scopes
-                .iter()
-                .map(|s| s.as_str())
-                .collect::<Vec<&str>>()
+            self.provider.default_scopes()
         };
 
         tracing::debug!(scopes = ?effective_scopes, "generating authorization URL");
 
         let url = self.provider.get_authorization_url(
             &state,
-            effective_scopes,
+            &effective_scopes,
             pkce_challenge,
             nonce.as_deref(),
         );
+        let url = self.append_resources(url);
 
         let auth_state = OAuth2State {
             state: state.clone(),
             nonce,
             code_verifier: None, // Will be set by the caller if needed before encryption
             success_url: None,
+            remember: false,
+            correlation_id: None,
+            scopes: effective_scopes.iter().copied().collect(),
             provider_id: self.provider.provider_id().to_string(),
             expires_at: chrono::Utc::now().timestamp() + 600,
         };
 
         tracing::info!("authorization login initiated successfully");
-        (url, auth_state)
+        AuthorizationRequest::parse(url, auth_state)
+    }
+
+    fn append_resources(&self, authorization_url: String) -> String {
+        if self.resources.is_empty() {
+            return authorization_url;
+        }
+        let Ok(mut url) = url::Url::parse(&authorization_url) else {
+            tracing::warn!(url = %authorization_url, "cannot add resource indicators to a relative authorization URL");
+            return authorization_url;
+        };
+        {
+            let mut query = url.query_pairs_mut();
+            for resource in &self.resources {
+                query.append_pair("resource", resource);
+            }
+        }
+        url.to_string()
     }
 
     /// Completes the flow by exchanging the code.
     /// If a mapper is provided, it will also map the identity to a local user.
+    ///
+    /// Failures are only logged at `DEBUG` here; the caller logs the returned
+    /// error, at the level its [`ErrorLevels`](crate::auth::ErrorLevels) set.
     #[tracing::instrument(skip(self, code, expected_state), fields(provider_id = %self.provider.provider_id()))]
     pub async fn finalize_login(
         &self,
@@ -169,34 +338,57 @@ impl<P: OAuthProvider, M: UserMapper> OAuth2Flow<P, M> {
         expected_state: &OAuth2State,
     ) -> Result<(Identity, OAuthToken, Option<M::LocalUser>), AuthError> {
         if received_state != expected_state.state {
-            tracing::error!("CSRF mismatch: received state does not match expected state");
+            tracing::debug!("received state does not match expected state");
+            return Err(AuthError::CsrfMismatch);
+        }
+        // A state minted for another provider must not be redeemed here.
+        if expected_state.provider_id != self.provider.provider_id() {
+            tracing::debug!(
+                issued_for = %expected_state.provider_id,
+                "state was issued for another provider"
+            );
             return Err(AuthError::CsrfMismatch);
         }
 
         tracing::debug!("exchanging code for identity");
         let (identity, token) = self
             .provider
-            .exchange_code_for_identity(
+            .exchange_code_for_identity_with_resources(
                 code,
                 expected_state.code_verifier.as_deref(),
                 expected_state.nonce.as_deref(),
+                &self.resources,
             )
             .await
-            .map_err(|e| {
-                tracing::error!(error = %e, "failed to exchange code for identity");
-                e
-            })?;
+            .inspect_err(|e| tracing::debug!(error = %e, "failed to exchange code for identity"))?;
 
         tracing::info!(user_id = %identity.external_id, "successfully retrieved identity from provider");
 
+        if self.require_verified_email
+            && identity.email.is_some()
+            && identity.email_verified != Some(true)
+        {
+            tracing::warn!(user_id = %identity.external_id, "rejecting login with unverified email");
+            return Err(AuthError::UnverifiedEmail);
+        }
+
+        let identity = match &self.identity_transform {
+            Some(transform) => transform(identity).inspect_err(
+                |e| tracing::debug!(error = %e, "identity transform rejected the identity"),
+            )?,
+            None => identity,
+        };
+
         // TODO: Validate nonce if present in identity/ID token
 
         let local_user = if let Some(mapper) = &self.mapper {
             tracing::debug!("mapping user identity");
-            Some(mapper.map_user(&identity).await.map_err(|e| {
-                tracing::error!(error = %e, "failed to map user");
-                e
-            })?)
+            Some(
+                mapper
+                    .map_user(&identity)
+                    .await
+                    .inspect_err(|e| tracing::debug!(error = %e, "failed to map user"))?,
+            )
         } else {
             None
         };
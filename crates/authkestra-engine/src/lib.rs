@@ -3,6 +3,7 @@ pub mod engine;
 pub mod flow;
 pub mod protocol;
 pub mod store;
+pub mod task;
 pub mod token;
 
 pub mod aliases;
@@ -11,7 +12,14 @@ pub use aliases::*;
 pub use auth::*;
 pub use engine::*;
 pub use flow::*;
+pub use task::TaskHandle;
 pub use token::*;
 
+/// Re-exported for the code generated by `authkestra-macros`.
+pub use chrono;
+
+#[cfg(feature = "memory")]
+pub use store::memory::MemoryStore;
+
 #[cfg(test)]
 mod tests;
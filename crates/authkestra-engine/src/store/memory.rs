@@ -2,12 +2,14 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-use crate::store::{AtomicConsume, IndexedKvStore, KvStore, StoreError};
+use crate::store::{AtomicConsume, AtomicInsert, IndexedKvStore, KvStore, StoreError};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
 struct StoreEntry<T> {
     value: T,
     expires_at: Option<Instant>,
+    created_at: DateTime<Utc>,
 }
 
 impl<T> StoreEntry<T> {
@@ -22,6 +24,16 @@ impl<T> StoreEntry<T> {
 
 /// An in-memory implementation of [`KvStore`].
 ///
+/// A `MemoryStore<Session>` is a complete [`SessionStore`](crate::SessionStore),
+/// including [`delete_sessions_before`](crate::SessionStore::delete_sessions_before):
+///
+/// ```
+/// use authkestra_engine::{MemoryStore, Session, SessionStore};
+/// use std::sync::Arc;
+///
+/// let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::new());
+/// ```
+///
 /// **Note**: This store is not persistent and will be cleared when the application restarts.
 /// It is primarily intended for development and testing.
 #[derive(Clone)]
@@ -44,6 +56,20 @@ impl<T> MemoryStore<T> {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Insert `value`, keeping the creation time of an existing entry under the same key.
+    fn insert(data: &mut HashMap<String, StoreEntry<T>>, key: &str, value: T, ttl: Duration) {
+        let created_at = data
+            .get(key)
+            .map(|entry| entry.created_at)
+            .unwrap_or_else(Utc::now);
+        let entry = StoreEntry {
+            value,
+            expires_at: Some(Instant::now() + ttl),
+            created_at,
+        };
+        data.insert(key.to_string(), entry);
+    }
 }
 
 #[async_trait]
@@ -66,11 +92,7 @@ impl<T: Clone + Send + Sync + 'static> KvStore<T> for MemoryStore<T> {
     #[tracing::instrument(skip(self, value), fields(key = %key))]
     async fn set(&self, key: &str, value: T, ttl: Duration) -> Result<(), StoreError> {
         tracing::debug!("saving to memory store");
-        let entry = StoreEntry {
-            value,
-            expires_at: Some(Instant::now() + ttl),
-        };
-        self.data.lock().unwrap().insert(key.to_string(), entry);
+        Self::insert(&mut self.data.lock().unwrap(), key, value, ttl);
         Ok(())
     }
 
@@ -80,6 +102,33 @@ impl<T: Clone + Send + Sync + 'static> KvStore<T> for MemoryStore<T> {
         self.data.lock().unwrap().remove(key);
         Ok(())
     }
+
+    #[tracing::instrument(skip(self))]
+    async fn delete_created_before(&self, cutoff: DateTime<Utc>) -> Result<u64, StoreError> {
+        let mut data = self.data.lock().unwrap();
+        let before = data.len();
+        data.retain(|_, entry| entry.created_at >= cutoff);
+        let deleted = (before - data.len()) as u64;
+        tracing::debug!(deleted, "deleted entries from memory store");
+        Ok(deleted)
+    }
+}
+
+#[async_trait]
+impl<T: Clone + Send + Sync + 'static> AtomicInsert<T> for MemoryStore<T> {
+    async fn insert_if_absent(
+        &self,
+        key: &str,
+        value: T,
+        ttl: Duration,
+    ) -> Result<bool, StoreError> {
+        let mut data = self.data.lock().unwrap();
+        if data.get(key).is_some_and(|entry| !entry.is_expired()) {
+            return Ok(false);
+        }
+        Self::insert(&mut data, key, value, ttl);
+        Ok(true)
+    }
 }
 
 #[async_trait]
@@ -107,14 +156,10 @@ impl<T: Clone + Send + Sync + 'static> IndexedKvStore<T> for MemoryStore<T> {
         ttl: Duration,
     ) -> Result<(), StoreError> {
         tracing::debug!("saving indexed record to memory store");
-        let entry = StoreEntry {
-            value,
-            expires_at: Some(Instant::now() + ttl),
-        };
         let mut data = self.data.lock().unwrap();
         let mut indices = self.indices.lock().unwrap();
 
-        data.insert(primary_key.to_string(), entry);
+        Self::insert(&mut data, primary_key, value, ttl);
         indices.insert(secondary_key.to_string(), primary_key.to_string());
 
         Ok(())
@@ -200,6 +245,31 @@ mod tests {
         assert_eq!(value2, None);
     }
 
+    #[tokio::test]
+    async fn test_insert_if_absent() {
+        let store = MemoryStore::<String>::new();
+
+        assert!(store
+            .insert_if_absent("key1", "first".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap());
+        assert!(!store
+            .insert_if_absent("key1", "second".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap());
+        assert_eq!(store.get("key1").await.unwrap(), Some("first".to_string()));
+
+        store
+            .set("key2", "old".to_string(), Duration::from_millis(10))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(store
+            .insert_if_absent("key2", "new".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap());
+    }
+
     #[tokio::test]
     async fn test_indexed_store() {
         let store = MemoryStore::<String>::new();
@@ -224,4 +294,90 @@ mod tests {
         // Next get by index should return None (and internally clean up the orphaned index)
         assert_eq!(store.get_by_index("sk1").await.unwrap(), None);
     }
+
+    #[tokio::test]
+    async fn test_delete_created_before() {
+        let store = MemoryStore::<String>::new();
+
+        store
+            .set("old1", "a".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap();
+        store
+            .set("old2", "b".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let cutoff = Utc::now();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        store
+            .set("new", "c".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap();
+        // Overwriting keeps the original creation time.
+        store
+            .set("old1", "a2".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap();
+
+        assert_eq!(store.delete_created_before(cutoff).await.unwrap(), 2);
+        assert_eq!(store.get("old1").await.unwrap(), None);
+        assert_eq!(store.get("old2").await.unwrap(), None);
+        assert_eq!(store.get("new").await.unwrap(), Some("c".to_string()));
+
+        assert_eq!(store.delete_created_before(cutoff).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_session_store() {
+        use crate::auth::{Identity, Session, SessionStore};
+
+        let store = MemoryStore::<Session>::new();
+        let session = |id: &str| Session {
+            id: id.to_string(),
+            identity: Identity {
+                provider_id: "mock".to_string(),
+                external_id: "user123".to_string(),
+                email: None,
+                email_verified: None,
+                username: None,
+                attributes: HashMap::new(),
+                attributes_multi: HashMap::new(),
+            },
+            expires_at: Utc::now() + chrono::Duration::hours(1),
+            created_at: Utc::now(),
+        };
+
+        assert_eq!(store.create_session(&session("s1")).await.unwrap(), "s1");
+        assert!(store.load_session("s1").await.unwrap().is_some());
+
+        let mut updated = session("s1");
+        updated.identity.username = Some("alice".to_string());
+        store.save_session(&updated).await.unwrap();
+        let loaded = store.load_session("s1").await.unwrap().unwrap();
+        assert_eq!(loaded.identity.username.as_deref(), Some("alice"));
+
+        store.delete_session("s1").await.unwrap();
+        assert!(store.load_session("s1").await.unwrap().is_none());
+
+        // Sessions that already expired are not stored.
+        let mut expired = session("s2");
+        expired.expires_at = Utc::now() - chrono::Duration::seconds(1);
+        store.save_session(&expired).await.unwrap();
+        assert!(store.load_session("s2").await.unwrap().is_none());
+
+        store.save_session(&session("s3")).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let cutoff = Utc::now();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        store.save_session(&session("s4")).await.unwrap();
+        // Touching an old session does not make it survive the cutoff.
+        let mut touched = session("s3");
+        touched.extend(chrono::Duration::hours(2));
+        store.save_session(&touched).await.unwrap();
+        assert_eq!(store.delete_sessions_before(cutoff).await.unwrap(), 1);
+        assert!(store.load_session("s3").await.unwrap().is_none());
+        assert!(store.load_session("s4").await.unwrap().is_some());
+        assert_eq!(store.delete_sessions_before(cutoff).await.unwrap(), 0);
+    }
 }
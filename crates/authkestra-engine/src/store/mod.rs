@@ -9,13 +9,33 @@ pub enum StoreError {
     NotFound,
     #[error("Serialization error: {0}")]
     Serialization(String),
+    #[error("Operation not supported by this store: {0}")]
+    Unsupported(&'static str),
 }
 
 #[async_trait]
 pub trait KvStore<T>: Send + Sync + 'static {
+    /// The value under `key`: `Ok(None)` only if there is none (or it
+    /// expired), an error if the backend could not be queried. A
+    /// [`StoreError::NotFound`] is treated like `Ok(None)` by session stores.
     async fn get(&self, key: &str) -> Result<Option<T>, StoreError>;
     async fn set(&self, key: &str, value: T, ttl: Duration) -> Result<(), StoreError>;
     async fn delete(&self, key: &str) -> Result<(), StoreError>;
+
+    /// Delete every entry first written before `cutoff`, returning how many
+    /// were removed.
+    ///
+    /// Overwriting a key keeps its original creation time. This operates on
+    /// the whole store, so keep the values it should apply to (e.g. sessions)
+    /// in a store of their own. Backends that do not track creation times
+    /// return [`StoreError::Unsupported`].
+    async fn delete_created_before(
+        &self,
+        cutoff: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, StoreError> {
+        let _ = cutoff;
+        Err(StoreError::Unsupported("delete_created_before"))
+    }
 }
 
 /// Backends that can atomically fetch-and-remove a value implement this.
@@ -24,6 +44,19 @@ pub trait AtomicConsume<T>: KvStore<T> {
     async fn consume(&self, key: &str) -> Result<Option<T>, StoreError>;
 }
 
+/// Backends that can atomically write a value only if its key is free
+/// implement this.
+#[async_trait]
+pub trait AtomicInsert<T>: KvStore<T> {
+    /// Set `key` unless it holds an unexpired value, returning whether it was set.
+    async fn insert_if_absent(
+        &self,
+        key: &str,
+        value: T,
+        ttl: Duration,
+    ) -> Result<bool, StoreError>;
+}
+
 /// Backends that can atomically write a value under a primary key while
 /// also maintaining a secondary lookup key implement this.
 #[async_trait]
@@ -107,7 +107,7 @@ impl<T: Serialize + DeserializeOwned + Send + Sync + 'static> KvStore<T> for Red
     }
 }
 
-use crate::store::{AtomicConsume, IndexedKvStore};
+use crate::store::{AtomicConsume, AtomicInsert, IndexedKvStore};
 
 #[async_trait]
 impl<T: Serialize + DeserializeOwned + Send + Sync + 'static> AtomicConsume<T> for RedisStore {
@@ -160,6 +160,49 @@ impl<T: Serialize + DeserializeOwned + Send + Sync + 'static> AtomicConsume<T> f
     }
 }
 
+#[async_trait]
+impl<T: Serialize + DeserializeOwned + Send + Sync + 'static> AtomicInsert<T> for RedisStore {
+    #[tracing::instrument(skip(self, value), fields(key = %key))]
+    async fn insert_if_absent(
+        &self,
+        key: &str,
+        value: T,
+        ttl: Duration,
+    ) -> Result<bool, StoreError> {
+        tracing::debug!("inserting into redis store if absent");
+        let mut conn = self
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "Redis connection error");
+                StoreError::Internal(format!("Redis connection error: {e}"))
+            })?;
+
+        let json = serde_json::to_string(&value).map_err(|e| {
+            tracing::error!(error = %e, "Serialization error");
+            StoreError::Serialization(format!("Serialization error: {e}"))
+        })?;
+
+        // Round up: an EX of 0 is rejected by Redis.
+        let ttl_secs = ttl.as_secs().max(1);
+        let reply: Option<String> = redis::cmd("SET")
+            .arg(self.key(key))
+            .arg(json)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "Redis set nx error");
+                StoreError::Internal(format!("Redis set nx error: {e}"))
+            })?;
+
+        Ok(reply.is_some())
+    }
+}
+
 impl RedisStore {
     fn index_key(&self, index: &str) -> String {
         format!("{prefix}:idx:{index}", prefix = self.prefix)
@@ -245,7 +288,7 @@ impl<T: Serialize + DeserializeOwned + Send + Sync + 'static> IndexedKvStore<T>
 #[cfg(all(test, feature = "redis"))]
 mod tests {
     use super::*;
-    use crate::store::{AtomicConsume, IndexedKvStore, KvStore};
+    use crate::store::{AtomicConsume, AtomicInsert, IndexedKvStore, KvStore};
     use std::time::Duration;
     use testcontainers::{runners::AsyncRunner, ContainerAsync};
     use testcontainers_modules::redis::Redis;
@@ -295,6 +338,22 @@ mod tests {
         assert_eq!(val2, None);
     }
 
+    #[tokio::test]
+    async fn test_redis_insert_if_absent() {
+        let (store, _c) = setup_redis().await;
+
+        assert!(store
+            .insert_if_absent("key1", "first".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap());
+        assert!(!store
+            .insert_if_absent("key1", "second".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap());
+        let val: Option<String> = store.get("key1").await.unwrap();
+        assert_eq!(val, Some("first".to_string()));
+    }
+
     #[tokio::test]
     async fn test_redis_indexed_store() {
         let (store, _c) = setup_redis().await;
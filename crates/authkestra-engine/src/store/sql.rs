@@ -16,6 +16,8 @@ pub struct SqlKvStore<DB: Database> {
     pool: sqlx::Pool<DB>,
     #[allow(dead_code)]
     table_name: String,
+    cipher: Option<ValueCipher>,
+    allow_plaintext_reads: bool,
 }
 
 pub type SqlStore<DB> = SqlKvStore<DB>;
@@ -33,11 +35,145 @@ impl<DB: Database> SqlKvStore<DB> {
         Self {
             pool,
             table_name: "authkestra_kv".to_string(),
+            cipher: None,
+            allow_plaintext_reads: false,
         }
     }
 
     pub fn with_table_name(pool: sqlx::Pool<DB>, table_name: String) -> Self {
-        Self { pool, table_name }
+        Self {
+            pool,
+            table_name,
+            cipher: None,
+            allow_plaintext_reads: false,
+        }
+    }
+
+    /// Encrypt values at rest with XChaCha20-Poly1305 under `key`.
+    ///
+    /// Values are stored as plaintext JSON by default. With a key, every
+    /// write is sealed with a random 24-byte nonce stored in front of the
+    /// ciphertext, and bound to its row key so it cannot be moved to another
+    /// row. Plaintext rows are rejected once a key is set, so a row written
+    /// straight to the database cannot pose as a stored value; see
+    /// [`allow_plaintext_reads`](Self::allow_plaintext_reads) to migrate an
+    /// existing table. Losing the key makes the encrypted rows unreadable.
+    pub fn with_encryption_key(mut self, key: &[u8; 32]) -> Self {
+        use chacha20poly1305::KeyInit;
+        self.cipher = Some(ValueCipher(chacha20poly1305::XChaCha20Poly1305::new(
+            key.into(),
+        )));
+        self
+    }
+
+    /// Whether rows without the encryption prefix still load once an
+    /// encryption key is set. Off by default.
+    ///
+    /// Enable it while migrating a table written before encryption was turned
+    /// on: plaintext rows then load and are encrypted the next time they are
+    /// saved. Has no effect without an encryption key.
+    pub fn allow_plaintext_reads(mut self, allow: bool) -> Self {
+        self.allow_plaintext_reads = allow;
+        self
+    }
+
+    /// Serialize `value` for the row `key`, encrypting it if a key is set.
+    fn encode<T: Serialize>(&self, key: &str, value: &T) -> Result<String, StoreError> {
+        let json = serde_json::to_string(value).map_err(|e| {
+            tracing::error!(error = %e, "Serialization error");
+            StoreError::Serialization(format!("Serialization error: {e}"))
+        })?;
+        match &self.cipher {
+            Some(cipher) => cipher.seal(key, &json),
+            None => Ok(json),
+        }
+    }
+
+    /// Deserialize the stored value of the row `key`.
+    fn decode<T: DeserializeOwned>(&self, key: &str, stored: &str) -> Result<T, StoreError> {
+        let json = match stored.strip_prefix(ENCRYPTED_PREFIX) {
+            Some(sealed) => {
+                let cipher = self.cipher.as_ref().ok_or_else(|| {
+                    StoreError::Serialization(
+                        "value is encrypted but no encryption key is set".to_string(),
+                    )
+                })?;
+                cipher.open(key, sealed)?
+            }
+            None if self.cipher.is_some() && !self.allow_plaintext_reads => {
+                tracing::warn!(%key, "rejected a plaintext value in an encrypted store");
+                return Err(StoreError::Serialization(
+                    "value is not encrypted but an encryption key is set".to_string(),
+                ));
+            }
+            None => stored.to_string(),
+        };
+        serde_json::from_str(&json).map_err(|e| {
+            tracing::error!(error = %e, "Deserialization error");
+            StoreError::Serialization(format!("Deserialization error: {e}"))
+        })
+    }
+}
+
+/// Marks values written by [`SqlKvStore::with_encryption_key`].
+const ENCRYPTED_PREFIX: &str = "xc20p:";
+
+const NONCE_LEN: usize = 24;
+
+#[derive(Clone)]
+struct ValueCipher(chacha20poly1305::XChaCha20Poly1305);
+
+impl std::fmt::Debug for ValueCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ValueCipher([redacted])")
+    }
+}
+
+impl ValueCipher {
+    fn seal(&self, key: &str, json: &str) -> Result<String, StoreError> {
+        use chacha20poly1305::aead::{Aead, Payload};
+        use rand::RngCore;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rng().fill_bytes(&mut nonce);
+        let payload = Payload {
+            msg: json.as_bytes(),
+            aad: key.as_bytes(),
+        };
+        let ciphertext = self
+            .0
+            .encrypt(&nonce.into(), payload)
+            .map_err(|e| StoreError::Serialization(format!("Encryption failed: {e}")))?;
+
+        let mut combined = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        combined.extend_from_slice(&nonce);
+        combined.extend_from_slice(&ciphertext);
+        let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, combined);
+        Ok(format!("{ENCRYPTED_PREFIX}{encoded}"))
+    }
+
+    fn open(&self, key: &str, sealed: &str) -> Result<String, StoreError> {
+        use chacha20poly1305::aead::{Aead, Payload};
+
+        let combined =
+            base64::Engine::decode(&base64::engine::general_purpose::STANDARD, sealed)
+                .map_err(|e| StoreError::Serialization(format!("Invalid encrypted value: {e}")))?;
+        if combined.len() < NONCE_LEN {
+            return Err(StoreError::Serialization(
+                "Invalid encrypted value".to_string(),
+            ));
+        }
+        let (nonce, ciphertext) = combined.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: key.as_bytes(),
+        };
+        let json = self.0.decrypt(nonce.into(), payload).map_err(|e| {
+            tracing::error!("failed to decrypt stored value");
+            StoreError::Serialization(format!("Decryption failed: {e}"))
+        })?;
+        String::from_utf8(json)
+            .map_err(|e| StoreError::Serialization(format!("Decryption failed: {e}")))
     }
 }
 
@@ -50,10 +186,15 @@ macro_rules! impl_sql_store {
         $get_query:expr,
         $set_query:expr,
         $delete_query:expr,
+        $delete_before_query:expr,
         $migrate_q1:expr,
         $migrate_q2:expr,
+        $has_created_at_query:expr,
+        [$($add_created_at_query:expr),+ $(,)?],
         $set_indexed_query:expr,
         $get_by_index_query:expr,
+        $delete_expired_query:expr,
+        $insert_if_absent_query:expr,
         $consume_impl:item
     ) => {
         #[cfg(feature = $feature)]
@@ -79,10 +220,7 @@ macro_rules! impl_sql_store {
 
                 match row {
                     Some(model) => {
-                        let entity: T = serde_json::from_str(&model.value).map_err(|e| {
-                            tracing::error!(error = %e, "Deserialization error");
-                            StoreError::Serialization(format!("Deserialization error: {e}"))
-                        })?;
+                        let entity: T = self.decode(&model.key, &model.value)?;
                         Ok(Some(entity))
                     }
                     None => Ok(None),
@@ -94,17 +232,16 @@ macro_rules! impl_sql_store {
                 tracing::debug!(concat!("saving to ", $dialect_name, " store"));
                 let query = format!($set_query, self.table_name);
 
-                let json = serde_json::to_string(&value).map_err(|e| {
-                    tracing::error!(error = %e, "Serialization error");
-                    StoreError::Serialization(format!("Serialization error: {e}"))
-                })?;
+                let json = self.encode(key, &value)?;
 
-                let expires_at = chrono::Utc::now() + chrono::Duration::seconds(ttl.as_secs() as i64);
+                let now = chrono::Utc::now();
+                let expires_at = now + chrono::Duration::seconds(ttl.as_secs() as i64);
 
                 sqlx::query(&query)
                     .bind(key)
                     .bind(json)
                     .bind(expires_at)
+                    .bind(now)
                     .execute(&self.pool)
                     .await
                     .map_err(|e| {
@@ -129,11 +266,34 @@ macro_rules! impl_sql_store {
                     })?;
                 Ok(())
             }
+
+            #[tracing::instrument(skip(self))]
+            async fn delete_created_before(
+                &self,
+                cutoff: chrono::DateTime<chrono::Utc>,
+            ) -> Result<u64, StoreError> {
+                let query = format!($delete_before_query, self.table_name);
+                let result = sqlx::query(&query)
+                    .bind(cutoff)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!(error = %e, concat!($dialect_name, " delete_created_before error"));
+                        StoreError::Internal(format!("{} delete_created_before error: {}", $dialect_name, e))
+                    })?;
+                let deleted = result.rows_affected();
+                tracing::debug!(deleted, concat!("deleted entries from ", $dialect_name, " store"));
+                Ok(deleted)
+            }
         }
 
         #[cfg(feature = $feature)]
         impl SqlKvStore<$backend> {
             /// Creates the necessary table and index if they do not exist.
+            ///
+            /// Tables created before the `created_at` column was introduced get
+            /// the column added, with the migration time as the creation time of
+            /// their existing rows.
             pub async fn migrate(&self) -> Result<(), StoreError> {
                 let query1 = format!($migrate_q1, table = self.table_name);
                 let query2 = format!($migrate_q2, table = self.table_name);
@@ -141,6 +301,20 @@ macro_rules! impl_sql_store {
                     .execute(&self.pool)
                     .await
                     .map_err(|e| StoreError::Internal(format!("{} migration error: {}", $dialect_name, e)))?;
+                let has_created_at: i64 = sqlx::query_scalar($has_created_at_query)
+                    .bind(&self.table_name)
+                    .fetch_one(&self.pool)
+                    .await
+                    .map_err(|e| StoreError::Internal(format!("{} migration error: {}", $dialect_name, e)))?;
+                if has_created_at == 0 {
+                    tracing::info!(table = %self.table_name, "adding created_at column");
+                    $(
+                        sqlx::query(&format!($add_created_at_query, table = self.table_name))
+                            .execute(&self.pool)
+                            .await
+                            .map_err(|e| StoreError::Internal(format!("{} migration error: {}", $dialect_name, e)))?;
+                    )+
+                }
                 sqlx::query(&query2)
                     .execute(&self.pool)
                     .await
@@ -165,18 +339,17 @@ macro_rules! impl_sql_store {
                 tracing::debug!(concat!("saving indexed to ", $dialect_name, " store"));
                 let query = format!($set_indexed_query, self.table_name);
 
-                let json = serde_json::to_string(&value).map_err(|e| {
-                    tracing::error!(error = %e, "Serialization error");
-                    StoreError::Serialization(format!("Serialization error: {e}"))
-                })?;
+                let json = self.encode(key, &value)?;
 
-                let expires_at = chrono::Utc::now() + chrono::Duration::seconds(ttl.as_secs() as i64);
+                let now = chrono::Utc::now();
+                let expires_at = now + chrono::Duration::seconds(ttl.as_secs() as i64);
 
                 sqlx::query(&query)
                     .bind(key)
                     .bind(index)
                     .bind(json)
                     .bind(expires_at)
+                    .bind(now)
                     .execute(&self.pool)
                     .await
                     .map_err(|e| {
@@ -205,10 +378,7 @@ macro_rules! impl_sql_store {
 
                 match row {
                     Some(model) => {
-                        let entity: T = serde_json::from_str(&model.value).map_err(|e| {
-                            tracing::error!(error = %e, "Deserialization error");
-                            StoreError::Serialization(format!("Deserialization error: {e}"))
-                        })?;
+                        let entity: T = self.decode(&model.key, &model.value)?;
                         Ok(Some(entity))
                     }
                     None => Ok(None),
@@ -216,6 +386,53 @@ macro_rules! impl_sql_store {
             }
         }
 
+        #[cfg(feature = $feature)]
+        #[async_trait]
+        impl<T: Serialize + DeserializeOwned + Send + Sync + 'static> crate::store::AtomicInsert<T>
+            for SqlKvStore<$backend>
+        {
+            #[tracing::instrument(skip(self, value), fields(key = %key))]
+            async fn insert_if_absent(
+                &self,
+                key: &str,
+                value: T,
+                ttl: Duration,
+            ) -> Result<bool, StoreError> {
+                tracing::debug!(concat!("inserting into ", $dialect_name, " store if absent"));
+                let json = self.encode(key, &value)?;
+                let now = chrono::Utc::now();
+                let expires_at = now + chrono::Duration::seconds(ttl.as_secs() as i64);
+
+                // An expired row still holds the key until it is removed. Of
+                // concurrent inserts after that, the primary key lets one win.
+                let query = format!($delete_expired_query, self.table_name);
+                sqlx::query(&query)
+                    .bind(key)
+                    .bind(now)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!(error = %e, concat!($dialect_name, " delete expired error"));
+                        StoreError::Internal(format!("{} delete expired error: {}", $dialect_name, e))
+                    })?;
+
+                let query = format!($insert_if_absent_query, self.table_name);
+                let result = sqlx::query(&query)
+                    .bind(key)
+                    .bind(json)
+                    .bind(expires_at)
+                    .bind(now)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!(error = %e, concat!($dialect_name, " insert_if_absent error"));
+                        StoreError::Internal(format!("{} insert_if_absent error: {}", $dialect_name, e))
+                    })?;
+
+                Ok(result.rows_affected() == 1)
+            }
+        }
+
         #[cfg(feature = $feature)]
         #[async_trait]
         impl<T: Serialize + DeserializeOwned + Send + Sync + 'static> crate::store::AtomicConsume<T>
@@ -232,12 +449,17 @@ impl_sql_store! {
     "Postgres",
     "key",
     "SELECT key, value, expires_at FROM {} WHERE key = $1 AND expires_at > $2",
-    "INSERT INTO {} (key, value, expires_at) VALUES ($1, $2, $3) ON CONFLICT(key) DO UPDATE SET value = $2, expires_at = $3",
+    "INSERT INTO {} (key, value, expires_at, created_at) VALUES ($1, $2, $3, $4) ON CONFLICT(key) DO UPDATE SET value = $2, expires_at = $3",
     "DELETE FROM {} WHERE key = $1",
-    "CREATE TABLE IF NOT EXISTS {table} (key TEXT PRIMARY KEY, index_key TEXT, value TEXT NOT NULL, expires_at TIMESTAMP WITH TIME ZONE NOT NULL)",
+    "DELETE FROM {} WHERE created_at < $1",
+    "CREATE TABLE IF NOT EXISTS {table} (key TEXT PRIMARY KEY, index_key TEXT, value TEXT NOT NULL, expires_at TIMESTAMP WITH TIME ZONE NOT NULL, created_at TIMESTAMP WITH TIME ZONE NOT NULL)",
     "CREATE UNIQUE INDEX IF NOT EXISTS {table}_idx ON {table}(index_key)",
-    "INSERT INTO {} (key, index_key, value, expires_at) VALUES ($1, $2, $3, $4) ON CONFLICT(key) DO UPDATE SET index_key = $2, value = $3, expires_at = $4",
+    "SELECT COUNT(*) FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1 AND column_name = 'created_at'",
+    ["ALTER TABLE {table} ADD COLUMN IF NOT EXISTS created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP"],
+    "INSERT INTO {} (key, index_key, value, expires_at, created_at) VALUES ($1, $2, $3, $4, $5) ON CONFLICT(key) DO UPDATE SET index_key = $2, value = $3, expires_at = $4",
     "SELECT key, value, expires_at FROM {} WHERE index_key = $1 AND expires_at > $2",
+    "DELETE FROM {} WHERE key = $1 AND expires_at <= $2",
+    "INSERT INTO {} (key, value, expires_at, created_at) VALUES ($1, $2, $3, $4) ON CONFLICT(key) DO NOTHING",
     #[tracing::instrument(skip(self))]
     async fn consume(&self, key: &str) -> Result<Option<T>, StoreError> {
         tracing::debug!(key = %key, "atomically consuming from Postgres store");
@@ -259,10 +481,7 @@ impl_sql_store! {
 
         match row {
             Some(model) => {
-                let entity: T = serde_json::from_str(&model.value).map_err(|e| {
-                    tracing::error!(error = %e, "Deserialization error");
-                    StoreError::Serialization(format!("Deserialization error: {e}"))
-                })?;
+                let entity: T = self.decode(&model.key, &model.value)?;
                 Ok(Some(entity))
             }
             None => Ok(None),
@@ -276,12 +495,22 @@ impl_sql_store! {
     "Sqlite",
     "key",
     "SELECT key, value, expires_at FROM {} WHERE key = ?1 AND expires_at > ?2",
-    "INSERT INTO {} (key, value, expires_at) VALUES (?1, ?2, ?3) ON CONFLICT(key) DO UPDATE SET value = ?2, expires_at = ?3",
+    "INSERT INTO {} (key, value, expires_at, created_at) VALUES (?1, ?2, ?3, ?4) ON CONFLICT(key) DO UPDATE SET value = ?2, expires_at = ?3",
     "DELETE FROM {} WHERE key = ?1",
-    "CREATE TABLE IF NOT EXISTS {table} (key TEXT PRIMARY KEY, index_key TEXT, value TEXT NOT NULL, expires_at DATETIME NOT NULL)",
+    "DELETE FROM {} WHERE created_at < ?1",
+    "CREATE TABLE IF NOT EXISTS {table} (key TEXT PRIMARY KEY, index_key TEXT, value TEXT NOT NULL, expires_at DATETIME NOT NULL, created_at DATETIME NOT NULL)",
     "CREATE UNIQUE INDEX IF NOT EXISTS {table}_idx ON {table}(index_key)",
-    "INSERT INTO {} (key, index_key, value, expires_at) VALUES (?1, ?2, ?3, ?4) ON CONFLICT(key) DO UPDATE SET index_key = ?2, value = ?3, expires_at = ?4",
+    "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = 'created_at'",
+    [
+        // SQLite only adds columns with a constant default; backfill afterwards
+        // in the RFC 3339 format sqlx writes, so comparisons stay lexicographic.
+        "ALTER TABLE {table} ADD COLUMN created_at DATETIME NOT NULL DEFAULT ''",
+        "UPDATE {table} SET created_at = strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')",
+    ],
+    "INSERT INTO {} (key, index_key, value, expires_at, created_at) VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT(key) DO UPDATE SET index_key = ?2, value = ?3, expires_at = ?4",
     "SELECT key, value, expires_at FROM {} WHERE index_key = ?1 AND expires_at > ?2",
+    "DELETE FROM {} WHERE key = ?1 AND expires_at <= ?2",
+    "INSERT INTO {} (key, value, expires_at, created_at) VALUES (?1, ?2, ?3, ?4) ON CONFLICT(key) DO NOTHING",
     #[tracing::instrument(skip(self))]
     async fn consume(&self, key: &str) -> Result<Option<T>, StoreError> {
         tracing::debug!(key = %key, "atomically consuming from Sqlite store");
@@ -303,10 +532,7 @@ impl_sql_store! {
 
         match row {
             Some(model) => {
-                let entity: T = serde_json::from_str(&model.value).map_err(|e| {
-                    tracing::error!(error = %e, "Deserialization error");
-                    StoreError::Serialization(format!("Deserialization error: {e}"))
-                })?;
+                let entity: T = self.decode(&model.key, &model.value)?;
                 Ok(Some(entity))
             }
             None => Ok(None),
@@ -320,12 +546,17 @@ impl_sql_store! {
     "MySql",
     "`key`",
     "SELECT `key`, value, expires_at FROM {} WHERE `key` = ? AND expires_at > ?",
-    "INSERT INTO {} (`key`, value, expires_at) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE value = VALUES(value), expires_at = VALUES(expires_at)",
+    "INSERT INTO {} (`key`, value, expires_at, created_at) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE value = VALUES(value), expires_at = VALUES(expires_at)",
     "DELETE FROM {} WHERE `key` = ?",
-    "CREATE TABLE IF NOT EXISTS {table} (`key` VARCHAR(255) PRIMARY KEY, index_key VARCHAR(255), value TEXT NOT NULL, expires_at TIMESTAMP NOT NULL)",
+    "DELETE FROM {} WHERE created_at < ?",
+    "CREATE TABLE IF NOT EXISTS {table} (`key` VARCHAR(255) PRIMARY KEY, index_key VARCHAR(255), value TEXT NOT NULL, expires_at TIMESTAMP NOT NULL, created_at TIMESTAMP(6) NOT NULL)",
     "CREATE UNIQUE INDEX {table}_idx ON {table}(index_key)",
-    "INSERT INTO {} (`key`, index_key, value, expires_at) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE index_key = VALUES(index_key), value = VALUES(value), expires_at = VALUES(expires_at)",
+    "SELECT COUNT(*) FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = ? AND column_name = 'created_at'",
+    ["ALTER TABLE {table} ADD COLUMN created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6)"],
+    "INSERT INTO {} (`key`, index_key, value, expires_at, created_at) VALUES (?, ?, ?, ?, ?) ON DUPLICATE KEY UPDATE index_key = VALUES(index_key), value = VALUES(value), expires_at = VALUES(expires_at)",
     "SELECT `key`, value, expires_at FROM {} WHERE index_key = ? AND expires_at > ?",
+    "DELETE FROM {} WHERE `key` = ? AND expires_at <= ?",
+    "INSERT IGNORE INTO {} (`key`, value, expires_at, created_at) VALUES (?, ?, ?, ?)",
     #[tracing::instrument(skip(self))]
     async fn consume(&self, key: &str) -> Result<Option<T>, StoreError> {
         tracing::debug!(key = %key, "atomically consuming from MySql store using transaction");
@@ -366,10 +597,7 @@ impl_sql_store! {
                 StoreError::Internal(format!("MySql commit error: {e}"))
             })?;
 
-            let entity: T = serde_json::from_str(&model.value).map_err(|e| {
-                tracing::error!(error = %e, "Deserialization error");
-                StoreError::Serialization(format!("Deserialization error: {e}"))
-            })?;
+            let entity: T = self.decode(&model.key, &model.value)?;
             Ok(Some(entity))
         } else {
             tx.rollback().await.map_err(|e| {
@@ -384,7 +612,7 @@ impl_sql_store! {
 #[cfg(all(test, feature = "sql-sqlite"))]
 mod tests {
     use super::*;
-    use crate::store::{AtomicConsume, IndexedKvStore, KvStore};
+    use crate::store::{AtomicConsume, AtomicInsert, IndexedKvStore, KvStore};
     use sqlx::sqlite::SqlitePoolOptions;
     use std::time::Duration;
 
@@ -417,6 +645,91 @@ mod tests {
         assert_eq!(res2, None);
     }
 
+    #[tokio::test]
+    async fn test_sqlite_delete_created_before() {
+        let store = setup_db().await;
+
+        store
+            .set("old1", "a".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap();
+        store
+            .set_indexed("old2", "sk2", "b".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let cutoff = chrono::Utc::now();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        store
+            .set("new", "c".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap();
+        // Overwriting keeps the original creation time.
+        store
+            .set("old1", "a2".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap();
+
+        let deleted = KvStore::<String>::delete_created_before(&store, cutoff)
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+
+        let old1: Option<String> = store.get("old1").await.unwrap();
+        assert_eq!(old1, None);
+        let old2: Option<String> = store.get_by_index("sk2").await.unwrap();
+        assert_eq!(old2, None);
+        let new: Option<String> = store.get("new").await.unwrap();
+        assert_eq!(new, Some("c".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_migrate_adds_created_at_to_existing_table() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        // The schema before `created_at` was introduced.
+        sqlx::query("CREATE TABLE authkestra_kv (key TEXT PRIMARY KEY, index_key TEXT, value TEXT NOT NULL, expires_at DATETIME NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO authkestra_kv (key, value, expires_at) VALUES ('legacy', '\"old\"', ?1)",
+        )
+        .bind(chrono::Utc::now() + chrono::Duration::hours(1))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let before = chrono::Utc::now() - chrono::Duration::seconds(1);
+        let store = SqlKvStore::<sqlx::Sqlite>::new(pool);
+        store.migrate().await.unwrap();
+        // Migrating again is a no-op.
+        store.migrate().await.unwrap();
+
+        let legacy: Option<String> = store.get("legacy").await.unwrap();
+        assert_eq!(legacy, Some("old".to_string()));
+        store
+            .set("new", "value".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap();
+
+        // Existing rows count as created at migration time.
+        let deleted = KvStore::<String>::delete_created_before(&store, before)
+            .await
+            .unwrap();
+        assert_eq!(deleted, 0);
+        let deleted = KvStore::<String>::delete_created_before(
+            &store,
+            chrono::Utc::now() + chrono::Duration::seconds(1),
+        )
+        .await
+        .unwrap();
+        assert_eq!(deleted, 2);
+    }
+
     #[tokio::test]
     async fn test_sqlite_atomic_consume() {
         let store = setup_db().await;
@@ -433,6 +746,32 @@ mod tests {
         assert_eq!(val2, None);
     }
 
+    #[tokio::test]
+    async fn test_sqlite_insert_if_absent() {
+        let store = setup_db().await;
+
+        assert!(store
+            .insert_if_absent("key1", "first".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap());
+        assert!(!store
+            .insert_if_absent("key1", "second".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap());
+        let val: Option<String> = store.get("key1").await.unwrap();
+        assert_eq!(val, Some("first".to_string()));
+
+        // An expired entry no longer holds the key.
+        store
+            .set("key2", "old".to_string(), Duration::from_secs(0))
+            .await
+            .unwrap();
+        assert!(store
+            .insert_if_absent("key2", "new".to_string(), Duration::from_secs(10))
+            .await
+            .unwrap());
+    }
+
     #[tokio::test]
     async fn test_sqlite_indexed_store() {
         let store = setup_db().await;
@@ -452,6 +791,157 @@ mod tests {
         let sk_res_none: Option<String> = store.get_by_index("sk1").await.unwrap();
         assert_eq!(sk_res_none, None);
     }
+
+    const KEY: &[u8; 32] = b"an-example-key-of-32-bytes-long!";
+
+    /// A single connection, so `stored` sees the rows written by the store.
+    async fn setup_encrypted_db() -> SqlKvStore<sqlx::Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        let store = SqlKvStore::new(pool).with_encryption_key(KEY);
+        store.migrate().await.unwrap();
+        store
+    }
+
+    async fn stored(store: &SqlKvStore<sqlx::Sqlite>, key: &str) -> String {
+        sqlx::query_scalar("SELECT value FROM authkestra_kv WHERE key = ?1")
+            .bind(key)
+            .fetch_one(&store.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_encrypted_session_round_trip() {
+        use crate::auth::{Identity, Session};
+        use std::collections::HashMap;
+
+        let store = setup_encrypted_db().await;
+        let session = Session {
+            id: "sid".to_string(),
+            identity: Identity {
+                provider_id: "github".to_string(),
+                external_id: "alice".to_string(),
+                email: Some("alice@example.com".to_string()),
+                email_verified: None,
+                username: None,
+                attributes: HashMap::from([(
+                    "refresh_token".to_string(),
+                    "upstream-refresh-token".to_string(),
+                )]),
+                attributes_multi: HashMap::new(),
+            },
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+            created_at: chrono::Utc::now(),
+        };
+        store
+            .set("session:sid", session.clone(), Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        let raw = stored(&store, "session:sid").await;
+        assert!(raw.starts_with(ENCRYPTED_PREFIX));
+        for plaintext in ["upstream-refresh-token", "alice", "github"] {
+            assert!(!raw.contains(plaintext), "{plaintext} stored in plaintext");
+        }
+
+        let loaded: Session = store.get("session:sid").await.unwrap().unwrap();
+        assert_eq!(loaded.identity.attributes, session.identity.attributes);
+        assert_eq!(loaded.identity.email, session.identity.email);
+
+        // Every write gets its own nonce.
+        store
+            .set("session:sid", session, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_ne!(stored(&store, "session:sid").await, raw);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_encrypted_values_are_bound_to_their_row() {
+        let store = setup_encrypted_db().await;
+        store
+            .set("a", "secret".to_string(), Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        // A value copied to another row does not decrypt there.
+        sqlx::query("INSERT INTO authkestra_kv (key, value, expires_at, created_at) SELECT 'b', value, expires_at, created_at FROM authkestra_kv WHERE key = 'a'")
+            .execute(&store.pool)
+            .await
+            .unwrap();
+        assert!(KvStore::<String>::get(&store, "b").await.is_err());
+
+        // Nor without the key, or with another one.
+        let plain = SqlKvStore::<sqlx::Sqlite>::new(store.pool.clone());
+        assert!(KvStore::<String>::get(&plain, "a").await.is_err());
+        let other = SqlKvStore::<sqlx::Sqlite>::new(store.pool.clone())
+            .with_encryption_key(b"another-key-of-exactly-32-bytes!");
+        assert!(KvStore::<String>::get(&other, "a").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_plaintext_rows_load_after_enabling_encryption() {
+        let store = setup_encrypted_db().await.allow_plaintext_reads(true);
+        let plain = SqlKvStore::<sqlx::Sqlite>::new(store.pool.clone());
+        plain
+            .set("key1", "value1".to_string(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(stored(&store, "key1").await, "\"value1\"");
+
+        assert_eq!(store.get("key1").await.unwrap(), Some("value1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_plaintext_rows_are_rejected_when_encrypted() {
+        let store = setup_encrypted_db().await;
+        let plain = SqlKvStore::<sqlx::Sqlite>::new(store.pool.clone());
+        plain
+            .set("key1", "value1".to_string(), Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            KvStore::<String>::get(&store, "key1").await,
+            Err(StoreError::Serialization(_))
+        ));
+        assert_eq!(plain.get("key1").await.unwrap(), Some("value1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_session_keeps_multi_valued_attributes() {
+        use crate::auth::{Identity, Session, SessionStore};
+        use std::collections::HashMap;
+
+        let store = setup_db().await;
+        let groups: Vec<String> = (0..300).map(|i| format!("group-{i}")).collect();
+        let session = Session {
+            id: "sid".to_string(),
+            identity: Identity {
+                provider_id: "oidc".to_string(),
+                external_id: "alice".to_string(),
+                email: None,
+                email_verified: None,
+                username: None,
+                attributes: HashMap::new(),
+                attributes_multi: HashMap::from([("groups".to_string(), groups)]),
+            },
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+            created_at: chrono::Utc::now(),
+        };
+        store.save_session(&session).await.unwrap();
+
+        let loaded = store.load_session("sid").await.unwrap().unwrap();
+        assert_eq!(
+            loaded.identity.attributes_multi,
+            session.identity.attributes_multi
+        );
+    }
 }
 
 #[cfg(all(test, feature = "sql-postgres"))]
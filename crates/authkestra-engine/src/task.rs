@@ -0,0 +1,108 @@
+//! Handles for background tasks.
+
+use std::future::Future;
+use tokio::task::JoinHandle;
+pub use tokio_util::sync::CancellationToken;
+
+/// A spawned background task, such as a JWKS refresher or OIDC rediscovery.
+///
+/// The task is cancelled when the handle is dropped, or by
+/// [`shutdown`](Self::shutdown), which also waits for it to exit. Tasks
+/// receive a [`CancellationToken`] and return once it is cancelled, typically
+/// by racing their work against `token.cancelled()`:
+///
+/// ```rust,ignore
+/// let handle = TaskHandle::spawn(|token| async move {
+///     loop {
+///         tokio::select! {
+///             _ = token.cancelled() => break,
+///             _ = tokio::time::sleep(interval) => refresh().await,
+///         }
+///     }
+/// });
+/// // ...
+/// handle.shutdown().await;
+/// ```
+#[derive(Debug)]
+#[must_use = "dropping a TaskHandle cancels its task"]
+pub struct TaskHandle {
+    token: CancellationToken,
+    join: Option<JoinHandle<()>>,
+}
+
+impl TaskHandle {
+    /// Spawn `task` on the current Tokio runtime, passing it the token that
+    /// cancels it.
+    pub fn spawn<F, Fut>(task: F) -> Self
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let token = CancellationToken::new();
+        let join = tokio::spawn(task(token.clone()));
+        Self {
+            token,
+            join: Some(join),
+        }
+    }
+
+    /// Whether the task has exited.
+    pub fn is_finished(&self) -> bool {
+        self.join.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Cancel the task and wait for it to exit.
+    pub async fn shutdown(mut self) {
+        self.token.cancel();
+        if let Some(join) = self.join.take() {
+            if let Err(e) = join.await {
+                tracing::error!(error = %e, "background task failed");
+            }
+        }
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    fn spawn(stopped: &Arc<AtomicBool>) -> TaskHandle {
+        let stopped = stopped.clone();
+        TaskHandle::spawn(|token| async move {
+            token.cancelled().await;
+            stopped.store(true, Ordering::SeqCst);
+        })
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_the_task() {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let handle = spawn(&stopped);
+        assert!(!handle.is_finished());
+
+        handle.shutdown().await;
+        assert!(stopped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_drop_cancels_the_task() {
+        let stopped = Arc::new(AtomicBool::new(false));
+        drop(spawn(&stopped));
+
+        for _ in 0..100 {
+            if stopped.load(Ordering::SeqCst) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("task was not cancelled on drop");
+    }
+}
@@ -15,8 +15,10 @@ impl AuthMethod for MockAuthMethod {
             provider_id: "mock".to_string(),
             external_id: "user123".to_string(),
             email: Some("mock@example.com".to_string()),
+            email_verified: None,
             username: Some("Mock User".to_string()),
             attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
         })
     }
 }
@@ -44,8 +46,10 @@ impl Flow for MockFlow {
             provider_id: "mock".to_string(),
             external_id: "user123".to_string(),
             email: Some("mock@example.com".to_string()),
+            email_verified: None,
             username: Some("Mock User".to_string()),
             attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
         }))
     }
 }
@@ -115,3 +119,362 @@ fn test_auth_engine_builder_typestate() {
 
     let _s = engine_with_session.session_store();
 }
+
+#[test]
+fn test_jwt_issuer_composes_with_jwt_secret_in_any_order() {
+    use crate::engine::Engine;
+
+    let before = Engine::builder()
+        .jwt_issuer("https://issuer.example")
+        .jwt_secret(b"secret")
+        .build();
+    let after = Engine::builder()
+        .jwt_secret(b"secret")
+        .jwt_issuer("https://issuer.example")
+        .build();
+
+    for engine in [before, after] {
+        let identity = Identity {
+            provider_id: "mock".to_string(),
+            external_id: "user123".to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        };
+        let token = engine.issue_token(identity, 60).unwrap();
+        let claims = engine.token_manager().validate_token(&token, None).unwrap();
+        assert_eq!(claims.iss.as_deref(), Some("https://issuer.example"));
+    }
+}
+
+#[test]
+fn test_partitioned_cookie_requires_same_site_none_and_secure() {
+    use crate::auth::{SameSite, SessionConfig};
+    use crate::engine::{Engine, EngineBuildError};
+
+    let partitioned = |same_site, secure| SessionConfig {
+        partitioned: true,
+        same_site,
+        secure,
+        ..Default::default()
+    };
+    for config in [
+        partitioned(SameSite::Lax, true),
+        partitioned(SameSite::None, false),
+    ] {
+        let result = Engine::builder().session_config(config).try_build();
+        assert!(matches!(
+            result.err(),
+            Some(EngineBuildError::InvalidPartitionedCookie)
+        ));
+    }
+    assert!(Engine::builder()
+        .session_config(partitioned(SameSite::None, true))
+        .try_build()
+        .is_ok());
+}
+
+#[test]
+fn test_unknown_provider_status_must_be_an_error_status() {
+    use crate::engine::{Engine, EngineBuildError, UnknownProviderResponse};
+
+    let with_status = |status| {
+        Engine::builder()
+            .unknown_provider_response(UnknownProviderResponse {
+                status,
+                ..Default::default()
+            })
+            .try_build()
+    };
+    for status in [0, 200, 302, 600, 1000] {
+        assert!(matches!(
+            with_status(status).err(),
+            Some(EngineBuildError::InvalidUnknownProviderStatus(s)) if s == status
+        ));
+    }
+    assert!(with_status(404).is_ok());
+}
+
+#[test]
+fn test_oauth_token_secrets_are_redacted() {
+    let token = crate::auth::OAuthToken {
+        access_token: "secret-access".to_string(),
+        token_type: "Bearer".to_string(),
+        expires_in: Some(3600),
+        issued_at: chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        refresh_token: Some("secret-refresh".to_string()),
+        scope: Some("openid".to_string()),
+        id_token: Some("secret-id".to_string()),
+    };
+
+    let debug = format!("{token:?}");
+    assert!(!debug.contains("secret-"));
+    assert!(debug.contains("Bearer"));
+
+    let json = serde_json::to_value(token.redacted()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "token_type": "Bearer",
+            "expires_in": 3600,
+            "issued_at": "2023-11-14T22:13:20Z",
+            "scope": "openid"
+        })
+    );
+
+    // The token itself round-trips with its values.
+    let json = serde_json::to_string(&token).unwrap();
+    let restored: crate::auth::OAuthToken = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.access_token, "secret-access");
+    assert_eq!(restored.refresh_token.as_deref(), Some("secret-refresh"));
+    assert_eq!(restored.id_token.as_deref(), Some("secret-id"));
+
+    let parsed: crate::auth::OAuthToken = serde_json::from_str(
+        r#"{"access_token":"a","token_type":"Bearer","refresh_token":"r","id_token":"i"}"#,
+    )
+    .unwrap();
+    assert_eq!(parsed.access_token, "a");
+    assert_eq!(parsed.refresh_token.as_deref(), Some("r"));
+    assert_eq!(parsed.id_token.as_deref(), Some("i"));
+}
+
+#[test]
+fn test_oauth_token_expires_at_counts_from_fetch_time() {
+    let before = chrono::Utc::now();
+    let token: crate::auth::OAuthToken =
+        serde_json::from_str(r#"{"access_token":"a","token_type":"Bearer","expires_in":3600}"#)
+            .unwrap();
+    let after = chrono::Utc::now();
+
+    assert!(token.issued_at >= before && token.issued_at <= after);
+    assert_eq!(
+        token.expires_at(),
+        Some(token.issued_at + chrono::Duration::seconds(3600))
+    );
+
+    // The fetch time survives a round trip, so a token read later keeps its expiry.
+    let json = serde_json::to_string(&token).unwrap();
+    let mut restored: crate::auth::OAuthToken = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.issued_at, token.issued_at);
+
+    restored.expires_in = None;
+    assert_eq!(restored.expires_at(), None);
+}
+
+#[test]
+fn test_identity_token_attributes_are_redacted() {
+    let mut attributes = HashMap::new();
+    attributes.insert("access_token".to_string(), "secret-access".to_string());
+    attributes.insert("refresh_token".to_string(), "secret-refresh".to_string());
+    attributes.insert("locale".to_string(), "en".to_string());
+    let identity = Identity {
+        provider_id: "mock".to_string(),
+        external_id: "user123".to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes,
+        attributes_multi: HashMap::new(),
+    };
+
+    let debug = format!("{identity:?}");
+    assert!(!debug.contains("secret-"));
+    assert!(debug.contains("\"locale\": \"en\""));
+    assert!(debug.contains("user123"));
+}
+
+#[test]
+fn test_identity_subject() {
+    let identity = Identity {
+        provider_id: "github".to_string(),
+        external_id: "12345".to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    };
+    assert_eq!(identity.subject(), "github:12345");
+
+    // The same external id from another provider is a different subject.
+    let other = Identity {
+        provider_id: "google".to_string(),
+        ..identity.clone()
+    };
+    assert_ne!(other.subject(), identity.subject());
+}
+
+/// An identity with a locale and 300 group memberships.
+fn identity_with_groups() -> Identity {
+    let groups: Vec<String> = (0..300).map(|i| format!("group-{i}")).collect();
+    Identity {
+        provider_id: "oidc".to_string(),
+        external_id: "alice".to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes: HashMap::from([("locale".to_string(), "en".to_string())]),
+        attributes_multi: HashMap::from([("groups".to_string(), groups)]),
+    }
+}
+
+#[test]
+fn test_identity_groups_serialize_as_a_json_array() {
+    let json = serde_json::to_value(identity_with_groups()).unwrap();
+    assert_eq!(json["attributes_multi"]["groups"][0], "group-0");
+    assert_eq!(json["attributes_multi"]["groups"][299], "group-299");
+
+    let identity: Identity = serde_json::from_value(json).unwrap();
+    assert_eq!(identity.attributes_multi["groups"].len(), 300);
+    assert_eq!(identity.attributes["locale"], "en");
+}
+
+#[test]
+fn test_identities_without_multi_valued_attributes() {
+    // Serialized before `attributes_multi` existed.
+    let json = r#"{"provider_id":"oidc","external_id":"alice","email":null,"username":null,"attributes":{"locale":"en"}}"#;
+    let identity: Identity = serde_json::from_str(json).unwrap();
+    assert!(identity.attributes_multi.is_empty());
+
+    // Empty lists are left out again.
+    let json = serde_json::to_value(&identity).unwrap();
+    assert!(json.get("attributes_multi").is_none());
+}
+
+#[test]
+fn test_identity_groups_round_trip_through_a_token() {
+    let manager = crate::TokenManager::new(b"a-test-secret-of-at-least-32-bytes!!", None);
+    let token = manager
+        .issue_user_token(identity_with_groups(), 3600, None, None)
+        .unwrap();
+    let claims = manager.validate_token(&token, None).unwrap();
+    assert_eq!(
+        claims.identity.unwrap().attributes_multi,
+        identity_with_groups().attributes_multi
+    );
+}
+
+#[test]
+fn test_remember_flag_is_carried_in_the_encrypted_state() {
+    use crate::auth::state::OAuth2State;
+
+    let state = OAuth2State {
+        state: "csrf".to_string(),
+        nonce: None,
+        code_verifier: None,
+        success_url: None,
+        remember: true,
+        correlation_id: None,
+        scopes: Default::default(),
+        provider_id: "mock".to_string(),
+        expires_at: chrono::Utc::now().timestamp() + 300,
+    };
+
+    let key = crate::auth::SessionConfig::default().state_encryption_key;
+    let encrypted = state.encrypt(&key).unwrap();
+    assert!(OAuth2State::decrypt(&encrypted, &key).unwrap().remember);
+    assert!(OAuth2State::decrypt(&encrypted, &[7u8; 32]).is_err());
+}
+
+#[test]
+fn test_correlation_id() {
+    assert_eq!(crate::auth::correlation_id(Some("req-42")), "req-42");
+    assert_eq!(crate::auth::correlation_id(Some(" req-42 ")), "req-42");
+
+    let long = "a".repeat(129);
+    for rejected in [
+        None,
+        Some(""),
+        Some("two words"),
+        Some("caf\u{e9}"),
+        Some(&long),
+    ] {
+        let id = crate::auth::correlation_id(rejected);
+        assert!(uuid::Uuid::parse_str(&id).is_ok(), "{rejected:?}");
+    }
+}
+
+#[cfg(feature = "memory")]
+mod session_fixation {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::store::memory::MemoryStore;
+    use std::sync::Arc;
+
+    fn identity(external_id: &str) -> Identity {
+        Identity {
+            provider_id: "mock".to_string(),
+            external_id: external_id.to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_regenerate_session_id_invalidates_old_id() {
+        let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::new());
+        let engine = Engine::builder().session_store(store.clone()).build();
+
+        let anonymous = engine.create_session(identity("anonymous")).await.unwrap();
+        let session = engine
+            .regenerate_session_id(&anonymous.id, identity("alice"))
+            .await
+            .unwrap();
+
+        assert_ne!(session.id, anonymous.id);
+        assert!(store.load_session(&anonymous.id).await.unwrap().is_none());
+        let loaded = store.load_session(&session.id).await.unwrap().unwrap();
+        assert_eq!(loaded.identity.external_id, "alice");
+    }
+
+    /// Fails to delete the session with id `pinned`.
+    struct PinnedStore {
+        memory: MemoryStore<Session>,
+        pinned: std::sync::Mutex<String>,
+    }
+
+    #[async_trait]
+    impl SessionStore for PinnedStore {
+        async fn load_session(&self, id: &str) -> Result<Option<Session>, AuthError> {
+            self.memory.load_session(id).await
+        }
+
+        async fn save_session(&self, session: &Session) -> Result<(), AuthError> {
+            self.memory.save_session(session).await
+        }
+
+        async fn delete_session(&self, id: &str) -> Result<(), AuthError> {
+            if *self.pinned.lock().unwrap() == id {
+                return Err(AuthError::Session("unavailable".to_string()));
+            }
+            self.memory.delete_session(id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_regenerate_session_id_fails_when_old_id_survives() {
+        let memory = MemoryStore::<Session>::new();
+        let store = Arc::new(PinnedStore {
+            memory: memory.clone(),
+            pinned: Default::default(),
+        });
+        let engine = Engine::builder().session_store(store.clone()).build();
+
+        let anonymous = engine.create_session(identity("anonymous")).await.unwrap();
+        *store.pinned.lock().unwrap() = anonymous.id.clone();
+        let result = engine
+            .regenerate_session_id(&anonymous.id, identity("alice"))
+            .await;
+
+        assert!(matches!(result, Err(AuthError::Session(_))));
+        assert!(memory.load_session(&anonymous.id).await.unwrap().is_some());
+        // The new session was discarded: only the old one is left.
+        let far_future = chrono::Utc::now() + chrono::Duration::days(1);
+        assert_eq!(memory.delete_sessions_before(far_future).await.unwrap(), 1);
+    }
+}
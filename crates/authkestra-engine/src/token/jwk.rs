@@ -1,6 +1,7 @@
 use crate::auth::error::AuthError;
-use jsonwebtoken::DecodingKey;
+use jsonwebtoken::{Algorithm, DecodingKey};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Jwk {
@@ -9,25 +10,74 @@ pub struct Jwk {
     pub alg: Option<String>,
     pub n: Option<String>,
     pub e: Option<String>,
+    /// The curve name for EC keys (e.g. `P-256`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    /// The x coordinate for EC keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    /// The y coordinate for EC keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
 }
 
 impl Jwk {
-    pub fn to_decoding_key(&self) -> Result<DecodingKey, AuthError> {
-        if self.kty != "RSA" {
-            return Err(AuthError::Token(
-                "Only RSA keys are supported currently".to_string(),
-            ));
+    /// Whether this key can verify signatures made with `alg`.
+    ///
+    /// The key type (and curve, for EC keys) must fit the algorithm, and so must
+    /// the key's `alg`, if it has one.
+    pub fn supports(&self, alg: Algorithm) -> bool {
+        if let Some(key_alg) = &self.alg {
+            if Algorithm::from_str(key_alg).ok() != Some(alg) {
+                return false;
+            }
+        }
+        match alg {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => self.kty == "RSA",
+            Algorithm::ES256 => {
+                self.kty == "EC" && self.crv.as_deref().is_none_or(|c| c == "P-256")
+            }
+            Algorithm::ES384 => {
+                self.kty == "EC" && self.crv.as_deref().is_none_or(|c| c == "P-384")
+            }
+            _ => false,
         }
+    }
 
-        let n = self
-            .n
-            .as_ref()
-            .ok_or_else(|| AuthError::Token("Missing 'n' component in JWK".to_string()))?;
-        let e = self
-            .e
-            .as_ref()
-            .ok_or_else(|| AuthError::Token("Missing 'e' component in JWK".to_string()))?;
+    pub fn to_decoding_key(&self) -> Result<DecodingKey, AuthError> {
+        match self.kty.as_str() {
+            "RSA" => {
+                let n = self
+                    .n
+                    .as_ref()
+                    .ok_or_else(|| AuthError::Token("Missing 'n' component in JWK".to_string()))?;
+                let e = self
+                    .e
+                    .as_ref()
+                    .ok_or_else(|| AuthError::Token("Missing 'e' component in JWK".to_string()))?;
+
+                DecodingKey::from_rsa_components(n, e).map_err(|e| AuthError::Token(e.to_string()))
+            }
+            "EC" => {
+                let x = self
+                    .x
+                    .as_ref()
+                    .ok_or_else(|| AuthError::Token("Missing 'x' component in JWK".to_string()))?;
+                let y = self
+                    .y
+                    .as_ref()
+                    .ok_or_else(|| AuthError::Token("Missing 'y' component in JWK".to_string()))?;
 
-        DecodingKey::from_rsa_components(n, e).map_err(|e| AuthError::Token(e.to_string()))
+                DecodingKey::from_ec_components(x, y).map_err(|e| AuthError::Token(e.to_string()))
+            }
+            other => Err(AuthError::Token(format!(
+                "Unsupported JWK key type: {other}"
+            ))),
+        }
     }
 }
@@ -0,0 +1,148 @@
+#![cfg(feature = "native-async")]
+
+use authkestra_engine::auth::{
+    AuthError, DynCompat, Identity, NativeSessionStore, Session, SessionStore,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+/// Counts the allocations of the current thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// A store answering without allocating, so only the call itself is measured.
+struct EmptyStore;
+
+impl NativeSessionStore for EmptyStore {
+    async fn load_session(&self, _id: &str) -> Result<Option<Session>, AuthError> {
+        Ok(None)
+    }
+
+    async fn save_session(&self, _session: &Session) -> Result<(), AuthError> {
+        Ok(())
+    }
+
+    async fn delete_session(&self, _id: &str) -> Result<(), AuthError> {
+        Ok(())
+    }
+}
+
+/// Polls a future that completes without waiting, counting the allocations.
+fn allocations<F: Future>(future: F) -> (F::Output, usize) {
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut future = std::pin::pin!(future);
+    let before = ALLOCATIONS.with(Cell::get);
+    let Poll::Ready(output) = future.as_mut().poll(&mut cx) else {
+        panic!("the store never waits");
+    };
+    (output, ALLOCATIONS.with(Cell::get) - before)
+}
+
+async fn load_native<S: NativeSessionStore>(store: &S) -> Result<Option<Session>, AuthError> {
+    store.load_session("id").await
+}
+
+#[test]
+fn test_native_store_does_not_box_the_future() {
+    let store = EmptyStore;
+    let (session, count) = allocations(load_native(&store));
+    assert!(session.unwrap().is_none());
+    assert_eq!(count, 0);
+
+    let boxed: Arc<dyn SessionStore> = Arc::new(DynCompat(EmptyStore));
+    // The boxed future is allocated when the trait method is called, so call
+    // it inside the measured future.
+    let (session, count) = allocations(async { boxed.load_session("id").await });
+    assert!(session.unwrap().is_none());
+    assert!(count >= 1, "the boxed store allocated {count} times");
+}
+
+#[test]
+fn test_dyn_compat_forwards_to_the_native_store() {
+    let store: Arc<dyn SessionStore> = Arc::new(DynCompat(EmptyStore));
+    let session = Session {
+        id: "id".to_string(),
+        identity: Identity {
+            provider_id: "mock".to_string(),
+            external_id: "alice".to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        },
+        expires_at: chrono::Utc::now(),
+        created_at: chrono::Utc::now(),
+    };
+
+    assert!(allocations(store.save_session(&session)).0.is_ok());
+    assert!(allocations(store.delete_session("id")).0.is_ok());
+    // `create_session` keeps its default on top of the native `save_session`.
+    assert_eq!(allocations(store.create_session(&session)).0.unwrap(), "id");
+}
+
+/// A store that deletes by creation time and is never reachable.
+struct BatchStore;
+
+impl NativeSessionStore for BatchStore {
+    async fn load_session(&self, _id: &str) -> Result<Option<Session>, AuthError> {
+        Ok(None)
+    }
+
+    async fn save_session(&self, _session: &Session) -> Result<(), AuthError> {
+        Ok(())
+    }
+
+    async fn delete_session(&self, _id: &str) -> Result<(), AuthError> {
+        Ok(())
+    }
+
+    async fn delete_sessions_before(
+        &self,
+        _cutoff: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, AuthError> {
+        Ok(3)
+    }
+
+    async fn ping(&self) -> Result<(), AuthError> {
+        Err(AuthError::Session("unreachable".to_string()))
+    }
+}
+
+#[test]
+fn test_dyn_compat_forwards_batch_deletion_and_ping() {
+    let store: Arc<dyn SessionStore> = Arc::new(DynCompat(BatchStore));
+    let deleted = allocations(store.delete_sessions_before(chrono::Utc::now())).0;
+    assert_eq!(deleted.unwrap(), 3);
+    assert!(allocations(store.ping()).0.is_err());
+
+    // Without overrides, the native defaults match the `SessionStore` ones.
+    let store: Arc<dyn SessionStore> = Arc::new(DynCompat(EmptyStore));
+    assert!(
+        allocations(store.delete_sessions_before(chrono::Utc::now()))
+            .0
+            .is_err()
+    );
+    assert!(allocations(store.ping()).0.is_ok());
+}
@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 use authkestra_engine::auth::{
-    AuthError, Identity, OAuthProvider, OAuthToken, Provider, ProviderConfig,
+    AuthError, Identity, OAuthProvider, OAuthToken, Provider, ProviderConfig, UserMapper,
 };
 use authkestra_engine::flow::OAuth2Flow;
 use std::collections::HashMap;
@@ -40,19 +40,26 @@ impl OAuthProvider for MockOAuthProvider {
         _code_verifier: Option<&str>,
         _nonce: Option<&str>,
     ) -> Result<(Identity, OAuthToken), AuthError> {
-        if code == "valid_code" {
+        if code == "valid_code" || code == "verified_code" || code == "mixed_case_code" {
             Ok((
                 Identity {
                     provider_id: "mock".to_string(),
                     external_id: "user123".to_string(),
                     email: Some("user@example.com".to_string()),
-                    username: Some("user".to_string()),
+                    email_verified: (code == "verified_code").then_some(true),
+                    username: Some(if code == "mixed_case_code" {
+                        "User.Name".to_string()
+                    } else {
+                        "user".to_string()
+                    }),
                     attributes: HashMap::new(),
+                    attributes_multi: HashMap::new(),
                 },
                 OAuthToken {
                     access_token: "token".to_string(),
                     token_type: "Bearer".to_string(),
                     expires_in: None,
+                    issued_at: chrono::Utc::now(),
                     refresh_token: None,
                     scope: None,
                     id_token: None,
@@ -102,3 +109,83 @@ async fn test_oauth2_flow_finalize_invalid_state() {
         .await;
     assert!(matches!(result, Err(AuthError::CsrfMismatch)));
 }
+
+#[tokio::test]
+async fn test_oauth2_flow_rejects_unverified_email() {
+    let flow = OAuth2Flow::new(MockOAuthProvider).with_require_verified_email(true);
+
+    let (_, state) = flow.initiate_login(&["openid"], None);
+    let result = flow
+        .finalize_login("valid_code", &state.state, &state)
+        .await;
+    assert!(matches!(result, Err(AuthError::UnverifiedEmail)));
+
+    let (identity, _, _) = flow
+        .finalize_login("verified_code", &state.state, &state)
+        .await
+        .unwrap();
+    assert_eq!(identity.email_verified, Some(true));
+}
+
+/// Maps an identity to its username and role.
+struct RoleMapper;
+
+#[async_trait]
+impl UserMapper for RoleMapper {
+    type LocalUser = (Option<String>, Option<String>);
+
+    async fn map_user(&self, identity: &Identity) -> Result<Self::LocalUser, AuthError> {
+        Ok((
+            identity.username.clone(),
+            identity.attributes.get("role").cloned(),
+        ))
+    }
+}
+
+#[tokio::test]
+async fn test_oauth2_flow_identity_transform() {
+    let flow = OAuth2Flow::with_mapper(MockOAuthProvider, RoleMapper).with_identity_transform(
+        |mut identity: Identity| {
+            identity.username = identity.username.map(|name| name.to_lowercase());
+            if identity
+                .email
+                .as_deref()
+                .is_some_and(|email| email.ends_with("@example.com"))
+            {
+                identity
+                    .attributes
+                    .insert("role".to_string(), "staff".to_string());
+            }
+            Ok(identity)
+        },
+    );
+
+    let (_, state) = flow.initiate_login(&["openid"], None);
+    let (identity, _, local_user) = flow
+        .finalize_login("mixed_case_code", &state.state, &state)
+        .await
+        .unwrap();
+
+    assert_eq!(identity.username.as_deref(), Some("user.name"));
+    assert_eq!(
+        identity.attributes.get("role").map(String::as_str),
+        Some("staff")
+    );
+    // The mapper sees the transformed identity.
+    assert_eq!(
+        local_user,
+        Some((Some("user.name".to_string()), Some("staff".to_string())))
+    );
+}
+
+#[tokio::test]
+async fn test_oauth2_flow_identity_transform_error_fails_login() {
+    let flow = OAuth2Flow::new(MockOAuthProvider)
+        .with_identity_transform(|_| Err(AuthError::InvalidCredentials));
+
+    let (_, state) = flow.initiate_login(&["openid"], None);
+    let result = flow
+        .finalize_login("valid_code", &state.state, &state)
+        .await;
+    assert!(matches!(result, Err(AuthError::InvalidCredentials)));
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use async_trait::async_trait;
+use authkestra_engine::auth::{
+    AuthError, CredentialsProvider, ErasedOAuthFlow, Identity, ProvisioningUserMapper, UserMapper,
+    UserRepository,
+};
+use authkestra_engine::flow::{CredentialsFlow, OAuth2Flow};
+use common::MockProvider;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug, PartialEq)]
+struct User {
+    id: usize,
+    subject: String,
+    email: Option<String>,
+    logins: u32,
+}
+
+/// In-memory repository recording every call.
+#[derive(Clone, Default)]
+struct MockRepository {
+    users: Arc<Mutex<HashMap<String, User>>>,
+    calls: Arc<Mutex<Vec<&'static str>>>,
+}
+
+#[async_trait]
+impl UserRepository for MockRepository {
+    type User = User;
+
+    async fn find_by_subject(&self, subject: &str) -> Result<Option<User>, AuthError> {
+        self.calls.lock().unwrap().push("find");
+        Ok(self.users.lock().unwrap().get(subject).cloned())
+    }
+
+    async fn create_from_identity(&self, identity: &Identity) -> Result<User, AuthError> {
+        self.calls.lock().unwrap().push("create");
+        let mut users = self.users.lock().unwrap();
+        let user = User {
+            id: users.len() + 1,
+            subject: identity.subject(),
+            email: identity.email.clone(),
+            logins: 1,
+        };
+        users.insert(user.subject.clone(), user.clone());
+        Ok(user)
+    }
+
+    async fn update_from_identity(
+        &self,
+        mut user: User,
+        identity: &Identity,
+    ) -> Result<User, AuthError> {
+        self.calls.lock().unwrap().push("update");
+        user.email = identity.email.clone();
+        user.logins += 1;
+        self.users
+            .lock()
+            .unwrap()
+            .insert(user.subject.clone(), user.clone());
+        Ok(user)
+    }
+}
+
+/// User `user123` of the `mock` provider, with `email`.
+fn identity(email: &str) -> Identity {
+    Identity {
+        email: Some(email.to_string()),
+        ..common::identity()
+    }
+}
+
+/// A provider whose every login is `identity(email)`.
+fn provider(email: &str) -> MockProvider {
+    MockProvider::new().with_identity(identity(email))
+}
+
+struct MockCredentialsProvider;
+
+#[async_trait]
+impl CredentialsProvider for MockCredentialsProvider {
+    type Credentials = String;
+
+    async fn authenticate(&self, email: String) -> Result<Identity, AuthError> {
+        Ok(identity(&email))
+    }
+}
+
+#[tokio::test]
+async fn test_mapper_creates_then_updates() {
+    let repository = MockRepository::default();
+    let mapper = ProvisioningUserMapper::new(repository.clone());
+
+    let created = mapper.map_user(&identity("old@example.com")).await.unwrap();
+    assert_eq!(created.subject, "mock:user123");
+    assert_eq!(created.logins, 1);
+
+    let updated = mapper.map_user(&identity("new@example.com")).await.unwrap();
+    assert_eq!(updated.id, created.id);
+    assert_eq!(updated.email.as_deref(), Some("new@example.com"));
+    assert_eq!(updated.logins, 2);
+
+    assert_eq!(
+        *repository.calls.lock().unwrap(),
+        ["find", "create", "find", "update"]
+    );
+}
+
+#[tokio::test]
+async fn test_oauth_callback_path_provisions_user() {
+    let repository = MockRepository::default();
+    let flow = OAuth2Flow::with_mapper(
+        provider("first@example.com"),
+        ProvisioningUserMapper::new(repository.clone()),
+    );
+
+    let (_, state) = flow.initiate_login(&[], None);
+    let (_, _, user) = flow
+        .finalize_login("code", &state.state, &state)
+        .await
+        .unwrap();
+    assert_eq!(user.unwrap().logins, 1);
+
+    // The adapters go through the type-erased flow, which must provision too.
+    let flow = OAuth2Flow::with_mapper(
+        provider("second@example.com"),
+        ProvisioningUserMapper::new(repository.clone()),
+    );
+    let erased: &dyn ErasedOAuthFlow = &flow;
+    let (_, state) = erased.initiate_login(&[], None);
+    erased
+        .finalize_login("code", &state.state, &state)
+        .await
+        .unwrap();
+
+    let stored = repository.users.lock().unwrap()["mock:user123"].clone();
+    assert_eq!(stored.logins, 2);
+    assert_eq!(stored.email.as_deref(), Some("second@example.com"));
+}
+
+#[tokio::test]
+async fn test_credentials_and_oauth_share_provisioned_user() {
+    let repository = MockRepository::default();
+    let credentials = CredentialsFlow::with_mapper(
+        MockCredentialsProvider,
+        ProvisioningUserMapper::new(repository.clone()),
+    );
+    let oauth = OAuth2Flow::with_mapper(
+        provider("user@example.com"),
+        ProvisioningUserMapper::new(repository.clone()),
+    );
+
+    let (_, user) = credentials
+        .authenticate("user@example.com".to_string())
+        .await
+        .unwrap();
+    let created = user.unwrap();
+
+    let (_, state) = oauth.initiate_login(&[], None);
+    let (_, _, user) = oauth
+        .finalize_login("code", &state.state, &state)
+        .await
+        .unwrap();
+    let updated = user.unwrap();
+
+    assert_eq!(updated.id, created.id);
+    assert_eq!(updated.logins, 2);
+    assert_eq!(repository.users.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use authkestra_engine::{AuthError, OAuth2Flow};
+use common::MockProvider;
+use std::sync::atomic::Ordering;
+
+#[tokio::test]
+async fn test_unsupported_provider_fails_instead_of_dropping_resources() {
+    // The mock provider cannot send resource indicators.
+    let provider = MockProvider::new();
+    let exchanges = provider.exchanges();
+    let flow = OAuth2Flow::new(provider).with_resources(vec!["https://api.example.com"]);
+    let request = flow.build_authorization_request(&[], None);
+    assert_eq!(request.resources, vec!["https://api.example.com"]);
+
+    let state = request.flow_state;
+    let err = flow
+        .finalize_login("code", &state.state.clone(), &state)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AuthError::Provider(_)));
+    assert_eq!(exchanges.load(Ordering::SeqCst), 0);
+}
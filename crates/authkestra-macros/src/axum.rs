@@ -20,11 +20,70 @@
 //!     db_pool: Arc<PgPool>,
 //! }
 //! ```
+//!
+//! Individual generated impls can be suppressed with a struct-level
+//! `#[authkestra(skip(...))]`, e.g. when the state already implements them:
+//!
+//! ```rust,ignore
+//! #[derive(Clone, AxumState)]
+//! #[authkestra(skip(session_config, token))]
+//! struct AppState { /* ... */ }
+//! ```
+//!
+//! Skip targets: `session_config`, `session_store`, `token`, `providers`,
+//! `error_levels`.
 
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, Data, DeriveInput, Fields, Type};
 
+/// Generated `FromRef` impls that can be suppressed with `#[authkestra(skip(...))]`.
+#[derive(Default)]
+struct Skips {
+    session_config: bool,
+    session_store: bool,
+    token: bool,
+    providers: bool,
+    error_levels: bool,
+}
+
+impl Skips {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut skips = Skips::default();
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("authkestra"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if !meta.path.is_ident("skip") {
+                    return Err(meta.error("expected `skip(...)`"));
+                }
+                meta.parse_nested_meta(|target| {
+                    let flag = if target.path.is_ident("session_config") {
+                        &mut skips.session_config
+                    } else if target.path.is_ident("session_store") {
+                        &mut skips.session_store
+                    } else if target.path.is_ident("token") {
+                        &mut skips.token
+                    } else if target.path.is_ident("providers") {
+                        &mut skips.providers
+                    } else if target.path.is_ident("error_levels") {
+                        &mut skips.error_levels
+                    } else {
+                        return Err(target.error(
+                            "unknown skip target, expected one of: session_config, session_store, token, providers, error_levels",
+                        ));
+                    };
+                    *flag = true;
+                    Ok(())
+                })
+            })?;
+        }
+        Ok(skips)
+    }
+}
+
 pub(crate) fn derive_authkestra_state_impl(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -33,6 +92,11 @@ pub(crate) fn derive_authkestra_state_impl(input: TokenStream) -> TokenStream {
     let generics = &input.generics;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
+    let skips = match Skips::parse(&input) {
+        Ok(skips) => skips,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
     let mut engine_field = None;
     let mut store_fields = Vec::new();
 
@@ -174,7 +238,7 @@ pub(crate) fn derive_authkestra_state_impl(input: TokenStream) -> TokenStream {
         });
 
         let s_param_str = quote!(#s_param).to_string();
-        if !s_param_str.contains("Missing") {
+        if !skips.session_store && !s_param_str.contains("Missing") {
             generated_impls.push(quote! {
                 impl #impl_generics axum::extract::FromRef<#struct_name #ty_generics>
                     for ::std::result::Result<::std::sync::Arc<dyn authkestra_engine::auth::SessionStore>, authkestra_axum::AxumError>
@@ -189,7 +253,21 @@ pub(crate) fn derive_authkestra_state_impl(input: TokenStream) -> TokenStream {
             });
         }
 
-        generated_impls.push(quote! {
+        if !skips.providers {
+            generated_impls.push(quote! {
+            impl #impl_generics axum::extract::FromRef<#struct_name #ty_generics>
+                for authkestra_engine::ProviderRegistry
+            #where_clause
+            {
+                fn from_ref(state: &#struct_name #ty_generics) -> Self {
+                    state.#field_name.providers.clone()
+                }
+            }
+            });
+        }
+
+        if !skips.session_config {
+            generated_impls.push(quote! {
             impl #impl_generics axum::extract::FromRef<#struct_name #ty_generics> for authkestra_engine::SessionConfig
             #where_clause
             {
@@ -197,10 +275,23 @@ pub(crate) fn derive_authkestra_state_impl(input: TokenStream) -> TokenStream {
                     state.#field_name.session_config.clone()
                 }
             }
-        });
+            });
+        }
+
+        if !skips.error_levels {
+            generated_impls.push(quote! {
+            impl #impl_generics axum::extract::FromRef<#struct_name #ty_generics> for authkestra_engine::ErrorLevels
+            #where_clause
+            {
+                fn from_ref(state: &#struct_name #ty_generics) -> Self {
+                    state.#field_name.error_levels.clone()
+                }
+            }
+            });
+        }
 
         let t_param_str = quote!(#t_param).to_string();
-        if !t_param_str.contains("Missing") {
+        if !skips.token && !t_param_str.contains("Missing") {
             generated_impls.push(quote! {
                 impl #impl_generics axum::extract::FromRef<#struct_name #ty_generics>
                     for ::std::result::Result<::std::sync::Arc<authkestra_engine::TokenManager>, authkestra_axum::AxumError>
@@ -0,0 +1,131 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Field, Fields, GenericArgument, PathArguments, Type,
+};
+
+/// Whether `ty` is `String`, `Vec<String>` or an `Option` of either.
+fn is_scope_type(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    let Some(segment) = path.path.segments.last() else {
+        return false;
+    };
+    let inner = || match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    if segment.ident == "String" {
+        segment.arguments.is_empty()
+    } else if segment.ident == "Vec" {
+        matches!(inner(), Some(Type::Path(p)) if p.path.is_ident("String"))
+    } else if segment.ident == "Option" {
+        inner().is_some_and(is_scope_type)
+    } else {
+        false
+    }
+}
+
+pub(crate) fn derive_jwt_claims_impl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => TokenStream::from(tokens),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let struct_name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    struct_name,
+                    "JwtClaims can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "JwtClaims can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut scopes: Option<&Field> = None;
+    let mut subject: Option<&Field> = None;
+
+    for field in fields {
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("jwt")) {
+            attr.parse_nested_meta(|meta| {
+                let (slot, name) = if meta.path.is_ident("scopes") {
+                    (&mut scopes, "scopes")
+                } else if meta.path.is_ident("subject") {
+                    (&mut subject, "subject")
+                } else {
+                    return Err(meta.error("unknown jwt attribute, expected `scopes` or `subject`"));
+                };
+                if slot.is_some() {
+                    return Err(meta.error(format!("duplicate `#[jwt({name})]` field")));
+                }
+                *slot = Some(field);
+                Ok(())
+            })?;
+        }
+    }
+
+    if scopes.is_none() && subject.is_none() {
+        return Err(syn::Error::new_spanned(
+            struct_name,
+            "JwtClaims requires a `#[jwt(scopes)]` or `#[jwt(subject)]` field",
+        ));
+    }
+
+    let scopes_impl = match scopes {
+        Some(field) => {
+            if !is_scope_type(&field.ty) {
+                return Err(syn::Error::new_spanned(
+                    &field.ty,
+                    "`#[jwt(scopes)]` field must be `String`, `Vec<String>` or an `Option` of either",
+                ));
+            }
+            let ident = &field.ident;
+            quote! {
+                impl #impl_generics authkestra_engine::auth::HasScopes for #struct_name #ty_generics #where_clause {
+                    fn scopes(&self) -> ::std::vec::Vec<&str> {
+                        authkestra_engine::auth::claims::ScopeField::scope_list(&self.#ident)
+                    }
+                }
+            }
+        }
+        None => quote! {},
+    };
+
+    let subject_impl = match subject {
+        Some(field) => {
+            let ident = &field.ident;
+            quote! {
+                impl #impl_generics authkestra_engine::auth::HasSubject for #struct_name #ty_generics #where_clause {
+                    fn subject(&self) -> &str {
+                        ::std::convert::AsRef::<str>::as_ref(&self.#ident)
+                    }
+                }
+            }
+        }
+        None => quote! {},
+    };
+
+    Ok(quote! {
+        #scopes_impl
+        #subject_impl
+    })
+}
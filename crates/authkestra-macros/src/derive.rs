@@ -25,6 +25,10 @@ pub(crate) fn derive_authkestra_kv_store_impl(input: TokenStream) -> TokenStream
             async fn delete(&self, key: &str) -> ::std::result::Result<(), authkestra_engine::store::StoreError> {
                 <_ as authkestra_engine::store::KvStore<T>>::delete(&self.0, key).await
             }
+
+            async fn delete_created_before(&self, cutoff: authkestra_engine::chrono::DateTime<authkestra_engine::chrono::Utc>) -> ::std::result::Result<u64, authkestra_engine::store::StoreError> {
+                <_ as authkestra_engine::store::KvStore<T>>::delete_created_before(&self.0, cutoff).await
+            }
         }
     };
 
@@ -6,6 +6,7 @@ mod axum;
 #[cfg(feature = "actix")]
 mod actix;
 
+mod claims;
 mod derive;
 
 #[cfg(feature = "axum")]
@@ -24,3 +25,23 @@ pub fn derive_authkestra_actix_state(input: TokenStream) -> TokenStream {
 pub fn derive_authkestra_kv_store(input: TokenStream) -> TokenStream {
     derive::derive_authkestra_kv_store_impl(input)
 }
+
+/// Implements `HasScopes` and `HasSubject` for a custom claims struct.
+///
+/// Mark the field holding the granted scopes with `#[jwt(scopes)]` (`String`,
+/// `Vec<String>` or an `Option` of either; strings are space-delimited) and the
+/// subject field with `#[jwt(subject)]`.
+///
+/// ```rust,ignore
+/// #[derive(Deserialize, JwtClaims)]
+/// struct MyClaims {
+///     #[jwt(subject)]
+///     sub: String,
+///     #[jwt(scopes)]
+///     scope: Option<String>,
+/// }
+/// ```
+#[proc_macro_derive(JwtClaims, attributes(jwt))]
+pub fn derive_jwt_claims(input: TokenStream) -> TokenStream {
+    claims::derive_jwt_claims_impl(input)
+}
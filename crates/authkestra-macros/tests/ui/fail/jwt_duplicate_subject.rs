@@ -0,0 +1,11 @@
+use authkestra_macros::JwtClaims;
+
+#[derive(JwtClaims)]
+struct MyClaims {
+    #[jwt(subject)]
+    sub: String,
+    #[jwt(subject)]
+    user_id: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use authkestra_macros::JwtClaims;
+
+#[derive(JwtClaims)]
+struct MyClaims {
+    #[jwt(scopes)]
+    scope: u64,
+}
+
+fn main() {}
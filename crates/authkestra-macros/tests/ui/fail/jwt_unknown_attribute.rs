@@ -0,0 +1,9 @@
+use authkestra_macros::JwtClaims;
+
+#[derive(JwtClaims)]
+struct MyClaims {
+    #[jwt(audience)]
+    aud: String,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use authkestra_axum::AxumState;
+use authkestra_engine::AkEngine;
+
+#[derive(Clone, AxumState)]
+#[authkestra(skip(session_config, sessions))]
+struct AppState {
+    #[authkestra(engine)]
+    auth: AkEngine,
+}
+
+fn main() {}
@@ -0,0 +1,36 @@
+use authkestra_macros::JwtClaims;
+use authkestra_resource::{Decision, Policy, RequireScopes, ScopeRequirement};
+
+#[derive(JwtClaims)]
+struct MyClaims {
+    #[jwt(subject)]
+    sub: String,
+    #[jwt(scopes)]
+    scp: Vec<String>,
+}
+
+struct ReadOrders;
+
+impl ScopeRequirement for ReadOrders {
+    const SCOPES: &'static [&'static str] = &["orders:read"];
+}
+
+fn main() {
+    let reader = MyClaims {
+        sub: "user-1".to_string(),
+        scp: vec!["orders:read".to_string()],
+    };
+    assert_eq!(
+        RequireScopes::<ReadOrders>::evaluate(&reader),
+        Decision::Allow
+    );
+
+    let other = MyClaims {
+        sub: "user-2".to_string(),
+        scp: vec!["profile".to_string()],
+    };
+    assert_eq!(
+        RequireScopes::<ReadOrders>::evaluate(&other),
+        Decision::Deny("missing scope: orders:read".to_string())
+    );
+}
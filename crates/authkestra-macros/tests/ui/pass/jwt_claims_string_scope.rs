@@ -0,0 +1,26 @@
+use authkestra_engine::auth::{HasScopes, HasSubject};
+use authkestra_macros::JwtClaims;
+
+#[derive(JwtClaims)]
+struct MyClaims {
+    #[jwt(subject)]
+    sub: String,
+    #[jwt(scopes)]
+    scope: Option<String>,
+}
+
+fn main() {
+    let claims = MyClaims {
+        sub: "user-1".to_string(),
+        scope: Some("read write".to_string()),
+    };
+    assert_eq!(claims.subject(), "user-1");
+    assert_eq!(claims.scopes(), vec!["read", "write"]);
+    assert!(claims.has_all_scopes(&["read", "write"]));
+
+    let unscoped = MyClaims {
+        sub: "user-2".to_string(),
+        scope: None,
+    };
+    assert!(!unscoped.has_scope("read"));
+}
@@ -0,0 +1,16 @@
+use authkestra_engine::auth::HasScopes;
+use authkestra_macros::JwtClaims;
+
+#[derive(JwtClaims)]
+struct MyClaims {
+    #[jwt(scopes)]
+    permissions: Vec<String>,
+}
+
+fn main() {
+    let claims = MyClaims {
+        permissions: vec!["orders:read".to_string(), "orders:write".to_string()],
+    };
+    assert!(claims.has_scope("orders:write"));
+    assert!(!claims.has_scope("orders"));
+}
@@ -0,0 +1,23 @@
+use authkestra_axum::AxumState;
+use authkestra_engine::{AkEngine, ErrorLevels, SessionConfig};
+use axum::extract::FromRef;
+
+fn assert_from_ref<S, T: FromRef<S>>() {}
+
+#[derive(Clone, AxumState)]
+#[authkestra(skip(error_levels))]
+struct AppState {
+    #[authkestra(engine)]
+    auth: AkEngine,
+}
+
+impl FromRef<AppState> for ErrorLevels {
+    fn from_ref(state: &AppState) -> Self {
+        state.auth.error_levels.clone()
+    }
+}
+
+fn main() {
+    assert_from_ref::<AppState, SessionConfig>();
+    assert_from_ref::<AppState, ErrorLevels>();
+}
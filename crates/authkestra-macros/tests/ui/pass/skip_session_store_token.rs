@@ -0,0 +1,32 @@
+use authkestra_axum::{AxumError, AxumState};
+use authkestra_engine::auth::SessionStore;
+use authkestra_engine::{AkEngine, SessionConfig, TokenManager};
+use axum::extract::FromRef;
+use std::sync::Arc;
+
+fn assert_from_ref<S, T: FromRef<S>>() {}
+
+#[derive(Clone, AxumState)]
+#[authkestra(skip(session_store, token))]
+struct AppState {
+    #[authkestra(engine)]
+    auth: AkEngine,
+}
+
+impl FromRef<AppState> for Result<Arc<dyn SessionStore>, AxumError> {
+    fn from_ref(state: &AppState) -> Self {
+        Ok(state.auth.session_store.0.clone())
+    }
+}
+
+impl FromRef<AppState> for Result<Arc<TokenManager>, AxumError> {
+    fn from_ref(state: &AppState) -> Self {
+        Ok(state.auth.token_manager.0.clone())
+    }
+}
+
+fn main() {
+    assert_from_ref::<AppState, SessionConfig>();
+    assert_from_ref::<AppState, Result<Arc<dyn SessionStore>, AxumError>>();
+    assert_from_ref::<AppState, Result<Arc<TokenManager>, AxumError>>();
+}
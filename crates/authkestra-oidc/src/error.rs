@@ -9,6 +9,9 @@ pub enum OidcError {
     #[error("Network error: {0}")]
     Network(String),
 
+    #[error("Upstream request timed out")]
+    Timeout,
+
     #[error("Validation error: {0}")]
     ValidationError(String),
 
@@ -24,6 +27,7 @@ impl From<OidcError> for AuthError {
         match err {
             OidcError::Discovery(e) => AuthError::Provider(format!("Discovery failed: {e}")),
             OidcError::Network(_) => AuthError::Network,
+            OidcError::Timeout => AuthError::Timeout,
             OidcError::ValidationError(e) => AuthError::Token(e),
             OidcError::Provider(e) => AuthError::Provider(e),
             OidcError::Internal(e) => AuthError::Provider(format!("Internal OIDC error: {e}")),
@@ -36,7 +40,8 @@ impl From<AuthError> for OidcError {
         match err {
             AuthError::Discovery(e) => OidcError::Discovery(e),
             AuthError::Network => OidcError::Network("Network error".to_string()),
-            AuthError::Token(e) => OidcError::ValidationError(e),
+            AuthError::Timeout => OidcError::Timeout,
+            AuthError::Token(e) | AuthError::InvalidToken(e) => OidcError::ValidationError(e),
             AuthError::Provider(e) => OidcError::Provider(e),
             _ => OidcError::Internal(err.to_string()),
         }
@@ -48,6 +53,7 @@ impl From<authkestra_resource::jwt::ValidationError> for OidcError {
         match err {
             authkestra_resource::jwt::ValidationError::Discovery(e) => match e {
                 AuthError::Discovery(msg) => OidcError::Discovery(msg),
+                AuthError::Timeout => OidcError::Timeout,
                 _ => OidcError::Discovery(e.to_string()),
             },
             authkestra_resource::jwt::ValidationError::Http(e) => OidcError::Network(e.to_string()),
@@ -67,6 +73,13 @@ impl From<authkestra_resource::jwt::ValidationError> for OidcError {
             authkestra_resource::jwt::ValidationError::Validation(e) => {
                 OidcError::ValidationError(e)
             }
+            e @ authkestra_resource::jwt::ValidationError::TokenTooLarge { .. } => {
+                OidcError::ValidationError(e.to_string())
+            }
+            e @ authkestra_resource::jwt::ValidationError::JwksTooLarge { .. } => {
+                OidcError::Provider(e.to_string())
+            }
+            authkestra_resource::jwt::ValidationError::Timeout => OidcError::Timeout,
         }
     }
 }
@@ -2,4 +2,4 @@ pub mod error;
 pub mod provider;
 
 pub use error::OidcError;
-pub use provider::OidcProvider;
+pub use provider::{OidcProvider, Rediscovery};
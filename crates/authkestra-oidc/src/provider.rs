@@ -1,26 +1,51 @@
 use crate::error::OidcError;
+use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use authkestra_engine::{
     auth::{Provider, ProviderConfig},
     discovery::ProviderMetadata,
     error::AuthError,
+    http_client::{self, HttpExecutor},
     state::{Identity, OAuthToken},
+    task::{CancellationToken, TaskHandle},
     OAuthProvider,
 };
-use authkestra_resource::jwt::{validate_jwt_generic, JwksCache};
-use jsonwebtoken::Validation;
+use authkestra_resource::jwt::{validate_jwt_generic, JwksCache, ASYMMETRIC_ALGORITHMS};
+use jsonwebtoken::{decode_header, Algorithm, Validation};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::{collections::HashMap, time::Duration};
+use tokio::sync::watch;
+
+/// When an [`OidcProvider`] re-runs discovery in the background.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rediscovery {
+    /// When the discovery document's `Cache-Control: max-age` elapses, or after
+    /// the fallback interval passed to [`OidcProvider::discover`].
+    #[default]
+    CacheControl,
+    /// At a fixed interval, regardless of `Cache-Control`.
+    Every(Duration),
+    /// Never: keep the metadata discovered at startup.
+    Disabled,
+}
+
+/// Discovered metadata and the JWKS cache for its `jwks_uri`, swapped together.
+struct Discovered {
+    metadata: ProviderMetadata,
+    cache: Arc<JwksCache>,
+}
 
 #[derive(Clone)]
 pub struct OidcProvider {
     client_id: String,
     client_secret: String,
     redirect_uri: String,
-    metadata: Arc<std::sync::RwLock<ProviderMetadata>>,
-    http_client: reqwest::Client,
-    cache: Arc<std::sync::RwLock<Arc<JwksCache>>>,
+    http_client: HttpExecutor,
+    discovered: Arc<ArcSwap<Discovered>>,
+    rediscovery: Arc<watch::Sender<Rediscovery>>,
+    rediscovery_task: Arc<Mutex<Option<TaskHandle>>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,11 +55,31 @@ pub struct Claims {
     pub aud: String,
     pub exp: u64,
     pub email: Option<String>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub email_verified: Option<bool>,
     pub name: Option<String>,
     pub picture: Option<String>,
     pub nonce: Option<String>,
 }
 
+/// Some providers (e.g. AWS Cognito) send `email_verified` as `"true"`/`"false"`.
+fn bool_or_string<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<bool>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match Option::<BoolOrString>::deserialize(deserializer)? {
+        Some(BoolOrString::Bool(b)) => Some(b),
+        Some(BoolOrString::String(s)) => Some(s.eq_ignore_ascii_case("true")),
+        None => None,
+    })
+}
+
 #[derive(Deserialize)]
 struct OidcTokenResponse {
     access_token: String,
@@ -45,21 +90,79 @@ struct OidcTokenResponse {
     id_token: Option<String>,
 }
 
+impl std::fmt::Debug for OidcProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OidcProvider")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &authkestra_engine::state::REDACTED)
+            .field("redirect_uri", &self.redirect_uri)
+            .field("issuer", &self.discovered.load().metadata.issuer)
+            .finish_non_exhaustive()
+    }
+}
+
 impl OidcProvider {
     /// Creates a new provider by performing discovery.
     /// Spawns a background task to periodically refresh the discovery document
     /// and JWKS cache based on the Cache-Control max-age header.
     /// If the header is missing, `fallback_refresh_interval` is used.
-    #[tracing::instrument(skip(client_id, client_secret))]
+    /// Use [`OidcProvider::with_rediscovery`] to refresh at a fixed interval instead.
+    ///
+    /// Refreshed metadata is swapped in atomically; readers never block.
     pub async fn discover(
         client_id: String,
         client_secret: String,
         redirect_uri: String,
         issuer_url: &str,
         fallback_refresh_interval: Duration,
+    ) -> Result<Self, OidcError> {
+        Self::discover_with_timeout(
+            client_id,
+            client_secret,
+            redirect_uri,
+            issuer_url,
+            fallback_refresh_interval,
+            http_client::DEFAULT_TIMEOUT,
+        )
+        .await
+    }
+
+    /// Like [`OidcProvider::discover`], but every request made by the provider
+    /// (discovery, JWKS and token exchange) fails with a timeout error after `timeout`.
+    pub async fn discover_with_timeout(
+        client_id: String,
+        client_secret: String,
+        redirect_uri: String,
+        issuer_url: &str,
+        fallback_refresh_interval: Duration,
+        timeout: Duration,
+    ) -> Result<Self, OidcError> {
+        Self::discover_with_client(
+            client_id,
+            client_secret,
+            redirect_uri,
+            issuer_url,
+            fallback_refresh_interval,
+            HttpExecutor::with_timeout(timeout),
+        )
+        .await
+    }
+
+    /// Like [`OidcProvider::discover`], but every request made by the provider
+    /// (discovery, JWKS and token exchange) is sent with `client`, a
+    /// `reqwest::Client` or, with the `reqwest-middleware` feature, a
+    /// `ClientWithMiddleware`. Timeouts are the client's own.
+    #[tracing::instrument(skip(client_id, client_secret, client))]
+    pub async fn discover_with_client(
+        client_id: String,
+        client_secret: String,
+        redirect_uri: String,
+        issuer_url: &str,
+        fallback_refresh_interval: Duration,
+        client: impl Into<HttpExecutor>,
     ) -> Result<Self, OidcError> {
         tracing::debug!("starting OIDC discovery process");
-        let client = reqwest::Client::new();
+        let client = client.into();
         let (metadata, cache_max_age) = ProviderMetadata::discover(issuer_url, client.clone())
             .await
             .map_err(|e| {
@@ -80,87 +183,197 @@ impl OidcProvider {
             }
         };
 
-        let cache = Arc::new(JwksCache::new(metadata.jwks_uri.clone(), refresh_interval));
+        let cache = Arc::new(
+            JwksCache::new(metadata.jwks_uri.clone(), refresh_interval)
+                .with_http_client(client.clone()),
+        );
+        let (rediscovery, rediscovery_rx) = watch::channel(Rediscovery::default());
+        let discovered = Arc::new(ArcSwap::from_pointee(Discovered { metadata, cache }));
+
+        let task = TaskHandle::spawn({
+            let issuer_url = issuer_url.to_string();
+            let client = client.clone();
+            let discovered = Arc::downgrade(&discovered);
+            move |token| {
+                rediscover(
+                    issuer_url,
+                    client,
+                    discovered,
+                    rediscovery_rx,
+                    token,
+                    refresh_interval,
+                    fallback_refresh_interval,
+                )
+            }
+        });
 
-        let provider = Self {
+        Ok(Self {
             client_id,
             client_secret,
             redirect_uri,
-            metadata: Arc::new(std::sync::RwLock::new(metadata)),
-            http_client: client.clone(),
-            cache: Arc::new(std::sync::RwLock::new(cache)),
-        };
+            http_client: client,
+            discovered,
+            rediscovery: Arc::new(rediscovery),
+            rediscovery_task: Arc::new(Mutex::new(Some(task))),
+        })
+    }
 
-        // Spawn background refresh task
-        let issuer_url_owned = issuer_url.to_string();
-        let metadata_ref = Arc::downgrade(&provider.metadata);
-        let cache_ref = Arc::downgrade(&provider.cache);
-
-        tokio::spawn(async move {
-            let mut current_interval = refresh_interval;
-            loop {
-                tokio::time::sleep(current_interval).await;
-
-                // If the provider has been dropped, exit the background task
-                let (metadata_arc, cache_arc) = match (metadata_ref.upgrade(), cache_ref.upgrade())
-                {
-                    (Some(m), Some(c)) => (m, c),
-                    _ => break,
-                };
+    /// Stops background rediscovery and waits for the task to exit.
+    ///
+    /// Applies to every clone of this provider, which keeps the metadata
+    /// discovered so far. The task is also stopped when the last clone is
+    /// dropped.
+    pub async fn shutdown(&self) {
+        let task = self
+            .rediscovery_task
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(task) = task {
+            task.shutdown().await;
+        }
+    }
 
-                tracing::debug!(
-                    "Refreshing OIDC discovery document for {}",
-                    issuer_url_owned
-                );
+    /// Set when discovery is re-run in the background.
+    ///
+    /// Applies to every clone of this provider and takes effect immediately.
+    pub fn with_rediscovery(self, rediscovery: Rediscovery) -> Self {
+        self.rediscovery.send_replace(rediscovery);
+        self
+    }
 
-                match ProviderMetadata::discover(&issuer_url_owned, client.clone()).await {
-                    Ok((new_metadata, new_cache_max_age)) => {
-                        current_interval = match new_cache_max_age {
-                            Some(duration) => duration,
-                            None => {
-                                tracing::warn!(
-                                    "No valid Cache-Control max-age found in discovery document from {}. Using fallback interval of {} seconds.",
-                                    issuer_url_owned,
-                                    fallback_refresh_interval.as_secs()
-                                );
-                                fallback_refresh_interval
-                            }
-                        };
-
-                        let mut meta_write = metadata_arc.write().unwrap();
-                        let jwks_uri_changed = meta_write.jwks_uri != new_metadata.jwks_uri;
-                        *meta_write = new_metadata.clone();
-                        drop(meta_write); // Release lock early
-
-                        if jwks_uri_changed {
-                            tracing::info!(
-                                "OIDC jwks_uri changed for {}, recreating JwksCache",
-                                issuer_url_owned
-                            );
-                            let new_cache =
-                                Arc::new(JwksCache::new(new_metadata.jwks_uri, current_interval));
-                            let mut cache_write = cache_arc.write().unwrap();
-                            *cache_write = new_cache;
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!(
-                            error = %e,
-                            "Failed to refresh OIDC discovery document for {}",
-                            issuer_url_owned
-                        );
-                        // Retry after a short delay on failure to avoid tight loop
-                        current_interval = Duration::from_secs(60);
-                    }
-                }
+    pub async fn get_metadata(&self) -> ProviderMetadata {
+        self.discovered.load().metadata.clone()
+    }
+
+    /// The JWKS cache for the current `jwks_uri`.
+    pub fn jwks_cache(&self) -> Arc<JwksCache> {
+        self.discovered.load().cache.clone()
+    }
+
+    /// Builds the validation rules for an ID token: the signing algorithm must be
+    /// asymmetric and advertised by the provider, and `iss`, `aud` (= client_id)
+    /// and `exp` are required and checked.
+    fn id_token_validation(
+        &self,
+        id_token: &str,
+        metadata: &ProviderMetadata,
+    ) -> Result<Validation, AuthError> {
+        let header = decode_header(id_token)
+            .map_err(|e| AuthError::Token(format!("Invalid ID token header: {e}")))?;
+
+        if !ASYMMETRIC_ALGORITHMS.contains(&header.alg) {
+            return Err(AuthError::Token(format!(
+                "Unsupported ID token algorithm: {:?}",
+                header.alg
+            )));
+        }
+
+        if let Some(supported) = &metadata.id_token_signing_alg_values_supported {
+            let advertised = supported
+                .iter()
+                .filter_map(|alg| Algorithm::from_str(alg).ok())
+                .any(|alg| alg == header.alg);
+            if !advertised {
+                return Err(AuthError::Token(format!(
+                    "ID token algorithm {:?} not advertised by provider",
+                    header.alg
+                )));
             }
-        });
+        }
 
-        Ok(provider)
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        Ok(validation)
     }
+}
 
-    pub async fn get_metadata(&self) -> ProviderMetadata {
-        self.metadata.read().unwrap().clone()
+/// Re-runs discovery according to the [`Rediscovery`] setting and swaps in the
+/// new metadata, recreating the JWKS cache if the `jwks_uri` changed. Exits
+/// once the provider is dropped or shut down.
+async fn rediscover(
+    issuer_url: String,
+    client: HttpExecutor,
+    discovered: std::sync::Weak<ArcSwap<Discovered>>,
+    mut rediscovery: watch::Receiver<Rediscovery>,
+    token: CancellationToken,
+    mut cache_control_interval: Duration,
+    fallback_refresh_interval: Duration,
+) {
+    loop {
+        let interval = match *rediscovery.borrow_and_update() {
+            Rediscovery::CacheControl => Some(cache_control_interval),
+            Rediscovery::Every(interval) => Some(interval),
+            Rediscovery::Disabled => None,
+        };
+        let wait = async {
+            match interval {
+                Some(interval) => tokio::time::sleep(interval).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = token.cancelled() => break,
+            _ = wait => {}
+            changed = rediscovery.changed() => {
+                // The sender is dropped with the last provider clone.
+                if changed.is_err() {
+                    break;
+                }
+                continue;
+            }
+        }
+
+        let Some(discovered) = discovered.upgrade() else {
+            break;
+        };
+
+        tracing::debug!("Refreshing OIDC discovery document for {}", issuer_url);
+        let result = tokio::select! {
+            _ = token.cancelled() => break,
+            result = ProviderMetadata::discover(&issuer_url, client.clone()) => result,
+        };
+        match result {
+            Ok((metadata, max_age)) => {
+                cache_control_interval = match max_age {
+                    Some(duration) => duration,
+                    None => {
+                        tracing::warn!(
+                            "No valid Cache-Control max-age found in discovery document from {}. Using fallback interval of {} seconds.",
+                            issuer_url,
+                            fallback_refresh_interval.as_secs()
+                        );
+                        fallback_refresh_interval
+                    }
+                };
+
+                let current = discovered.load();
+                let cache = if current.metadata.jwks_uri == metadata.jwks_uri {
+                    current.cache.clone()
+                } else {
+                    tracing::info!(
+                        "OIDC jwks_uri changed for {}, recreating JwksCache",
+                        issuer_url
+                    );
+                    Arc::new(
+                        JwksCache::new(metadata.jwks_uri.clone(), cache_control_interval)
+                            .with_http_client(client.clone()),
+                    )
+                };
+                discovered.store(Arc::new(Discovered { metadata, cache }));
+            }
+            Err(e) => {
+                tracing::error!(
+                    error = %e,
+                    "Failed to refresh OIDC discovery document for {}",
+                    issuer_url
+                );
+                // Retry after a short delay on failure to avoid tight loop
+                cache_control_interval = Duration::from_secs(60);
+            }
+        }
     }
 }
 
@@ -181,6 +394,18 @@ impl OAuthProvider for OidcProvider {
         "oidc"
     }
 
+    fn redirect_uri(&self) -> Option<&str> {
+        Some(&self.redirect_uri)
+    }
+
+    fn jwks_uri(&self) -> Option<String> {
+        Some(self.discovered.load().metadata.jwks_uri.clone())
+    }
+
+    fn default_scopes(&self) -> Vec<&str> {
+        vec!["openid", "email", "profile"]
+    }
+
     fn get_authorization_url(
         &self,
         state: &str,
@@ -188,7 +413,7 @@ impl OAuthProvider for OidcProvider {
         code_challenge: Option<&str>,
         nonce: Option<&str>,
     ) -> String {
-        let metadata = self.metadata.read().unwrap().clone();
+        let metadata = &self.discovered.load().metadata;
 
         let mut full_scopes = scopes.to_vec();
         if !full_scopes.contains(&"openid") {
@@ -219,29 +444,45 @@ impl OAuthProvider for OidcProvider {
         url
     }
 
-    #[tracing::instrument(skip(self, code, code_verifier, nonce))]
     async fn exchange_code_for_identity(
         &self,
         code: &str,
         code_verifier: Option<&str>,
         nonce: Option<&str>,
+    ) -> Result<(Identity, OAuthToken), AuthError> {
+        self.exchange_code_for_identity_with_resources(code, code_verifier, nonce, &[])
+            .await
+    }
+
+    #[tracing::instrument(skip(self, code, code_verifier, nonce))]
+    async fn exchange_code_for_identity_with_resources(
+        &self,
+        code: &str,
+        code_verifier: Option<&str>,
+        nonce: Option<&str>,
+        resources: &[String],
     ) -> Result<(Identity, OAuthToken), AuthError> {
         tracing::debug!("exchanging OIDC code for tokens");
-        // 1. Exchange code for tokens
-        let mut params = HashMap::new();
-        params.insert("grant_type", "authorization_code".to_string());
-        params.insert("code", code.to_string());
-        params.insert("redirect_uri", self.redirect_uri.clone());
-        params.insert("client_id", self.client_id.clone());
-        params.insert("client_secret", self.client_secret.clone());
+        // 1. Exchange code for tokens; a Vec, since `resource` may repeat
+        let mut params = vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code.to_string()),
+            ("redirect_uri", self.redirect_uri.clone()),
+            ("client_id", self.client_id.clone()),
+            ("client_secret", self.client_secret.clone()),
+        ];
 
         if let Some(verifier) = code_verifier {
-            params.insert("code_verifier", verifier.to_string());
+            params.push(("code_verifier", verifier.to_string()));
+        }
+        for resource in resources {
+            params.push(("resource", resource.clone()));
         }
 
-        let metadata = self.metadata.read().unwrap().clone();
+        let discovered = self.discovered.load_full();
+        let metadata = &discovered.metadata;
 
-        let token_response = self
+        let response = self
             .http_client
             .post(&metadata.token_endpoint)
             .form(&params)
@@ -249,14 +490,21 @@ impl OAuthProvider for OidcProvider {
             .await
             .map_err(|e| {
                 tracing::error!(error = %e, "network error while exchanging OIDC code");
-                AuthError::Network
-            })?
-            .json::<OidcTokenResponse>()
+                http_client::map_error(&e)
+            })?;
+        let body = http_client::read_limited(response, http_client::DEFAULT_MAX_RESPONSE_SIZE)
             .await
             .map_err(|e| {
-                tracing::error!(error = %e, "failed to parse OIDC token response");
-                AuthError::Provider(format!("Failed to parse token response: {e}"))
+                tracing::error!(error = %e, "failed to read OIDC token response");
+                match e {
+                    http_client::BodyError::TooLarge { .. } => AuthError::Provider(e.to_string()),
+                    http_client::BodyError::Reqwest(_) => http_client::map_error(&e),
+                }
             })?;
+        let token_response: OidcTokenResponse = serde_json::from_slice(&body).map_err(|e| {
+            tracing::error!(error = %e, "failed to parse OIDC token response");
+            AuthError::Provider(format!("Failed to parse token response: {e}"))
+        })?;
 
         let id_token = token_response.id_token.ok_or_else(|| {
             tracing::error!("missing id_token in OIDC response");
@@ -264,9 +512,13 @@ impl OAuthProvider for OidcProvider {
         })?;
 
         tracing::debug!("validating OIDC ID Token");
-        // 2. Validate ID Token using the validator
-        let cache = self.cache.read().unwrap().clone(); // Clone the Arc, releasing the lock immediately
-        let claims = validate_jwt_generic::<Claims>(&id_token, &cache, &Validation::default())
+        let cache = &discovered.cache;
+        // 2. Validate ID Token signature, issuer, audience and expiry against the JWKS
+        let validation = self.id_token_validation(&id_token, metadata).map_err(|e| {
+            tracing::error!(error = %e, "rejected OIDC ID Token");
+            e
+        })?;
+        let claims = validate_jwt_generic::<Claims>(&id_token, cache, &validation)
             .await
             .map_err(|e| {
                 tracing::error!(error = %e, "failed to validate OIDC ID Token");
@@ -291,14 +543,17 @@ impl OAuthProvider for OidcProvider {
             provider_id: "oidc".to_string(), // Could be parameterized or inferred from issuer
             external_id: claims.sub,
             email: claims.email,
+            email_verified: claims.email_verified,
             username: claims.name,
             attributes,
+            attributes_multi: std::collections::HashMap::new(),
         };
 
         let token = OAuthToken {
             access_token: token_response.access_token,
             token_type: token_response.token_type,
             expires_in: token_response.expires_in,
+            issued_at: authkestra_engine::chrono::Utc::now(),
             refresh_token: token_response.refresh_token,
             scope: token_response.scope,
             id_token: Some(id_token),
@@ -0,0 +1,42 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use authkestra_engine::{state::Identity, TokenManager};
+use authkestra_oidc::OidcProvider;
+use std::collections::HashMap;
+use std::time::Duration;
+use wiremock::MockServer;
+
+/// A 2048-bit RSA key for signing test ID tokens.
+pub const RSA_PEM: &[u8] = include_bytes!("../fixtures/rsa_2048.pem");
+
+/// User `user123` of the `oidc` provider.
+pub fn identity() -> Identity {
+    Identity {
+        provider_id: "oidc".to_string(),
+        external_id: "user123".to_string(),
+        email: Some("user@example.com".to_string()),
+        email_verified: None,
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+/// Signs ID tokens for `server` with [`RSA_PEM`] under kid `kid-1`.
+pub fn signer(server: &MockServer) -> TokenManager {
+    TokenManager::new_asymmetric(RSA_PEM, Some(server.uri()), Some("kid-1".to_string())).unwrap()
+}
+
+/// Discovers `server` as client `client-1`.
+pub async fn provider(server: &MockServer) -> OidcProvider {
+    OidcProvider::discover(
+        "client-1".to_string(),
+        "secret".to_string(),
+        "http://localhost/callback".to_string(),
+        &server.uri(),
+        Duration::from_secs(3600),
+    )
+    .await
+    .unwrap()
+}
@@ -0,0 +1,207 @@
+mod common;
+
+use authkestra_engine::{error::AuthError, OAuthProvider, TokenManager};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use common::{identity, provider, signer, RSA_PEM};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Boots a mock issuer serving discovery and JWKS, and returns a signer for its ID tokens.
+async fn mock_issuer() -> (MockServer, TokenManager) {
+    let server = MockServer::start().await;
+    let manager = signer(&server);
+
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "issuer": server.uri(),
+            "authorization_endpoint": format!("{}/authorize", server.uri()),
+            "token_endpoint": format!("{}/token", server.uri()),
+            "jwks_uri": format!("{}/jwks", server.uri()),
+            "id_token_signing_alg_values_supported": ["RS256"],
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "keys": [manager.public_jwk().unwrap()],
+        })))
+        .mount(&server)
+        .await;
+
+    (server, manager)
+}
+
+async fn mount_token_response(server: &MockServer, id_token: &str) {
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": "access",
+            "token_type": "Bearer",
+            "id_token": id_token,
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_valid_id_token_is_accepted() {
+    let (server, manager) = mock_issuer().await;
+    let id_token = manager
+        .issue_id_token(identity(), "client-1", Some("nonce-1".to_string()), 300)
+        .unwrap();
+    mount_token_response(&server, &id_token).await;
+
+    let (identity, token) = provider(&server)
+        .await
+        .exchange_code_for_identity("code", None, Some("nonce-1"))
+        .await
+        .unwrap();
+
+    assert_eq!(identity.external_id, "user123");
+    assert_eq!(token.id_token.as_deref(), Some(id_token.as_str()));
+}
+
+#[tokio::test]
+async fn test_tampered_id_token_is_rejected() {
+    let (server, manager) = mock_issuer().await;
+    let id_token = manager
+        .issue_id_token(identity(), "client-1", Some("nonce-1".to_string()), 300)
+        .unwrap();
+
+    // Swap the subject in the payload while keeping the original signature.
+    let parts: Vec<&str> = id_token.split('.').collect();
+    let mut payload: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[1]).unwrap()).unwrap();
+    payload["sub"] = serde_json::json!("admin");
+    let tampered = format!(
+        "{}.{}.{}",
+        parts[0],
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(&payload).unwrap()),
+        parts[2]
+    );
+    mount_token_response(&server, &tampered).await;
+
+    let result = provider(&server)
+        .await
+        .exchange_code_for_identity("code", None, Some("nonce-1"))
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_id_token_for_other_audience_is_rejected() {
+    let (server, manager) = mock_issuer().await;
+    let id_token = manager
+        .issue_id_token(identity(), "other-client", Some("nonce-1".to_string()), 300)
+        .unwrap();
+    mount_token_response(&server, &id_token).await;
+
+    let result = provider(&server)
+        .await
+        .exchange_code_for_identity("code", None, Some("nonce-1"))
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_id_token_from_other_issuer_is_rejected() {
+    let (server, _) = mock_issuer().await;
+    let foreign = TokenManager::new_asymmetric(
+        RSA_PEM,
+        Some("https://evil.example.com".to_string()),
+        Some("kid-1".to_string()),
+    )
+    .unwrap();
+    let id_token = foreign
+        .issue_id_token(identity(), "client-1", Some("nonce-1".to_string()), 300)
+        .unwrap();
+    mount_token_response(&server, &id_token).await;
+
+    let result = provider(&server)
+        .await
+        .exchange_code_for_identity("code", None, Some("nonce-1"))
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_id_token_with_wrong_nonce_is_rejected() {
+    let (server, manager) = mock_issuer().await;
+    let id_token = manager
+        .issue_id_token(identity(), "client-1", Some("nonce-1".to_string()), 300)
+        .unwrap();
+    mount_token_response(&server, &id_token).await;
+
+    let result = provider(&server)
+        .await
+        .exchange_code_for_identity("code", None, Some("nonce-2"))
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_symmetric_id_token_is_rejected() {
+    let (server, _) = mock_issuer().await;
+    let hs256 = TokenManager::new(b"client-secret", Some(server.uri()));
+    let id_token = hs256
+        .issue_id_token(identity(), "client-1", Some("nonce-1".to_string()), 300)
+        .unwrap();
+    mount_token_response(&server, &id_token).await;
+
+    let result = provider(&server)
+        .await
+        .exchange_code_for_identity("code", None, Some("nonce-1"))
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_oversized_token_response_is_rejected() {
+    let (server, _) = mock_issuer().await;
+    // Over the 256 KiB response cap, so the ID token is never parsed.
+    mount_token_response(&server, &"a".repeat(300 * 1024)).await;
+
+    let result = provider(&server)
+        .await
+        .exchange_code_for_identity("code", None, None)
+        .await;
+    assert!(
+        matches!(result, Err(AuthError::Provider(message)) if message.contains("maximum size"))
+    );
+}
+
+#[tokio::test]
+async fn test_oversized_id_token_is_rejected() {
+    let (server, _) = mock_issuer().await;
+    // Under the response cap but over the 16 KiB token limit.
+    mount_token_response(&server, &"a".repeat(32 * 1024)).await;
+
+    let result = provider(&server)
+        .await
+        .exchange_code_for_identity("code", None, None)
+        .await;
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_email_verified_claim_accepts_bool_or_string() {
+    let claims = |email_verified: serde_json::Value| {
+        serde_json::from_value::<authkestra_oidc::provider::Claims>(serde_json::json!({
+            "sub": "user123",
+            "iss": "https://issuer.example",
+            "aud": "client-1",
+            "exp": 0,
+            "email": "user@example.com",
+            "email_verified": email_verified,
+        }))
+        .unwrap()
+        .email_verified
+    };
+
+    assert_eq!(claims(serde_json::json!(true)), Some(true));
+    assert_eq!(claims(serde_json::json!("false")), Some(false));
+    assert_eq!(claims(serde_json::Value::Null), None);
+}
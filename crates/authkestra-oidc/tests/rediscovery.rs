@@ -0,0 +1,142 @@
+mod common;
+
+use authkestra_engine::OAuthProvider;
+use authkestra_oidc::Rediscovery;
+use common::{identity, provider, signer};
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn discovery(server: &MockServer, prefix: &str) -> serde_json::Value {
+    serde_json::json!({
+        "issuer": server.uri(),
+        "authorization_endpoint": format!("{}{prefix}/authorize", server.uri()),
+        "token_endpoint": format!("{}{prefix}/token", server.uri()),
+        "jwks_uri": format!("{}{prefix}/jwks", server.uri()),
+        "id_token_signing_alg_values_supported": ["RS256"],
+    })
+}
+
+/// Serves the original endpoints for the first discovery only, then the rotated `/v2` ones.
+/// The signing key is only published at the rotated `jwks_uri`.
+async fn rotating_issuer() -> MockServer {
+    let server = MockServer::start().await;
+    let manager = signer(&server);
+
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(discovery(&server, "")))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(discovery(&server, "/v2")))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "keys": [] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/jwks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "keys": [manager.public_jwk().unwrap()],
+        })))
+        .mount(&server)
+        .await;
+
+    let id_token = manager
+        .issue_id_token(identity(), "client-1", None, 300)
+        .unwrap();
+    Mock::given(method("POST"))
+        .and(path("/v2/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": "access",
+            "token_type": "Bearer",
+            "id_token": id_token,
+        })))
+        .mount(&server)
+        .await;
+
+    server
+}
+
+#[tokio::test]
+async fn test_rotated_endpoints_are_picked_up_after_interval() {
+    let server = rotating_issuer().await;
+    let provider = provider(&server).await;
+    assert_eq!(
+        provider.get_metadata().await.token_endpoint,
+        format!("{}/token", server.uri())
+    );
+    // The old token endpoint is gone.
+    assert!(provider
+        .exchange_code_for_identity("code", None, None)
+        .await
+        .is_err());
+
+    let provider = provider.with_rediscovery(Rediscovery::Every(Duration::from_millis(50)));
+    let mut attempts = 0;
+    while provider.get_metadata().await.token_endpoint != format!("{}/v2/token", server.uri()) {
+        attempts += 1;
+        assert!(attempts < 100, "rotated metadata was never picked up");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    assert!(provider
+        .get_authorization_url("state", &[], None, None)
+        .starts_with(&format!("{}/v2/authorize?", server.uri())));
+    // The ID token is only verifiable with the key at the rotated jwks_uri.
+    let (identity, _) = provider
+        .exchange_code_for_identity("code", None, None)
+        .await
+        .unwrap();
+    assert_eq!(identity.external_id, "user123");
+}
+
+#[tokio::test]
+async fn test_disabled_rediscovery_keeps_startup_metadata() {
+    let server = rotating_issuer().await;
+    let provider = provider(&server)
+        .await
+        .with_rediscovery(Rediscovery::Disabled);
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(
+        provider.get_metadata().await.token_endpoint,
+        format!("{}/token", server.uri())
+    );
+}
+
+#[tokio::test]
+async fn test_shutdown_stops_rediscovery() {
+    let server = rotating_issuer().await;
+    let provider = provider(&server)
+        .await
+        .with_rediscovery(Rediscovery::Every(Duration::from_millis(20)));
+    let discoveries = || async {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|r| r.url.path() == "/.well-known/openid-configuration")
+            .count()
+    };
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(discoveries().await >= 2);
+
+    // Shutting down one clone stops the task shared by all of them.
+    provider.clone().shutdown().await;
+    let after_shutdown = discoveries().await;
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(discoveries().await, after_shutdown);
+    // The metadata discovered so far stays in use.
+    assert_eq!(
+        provider.get_metadata().await.token_endpoint,
+        format!("{}/v2/token", server.uri())
+    );
+}
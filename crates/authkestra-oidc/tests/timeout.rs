@@ -0,0 +1,63 @@
+use authkestra_engine::{error::AuthError, OAuthProvider};
+use authkestra_oidc::{OidcError, OidcProvider};
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TIMEOUT: Duration = Duration::from_millis(200);
+
+fn discovery_document(server: &MockServer) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "issuer": server.uri(),
+        "authorization_endpoint": format!("{}/authorize", server.uri()),
+        "token_endpoint": format!("{}/token", server.uri()),
+        "jwks_uri": format!("{}/jwks", server.uri()),
+    }))
+}
+
+async fn discover(server: &MockServer) -> Result<OidcProvider, OidcError> {
+    OidcProvider::discover_with_timeout(
+        "client-1".to_string(),
+        "secret".to_string(),
+        "http://localhost/callback".to_string(),
+        &server.uri(),
+        Duration::from_secs(3600),
+        TIMEOUT,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_slow_discovery_times_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(discovery_document(&server).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let result = discover(&server).await;
+    assert!(matches!(result, Err(OidcError::Timeout)));
+}
+
+#[tokio::test]
+async fn test_slow_token_endpoint_times_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(discovery_document(&server))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let result = discover(&server)
+        .await
+        .unwrap()
+        .exchange_code_for_identity("code", None, None)
+        .await;
+    assert!(matches!(result, Err(AuthError::Timeout)));
+}
@@ -206,8 +206,10 @@ mod tests {
             provider_id: "local".to_string(),
             external_id: "user-123".to_string(),
             email: None,
+            email_verified: None,
             username: None,
             attributes: std::collections::HashMap::new(),
+            attributes_multi: std::collections::HashMap::new(),
         }
     }
 
@@ -258,7 +258,9 @@ mod tests {
             external_id: "user123".to_string(),
             username: Some("user123".to_string()),
             email: None,
+            email_verified: None,
             attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
         });
         devices.update_device_code(session).await.unwrap();
 
@@ -75,7 +75,9 @@ mod tests {
             external_id: "user1".to_string(),
             username: Some("user1".to_string()),
             email: None,
+            email_verified: None,
             attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
         };
 
         let req = DeviceVerifyRequest {
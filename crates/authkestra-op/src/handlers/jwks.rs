@@ -18,6 +18,12 @@ impl JwksResponse {
             keys: jwk.into_iter().collect(),
         }
     }
+
+    /// Creates a JWKS response publishing all of the given keys, e.g.
+    /// `TokenManager::jwks()` during a key rotation.
+    pub fn from_keys(keys: Vec<Jwk>) -> Self {
+        Self { keys }
+    }
 }
 
 #[cfg(test)]
@@ -39,9 +45,33 @@ mod tests {
             kid: Some("123".to_string()),
             n: Some("abc".to_string()),
             e: Some("AQAB".to_string()),
+            crv: None,
+            x: None,
+            y: None,
         };
         let response = JwksResponse::new(Some(jwk.clone()));
         assert_eq!(response.keys.len(), 1);
         assert_eq!(response.keys[0].kid.as_deref(), Some("123"));
     }
+
+    #[test]
+    fn test_jwks_response_from_keys() {
+        let jwk = |kid: &str| Jwk {
+            kty: "RSA".to_string(),
+            alg: Some("RS256".to_string()),
+            kid: Some(kid.to_string()),
+            n: Some("abc".to_string()),
+            e: Some("AQAB".to_string()),
+            crv: None,
+            x: None,
+            y: None,
+        };
+        let response = JwksResponse::from_keys(vec![jwk("current"), jwk("retired")]);
+        let kids: Vec<_> = response
+            .keys
+            .iter()
+            .filter_map(|k| k.kid.as_deref())
+            .collect();
+        assert_eq!(kids, vec!["current", "retired"]);
+    }
 }
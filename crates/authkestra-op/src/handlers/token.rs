@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use sha2::Digest;
 
 /// Request payload for the token endpoint.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Deserialize, Clone)]
 pub struct TokenRequest {
     /// OAuth2 grant type.
     pub grant_type: String,
@@ -45,6 +45,29 @@ pub struct TokenRequest {
     pub audience: Option<String>,
 }
 
+impl std::fmt::Debug for TokenRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redact = |v: &Option<String>| v.as_ref().map(|_| authkestra_engine::state::REDACTED);
+        f.debug_struct("TokenRequest")
+            .field("grant_type", &self.grant_type)
+            .field("code", &redact(&self.code))
+            .field("device_code", &redact(&self.device_code))
+            .field("redirect_uri", &self.redirect_uri)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &redact(&self.client_secret))
+            .field("code_verifier", &redact(&self.code_verifier))
+            .field("scope", &self.scope)
+            .field("refresh_token", &redact(&self.refresh_token))
+            .field("subject_token", &redact(&self.subject_token))
+            .field("subject_token_type", &self.subject_token_type)
+            .field("actor_token", &redact(&self.actor_token))
+            .field("actor_token_type", &self.actor_token_type)
+            .field("requested_token_type", &self.requested_token_type)
+            .field("audience", &self.audience)
+            .finish()
+    }
+}
+
 /// Success response for the token endpoint.
 #[derive(Debug, Serialize)]
 pub struct TokenResponse {
@@ -928,7 +951,9 @@ mod tests {
             external_id: "user123".to_string(),
             username: Some("user123".to_string()),
             email: None,
+            email_verified: None,
             attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
         }
     }
 
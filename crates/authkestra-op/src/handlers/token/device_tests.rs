@@ -35,7 +35,9 @@ fn test_identity() -> Identity {
         external_id: "user1".to_string(),
         username: Some("user1".to_string()),
         email: None,
+        email_verified: None,
         attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
     }
 }
 
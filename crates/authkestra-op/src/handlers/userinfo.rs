@@ -128,8 +128,10 @@ mod tests {
             provider_id: "local".to_string(),
             external_id: "user-123".to_string(),
             email: Some("user@example.com".to_string()),
+            email_verified: None,
             username: Some("Test User".to_string()),
             attributes: std::collections::HashMap::new(),
+            attributes_multi: std::collections::HashMap::new(),
         }
     }
 
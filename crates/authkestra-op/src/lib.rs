@@ -8,9 +8,14 @@
 //! any web framework. `authkestra-axum` and `authkestra-actix` (behind an
 //! `op` feature flag) wrap these types into framework-native routes.
 //!
-//! ## Status
-//! This crate is a skeleton (RFC-003, PR `OP.0`). No handler logic has
-//! landed yet — see `docs/rfc-003-oidc-provider.md` for the full plan.
+//! ## Authorization code grant
+//! [`handlers::authorize::handle_authorize`] validates the client and its
+//! exact-match `redirect_uri`, enforces PKCE (`S256`) for clients with
+//! `require_pkce`, and stores a single-use [`AuthorizationCode`] in the
+//! pluggable [`AuthorizationCodeStore`]. [`handlers::token::handle_token`]
+//! consumes the code, checks the PKCE verifier and `redirect_uri`, and issues
+//! tokens with the engine's `TokenManager`. Clients are looked up through
+//! [`ClientStore`]. See `docs/rfc-003-oidc-provider.md` for the full plan.
 
 #![warn(missing_docs)]
 
@@ -11,14 +11,17 @@ crate::define_oauth_provider! {
         username: String,
         discriminator: String,
         email: Option<String>,
+        verified: Option<bool>,
     },
     |user| {
         authkestra_engine::state::Identity {
             provider_id: "discord".to_string(),
             external_id: user.id,
             email: user.email,
+            email_verified: user.verified,
             username: Some(format!("{}#{}", user.username, user.discriminator)),
             attributes: std::collections::HashMap::new(),
+            attributes_multi: std::collections::HashMap::new(),
         }
     }
 }
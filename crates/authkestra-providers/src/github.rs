@@ -5,7 +5,7 @@ crate::define_oauth_provider! {
     "https://github.com/login/oauth/authorize",
     "https://github.com/login/oauth/access_token",
     "https://api.github.com/user",
-    vec!["user:email"],
+    vec!["read:user", "user:email"],
     GithubUserResponse {
         id: u64,
         login: String,
@@ -16,8 +16,47 @@ crate::define_oauth_provider! {
             provider_id: "github".to_string(),
             external_id: user.id.to_string(),
             email: user.email,
+            email_verified: None,
             username: Some(user.login),
             attributes: std::collections::HashMap::new(),
+            attributes_multi: std::collections::HashMap::new(),
+        }
+    },
+    // `/user` only returns the public email and says nothing about its
+    // verification, so look the address up in `/user/emails`.
+    verify_email: |http, user_url, access_token, identity| {
+        #[derive(serde::Deserialize)]
+        struct GithubEmail {
+            email: String,
+            primary: bool,
+            verified: bool,
+        }
+
+        let emails = match http
+            .get(format!("{user_url}/emails"))
+            .header("Authorization", format!("Bearer {access_token}"))
+            .send()
+            .await
+            .and_then(|response| Ok(response.error_for_status()?))
+        {
+            Ok(response) => response.json::<Vec<GithubEmail>>().await.map_err(Into::into),
+            Err(e) => Err(e),
+        };
+
+        match emails {
+            Ok(emails) => {
+                let entry = match &identity.email {
+                    Some(email) => emails.into_iter().find(|e| &e.email == email),
+                    None => emails.into_iter().find(|e| e.primary),
+                };
+                if let Some(entry) = entry {
+                    identity.email = Some(entry.email);
+                    identity.email_verified = Some(entry.verified);
+                }
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to fetch GitHub emails; email verification unknown");
+            }
         }
     }
 }
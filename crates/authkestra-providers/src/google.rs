@@ -30,8 +30,10 @@ crate::define_oauth_provider! {
             provider_id: "google".to_string(),
             external_id: user.sub,
             email: user.email,
+            email_verified: user.email_verified,
             username: user.name,
             attributes,
+            attributes_multi: std::collections::HashMap::new(),
         }
     }
 }
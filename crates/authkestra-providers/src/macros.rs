@@ -10,15 +10,31 @@ macro_rules! define_oauth_provider {
         $default_scopes:expr,
         $user_response:ident { $($user_field:ident : $user_type:ty),* $(,)? },
         | $user_var:ident | $map_identity:block
+        $(, verify_email: | $http:ident, $user_url:ident, $access_token:ident, $identity:ident | $verify_email:block )?
+        $(,)?
     ) => {
         pub struct $provider_struct {
             client_id: String,
             client_secret: String,
             redirect_uri: String,
-            http_client: reqwest::Client,
+            http_client: authkestra_engine::http_client::HttpExecutor,
             authorization_url: String,
             token_url: String,
             user_url: String,
+            userinfo_cache: Option<std::sync::Arc<authkestra_engine::UserInfoCache>>,
+        }
+
+        impl std::fmt::Debug for $provider_struct {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!($provider_struct))
+                    .field("client_id", &self.client_id)
+                    .field("client_secret", &authkestra_engine::state::REDACTED)
+                    .field("redirect_uri", &self.redirect_uri)
+                    .field("authorization_url", &self.authorization_url)
+                    .field("token_url", &self.token_url)
+                    .field("user_url", &self.user_url)
+                    .finish_non_exhaustive()
+            }
         }
 
         impl $provider_struct {
@@ -27,13 +43,11 @@ macro_rules! define_oauth_provider {
                     client_id,
                     client_secret,
                     redirect_uri,
-                    http_client: reqwest::Client::builder()
-                        .user_agent("authkestra")
-                        .build()
-                        .unwrap_or_else(|_| reqwest::Client::new()),
+                    http_client: Self::http_client(authkestra_engine::http_client::DEFAULT_TIMEOUT),
                     authorization_url: $default_auth_url.to_string(),
                     token_url: $default_token_url.to_string(),
                     user_url: $default_userinfo_url.to_string(),
+                    userinfo_cache: None,
                 }
             }
 
@@ -53,6 +67,43 @@ macro_rules! define_oauth_provider {
                 self.authorization_url = authorization_url;
                 self
             }
+
+            /// Set the timeout for requests to the token and user endpoints.
+            ///
+            /// Requests that exceed it fail with `AuthError::Timeout`.
+            pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+                self.http_client = Self::http_client(timeout);
+                self
+            }
+
+            /// Cache the user information fetched with an access token for up to `ttl`.
+            ///
+            /// Entries are keyed by a hash of the access token and expire with the
+            /// token if it expires sooner. Off by default.
+            pub fn with_userinfo_cache(mut self, ttl: std::time::Duration) -> Self {
+                self.userinfo_cache = Some(std::sync::Arc::new(authkestra_engine::UserInfoCache::new(ttl)));
+                self
+            }
+
+            /// Send requests through `client`, e.g. a `reqwest_middleware::ClientWithMiddleware`
+            /// adding tracing headers. Replaces the client set by `with_timeout`.
+            ///
+            /// GitHub rejects requests without a `User-Agent`, so configure one on `client`.
+            pub fn with_http_client(
+                mut self,
+                client: impl Into<authkestra_engine::http_client::HttpExecutor>,
+            ) -> Self {
+                self.http_client = client.into();
+                self
+            }
+
+            fn http_client(timeout: std::time::Duration) -> authkestra_engine::http_client::HttpExecutor {
+                authkestra_engine::http_client::builder(timeout)
+                    .user_agent("authkestra")
+                    .build()
+                    .unwrap_or_else(|_| reqwest::Client::new())
+                    .into()
+            }
         }
 
         #[async_trait::async_trait]
@@ -87,6 +138,14 @@ macro_rules! define_oauth_provider {
                 $provider_id
             }
 
+            fn redirect_uri(&self) -> Option<&str> {
+                Some(&self.redirect_uri)
+            }
+
+            fn default_scopes(&self) -> Vec<&str> {
+                $default_scopes
+            }
+
             fn get_authorization_url(
                 &self,
                 state: &str,
@@ -94,9 +153,8 @@ macro_rules! define_oauth_provider {
                 code_challenge: Option<&str>,
                 nonce: Option<&str>,
             ) -> String {
-                let default_scopes: Vec<&str> = $default_scopes;
                 let scope_param = if scopes.is_empty() {
-                    default_scopes.join(" ")
+                    self.default_scopes().join(" ")
                 } else {
                     scopes.join(" ")
                 };
@@ -121,12 +179,23 @@ macro_rules! define_oauth_provider {
                 url
             }
 
-            #[tracing::instrument(skip(self, code, code_verifier, _nonce))]
             async fn exchange_code_for_identity(
+                &self,
+                code: &str,
+                code_verifier: Option<&str>,
+                nonce: Option<&str>,
+            ) -> Result<(authkestra_engine::state::Identity, authkestra_engine::state::OAuthToken), authkestra_engine::error::AuthError> {
+                self.exchange_code_for_identity_with_resources(code, code_verifier, nonce, &[])
+                    .await
+            }
+
+            #[tracing::instrument(skip(self, code, code_verifier, _nonce))]
+            async fn exchange_code_for_identity_with_resources(
                 &self,
                 code: &str,
                 code_verifier: Option<&str>,
                 _nonce: Option<&str>,
+                resources: &[String],
             ) -> Result<(authkestra_engine::state::Identity, authkestra_engine::state::OAuthToken), authkestra_engine::error::AuthError> {
                 tracing::debug!(concat!("exchanging ", $provider_name, " code for access token"));
 
@@ -141,6 +210,9 @@ macro_rules! define_oauth_provider {
                 if let Some(verifier) = code_verifier {
                     params.push(("code_verifier", verifier.to_string()));
                 }
+                for resource in resources {
+                    params.push(("resource", resource.clone()));
+                }
 
                 let token_response = self
                     .http_client
@@ -151,42 +223,75 @@ macro_rules! define_oauth_provider {
                     .await
                     .map_err(|e| {
                         tracing::error!(error = %e, concat!("network error while exchanging ", $provider_name, " code"));
-                        authkestra_engine::error::AuthError::Network
+                        authkestra_engine::http_client::map_error(&e)
                     })?
                     .json::<TokenResponse>()
                     .await
                     .map_err(|e| {
                         tracing::error!(error = %e, concat!("failed to parse ", $provider_name, " token response"));
-                        authkestra_engine::error::AuthError::Provider(format!("Failed to parse token response: {e}"))
+                        authkestra_engine::http_client::map_body_error(&e, |e| {
+                            authkestra_engine::error::AuthError::Provider(format!("Failed to parse token response: {e}"))
+                        })
                     })?;
 
-                tracing::debug!(concat!("fetching ", $provider_name, " user information"));
-                let $user_var = self
-                    .http_client
-                    .get(&self.user_url)
-                    .header(
-                        "Authorization",
-                        format!("Bearer {token}", token = token_response.access_token),
-                    )
-                    .send()
-                    .await
-                    .map_err(|e| {
-                        tracing::error!(error = %e, concat!("network error while fetching ", $provider_name, " user"));
-                        authkestra_engine::error::AuthError::Network
-                    })?
-                    .json::<$user_response>()
-                    .await
-                    .map_err(|e| {
-                        tracing::error!(error = %e, concat!("failed to parse ", $provider_name, " user response"));
-                        authkestra_engine::error::AuthError::Provider(format!("Failed to parse user response: {e}"))
-                    })?;
+                let cached = self
+                    .userinfo_cache
+                    .as_ref()
+                    .and_then(|cache| cache.get(&token_response.access_token));
+                let identity = match cached {
+                    Some(identity) => {
+                        tracing::debug!(concat!("using cached ", $provider_name, " user information"));
+                        identity
+                    }
+                    None => {
+                        tracing::debug!(concat!("fetching ", $provider_name, " user information"));
+                        let $user_var = self
+                            .http_client
+                            .get(&self.user_url)
+                            .header(
+                                "Authorization",
+                                format!("Bearer {token}", token = token_response.access_token),
+                            )
+                            .send()
+                            .await
+                            .map_err(|e| {
+                                tracing::error!(error = %e, concat!("network error while fetching ", $provider_name, " user"));
+                                authkestra_engine::http_client::map_error(&e)
+                            })?
+                            .json::<$user_response>()
+                            .await
+                            .map_err(|e| {
+                                tracing::error!(error = %e, concat!("failed to parse ", $provider_name, " user response"));
+                                authkestra_engine::http_client::map_body_error(&e, |e| {
+                            authkestra_engine::error::AuthError::Provider(format!("Failed to parse user response: {e}"))
+                        })
+                            })?;
+
+                        let identity: authkestra_engine::state::Identity = $map_identity;
 
-                let identity: authkestra_engine::state::Identity = $map_identity;
+                        $(
+                            let mut $identity = identity;
+                            {
+                                let $http = &self.http_client;
+                                let $user_url = self.user_url.as_str();
+                                let $access_token = token_response.access_token.as_str();
+                                $verify_email
+                            }
+                            let identity = $identity;
+                        )?
+
+                        if let Some(cache) = &self.userinfo_cache {
+                            cache.insert(&token_response.access_token, identity.clone(), token_response.expires_in);
+                        }
+                        identity
+                    }
+                };
 
                 let token = authkestra_engine::state::OAuthToken {
                     access_token: token_response.access_token,
                     token_type: token_response.token_type,
                     expires_in: token_response.expires_in,
+                    issued_at: authkestra_engine::chrono::Utc::now(),
                     refresh_token: token_response.refresh_token,
                     scope: token_response.scope,
                     id_token: token_response.id_token,
@@ -213,13 +318,15 @@ macro_rules! define_oauth_provider {
                     .await
                     .map_err(|e| {
                         tracing::error!(error = %e, concat!("network error while refreshing ", $provider_name, " token"));
-                        authkestra_engine::error::AuthError::Network
+                        authkestra_engine::http_client::map_error(&e)
                     })?
                     .json::<TokenResponse>()
                     .await
                     .map_err(|e| {
                         tracing::error!(error = %e, concat!("failed to parse ", $provider_name, " refresh token response"));
-                        authkestra_engine::error::AuthError::Provider(format!("Failed to parse refresh token response: {e}"))
+                        authkestra_engine::http_client::map_body_error(&e, |e| {
+                            authkestra_engine::error::AuthError::Provider(format!("Failed to parse refresh token response: {e}"))
+                        })
                     })?;
 
                 tracing::info!(concat!("successfully refreshed ", $provider_name, " access token"));
@@ -227,6 +334,7 @@ macro_rules! define_oauth_provider {
                     access_token: token_response.access_token,
                     token_type: token_response.token_type,
                     expires_in: token_response.expires_in,
+                    issued_at: authkestra_engine::chrono::Utc::now(),
                     refresh_token: token_response.refresh_token,
                     scope: token_response.scope,
                     id_token: token_response.id_token,
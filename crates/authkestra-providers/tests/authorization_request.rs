@@ -0,0 +1,78 @@
+use authkestra_engine::{auth::pkce::Pkce, OAuth2Flow};
+use authkestra_providers::google::GoogleProvider;
+
+fn flow() -> OAuth2Flow<GoogleProvider> {
+    let provider = GoogleProvider::new(
+        "client-id".to_string(),
+        "secret".to_string(),
+        "https://app.example/auth/callback/google".to_string(),
+    )
+    .with_test_urls(
+        "https://accounts.example/o/oauth2/auth".to_string(),
+        "https://accounts.example/token".to_string(),
+        "https://accounts.example/userinfo".to_string(),
+    );
+    OAuth2Flow::new(provider)
+}
+
+#[test]
+fn test_authorization_request_matches_the_url() {
+    let pkce = Pkce::new();
+    let request =
+        flow().build_authorization_request(&["openid", "email"], Some(&pkce.code_challenge));
+
+    assert_eq!(request.endpoint, "https://accounts.example/o/oauth2/auth");
+    assert_eq!(request.client_id.as_deref(), Some("client-id"));
+    assert_eq!(
+        request.redirect_uri.as_deref(),
+        Some("https://app.example/auth/callback/google")
+    );
+    assert_eq!(request.scope.as_deref(), Some("openid email"));
+    assert_eq!(
+        request.state.as_deref(),
+        Some(request.flow_state.state.as_str())
+    );
+    assert_eq!(
+        request.code_challenge.as_deref(),
+        Some(pkce.code_challenge.as_str())
+    );
+    assert_eq!(request.code_challenge_method.as_deref(), Some("S256"));
+    assert_eq!(request.extra["response_type"], "code");
+    assert_eq!(
+        Some(&request.extra["nonce"]),
+        request.flow_state.nonce.as_ref()
+    );
+
+    // Every parameter of the URL is accounted for, with the same value.
+    let url = url::Url::parse(&request.url).unwrap();
+    assert!(request.url.starts_with(&format!("{}?", request.endpoint)));
+    for (name, value) in url.query_pairs() {
+        let field = match name.as_ref() {
+            "client_id" => request.client_id.as_deref(),
+            "redirect_uri" => request.redirect_uri.as_deref(),
+            "scope" => request.scope.as_deref(),
+            "state" => request.state.as_deref(),
+            "code_challenge" => request.code_challenge.as_deref(),
+            "code_challenge_method" => request.code_challenge_method.as_deref(),
+            other => request.extra.get(other).map(String::as_str),
+        };
+        assert_eq!(field, Some(value.as_ref()), "{name}");
+    }
+    assert_eq!(url.query_pairs().count(), 6 + request.extra.len());
+}
+
+#[test]
+fn test_initiate_login_returns_the_request() {
+    let (url, state) = flow().initiate_login(&[], None);
+    let request = url::Url::parse(&url).unwrap();
+    let params: Vec<_> = request.query_pairs().collect();
+    assert!(params
+        .iter()
+        .any(|(k, v)| k == "state" && *v == state.state));
+    assert!(!params.iter().any(|(k, _)| k == "code_challenge"));
+
+    let request = flow().build_authorization_request(&[], None);
+    assert!(request.code_challenge.is_none());
+    assert!(request.code_challenge_method.is_none());
+    assert_eq!(request.flow_state.provider_id, "google");
+}
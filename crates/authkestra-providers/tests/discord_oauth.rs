@@ -80,4 +80,5 @@ async fn test_discord_oauth_flow() {
     assert_eq!(identity.external_id, "123456789");
     assert_eq!(identity.username, Some("testuser#0001".to_string()));
     assert_eq!(identity.email, Some("test@example.com".to_string()));
+    assert_eq!(identity.email_verified, Some(true));
 }
@@ -43,6 +43,21 @@ async fn test_github_oauth_flow() {
         .mount(&server)
         .await;
 
+    // Mock the GitHub emails endpoint
+    Mock::given(method("GET"))
+        .and(path("/user/emails"))
+        .and(header("Authorization", "Bearer test_access_token"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .append_header("content-type", "application/json")
+                .set_body_json(serde_json::json!([
+                    { "email": "old@example.com", "primary": false, "verified": false },
+                    { "email": "test@example.com", "primary": true, "verified": true }
+                ])),
+        )
+        .mount(&server)
+        .await;
+
     let provider = GithubProvider::new(
         "test_client_id".to_string(),
         "test_client_secret".to_string(),
@@ -73,4 +88,58 @@ async fn test_github_oauth_flow() {
     assert_eq!(identity.external_id, "123");
     assert_eq!(identity.username, Some("test_user".to_string()));
     assert_eq!(identity.email, Some("test@example.com".to_string()));
+    assert_eq!(identity.email_verified, Some(true));
+}
+
+#[tokio::test]
+async fn test_github_unverified_public_email() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/login/oauth/access_token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": "test_access_token",
+            "token_type": "bearer"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/user"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": 123,
+            "login": "test_user",
+            "email": "victim@example.com"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/user/emails"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            { "email": "test@example.com", "primary": true, "verified": true },
+            { "email": "victim@example.com", "primary": false, "verified": false }
+        ])))
+        .mount(&server)
+        .await;
+
+    let provider = GithubProvider::new(
+        "test_client_id".to_string(),
+        "test_client_secret".to_string(),
+        format!("{}/callback", server.uri()),
+    )
+    .with_test_urls(
+        format!("{}/login/oauth/authorize", server.uri()),
+        format!("{}/login/oauth/access_token", server.uri()),
+        format!("{}/user", server.uri()),
+    );
+
+    let (identity, _) = provider
+        .exchange_code_for_identity("test_code", None, None)
+        .await
+        .unwrap();
+
+    // The public email is kept, with its own verification status.
+    assert_eq!(identity.email, Some("victim@example.com".to_string()));
+    assert_eq!(identity.email_verified, Some(false));
 }
@@ -79,6 +79,7 @@ async fn test_google_oauth_flow() {
     assert_eq!(identity.external_id, "google-123");
     assert_eq!(identity.username, Some("Test User".to_string()));
     assert_eq!(identity.email, Some("test@example.com".to_string()));
+    assert_eq!(identity.email_verified, Some(true));
     assert_eq!(
         identity.attributes.get("locale").map(|v| v.as_str()),
         Some("en")
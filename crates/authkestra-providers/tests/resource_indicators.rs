@@ -0,0 +1,95 @@
+use authkestra_engine::OAuth2Flow;
+use authkestra_providers::discord::DiscordProvider;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const API: &str = "https://api.example.com";
+const FILES: &str = "https://files.example.com";
+
+fn provider(server: &MockServer) -> DiscordProvider {
+    DiscordProvider::new(
+        "client-id".to_string(),
+        "secret".to_string(),
+        "https://app.example/callback".to_string(),
+    )
+    .with_test_urls(
+        format!("{}/authorize", server.uri()),
+        format!("{}/token", server.uri()),
+        format!("{}/users/@me", server.uri()),
+    )
+}
+
+async fn mock_userinfo(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/users/@me"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "42",
+            "username": "alice",
+            "discriminator": "0001",
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_resources_are_sent_in_auth_url_and_token_request() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains(
+            "resource=https%3A%2F%2Fapi.example.com",
+        ))
+        .and(body_string_contains(
+            "resource=https%3A%2F%2Ffiles.example.com",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": "at",
+            "token_type": "Bearer",
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    mock_userinfo(&server).await;
+
+    let flow = OAuth2Flow::new(provider(&server)).with_resources(vec![API, FILES]);
+    let request = flow.build_authorization_request(&[], None);
+    assert_eq!(request.resources, vec![API, FILES]);
+
+    let state = request.flow_state;
+    let (identity, token, _) = flow
+        .finalize_login("code", &state.state.clone(), &state)
+        .await
+        .unwrap();
+    assert_eq!(identity.external_id, "42");
+    assert_eq!(token.access_token, "at");
+}
+
+#[tokio::test]
+async fn test_no_resources_are_sent_by_default() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains("resource="))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": "at",
+            "token_type": "Bearer",
+        })))
+        .mount(&server)
+        .await;
+    mock_userinfo(&server).await;
+
+    let flow = OAuth2Flow::new(provider(&server));
+    let request = flow.build_authorization_request(&[], None);
+    assert!(request.resources.is_empty());
+
+    let state = request.flow_state;
+    flow.finalize_login("code", &state.state.clone(), &state)
+        .await
+        .unwrap();
+}
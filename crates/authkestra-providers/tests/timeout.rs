@@ -0,0 +1,35 @@
+use authkestra_engine::{error::AuthError, OAuthProvider};
+use authkestra_providers::github::GithubProvider;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_slow_token_endpoint_times_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login/oauth/access_token"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let provider = GithubProvider::new(
+        "test_client_id".to_string(),
+        "test_client_secret".to_string(),
+        format!("{}/callback", server.uri()),
+    )
+    .with_test_urls(
+        format!("{}/login/oauth/authorize", server.uri()),
+        format!("{}/login/oauth/access_token", server.uri()),
+        format!("{}/user", server.uri()),
+    )
+    .with_timeout(Duration::from_millis(200));
+
+    let result = provider
+        .exchange_code_for_identity("test_code", None, None)
+        .await;
+    assert!(matches!(result, Err(AuthError::Timeout)));
+
+    let result = provider.refresh_token("refresh").await;
+    assert!(matches!(result, Err(AuthError::Timeout)));
+}
@@ -0,0 +1,80 @@
+use authkestra_engine::OAuthProvider;
+use authkestra_providers::google::GoogleProvider;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A server issuing the same access token, expiring in `expires_in`, on every exchange.
+async fn token_server(expires_in: u64, userinfo_requests: u64) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": "test_access_token",
+            "token_type": "Bearer",
+            "expires_in": expires_in,
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/userinfo"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "sub": "google-123",
+            "email": "test@example.com",
+            "email_verified": true,
+        })))
+        .expect(userinfo_requests)
+        .mount(&server)
+        .await;
+    server
+}
+
+fn provider(server: &MockServer) -> GoogleProvider {
+    GoogleProvider::new(
+        "test_client_id".to_string(),
+        "test_client_secret".to_string(),
+        format!("{}/callback", server.uri()),
+    )
+    .with_test_urls(
+        format!("{}/auth", server.uri()),
+        format!("{}/token", server.uri()),
+        format!("{}/userinfo", server.uri()),
+    )
+}
+
+#[tokio::test]
+async fn test_cache_hit_skips_userinfo_request() {
+    let server = token_server(3600, 1).await;
+    let cached = provider(&server).with_userinfo_cache(Duration::from_secs(60));
+
+    for _ in 0..2 {
+        let (identity, _) = cached
+            .exchange_code_for_identity("code", None, None)
+            .await
+            .unwrap();
+        assert_eq!(identity.external_id, "google-123");
+        assert_eq!(identity.email_verified, Some(true));
+    }
+    // `expect(1)` is verified when the server drops.
+}
+
+#[tokio::test]
+async fn test_userinfo_is_not_cached_by_default_or_past_token_expiry() {
+    let server = token_server(3600, 2).await;
+    let uncached = provider(&server);
+    for _ in 0..2 {
+        uncached
+            .exchange_code_for_identity("code", None, None)
+            .await
+            .unwrap();
+    }
+
+    let expiring = token_server(0, 2).await;
+    let cached = provider(&expiring).with_userinfo_cache(Duration::from_secs(60));
+    for _ in 0..2 {
+        cached
+            .exchange_code_for_identity("code", None, None)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use crate::Decision;
+use authkestra_engine::error::AuthError;
+
+/// Decides whether an authenticated identity may perform an action on a
+/// resource, e.g. by asking an external policy engine such as OPA or Cedar.
+///
+/// Unlike a [`Policy`](crate::Policy), an engine is an instance held in the
+/// application state and may perform I/O. The framework `Authorized`
+/// extractors call it after authentication with the request method as the
+/// action and the request path as the resource:
+///
+/// ```rust,ignore
+/// struct Opa { client: reqwest::Client }
+///
+/// #[async_trait]
+/// impl AuthorizationEngine<User> for Opa {
+///     async fn authorize(&self, user: &User, action: &str, resource: &str)
+///         -> Result<Decision, AuthError> {
+///         // POST the input to OPA and map its result to a Decision.
+///     }
+/// }
+/// ```
+#[async_trait::async_trait]
+pub trait AuthorizationEngine<I>: Send + Sync + 'static {
+    /// Allow or deny `identity` to perform `action` on `resource`.
+    ///
+    /// An error means no decision could be made and is answered with `500`.
+    async fn authorize(
+        &self,
+        identity: &I,
+        action: &str,
+        resource: &str,
+    ) -> Result<Decision, AuthError>;
+}
+
+type Check<I> = Box<dyn Fn(&I) -> bool + Send + Sync>;
+
+struct Rule<I> {
+    action: String,
+    resource_prefix: String,
+    check: Check<I>,
+}
+
+/// An [`AuthorizationEngine`] evaluating in-process rules.
+///
+/// A request is allowed if a rule matching its action and resource accepts
+/// the identity, and denied otherwise:
+///
+/// ```rust,ignore
+/// let rules = LocalRules::new()
+///     .allow("GET", "/reports", |_: &User| true)
+///     .allow("*", "/admin", |user: &User| user.admin);
+/// ```
+pub struct LocalRules<I> {
+    rules: Vec<Rule<I>>,
+}
+
+impl<I> Default for LocalRules<I> {
+    fn default() -> Self {
+        Self { rules: Vec::new() }
+    }
+}
+
+impl<I> LocalRules<I> {
+    /// Rules that deny every request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow `action` (`"*"` for any) on `resource_prefix` and the resources
+    /// below it to identities accepted by `check`.
+    ///
+    /// The prefix matches whole path segments: `/admin` covers `/admin` and
+    /// `/admin/users` but not `/administrator`.
+    pub fn allow(
+        mut self,
+        action: impl Into<String>,
+        resource_prefix: impl Into<String>,
+        check: impl Fn(&I) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.rules.push(Rule {
+            action: action.into(),
+            resource_prefix: resource_prefix.into(),
+            check: Box::new(check),
+        });
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<I> AuthorizationEngine<I> for LocalRules<I>
+where
+    I: Sync + 'static,
+{
+    async fn authorize(
+        &self,
+        identity: &I,
+        action: &str,
+        resource: &str,
+    ) -> Result<Decision, AuthError> {
+        let allowed = self.rules.iter().any(|rule| {
+            (rule.action == "*" || rule.action.eq_ignore_ascii_case(action))
+                && covers(&rule.resource_prefix, resource)
+                && (rule.check)(identity)
+        });
+        Ok(if allowed {
+            Decision::Allow
+        } else {
+            Decision::Deny(format!("{action} {resource} is not allowed"))
+        })
+    }
+}
+
+/// Whether `resource` is `prefix` or lies below it.
+fn covers(prefix: &str, resource: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match resource.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_rules() {
+        let rules = LocalRules::new()
+            .allow("GET", "/reports", |_: &bool| true)
+            .allow("*", "/admin", |admin: &bool| *admin);
+
+        assert_eq!(
+            rules.authorize(&false, "get", "/reports/1").await.unwrap(),
+            Decision::Allow
+        );
+        assert_eq!(
+            rules
+                .authorize(&true, "DELETE", "/admin/users")
+                .await
+                .unwrap(),
+            Decision::Allow
+        );
+        assert_eq!(
+            rules
+                .authorize(&false, "DELETE", "/admin/users")
+                .await
+                .unwrap(),
+            Decision::Deny("DELETE /admin/users is not allowed".to_string())
+        );
+        assert!(matches!(
+            rules.authorize(&true, "POST", "/reports").await.unwrap(),
+            Decision::Deny(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_local_rules_match_whole_segments() {
+        let rules = LocalRules::new().allow("*", "/admin", |_: &()| true);
+
+        for allowed in ["/admin", "/admin/", "/admin/users"] {
+            assert_eq!(
+                rules.authorize(&(), "GET", allowed).await.unwrap(),
+                Decision::Allow,
+                "{allowed}"
+            );
+        }
+        for denied in ["/adminX", "/administrator", "/admin-panel/users"] {
+            assert!(
+                matches!(
+                    rules.authorize(&(), "GET", denied).await.unwrap(),
+                    Decision::Deny(_)
+                ),
+                "{denied}"
+            );
+        }
+
+        // A trailing slash on the prefix is not required on the resource.
+        let rules = LocalRules::new().allow("*", "/documents/", |_: &()| true);
+        assert_eq!(
+            rules.authorize(&(), "GET", "/documents").await.unwrap(),
+            Decision::Allow
+        );
+    }
+}
@@ -1,12 +1,21 @@
 use async_trait::async_trait;
+#[cfg(feature = "remote-jwks")]
+use authkestra_engine::{
+    discovery::ProviderMetadata,
+    http_client::{self, HttpError, HttpExecutor, RequestError},
+    task::TaskHandle,
+};
 use authkestra_engine::{
     error::AuthError,
-    strategy::{utils, AuthenticationStrategy},
+    strategy::{utils, AuthenticationStrategy, TokenSource},
     token::Claims,
 };
 use http::request::Parts;
-use jsonwebtoken::{decode, decode_header, Algorithm, Validation};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Header, Validation};
 use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::hash_map::{Entry, HashMap};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::RwLock;
@@ -14,8 +23,9 @@ use tokio::sync::RwLock;
 /// Errors that can occur during offline validation.
 #[derive(Debug, Error)]
 pub enum ValidationError {
+    #[cfg(feature = "remote-jwks")]
     #[error("HTTP error: {0}")]
-    Http(#[from] reqwest::Error),
+    Http(#[from] HttpError),
     #[error("JWT error: {0}")]
     Jwt(#[from] jsonwebtoken::errors::Error),
     #[error("Serialization error: {0}")]
@@ -30,8 +40,86 @@ pub enum ValidationError {
     Discovery(#[from] AuthError),
     #[error("Validation error: {0}")]
     Validation(String),
+    #[error("Token exceeds maximum size of {max} bytes")]
+    TokenTooLarge { max: usize },
+    #[error("JWKS response exceeds maximum size of {max} bytes")]
+    JwksTooLarge { max: usize },
+    #[error("Upstream request timed out")]
+    Timeout,
 }
 
+impl ValidationError {
+    #[cfg(feature = "remote-jwks")]
+    fn from_http(err: impl Into<HttpError>) -> Self {
+        let err = err.into();
+        if err.is_timeout() {
+            ValidationError::Timeout
+        } else {
+            ValidationError::Http(err)
+        }
+    }
+
+    /// Whether validating the same token again may succeed: the JWKS or
+    /// discovery request failed on the network or timed out. Errors about the
+    /// token itself, and responses the issuer served but that could not be
+    /// used, are not retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            #[cfg(feature = "remote-jwks")]
+            ValidationError::Http(_) => true,
+            ValidationError::Timeout => true,
+            ValidationError::Discovery(e) => matches!(e, AuthError::Network | AuthError::Timeout),
+            _ => false,
+        }
+    }
+
+    /// A copy of a JWKS fetch error for each token of a batch. HTTP and JSON
+    /// errors cannot be cloned, so they are kept as their message.
+    fn for_batch(&self) -> Self {
+        match self {
+            ValidationError::Timeout => ValidationError::Timeout,
+            ValidationError::JwksTooLarge { max } => ValidationError::JwksTooLarge { max: *max },
+            other => ValidationError::Validation(format!("Failed to fetch JWKS: {other}")),
+        }
+    }
+}
+
+/// Classifies a validation failure for strategies and guards.
+///
+/// Problems with the token itself (signature, expiry, standard claims, size,
+/// an unknown `kid`) become [`AuthError::InvalidToken`], which strategies
+/// answer with `Ok(None)`. A verified token failing the configured
+/// [required claims](ValidationConfigBuilder::required_claims) or
+/// [`iat`](ValidationConfigBuilder::max_future_iat) checks is rejected with
+/// [`AuthError::Token`]. Failures to fetch or use the issuer's keys are hard
+/// errors: [`AuthError::Timeout`], [`AuthError::Network`], the discovery error
+/// itself, or [`AuthError::Provider`] for an unusable JWKS response.
+impl From<ValidationError> for AuthError {
+    fn from(err: ValidationError) -> Self {
+        match err {
+            ValidationError::Jwt(_)
+            | ValidationError::InvalidToken(_)
+            | ValidationError::KeyNotFound
+            | ValidationError::Paseto(_)
+            | ValidationError::TokenTooLarge { .. } => AuthError::InvalidToken(err.to_string()),
+            ValidationError::Validation(_) => AuthError::Token(err.to_string()),
+            ValidationError::Timeout => AuthError::Timeout,
+            #[cfg(feature = "remote-jwks")]
+            ValidationError::Http(_) => AuthError::Network,
+            ValidationError::Discovery(e) => e,
+            ValidationError::Serialization(_) | ValidationError::JwksTooLarge { .. } => {
+                AuthError::Provider(err.to_string())
+            }
+        }
+    }
+}
+
+/// Default maximum size, in bytes, of a token accepted for validation.
+pub const DEFAULT_MAX_TOKEN_SIZE: usize = 16 * 1024;
+
+/// Default maximum size, in bytes, of a JWKS response body.
+pub const DEFAULT_MAX_JWKS_SIZE: usize = 256 * 1024;
+
 pub use authkestra_engine::token::jwk::Jwk;
 
 #[derive(Debug, Clone, Deserialize)]
@@ -40,36 +128,224 @@ pub struct Jwks {
 }
 
 impl Jwks {
+    #[cfg(feature = "remote-jwks")]
     pub async fn fetch(jwks_uri: &str) -> Result<Self, ValidationError> {
-        let client = reqwest::Client::new();
-        let jwks = client.get(jwks_uri).send().await?.json::<Jwks>().await?;
-        Ok(jwks)
+        Self::fetch_with_limit(jwks_uri, DEFAULT_MAX_JWKS_SIZE).await
+    }
+
+    /// Fetches the JWKS, rejecting response bodies larger than `max_bytes`.
+    #[cfg(feature = "remote-jwks")]
+    pub async fn fetch_with_limit(
+        jwks_uri: &str,
+        max_bytes: usize,
+    ) -> Result<Self, ValidationError> {
+        Self::fetch_with_timeout(jwks_uri, max_bytes, http_client::DEFAULT_TIMEOUT).await
+    }
+
+    /// Fetches the JWKS, failing with [`ValidationError::Timeout`] if the request
+    /// takes longer than `timeout`.
+    #[cfg(feature = "remote-jwks")]
+    pub async fn fetch_with_timeout(
+        jwks_uri: &str,
+        max_bytes: usize,
+        timeout: Duration,
+    ) -> Result<Self, ValidationError> {
+        Self::fetch_with_client(jwks_uri, max_bytes, &HttpExecutor::with_timeout(timeout)).await
+    }
+
+    /// Fetches the JWKS with `client`, e.g. one carrying `reqwest-middleware`.
+    #[cfg(feature = "remote-jwks")]
+    pub async fn fetch_with_client(
+        jwks_uri: &str,
+        max_bytes: usize,
+        client: &HttpExecutor,
+    ) -> Result<Self, ValidationError> {
+        let response = client
+            .get(jwks_uri)
+            .send()
+            .await
+            .map_err(ValidationError::from_http)?;
+
+        let body = http_client::read_limited(response, max_bytes)
+            .await
+            .map_err(|e| match e {
+                http_client::BodyError::TooLarge { max } => ValidationError::JwksTooLarge { max },
+                http_client::BodyError::Reqwest(e) => ValidationError::from_http(e),
+            })?;
+
+        Ok(serde_json::from_slice(&body)?)
     }
 
+    /// Finds the key with id `kid`, or the first key if the token has no
+    /// `kid`. [`JwksCache`] applies its [`MissingKidPolicy`] instead.
     pub fn find_key(&self, kid: Option<&str>) -> Option<&Jwk> {
         match kid {
             Some(id) => self.keys.iter().find(|k| k.kid.as_deref() == Some(id)),
             None => self.keys.first(),
         }
     }
+
+    /// Finds the key for a token signed with `alg`: the key with id `kid` if
+    /// it [supports](Jwk::supports) `alg`, or the first key that does if the
+    /// token has no `kid`.
+    ///
+    /// Unlike [`find_key`](Self::find_key), this picks the right key from a set
+    /// mixing RSA and EC keys.
+    pub fn find_key_for(&self, kid: Option<&str>, alg: Algorithm) -> Option<&Jwk> {
+        self.keys
+            .iter()
+            .filter(|k| kid.is_none() || k.kid.as_deref() == kid)
+            .find(|k| k.supports(alg))
+    }
+}
+
+/// A fixed key set for validating tokens without contacting the issuer, e.g. in
+/// air-gapped deployments where the keys are pinned out of band.
+#[derive(Debug, Clone)]
+pub struct StaticJwksSource {
+    jwks: Jwks,
+}
+
+impl StaticJwksSource {
+    pub fn new(jwks: Jwks) -> Self {
+        Self { jwks }
+    }
+
+    /// Parses a JWKS document, e.g. one read from disk.
+    pub fn from_json(json: &str) -> Result<Self, ValidationError> {
+        Ok(Self::new(serde_json::from_str(json)?))
+    }
+
+    pub fn jwks(&self) -> &Jwks {
+        &self.jwks
+    }
+}
+
+/// How a [`JwksCache`] picks the key for a token without a `kid` header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissingKidPolicy {
+    /// Reject tokens without a `kid`.
+    Strict,
+    /// Use the only key of the set that can verify the token, and reject the
+    /// token if there are several, e.g. while keys are rotated.
+    #[default]
+    SoleKey,
+}
+
+impl MissingKidPolicy {
+    fn select(self, jwks: &Jwks, alg: Option<Algorithm>) -> Result<Option<&Jwk>, ValidationError> {
+        if self == MissingKidPolicy::Strict {
+            return Err(ValidationError::InvalidToken(
+                "Token has no kid".to_string(),
+            ));
+        }
+        let mut candidates = jwks
+            .keys
+            .iter()
+            .filter(|k| alg.is_none_or(|alg| k.supports(alg)));
+        match (candidates.next(), candidates.next()) {
+            (Some(_), Some(_)) => Err(ValidationError::InvalidToken(
+                "Token has no kid and the JWKS has several matching keys".to_string(),
+            )),
+            (key, _) => Ok(key),
+        }
+    }
+}
+
+enum JwksSource {
+    // Only `JwksCache::new` builds a remote source.
+    #[cfg_attr(not(feature = "remote-jwks"), allow(dead_code))]
+    Remote(String),
+    Static(StaticJwksSource),
 }
 
 pub struct JwksCache {
-    jwks_uri: String,
+    source: JwksSource,
     jwks: RwLock<Option<(Jwks, Instant)>>,
     ttl: Duration,
+    missing_kid: MissingKidPolicy,
+    max_token_size: usize,
+    #[cfg_attr(not(feature = "remote-jwks"), allow(dead_code))]
+    max_jwks_size: usize,
+    #[cfg(feature = "remote-jwks")]
+    http_client: HttpExecutor,
 }
 
 impl JwksCache {
+    #[cfg(feature = "remote-jwks")]
     pub fn new(jwks_uri: String, refresh_interval: Duration) -> Self {
+        Self::with_source(JwksSource::Remote(jwks_uri), refresh_interval)
+    }
+
+    /// Creates a cache that always serves `jwks` and never performs network I/O.
+    pub fn from_static(jwks: Jwks) -> Self {
+        Self::from(StaticJwksSource::new(jwks))
+    }
+
+    fn with_source(source: JwksSource, refresh_interval: Duration) -> Self {
         Self {
-            jwks_uri,
+            source,
             jwks: RwLock::new(None),
             ttl: refresh_interval,
+            missing_kid: MissingKidPolicy::default(),
+            max_token_size: DEFAULT_MAX_TOKEN_SIZE,
+            max_jwks_size: DEFAULT_MAX_JWKS_SIZE,
+            #[cfg(feature = "remote-jwks")]
+            http_client: HttpExecutor::default(),
         }
     }
 
+    /// Set the timeout for JWKS requests.
+    ///
+    /// Replaces a client set with [`JwksCache::with_http_client`].
+    #[cfg(feature = "remote-jwks")]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http_client = HttpExecutor::with_timeout(timeout);
+        self
+    }
+
+    /// Send JWKS requests with `client`, a `reqwest::Client` or, with the
+    /// `reqwest-middleware` feature, a `ClientWithMiddleware`.
+    #[cfg(feature = "remote-jwks")]
+    pub fn with_http_client(mut self, client: impl Into<HttpExecutor>) -> Self {
+        self.http_client = client.into();
+        self
+    }
+
+    /// Set how keys are picked for tokens without a `kid`. Defaults to
+    /// [`MissingKidPolicy::SoleKey`].
+    pub fn with_missing_kid_policy(mut self, policy: MissingKidPolicy) -> Self {
+        self.missing_kid = policy;
+        self
+    }
+
+    /// Set the maximum size, in bytes, of tokens validated against this cache.
+    pub fn with_max_token_size(mut self, max_bytes: usize) -> Self {
+        self.max_token_size = max_bytes;
+        self
+    }
+
+    /// Set the maximum size, in bytes, of the JWKS response body.
+    pub fn with_max_jwks_size(mut self, max_bytes: usize) -> Self {
+        self.max_jwks_size = max_bytes;
+        self
+    }
+
+    /// The maximum size, in bytes, of tokens validated against this cache.
+    pub fn max_token_size(&self) -> usize {
+        self.max_token_size
+    }
+
+    /// Whether keys come from a [`StaticJwksSource`] rather than a JWKS URL.
+    pub fn is_static(&self) -> bool {
+        matches!(self.source, JwksSource::Static(_))
+    }
+
     pub async fn get_jwks(&self) -> Result<Jwks, ValidationError> {
+        if let JwksSource::Static(source) = &self.source {
+            return Ok(source.jwks.clone());
+        }
+
         {
             let read_guard = self.jwks.read().await;
             if let Some((jwks, last_updated)) = read_guard.as_ref() {
@@ -82,23 +358,194 @@ impl JwksCache {
         self.refresh().await
     }
 
+    /// Looks up the key with id `kid`. Without a `kid`, the key is picked
+    /// according to the [`MissingKidPolicy`].
     pub async fn get_key(&self, kid: Option<&str>) -> Result<Option<Jwk>, ValidationError> {
+        self.lookup(kid, None).await
+    }
+
+    /// Like [`get_key`](Self::get_key), but looks the key up with
+    /// [`Jwks::find_key_for`].
+    pub async fn get_key_for(
+        &self,
+        kid: Option<&str>,
+        alg: Algorithm,
+    ) -> Result<Option<Jwk>, ValidationError> {
+        self.lookup(kid, Some(alg)).await
+    }
+
+    async fn lookup(
+        &self,
+        kid: Option<&str>,
+        alg: Option<Algorithm>,
+    ) -> Result<Option<Jwk>, ValidationError> {
+        if let JwksSource::Static(source) = &self.source {
+            return Ok(self.find_key(&source.jwks, kid, alg)?.cloned());
+        }
+
         let jwks = self.get_jwks().await?;
-        if let Some(key) = jwks.find_key(kid) {
+        if let Some(key) = self.find_key(&jwks, kid, alg)? {
             return Ok(Some(key.clone()));
         }
 
         // If key not found, try refreshing once in case of rotation
         let jwks = self.refresh().await?;
-        Ok(jwks.find_key(kid).cloned())
+        Ok(self.find_key(&jwks, kid, alg)?.cloned())
     }
 
+    fn find_key<'a>(
+        &self,
+        jwks: &'a Jwks,
+        kid: Option<&str>,
+        alg: Option<Algorithm>,
+    ) -> Result<Option<&'a Jwk>, ValidationError> {
+        match (kid, alg) {
+            (None, _) => self.missing_kid.select(jwks, alg),
+            (Some(_), Some(alg)) => Ok(jwks.find_key_for(kid, alg)),
+            (Some(_), None) => Ok(jwks.find_key(kid)),
+        }
+    }
+
+    /// Validates a batch of JWTs against one read of the JWKS.
+    ///
+    /// Gives the same results as calling [`validate_jwt_generic`] on each token,
+    /// in the order of `tokens`, but the key set is read (and refreshed at most
+    /// once, if a token names an unknown key) for the whole batch, and each key
+    /// is converted to a decoding key only once.
+    pub async fn validate_batch<T>(
+        &self,
+        tokens: &[&str],
+        validation: &Validation,
+    ) -> Vec<Result<T, ValidationError>>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let kids: Vec<Result<(Option<String>, Algorithm), ValidationError>> = tokens
+            .iter()
+            .map(|token| {
+                if token.len() > self.max_token_size {
+                    return Err(ValidationError::TokenTooLarge {
+                        max: self.max_token_size,
+                    });
+                }
+                let header = accepted_header(token, validation)?;
+                Ok((header.kid, header.alg))
+            })
+            .collect();
+
+        if kids.iter().all(Result::is_err) {
+            return kids.into_iter().map(|kid| Err(kid.unwrap_err())).collect();
+        }
+
+        let mut jwks = match self.get_jwks().await {
+            Ok(jwks) => jwks,
+            Err(e) => {
+                return kids
+                    .into_iter()
+                    .map(|kid| Err(kid.err().unwrap_or_else(|| e.for_batch())))
+                    .collect();
+            }
+        };
+
+        // If a key is not found, refresh once in case of rotation.
+        let mut refresh_error = None;
+        if kids
+            .iter()
+            .flatten()
+            .any(|(kid, alg)| matches!(self.find_key(&jwks, kid.as_deref(), Some(*alg)), Ok(None)))
+        {
+            match self.refresh().await {
+                Ok(fresh) => jwks = fresh,
+                Err(e) => refresh_error = Some(e),
+            }
+        }
+
+        let mut decoding_keys: HashMap<(Option<String>, Algorithm), DecodingKey> = HashMap::new();
+        let mut results = Vec::with_capacity(tokens.len());
+        for (token, kid) in tokens.iter().zip(kids) {
+            let result = kid.and_then(|(kid, alg)| {
+                let decoding_key = match decoding_keys.entry((kid, alg)) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let (kid, alg) = entry.key();
+                        let jwk = self
+                            .find_key(&jwks, kid.as_deref(), Some(*alg))?
+                            .ok_or_else(|| {
+                                refresh_error.as_ref().map_or(
+                                    ValidationError::KeyNotFound,
+                                    ValidationError::for_batch,
+                                )
+                            })?;
+                        entry.insert(jwk.to_decoding_key()?)
+                    }
+                };
+                let validation = for_algorithm(validation, alg);
+                Ok(decode::<T>(token, decoding_key, &validation)?.claims)
+            });
+            results.push(result);
+        }
+        results
+    }
+
+    /// Re-fetches the JWKS. A static cache returns its key set unchanged.
     pub async fn refresh(&self) -> Result<Jwks, ValidationError> {
+        let jwks_uri = match &self.source {
+            JwksSource::Remote(uri) => uri,
+            JwksSource::Static(source) => return Ok(source.jwks.clone()),
+        };
+        self.fetch(jwks_uri).await
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    async fn fetch(&self, jwks_uri: &str) -> Result<Jwks, ValidationError> {
         let mut write_guard = self.jwks.write().await;
-        let jwks = Jwks::fetch(&self.jwks_uri).await?;
+        let jwks = Jwks::fetch_with_client(jwks_uri, self.max_jwks_size, &self.http_client).await?;
         *write_guard = Some((jwks.clone(), Instant::now()));
         Ok(jwks)
     }
+
+    #[cfg(not(feature = "remote-jwks"))]
+    async fn fetch(&self, _jwks_uri: &str) -> Result<Jwks, ValidationError> {
+        unreachable!("remote JWKS caches require the `remote-jwks` feature")
+    }
+
+    /// Prefetches the JWKS now and then every `interval` in the background, so
+    /// requests do not wait on a fetch when the cached keys expire.
+    ///
+    /// Failed fetches are logged and retried at the next interval. The task
+    /// stops when the returned handle is dropped or shut down, or when the
+    /// cache itself is dropped.
+    #[cfg(feature = "remote-jwks")]
+    pub fn spawn_refresher(self: &Arc<Self>, interval: Duration) -> TaskHandle {
+        let cache = Arc::downgrade(self);
+        TaskHandle::spawn(move |token| async move {
+            loop {
+                let Some(cache) = cache.upgrade() else {
+                    break;
+                };
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    result = cache.refresh() => {
+                        if let Err(e) = result {
+                            tracing::warn!(error = %e, "background JWKS refresh failed");
+                        }
+                    }
+                }
+                drop(cache);
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = tokio::time::sleep(interval) => {}
+                }
+            }
+        })
+    }
+}
+
+impl From<StaticJwksSource> for JwksCache {
+    fn from(source: StaticJwksSource) -> Self {
+        // The TTL is irrelevant: a static source is never refreshed.
+        Self::with_source(JwksSource::Static(source), Duration::MAX)
+    }
 }
 
 /// A builder for configuring offline JWT validation.
@@ -109,6 +556,14 @@ pub struct ValidationConfig {
     pub issuer: Option<String>,
     pub audience: Option<String>,
     pub algorithms: Vec<Algorithm>,
+    /// Set with [`ValidationConfigBuilder::max_token_size`].
+    max_token_size: usize,
+    /// Set with [`ValidationConfigBuilder::max_jwks_size`].
+    max_jwks_size: usize,
+    #[cfg(feature = "remote-jwks")]
+    pub timeout: Duration,
+    pub required_claims: Vec<String>,
+    pub max_future_iat: Option<Duration>,
 }
 
 impl ValidationConfig {
@@ -116,6 +571,16 @@ impl ValidationConfig {
     pub fn builder() -> ValidationConfigBuilder {
         ValidationConfigBuilder::default()
     }
+
+    /// The maximum accepted token size in bytes.
+    pub fn max_token_size(&self) -> usize {
+        self.max_token_size
+    }
+
+    /// The maximum accepted JWKS response size in bytes.
+    pub fn max_jwks_size(&self) -> usize {
+        self.max_jwks_size
+    }
 }
 
 /// A builder for configuring JWT validation.
@@ -126,6 +591,12 @@ pub struct ValidationConfigBuilder {
     issuer: Option<String>,
     audience: Option<String>,
     algorithms: Vec<Algorithm>,
+    max_token_size: Option<usize>,
+    max_jwks_size: Option<usize>,
+    #[cfg(feature = "remote-jwks")]
+    timeout: Option<Duration>,
+    required_claims: Vec<String>,
+    max_future_iat: Option<Duration>,
 }
 
 impl ValidationConfigBuilder {
@@ -159,6 +630,44 @@ impl ValidationConfigBuilder {
         self
     }
 
+    /// Set the maximum accepted token size in bytes.
+    pub fn max_token_size(mut self, max_bytes: usize) -> Self {
+        self.max_token_size = Some(max_bytes);
+        self
+    }
+
+    /// Set the maximum accepted JWKS response size in bytes.
+    pub fn max_jwks_size(mut self, max_bytes: usize) -> Self {
+        self.max_jwks_size = Some(max_bytes);
+        self
+    }
+
+    /// Set the timeout for JWKS requests.
+    #[cfg(feature = "remote-jwks")]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Require claims to be present in the token, e.g. `sub` or `azp`.
+    ///
+    /// `Validation` only checks most claims if they are present; tokens
+    /// missing any of these are rejected instead. See [`check_required_claims`].
+    pub fn required_claims(mut self, claims: Vec<String>) -> Self {
+        self.required_claims = claims;
+        self
+    }
+
+    /// Reject tokens whose `iat` is more than `max_skew` in the future, which
+    /// points to a misconfigured or malicious issuer. Off by default.
+    ///
+    /// Tokens without `iat` pass unless it is also a
+    /// [required claim](Self::required_claims). See [`check_future_iat`].
+    pub fn max_future_iat(mut self, max_skew: Duration) -> Self {
+        self.max_future_iat = Some(max_skew);
+        self
+    }
+
     /// Build a `ValidationConfig`.
     pub fn build(self) -> ValidationConfig {
         ValidationConfig {
@@ -175,7 +684,141 @@ impl ValidationConfigBuilder {
             } else {
                 self.algorithms
             },
+            max_token_size: self.max_token_size.unwrap_or(DEFAULT_MAX_TOKEN_SIZE),
+            max_jwks_size: self.max_jwks_size.unwrap_or(DEFAULT_MAX_JWKS_SIZE),
+            #[cfg(feature = "remote-jwks")]
+            timeout: self.timeout.unwrap_or(http_client::DEFAULT_TIMEOUT),
+            required_claims: self.required_claims,
+            max_future_iat: self.max_future_iat,
+        }
+    }
+}
+
+/// Builds the `Validation` for a config's algorithms, issuer and audience.
+///
+/// Fails if `algorithms` is empty, which the builder prevents but a config
+/// built by hand may not.
+impl TryFrom<&ValidationConfig> for Validation {
+    type Error = ValidationError;
+
+    fn try_from(config: &ValidationConfig) -> Result<Self, Self::Error> {
+        let first = *config.algorithms.first().ok_or_else(|| {
+            ValidationError::Validation("No signing algorithms configured".to_string())
+        })?;
+        let mut validation = Validation::new(first);
+        validation.algorithms = config.algorithms.clone();
+
+        if let Some(iss) = &config.issuer {
+            validation.set_issuer(&[iss]);
+        }
+
+        if let Some(aud) = &config.audience {
+            validation.set_audience(&[aud]);
+        }
+
+        Ok(validation)
+    }
+}
+
+impl TryFrom<ValidationConfig> for Validation {
+    type Error = ValidationError;
+
+    fn try_from(config: ValidationConfig) -> Result<Self, Self::Error> {
+        Validation::try_from(&config)
+    }
+}
+
+/// Asymmetric algorithms accepted for tokens validated against a discovered
+/// JWKS, including OIDC ID tokens. Symmetric (`HS*`) algorithms are excluded
+/// to prevent key-confusion attacks.
+pub const ASYMMETRIC_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+];
+
+/// The `JwksCache` and `Validation` needed to validate tokens from a single issuer.
+///
+/// Both fields can be placed directly in framework state, e.g. for the `Jwt<T>`
+/// extractor in `authkestra-axum`.
+#[derive(Clone)]
+pub struct ResourceServer {
+    pub jwks_cache: Arc<JwksCache>,
+    pub validation: Validation,
+}
+
+impl ResourceServer {
+    /// Performs OIDC discovery against `issuer` and configures JWKS validation for it.
+    ///
+    /// The issuer claim is always checked. If `audience` is `None`, the `aud` claim
+    /// is not validated.
+    #[cfg(feature = "remote-jwks")]
+    pub async fn from_issuer(
+        issuer: &str,
+        audience: Option<&str>,
+    ) -> Result<Self, ValidationError> {
+        let (metadata, cache_max_age) = ProviderMetadata::discover(issuer, HttpExecutor::default())
+            .await
+            .map_err(|e| match e {
+                AuthError::Timeout => ValidationError::Timeout,
+                e => e.into(),
+            })?;
+
+        let algorithms: Vec<Algorithm> = match &metadata.id_token_signing_alg_values_supported {
+            Some(supported) => ASYMMETRIC_ALGORITHMS
+                .iter()
+                .copied()
+                .filter(|alg| {
+                    supported
+                        .iter()
+                        .any(|s| s.parse::<Algorithm>().is_ok_and(|s| s == *alg))
+                })
+                .collect(),
+            None => vec![Algorithm::RS256],
+        };
+        if algorithms.is_empty() {
+            return Err(ValidationError::Validation(
+                "Issuer advertises no supported asymmetric signing algorithms".to_string(),
+            ));
+        }
+
+        let mut validation = Validation::new(algorithms[0]);
+        validation.algorithms = algorithms;
+        validation.set_issuer(&[&metadata.issuer]);
+        match audience {
+            Some(aud) => validation.set_audience(&[aud]),
+            None => validation.validate_aud = false,
         }
+
+        let jwks_cache = Arc::new(JwksCache::new(
+            metadata.jwks_uri,
+            cache_max_age.unwrap_or(Duration::from_secs(3600)),
+        ));
+
+        Ok(Self {
+            jwks_cache,
+            validation,
+        })
+    }
+
+    /// Accept tokens signed with any of `algorithms`, replacing the ones
+    /// advertised by the issuer, e.g. both `RS256` and `ES256` while the issuer
+    /// migrates its signing key.
+    ///
+    /// Each token is checked against the key of the matching type in the JWKS.
+    pub fn with_algorithms(mut self, algorithms: Vec<Algorithm>) -> Self {
+        self.validation.algorithms = algorithms;
+        self
+    }
+
+    /// Splits into the `(JwksCache, Validation)` pair.
+    pub fn into_parts(self) -> (Arc<JwksCache>, Validation) {
+        (self.jwks_cache, self.validation)
     }
 }
 
@@ -183,29 +826,59 @@ impl ValidationConfigBuilder {
 pub struct JwtStrategy<I> {
     cache: JwksCache,
     validation: Validation,
+    required_claims: Vec<String>,
+    max_future_iat: Option<Duration>,
+    sources: Vec<TokenSource>,
     _marker: std::marker::PhantomData<I>,
 }
 
 impl<I> JwtStrategy<I> {
     /// Create a new `JwtStrategy` with the given `ValidationConfig`.
-    pub fn new(config: ValidationConfig) -> Self {
-        let cache = JwksCache::new(config.jwks_url, config.refresh_interval);
-        let mut validation = Validation::new(config.algorithms[0]);
-        validation.algorithms = config.algorithms;
-
-        if let Some(iss) = config.issuer {
-            validation.set_issuer(&[iss]);
-        }
-
-        if let Some(aud) = config.audience {
-            validation.set_audience(&[aud]);
-        }
+    ///
+    /// Tokens are read from the `Authorization` header only. Fails if the
+    /// config has no algorithms.
+    #[cfg(feature = "remote-jwks")]
+    pub fn new(config: ValidationConfig) -> Result<Self, ValidationError> {
+        let validation = Validation::try_from(&config)?;
+        let cache = JwksCache::new(config.jwks_url, config.refresh_interval)
+            .with_max_token_size(config.max_token_size)
+            .with_max_jwks_size(config.max_jwks_size)
+            .with_timeout(config.timeout);
 
-        Self {
+        Ok(Self {
             cache,
             validation,
+            required_claims: config.required_claims,
+            max_future_iat: config.max_future_iat,
+            sources: vec![TokenSource::Header],
             _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// Set the sources the token is read from, in order of precedence.
+    ///
+    /// See [`TokenSource`] for the risks of accepting tokens in the query string.
+    pub fn with_sources(mut self, sources: Vec<TokenSource>) -> Self {
+        self.sources = sources;
+        self
+    }
+}
+
+impl<I> JwtStrategy<I>
+where
+    I: for<'de> Deserialize<'de>,
+{
+    async fn validate(&self, token: &str) -> Result<I, ValidationError> {
+        if self.required_claims.is_empty() && self.max_future_iat.is_none() {
+            return validate_jwt_generic::<I>(token, &self.cache, &self.validation).await;
         }
+        let claims =
+            validate_jwt_generic::<serde_json::Value>(token, &self.cache, &self.validation).await?;
+        check_required_claims(&claims, &self.required_claims)?;
+        if let Some(max_skew) = self.max_future_iat {
+            check_future_iat(&claims, max_skew)?;
+        }
+        serde_json::from_value(claims).map_err(|e| ValidationError::Jwt(e.into()))
     }
 }
 
@@ -215,11 +888,11 @@ where
     I: for<'de> Deserialize<'de> + Send + Sync + 'static,
 {
     async fn authenticate(&self, parts: &Parts) -> Result<Option<I>, AuthError> {
-        if let Some(token) = utils::extract_bearer_token(&parts.headers) {
-            match validate_jwt_generic::<I>(token, &self.cache, &self.validation).await {
+        if let Some(token) = utils::extract_token(parts, &self.sources) {
+            match self.validate(&token).await.map_err(AuthError::from) {
                 Ok(claims) => Ok(Some(claims)),
-                Err(ValidationError::InvalidToken(_)) | Err(ValidationError::Jwt(_)) => Ok(None),
-                Err(e) => Err(AuthError::Token(e.to_string())),
+                Err(AuthError::InvalidToken(_)) => Ok(None),
+                Err(e) => Err(e),
             }
         } else {
             Ok(None)
@@ -245,20 +918,101 @@ pub async fn validate_jwt_generic<T>(
 where
     T: for<'de> Deserialize<'de>,
 {
-    let header = decode_header(token)?;
+    if token.len() > cache.max_token_size() {
+        return Err(ValidationError::TokenTooLarge {
+            max: cache.max_token_size(),
+        });
+    }
+
+    let header = accepted_header(token, validation)?;
     let kid = header.kid.as_deref();
 
     let jwk = cache
-        .get_key(kid)
+        .get_key_for(kid, header.alg)
         .await?
         .ok_or(ValidationError::KeyNotFound)?;
 
     let decoding_key = jwk.to_decoding_key()?;
-    let token_data = decode::<T>(token, &decoding_key, validation)?;
+    let token_data = decode::<T>(token, &decoding_key, &for_algorithm(validation, header.alg))?;
 
     Ok(token_data.claims)
 }
 
+/// Checks that each of `required` is present, and not null, in decoded `claims`.
+///
+/// Fails with [`ValidationError::Validation`] listing the missing claims.
+pub fn check_required_claims(
+    claims: &serde_json::Value,
+    required: &[String],
+) -> Result<(), ValidationError> {
+    let missing: Vec<&str> = required
+        .iter()
+        .filter(|name| claims.get(name.as_str()).is_none_or(|v| v.is_null()))
+        .map(String::as_str)
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError::Validation(format!(
+            "Missing required claims: {}",
+            missing.join(", ")
+        )))
+    }
+}
+
+/// Checks that the `iat` of decoded `claims`, if any, is at most `max_skew`
+/// in the future.
+///
+/// Fails with [`ValidationError::Validation`] for a future or non-numeric `iat`.
+pub fn check_future_iat(
+    claims: &serde_json::Value,
+    max_skew: Duration,
+) -> Result<(), ValidationError> {
+    let Some(iat) = claims.get("iat") else {
+        return Ok(());
+    };
+    let iat = iat
+        .as_f64()
+        .ok_or_else(|| ValidationError::Validation("Invalid iat claim".to_string()))?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    if iat > (now + max_skew).as_secs_f64() {
+        return Err(ValidationError::Validation(
+            "Token issued in the future".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Decodes the header of `token`, rejecting algorithms `validation` does not
+/// accept before any key is looked up.
+fn accepted_header(token: &str, validation: &Validation) -> Result<Header, ValidationError> {
+    let header = decode_header(token)?;
+    if !validation.algorithms.contains(&header.alg) {
+        return Err(jsonwebtoken::errors::Error::from(
+            jsonwebtoken::errors::ErrorKind::InvalidAlgorithm,
+        )
+        .into());
+    }
+    Ok(header)
+}
+
+/// `validation` narrowed to `alg`, the algorithm of a token it accepts.
+///
+/// jsonwebtoken rejects a key unless every algorithm in the validation is of the
+/// key's family, so a set mixing e.g. `RS256` and `ES256` must be narrowed to
+/// the token's own algorithm before decoding.
+fn for_algorithm(validation: &Validation, alg: Algorithm) -> Cow<'_, Validation> {
+    if validation.algorithms == [alg] {
+        Cow::Borrowed(validation)
+    } else {
+        let mut narrowed = validation.clone();
+        narrowed.algorithms = vec![alg];
+        Cow::Owned(narrowed)
+    }
+}
+
 /// Validates a PASETO V4 Local/Public token.
 /// Note: This implementation assumes V4 Public for parity with JWKS-like usage if applicable,
 /// but PASETO usually handles its own keying. This is a placeholder for the requested logic.
@@ -269,3 +1023,223 @@ pub async fn validate_paseto(_token: &str, _key: &[u8]) -> Result<Claims, Valida
         "PASETO validation not yet fully implemented with JWKS".to_string(),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "remote-jwks")]
+    use wiremock::matchers::{method, path};
+    #[cfg(feature = "remote-jwks")]
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Signs the token for the offline test.
+    #[cfg(not(feature = "remote-jwks"))]
+    const RSA_PEM: &[u8] = include_bytes!("../tests/fixtures/rsa_2048.pem");
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_oversized_token_rejected_before_parsing() {
+        // The JWKS URI is never contacted: the size check runs first.
+        let cache = JwksCache::new(
+            "http://127.0.0.1:9/jwks".to_string(),
+            Duration::from_secs(60),
+        );
+        let token = "a".repeat(1024 * 1024);
+
+        let result =
+            validate_jwt_generic::<Claims>(&token, &cache, &Validation::new(Algorithm::RS256))
+                .await;
+
+        assert!(matches!(
+            result,
+            Err(ValidationError::TokenTooLarge {
+                max: DEFAULT_MAX_TOKEN_SIZE
+            })
+        ));
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_oversized_jwks_rejected() {
+        let server = MockServer::start().await;
+        let padding = "x".repeat(4096);
+        Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(format!(r#"{{"keys":[],"padding":"{padding}"}}"#)),
+            )
+            .mount(&server)
+            .await;
+
+        let cache = JwksCache::new(format!("{}/jwks", server.uri()), Duration::from_secs(60))
+            .with_max_jwks_size(1024);
+
+        let result = cache.get_key(None).await;
+        assert!(matches!(
+            result,
+            Err(ValidationError::JwksTooLarge { max: 1024 })
+        ));
+
+        let jwks = Jwks::fetch(&format!("{}/jwks", server.uri()))
+            .await
+            .unwrap();
+        assert!(jwks.keys.is_empty());
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_slow_jwks_endpoint_times_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"keys":[]}"#)
+                    .set_delay(Duration::from_secs(5)),
+            )
+            .mount(&server)
+            .await;
+
+        let cache = JwksCache::new(format!("{}/jwks", server.uri()), Duration::from_secs(60))
+            .with_timeout(Duration::from_millis(200));
+
+        let result = cache.get_key(None).await;
+        assert!(matches!(result, Err(ValidationError::Timeout)));
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_refresher_stops_after_shutdown() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"keys":[]}"#))
+            .mount(&server)
+            .await;
+        let fetches = || async { server.received_requests().await.unwrap().len() };
+
+        let cache = Arc::new(JwksCache::new(
+            format!("{}/jwks", server.uri()),
+            Duration::from_secs(60),
+        ));
+        let refresher = cache.spawn_refresher(Duration::from_millis(20));
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(fetches().await >= 2);
+
+        refresher.shutdown().await;
+        let after_shutdown = fetches().await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(fetches().await, after_shutdown);
+    }
+
+    #[test]
+    fn test_empty_algorithms_is_an_error() {
+        let mut config = ValidationConfig::builder()
+            .jwks_url("https://example.com/jwks")
+            .issuer("https://example.com")
+            .build();
+        let validation = Validation::try_from(&config).unwrap();
+        assert_eq!(validation.algorithms, vec![Algorithm::RS256]);
+
+        config.algorithms.clear();
+        assert!(matches!(
+            Validation::try_from(&config),
+            Err(ValidationError::Validation(_))
+        ));
+        #[cfg(feature = "remote-jwks")]
+        assert!(matches!(
+            JwtStrategy::<Claims>::new(config),
+            Err(ValidationError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_error_classification() {
+        let invalid = [
+            decode_header("garbage").unwrap_err().into(),
+            ValidationError::InvalidToken("bad".to_string()),
+            ValidationError::KeyNotFound,
+            ValidationError::Paseto("bad".to_string()),
+            ValidationError::TokenTooLarge { max: 16 },
+        ];
+        for err in invalid {
+            assert!(!err.is_retryable(), "{err}");
+            assert!(
+                matches!(AuthError::from(err), AuthError::InvalidToken(_)),
+                "token errors are invalid tokens"
+            );
+        }
+
+        let rejected = ValidationError::Validation("missing claims".to_string());
+        assert!(!rejected.is_retryable());
+        assert!(matches!(AuthError::from(rejected), AuthError::Token(_)));
+
+        // Nothing listens on port 9.
+        #[cfg(feature = "remote-jwks")]
+        {
+            let http = Jwks::fetch("http://127.0.0.1:9/jwks").await.unwrap_err();
+            assert!(matches!(http, ValidationError::Http(_)));
+            assert!(http.is_retryable());
+            assert!(matches!(AuthError::from(http), AuthError::Network));
+        }
+
+        let timeout = ValidationError::Timeout;
+        assert!(timeout.is_retryable());
+        assert!(matches!(AuthError::from(timeout), AuthError::Timeout));
+
+        let network = ValidationError::Discovery(AuthError::Network);
+        assert!(network.is_retryable());
+        assert!(matches!(AuthError::from(network), AuthError::Network));
+        let discovery = ValidationError::Discovery(AuthError::Discovery("404".to_string()));
+        assert!(!discovery.is_retryable());
+        assert!(matches!(
+            AuthError::from(discovery),
+            AuthError::Discovery(_)
+        ));
+
+        let unusable = [
+            serde_json::from_str::<Jwks>("{").unwrap_err().into(),
+            ValidationError::JwksTooLarge { max: 16 },
+        ];
+        for err in unusable {
+            assert!(!err.is_retryable(), "{err}");
+            assert!(matches!(AuthError::from(err), AuthError::Provider(_)));
+        }
+    }
+
+    #[cfg(not(feature = "remote-jwks"))]
+    #[tokio::test]
+    async fn test_static_cache_validates_without_remote_jwks() {
+        use authkestra_engine::{state::Identity, TokenManager};
+
+        let manager = TokenManager::new_asymmetric(
+            RSA_PEM,
+            Some("https://issuer.example".to_string()),
+            Some("kid-1".to_string()),
+        )
+        .unwrap();
+        let cache = JwksCache::from_static(Jwks {
+            keys: vec![manager.public_jwk().unwrap()],
+        });
+        let identity = Identity {
+            provider_id: "static".to_string(),
+            external_id: "user123".to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        };
+        let token = manager.issue_user_token(identity, 300, None, None).unwrap();
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&["https://issuer.example"]);
+        validation.validate_aud = false;
+        let claims = validate_jwt_generic::<Claims>(&token, &cache, &validation)
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "user123");
+        assert_eq!(cache.refresh().await.unwrap().keys.len(), 1);
+    }
+}
@@ -1,8 +1,15 @@
-use authkestra_engine::error::AuthError;
+use authkestra_engine::error::{AuthError, ErrorLevels};
 use authkestra_engine::strategy::AuthenticationStrategy;
 use http::request::Parts;
+use std::time::Duration;
 
+pub mod authz;
 pub mod jwt;
+pub mod scope;
+pub mod tenant;
+pub use authz::{AuthorizationEngine, LocalRules};
+pub use scope::{RequireScopes, ScopeRequirement};
+pub use tenant::{check_tenant, PathTenant, TenantBinding, TenantSource};
 
 /// Policy for controlling the behavior of chained authentication strategies.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -13,16 +20,76 @@ pub enum AuthPolicy {
     #[default]
     FirstSuccess,
     /// All strategies must succeed. If any fails or returns `None`, the whole chain fails.
-    /// Returns the identity from the last strategy.
+    /// Returns the identity from the last strategy, or the identities combined by
+    /// [`GuardBuilder::merge_identities`].
     AllSuccess,
     /// If the first strategy fails or returns `None`, stop immediately.
     FailFast,
 }
 
+/// The outcome of an authorization [`Policy`] or [`AuthorizationEngine`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    /// Deny access, with a reason shown to the client.
+    Deny(String),
+}
+
+/// Decides whether an authenticated identity may access a resource.
+///
+/// Policies run after authentication, so the framework extractors can tell
+/// "not logged in" (`401`) from "not allowed" (`403`):
+///
+/// ```rust,ignore
+/// struct AdminOnly;
+///
+/// impl Policy<User> for AdminOnly {
+///     fn evaluate(user: &User) -> Decision {
+///         if user.roles.iter().any(|r| r == "admin") {
+///             Decision::Allow
+///         } else {
+///             Decision::Deny("admin role required".to_string())
+///         }
+///     }
+/// }
+///
+/// async fn handler(Authz(user, _): Authz<User, AdminOnly>) { /* ... */ }
+/// ```
+pub trait Policy<I>: Send + Sync + 'static {
+    /// Allow or deny `identity`.
+    fn evaluate(identity: &I) -> Decision;
+}
+
 /// A service that orchestrates multiple authentication strategies.
+///
+/// A `Guard` is itself an [`AuthenticationStrategy`], so guards nest to compose
+/// policies, e.g. "session OR (api key AND scope check)":
+///
+/// ```rust,ignore
+/// let api_key_with_scope = Guard::builder()
+///     .strategy(api_key)
+///     .strategy(scope_check)
+///     .policy(AuthPolicy::AllSuccess)
+///     .build();
+/// let guard = Guard::builder()
+///     .strategy(session)
+///     .strategy(api_key_with_scope)
+///     .policy(AuthPolicy::FirstSuccess)
+///     .build();
+/// ```
+///
+/// A nested guard's result is handled by the outer policy like any other
+/// strategy's. In particular, a nested guard returns `Ok(None)` when its own
+/// policy finds no identity (under `AllSuccess`, as soon as one of its
+/// strategies does), so an outer `FirstSuccess` moves on to the next strategy
+/// and an outer `AllSuccess` yields `Ok(None)`. Errors, including timeouts,
+/// propagate to the outer guard.
 pub struct Guard<I> {
     strategies: Vec<Box<dyn AuthenticationStrategy<I>>>,
     policy: AuthPolicy,
+    strategy_timeout: Option<Duration>,
+    merge: Option<fn(Vec<I>) -> I>,
+    error_levels: ErrorLevels,
 }
 
 impl<I> Guard<I> {
@@ -31,12 +98,17 @@ impl<I> Guard<I> {
         GuardBuilder::default()
     }
 
+    /// The levels the framework extractors log this guard's errors at.
+    pub fn error_levels(&self) -> &ErrorLevels {
+        &self.error_levels
+    }
+
     /// Attempt to authenticate the request using the configured strategies and policy.
     pub async fn authenticate(&self, parts: &Parts) -> Result<Option<I>, AuthError> {
         match self.policy {
             AuthPolicy::FirstSuccess => {
                 for strategy in &self.strategies {
-                    match strategy.authenticate(parts).await {
+                    match self.run(strategy.as_ref(), parts).await {
                         Ok(Some(identity)) => return Ok(Some(identity)),
                         Ok(None) => continue,
                         Err(e) => return Err(e),
@@ -45,31 +117,62 @@ impl<I> Guard<I> {
                 Ok(None)
             }
             AuthPolicy::AllSuccess => {
-                let mut last_identity = None;
+                let mut identities = Vec::with_capacity(self.strategies.len());
                 for strategy in &self.strategies {
-                    match strategy.authenticate(parts).await {
-                        Ok(Some(identity)) => last_identity = Some(identity),
+                    match self.run(strategy.as_ref(), parts).await {
+                        Ok(Some(identity)) => identities.push(identity),
                         Ok(None) => return Ok(None),
                         Err(e) => return Err(e),
                     }
                 }
-                Ok(last_identity)
+                match self.merge {
+                    Some(merge) if !identities.is_empty() => Ok(Some(merge(identities))),
+                    _ => Ok(identities.pop()),
+                }
             }
             AuthPolicy::FailFast => {
                 if let Some(strategy) = self.strategies.first() {
-                    strategy.authenticate(parts).await
+                    self.run(strategy.as_ref(), parts).await
                 } else {
                     Ok(None)
                 }
             }
         }
     }
+
+    /// Runs one strategy, bounded by the strategy timeout if set.
+    async fn run(
+        &self,
+        strategy: &dyn AuthenticationStrategy<I>,
+        parts: &Parts,
+    ) -> Result<Option<I>, AuthError> {
+        match self.strategy_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, strategy.authenticate(parts))
+                .await
+                .map_err(|_| AuthError::Timeout)?,
+            None => strategy.authenticate(parts).await,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<I> AuthenticationStrategy<I> for Guard<I>
+where
+    I: Send + Sync + 'static,
+{
+    async fn authenticate(&self, parts: &Parts) -> Result<Option<I>, AuthError> {
+        Guard::authenticate(self, parts).await
+    }
 }
 
 /// Builder for the `Guard`.
 pub struct GuardBuilder<I> {
     strategies: Vec<Box<dyn AuthenticationStrategy<I>>>,
     policy: AuthPolicy,
+    strategy_timeout: Option<Duration>,
+    merge: Option<fn(Vec<I>) -> I>,
+    allow_empty: bool,
+    error_levels: ErrorLevels,
 }
 
 impl<I> Default for GuardBuilder<I> {
@@ -77,6 +180,10 @@ impl<I> Default for GuardBuilder<I> {
         Self {
             strategies: Vec::new(),
             policy: AuthPolicy::default(),
+            strategy_timeout: None,
+            merge: None,
+            allow_empty: false,
+            error_levels: ErrorLevels::default(),
         }
     }
 }
@@ -100,11 +207,271 @@ where
         self
     }
 
+    /// Bound each strategy's `authenticate` call by `timeout`.
+    ///
+    /// An elapsed timeout is an `AuthError::Timeout` from that strategy, handled
+    /// like any other strategy error by the policy. No timeout by default.
+    pub fn strategy_timeout(mut self, timeout: Duration) -> Self {
+        self.strategy_timeout = Some(timeout);
+        self
+    }
+
+    /// Combine the identities of all strategies under [`AuthPolicy::AllSuccess`],
+    /// in strategy order, e.g. a user identity and the attributes of an MFA check.
+    ///
+    /// Without it the last strategy's identity is returned.
+    pub fn merge_identities(mut self, merge: fn(Vec<I>) -> I) -> Self {
+        self.merge = Some(merge);
+        self
+    }
+
+    /// Set the levels the framework extractors log this guard's errors at,
+    /// e.g. the `error_levels` of the app's `Engine`. Every kind keeps its
+    /// default level unless overridden.
+    pub fn error_levels(mut self, levels: ErrorLevels) -> Self {
+        self.error_levels = levels;
+        self
+    }
+
+    /// Accept a chain without strategies, which authenticates no request.
+    ///
+    /// Without it, [`GuardBuilder::try_build`] rejects an empty chain and
+    /// [`GuardBuilder::build`] logs a warning.
+    pub fn allow_empty(mut self) -> Self {
+        self.allow_empty = true;
+        self
+    }
+
+    /// Build the `Guard`, failing with [`AuthError::ComponentMissing`] if no
+    /// strategy was added and [`GuardBuilder::allow_empty`] was not set.
+    pub fn try_build(self) -> Result<Guard<I>, AuthError> {
+        if self.strategies.is_empty() && !self.allow_empty {
+            return Err(AuthError::ComponentMissing(
+                "Guard has no authentication strategies".to_string(),
+            ));
+        }
+        Ok(self.build_unchecked())
+    }
+
     /// Build the `Guard`.
+    ///
+    /// A guard without strategies returns `Ok(None)` for every request under
+    /// every policy, which is almost always a misconfiguration, so it is logged
+    /// as a warning unless [`GuardBuilder::allow_empty`] was set. Use
+    /// [`GuardBuilder::try_build`] to reject it instead.
     pub fn build(self) -> Guard<I> {
+        if self.strategies.is_empty() && !self.allow_empty {
+            tracing::warn!("Guard built without authentication strategies; every request will be unauthenticated");
+        }
+        self.build_unchecked()
+    }
+
+    fn build_unchecked(self) -> Guard<I> {
         Guard {
             strategies: self.strategies,
             policy: self.policy,
+            strategy_timeout: self.strategy_timeout,
+            merge: self.merge,
+            error_levels: self.error_levels,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    /// Resolves to `identity` after `delay`.
+    struct Delayed {
+        delay: Duration,
+        identity: &'static str,
+    }
+
+    #[async_trait]
+    impl AuthenticationStrategy<String> for Delayed {
+        async fn authenticate(&self, _parts: &Parts) -> Result<Option<String>, AuthError> {
+            tokio::time::sleep(self.delay).await;
+            Ok(Some(self.identity.to_string()))
+        }
+    }
+
+    fn parts() -> Parts {
+        http::Request::new(()).into_parts().0
+    }
+
+    /// Authenticates as `identity` when `header` is present and contains `value`.
+    struct HeaderStrategy {
+        header: &'static str,
+        value: &'static str,
+        identity: &'static str,
+    }
+
+    #[async_trait]
+    impl AuthenticationStrategy<String> for HeaderStrategy {
+        async fn authenticate(&self, parts: &Parts) -> Result<Option<String>, AuthError> {
+            Ok(parts
+                .headers
+                .get(self.header)
+                .and_then(|v| v.to_str().ok())
+                .filter(|v| v.split_whitespace().any(|v| v == self.value))
+                .map(|_| self.identity.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_all_success_merges_identities() {
+        let strategies = || {
+            Guard::builder()
+                .strategy(Delayed {
+                    delay: Duration::ZERO,
+                    identity: "alice",
+                })
+                .strategy(Delayed {
+                    delay: Duration::ZERO,
+                    identity: "mfa=totp",
+                })
+                .policy(AuthPolicy::AllSuccess)
+        };
+
+        let guard = strategies().build();
+        assert_eq!(
+            guard.authenticate(&parts()).await.unwrap().as_deref(),
+            Some("mfa=totp")
+        );
+
+        let guard = strategies()
+            .merge_identities(|identities| identities.join(";"))
+            .build();
+        assert_eq!(
+            guard.authenticate(&parts()).await.unwrap().as_deref(),
+            Some("alice;mfa=totp")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nested_guards_compose_policies() {
+        // session OR (api key AND scope check)
+        let api_key_with_scope = Guard::builder()
+            .strategy(HeaderStrategy {
+                header: "x-api-key",
+                value: "key-1",
+                identity: "api-client",
+            })
+            .strategy(HeaderStrategy {
+                header: "x-scopes",
+                value: "admin",
+                identity: "api-client",
+            })
+            .policy(AuthPolicy::AllSuccess)
+            .build();
+        let guard = Guard::builder()
+            .strategy(HeaderStrategy {
+                header: "cookie",
+                value: "session=abc",
+                identity: "session-user",
+            })
+            .strategy(api_key_with_scope)
+            .policy(AuthPolicy::FirstSuccess)
+            .build();
+
+        let request = |headers: &[(&str, &str)]| {
+            let mut builder = http::Request::builder();
+            for (name, value) in headers {
+                builder = builder.header(*name, *value);
+            }
+            builder.body(()).unwrap().into_parts().0
+        };
+
+        type Headers = &'static [(&'static str, &'static str)];
+        let cases: [(Headers, Option<&str>); 5] = [
+            (&[("cookie", "session=abc")], Some("session-user")),
+            (
+                &[("x-api-key", "key-1"), ("x-scopes", "read admin")],
+                Some("api-client"),
+            ),
+            // The nested AllSuccess guard returns `Ok(None)` when the scope is missing.
+            (&[("x-api-key", "key-1"), ("x-scopes", "read")], None),
+            (&[("x-api-key", "key-1")], None),
+            (&[("x-scopes", "admin")], None),
+        ];
+        for (headers, expected) in cases {
+            assert_eq!(
+                guard
+                    .authenticate(&request(headers))
+                    .await
+                    .unwrap()
+                    .as_deref(),
+                expected,
+                "{headers:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_strategy_times_out() {
+        let guard = Guard::builder()
+            .strategy(Delayed {
+                delay: Duration::from_secs(5),
+                identity: "slow",
+            })
+            .strategy(Delayed {
+                delay: Duration::ZERO,
+                identity: "fast",
+            })
+            .strategy_timeout(Duration::from_millis(50))
+            .build();
+
+        // Under FirstSuccess the timeout is an error and ends the chain.
+        assert!(matches!(
+            guard.authenticate(&parts()).await,
+            Err(AuthError::Timeout)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_no_timeout_by_default() {
+        let guard = Guard::builder()
+            .strategy(Delayed {
+                delay: Duration::from_millis(100),
+                identity: "slow",
+            })
+            .build();
+
+        assert_eq!(
+            guard.authenticate(&parts()).await.unwrap().as_deref(),
+            Some("slow")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_chain_is_a_misconfiguration() {
+        for policy in [
+            AuthPolicy::FirstSuccess,
+            AuthPolicy::AllSuccess,
+            AuthPolicy::FailFast,
+        ] {
+            let result = Guard::<String>::builder().policy(policy).try_build();
+            assert!(matches!(result, Err(AuthError::ComponentMissing(_))));
+
+            let guard = Guard::<String>::builder()
+                .policy(policy)
+                .allow_empty()
+                .try_build()
+                .unwrap();
+            assert_eq!(guard.authenticate(&parts()).await.unwrap(), None);
+        }
+
+        let guard = Guard::builder()
+            .strategy(Delayed {
+                delay: Duration::ZERO,
+                identity: "alice",
+            })
+            .try_build()
+            .unwrap();
+        assert_eq!(
+            guard.authenticate(&parts()).await.unwrap().as_deref(),
+            Some("alice")
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{Decision, Policy};
+use authkestra_engine::auth::{HasScopes, HasSubject};
+
+/// The scopes a route requires, for [`RequireScopes`].
+///
+/// ```rust,ignore
+/// struct ReadOrders;
+///
+/// impl ScopeRequirement for ReadOrders {
+///     const SCOPES: &'static [&'static str] = &["orders:read"];
+/// }
+/// ```
+pub trait ScopeRequirement: Send + Sync + 'static {
+    /// Scopes that must all be granted.
+    const SCOPES: &'static [&'static str];
+}
+
+/// A [`Policy`] allowing identities granted every scope of `R`.
+///
+/// Works with any identity implementing [`HasScopes`] and [`HasSubject`], such
+/// as the built-in [`Claims`](authkestra_engine::Claims) or a claim struct
+/// deriving `JwtClaims`:
+///
+/// ```rust,ignore
+/// async fn orders(Authz(claims, _): Authz<MyClaims, RequireScopes<ReadOrders>>) { /* ... */ }
+/// ```
+///
+/// Denials name the missing scopes and are reported as a security event: an
+/// `INFO` event with target `authkestra::security` and
+/// `event = "insufficient_scope"`, carrying the subject.
+pub struct RequireScopes<R>(std::marker::PhantomData<fn() -> R>);
+
+impl<I, R> Policy<I> for RequireScopes<R>
+where
+    I: HasScopes + HasSubject,
+    R: ScopeRequirement,
+{
+    fn evaluate(identity: &I) -> Decision {
+        let granted = identity.scopes();
+        let missing: Vec<&str> = R::SCOPES
+            .iter()
+            .copied()
+            .filter(|scope| !granted.contains(scope))
+            .collect();
+        if missing.is_empty() {
+            return Decision::Allow;
+        }
+
+        let missing = missing.join(" ");
+        tracing::info!(
+            target: "authkestra::security",
+            event = "insufficient_scope",
+            subject = %identity.subject(),
+            %missing,
+            "token lacks required scopes"
+        );
+        Decision::Deny(format!("missing scope: {missing}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use authkestra_engine::Claims;
+
+    struct ReadWrite;
+
+    impl ScopeRequirement for ReadWrite {
+        const SCOPES: &'static [&'static str] = &["read", "write"];
+    }
+
+    fn claims(scope: Option<&str>) -> Claims {
+        serde_json::from_value(serde_json::json!({
+            "sub": "alice",
+            "exp": 0,
+            "iat": 0,
+            "scope": scope,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_require_scopes() {
+        assert_eq!(
+            RequireScopes::<ReadWrite>::evaluate(&claims(Some("write admin read"))),
+            Decision::Allow
+        );
+        assert_eq!(
+            RequireScopes::<ReadWrite>::evaluate(&claims(Some("read"))),
+            Decision::Deny("missing scope: write".to_string())
+        );
+        assert_eq!(
+            RequireScopes::<ReadWrite>::evaluate(&claims(None)),
+            Decision::Deny("missing scope: read write".to_string())
+        );
+    }
+}
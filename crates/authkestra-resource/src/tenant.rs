@@ -0,0 +1,102 @@
+//! Binding an authenticated identity to the tenant a request addresses.
+//!
+//! In a multi-tenant API, a token that is valid for tenant A must not open
+//! tenant B's resources, even though its signature and issuer check out. The
+//! framework adapters run the check in their `TenantAuth` extractors, after
+//! normal authentication.
+
+use serde::Serialize;
+
+/// Where a request names the tenant it addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TenantSource {
+    /// A route path parameter, e.g. `tenant_id` in `/tenants/{tenant_id}/orders`.
+    Path(&'static str),
+    /// A request header, e.g. `X-Tenant-Id`.
+    Header(&'static str),
+}
+
+/// Names the identity claim holding the tenant id and where the request names
+/// its tenant.
+///
+/// ```rust,ignore
+/// struct OrgHeader;
+///
+/// impl TenantBinding for OrgHeader {
+///     const CLAIM: &'static str = "org";
+///     const SOURCE: TenantSource = TenantSource::Header("x-org-id");
+/// }
+///
+/// async fn handler(TenantAuth(user, _): TenantAuth<User, OrgHeader>) { /* ... */ }
+/// ```
+pub trait TenantBinding: Send + Sync + 'static {
+    /// The identity claim holding its tenant id.
+    const CLAIM: &'static str = "tenant_id";
+    /// Where the request names its tenant.
+    const SOURCE: TenantSource;
+}
+
+/// Binds the `tenant_id` claim to the `tenant_id` path parameter.
+pub struct PathTenant;
+
+impl TenantBinding for PathTenant {
+    const SOURCE: TenantSource = TenantSource::Path("tenant_id");
+}
+
+/// The tenant id held by `identity` in the claim named by `B`.
+///
+/// The identity is read through its `Serialize` implementation, so the claim
+/// may be a field or part of flattened extra claims. Numbers are accepted as
+/// well as strings.
+pub fn identity_tenant<B: TenantBinding>(identity: &impl Serialize) -> Option<String> {
+    match serde_json::to_value(identity).ok()?.get(B::CLAIM)? {
+        serde_json::Value::String(tenant) => Some(tenant.clone()),
+        serde_json::Value::Number(tenant) => Some(tenant.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether `identity` belongs to `tenant`, the tenant the request addresses.
+///
+/// A mismatch, or an identity without the claim, is reported as a security
+/// event: a `WARN` event with target `authkestra::security` and
+/// `event = "cross_tenant_request"`, carrying both tenant ids.
+pub fn check_tenant<B: TenantBinding>(identity: &impl Serialize, tenant: &str) -> bool {
+    let identity_tenant = identity_tenant::<B>(identity);
+    if identity_tenant.as_deref() == Some(tenant) {
+        return true;
+    }
+    tracing::warn!(
+        target: "authkestra::security",
+        event = "cross_tenant_request",
+        claim = B::CLAIM,
+        identity_tenant = identity_tenant.as_deref().unwrap_or_default(),
+        requested_tenant = %tenant,
+        "identity does not belong to the requested tenant"
+    );
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct OrgHeader;
+
+    impl TenantBinding for OrgHeader {
+        const CLAIM: &'static str = "org";
+        const SOURCE: TenantSource = TenantSource::Header("x-org-id");
+    }
+
+    #[test]
+    fn test_check_tenant() {
+        let identity = serde_json::json!({ "sub": "alice", "tenant_id": "acme", "org": 42 });
+        assert!(check_tenant::<PathTenant>(&identity, "acme"));
+        assert!(!check_tenant::<PathTenant>(&identity, "globex"));
+        assert!(check_tenant::<OrgHeader>(&identity, "42"));
+        assert!(!check_tenant::<OrgHeader>(&identity, "acme"));
+
+        let untenanted = serde_json::json!({ "sub": "alice" });
+        assert!(!check_tenant::<PathTenant>(&untenanted, ""));
+    }
+}
@@ -0,0 +1,73 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use authkestra_engine::{state::Identity, TokenManager};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use std::collections::HashMap;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// The `iss` of tokens signed by [`signer`].
+pub const ISSUER: &str = "https://issuer.example";
+
+/// A 2048-bit RSA key for signing test tokens.
+pub const RSA_PEM: &[u8] = include_bytes!("../fixtures/rsa_2048.pem");
+
+/// A user of provider `idp`.
+pub fn identity(external_id: &str) -> Identity {
+    Identity {
+        provider_id: "idp".to_string(),
+        external_id: external_id.to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+/// Signs tokens for [`ISSUER`] with [`RSA_PEM`] under `kid`.
+pub fn signer_with_kid(kid: &str) -> TokenManager {
+    TokenManager::new_asymmetric(RSA_PEM, Some(ISSUER.to_string()), Some(kid.to_string())).unwrap()
+}
+
+/// Signs tokens for [`ISSUER`] with [`RSA_PEM`] under kid `kid-1`.
+pub fn signer() -> TokenManager {
+    signer_with_kid("kid-1")
+}
+
+/// An RS256 token with kid `kid-1` carrying exactly `claims`.
+pub fn sign(claims: &serde_json::Value) -> String {
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some("kid-1".to_string());
+    jsonwebtoken::encode(
+        &header,
+        claims,
+        &EncodingKey::from_rsa_pem(RSA_PEM).unwrap(),
+    )
+    .unwrap()
+}
+
+/// Serves the public key of [`signer`] at `/jwks`.
+pub async fn mount_jwks(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "keys": [signer().public_jwk().unwrap()] })),
+        )
+        .mount(server)
+        .await;
+}
+
+/// The parts of a request carrying `token` as a bearer token.
+pub fn bearer(token: &str) -> http::request::Parts {
+    http::Request::builder()
+        .header("authorization", format!("Bearer {token}"))
+        .body(())
+        .unwrap()
+        .into_parts()
+        .0
+}
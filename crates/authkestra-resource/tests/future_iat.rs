@@ -0,0 +1,72 @@
+#![cfg(feature = "remote-jwks")]
+
+mod common;
+
+use authkestra_engine::{strategy::AuthenticationStrategy, token::Claims, AuthError};
+use authkestra_resource::jwt::{JwtStrategy, ValidationConfig, ValidationConfigBuilder};
+use common::{bearer, mount_jwks, sign, ISSUER};
+use std::time::Duration;
+use wiremock::MockServer;
+
+fn token(iat_offset_secs: i64) -> String {
+    let now = chrono::Utc::now().timestamp();
+    sign(&serde_json::json!({
+        "sub": "user123",
+        "iss": ISSUER,
+        "iat": now + iat_offset_secs,
+        "exp": now + 7200,
+    }))
+}
+
+async fn strategy(
+    server: &MockServer,
+    configure: impl FnOnce(ValidationConfigBuilder) -> ValidationConfigBuilder,
+) -> JwtStrategy<Claims> {
+    mount_jwks(server).await;
+    let builder = ValidationConfig::builder()
+        .jwks_url(format!("{}/jwks", server.uri()))
+        .issuer(ISSUER);
+    JwtStrategy::new(configure(builder).build()).unwrap()
+}
+
+async fn authenticate(
+    strategy: &JwtStrategy<Claims>,
+    token: &str,
+) -> Result<Option<Claims>, AuthError> {
+    strategy.authenticate(&bearer(token)).await
+}
+
+#[tokio::test]
+async fn test_future_issued_token_is_rejected() {
+    let server = MockServer::start().await;
+    let strategy = strategy(&server, |config| {
+        config.max_future_iat(Duration::from_secs(60))
+    })
+    .await;
+
+    let result = authenticate(&strategy, &token(3600)).await;
+    assert!(
+        matches!(&result, Err(AuthError::Token(message)) if message.contains("future")),
+        "{result:?}"
+    );
+
+    // Within the allowed skew.
+    for offset in [0, 30, -3600] {
+        let claims = authenticate(&strategy, &token(offset))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(claims.sub, "user123");
+    }
+}
+
+#[tokio::test]
+async fn test_future_iat_is_not_checked_by_default() {
+    let server = MockServer::start().await;
+    let strategy = strategy(&server, |config| config).await;
+
+    assert!(authenticate(&strategy, &token(3600))
+        .await
+        .unwrap()
+        .is_some());
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "remote-jwks")]
+
+mod common;
+
+use authkestra_engine::token::Claims;
+use authkestra_resource::jwt::{JwksCache, ValidationError};
+use common::{sign, signer, ISSUER};
+use jsonwebtoken::{Algorithm, Validation};
+use std::time::Duration;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+fn token(exp_offset_secs: i64) -> String {
+    let now = chrono::Utc::now().timestamp();
+    sign(&serde_json::json!({
+        "sub": "user123",
+        "iss": ISSUER,
+        "iat": now,
+        "exp": now + exp_offset_secs,
+    }))
+}
+
+#[tokio::test]
+async fn test_validate_batch_fetches_jwks_once() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "keys": [signer().public_jwk().unwrap()] })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let cache = JwksCache::new(format!("{}/jwks", server.uri()), Duration::from_secs(60));
+
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_issuer(&[ISSUER]);
+    validation.leeway = 0;
+
+    let valid = token(300);
+    let expired = token(-300);
+    let results = cache
+        .validate_batch::<Claims>(&[&valid, &expired, "not-a-jwt", &valid], &validation)
+        .await;
+
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap().sub, "user123");
+    assert!(matches!(
+        &results[1],
+        Err(ValidationError::Jwt(e))
+            if *e.kind() == jsonwebtoken::errors::ErrorKind::ExpiredSignature
+    ));
+    assert!(matches!(results[2], Err(ValidationError::Jwt(_))));
+    assert!(results[3].is_ok());
+}
+
+#[tokio::test]
+async fn test_validate_batch_reports_unavailable_jwks_per_token() {
+    let cache = JwksCache::new(
+        "http://127.0.0.1:9/jwks".to_string(),
+        Duration::from_secs(60),
+    )
+    .with_max_token_size(4096);
+
+    let oversized = "a".repeat(8192);
+    let valid = token(300);
+    let results = cache
+        .validate_batch::<Claims>(&[&oversized, &valid], &Validation::new(Algorithm::RS256))
+        .await;
+
+    assert!(matches!(
+        results[0],
+        Err(ValidationError::TokenTooLarge { max: 4096 })
+    ));
+    assert!(matches!(results[1], Err(ValidationError::Validation(_))));
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use authkestra_engine::{token::Claims, TokenManager};
+use authkestra_resource::jwt::{validate_jwt_generic, Jwk, Jwks, JwksCache, ValidationError};
+use common::{identity, signer_with_kid, ISSUER};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header, Validation};
+
+const EC_PEM: &[u8] = include_bytes!("fixtures/ec_p256.pem");
+
+fn rsa_signer() -> TokenManager {
+    signer_with_kid("rsa-1")
+}
+
+fn ec_jwk() -> Jwk {
+    serde_json::from_value(serde_json::json!({
+        "kty": "EC",
+        "kid": "ec-1",
+        "crv": "P-256",
+        "x": "i7_cplKJa2ZRflRnj4DEKu1WuBjT9jgbUj7ppgJqkuU",
+        "y": "TnyUo8z9Wy06snGJnNEFQ_iaR1IIoOdReJb-WY5cLLw",
+    }))
+    .unwrap()
+}
+
+/// A key set serving both the old RSA key and the new EC key.
+fn mixed_cache() -> JwksCache {
+    JwksCache::from_static(Jwks {
+        keys: vec![rsa_signer().public_jwk().unwrap(), ec_jwk()],
+    })
+}
+
+fn rsa_token(sub: &str) -> String {
+    rsa_signer()
+        .issue_user_token(identity(sub), 300, None, None)
+        .unwrap()
+}
+
+fn ec_token(sub: &str, kid: Option<&str>) -> String {
+    let now = chrono::Utc::now().timestamp();
+    let mut header = Header::new(Algorithm::ES256);
+    header.kid = kid.map(str::to_string);
+    encode(
+        &header,
+        &serde_json::json!({ "sub": sub, "iss": ISSUER, "exp": now + 300, "iat": now }),
+        &EncodingKey::from_ec_pem(EC_PEM).unwrap(),
+    )
+    .unwrap()
+}
+
+fn validation(algorithms: Vec<Algorithm>) -> Validation {
+    let mut validation = Validation::new(algorithms[0]);
+    validation.algorithms = algorithms;
+    validation.set_issuer(&[ISSUER]);
+    validation.validate_aud = false;
+    validation
+}
+
+#[tokio::test]
+async fn test_rsa_and_ec_tokens_validate_against_one_jwks() {
+    let cache = mixed_cache();
+    let validation = validation(vec![Algorithm::RS256, Algorithm::ES256]);
+
+    for (token, sub) in [
+        (rsa_token("rsa-user"), "rsa-user"),
+        (ec_token("ec-user", Some("ec-1")), "ec-user"),
+        // Without a `kid`, the first key of the token's type is used, not the
+        // first key of the set.
+        (ec_token("no-kid", None), "no-kid"),
+    ] {
+        let claims = validate_jwt_generic::<Claims>(&token, &cache, &validation)
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, sub);
+    }
+
+    let results = cache
+        .validate_batch::<Claims>(
+            &[
+                &rsa_token("a"),
+                &ec_token("b", Some("ec-1")),
+                &ec_token("c", None),
+            ],
+            &validation,
+        )
+        .await;
+    let subs: Vec<_> = results.into_iter().map(|r| r.unwrap().sub).collect();
+    assert_eq!(subs, ["a", "b", "c"]);
+}
+
+#[tokio::test]
+async fn test_key_of_the_wrong_type_is_not_used() {
+    let cache = mixed_cache();
+    let validation = validation(vec![Algorithm::RS256, Algorithm::ES256]);
+
+    let result =
+        validate_jwt_generic::<Claims>(&ec_token("x", Some("rsa-1")), &cache, &validation).await;
+    assert!(matches!(result, Err(ValidationError::KeyNotFound)));
+}
+
+#[tokio::test]
+async fn test_algorithm_outside_the_configured_set_is_rejected() {
+    let cache = mixed_cache();
+    let validation = validation(vec![Algorithm::RS256]);
+
+    assert!(
+        validate_jwt_generic::<Claims>(&rsa_token("x"), &cache, &validation)
+            .await
+            .is_ok()
+    );
+    let result =
+        validate_jwt_generic::<Claims>(&ec_token("x", Some("ec-1")), &cache, &validation).await;
+    assert!(matches!(result, Err(ValidationError::Jwt(_))));
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use authkestra_resource::jwt::{
+    validate_jwt_generic, Jwks, JwksCache, MissingKidPolicy, ValidationError,
+};
+use common::{signer, RSA_PEM};
+use jsonwebtoken::{Algorithm, EncodingKey, Header, Validation};
+
+fn kidless_token() -> String {
+    let claims = serde_json::json!({
+        "sub": "user123",
+        "exp": chrono::Utc::now().timestamp() + 300,
+    });
+    jsonwebtoken::encode(
+        &Header::new(Algorithm::RS256),
+        &claims,
+        &EncodingKey::from_rsa_pem(RSA_PEM).unwrap(),
+    )
+    .unwrap()
+}
+
+/// The public key of `RSA_PEM` under each of `kids`.
+fn jwks(kids: &[&str]) -> Jwks {
+    let manager = signer();
+    let keys = kids
+        .iter()
+        .map(|kid| {
+            let mut jwk = manager.public_jwk().unwrap();
+            jwk.kid = Some(kid.to_string());
+            jwk
+        })
+        .collect();
+    Jwks { keys }
+}
+
+async fn validate(cache: &JwksCache) -> Result<serde_json::Value, ValidationError> {
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.validate_aud = false;
+    validate_jwt_generic(&kidless_token(), cache, &validation).await
+}
+
+#[tokio::test]
+async fn test_kidless_token_uses_the_sole_key() {
+    let cache = JwksCache::from_static(jwks(&["current"]));
+    let claims = validate(&cache).await.unwrap();
+    assert_eq!(claims["sub"], "user123");
+    assert_eq!(
+        cache.get_key(None).await.unwrap().unwrap().kid.as_deref(),
+        Some("current")
+    );
+}
+
+#[tokio::test]
+async fn test_kidless_token_is_rejected_with_several_keys() {
+    // Both keys would verify the token; picking either one silently is what
+    // goes wrong during a rotation.
+    let cache = JwksCache::from_static(jwks(&["current", "next"]));
+    assert!(matches!(
+        validate(&cache).await,
+        Err(ValidationError::InvalidToken(_))
+    ));
+    assert!(cache.get_key(None).await.is_err());
+    assert!(cache.get_key(Some("next")).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_strict_policy_requires_a_kid() {
+    let cache = JwksCache::from_static(jwks(&["current"]))
+        .with_missing_kid_policy(MissingKidPolicy::Strict);
+    assert!(matches!(
+        validate(&cache).await,
+        Err(ValidationError::InvalidToken(_))
+    ));
+    assert!(cache.get_key(Some("current")).await.unwrap().is_some());
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "remote-jwks")]
+
+mod common;
+
+use authkestra_engine::{strategy::AuthenticationStrategy, AuthError};
+use authkestra_resource::jwt::{
+    check_required_claims, JwtStrategy, ValidationConfig, ValidationError,
+};
+use common::{bearer, mount_jwks, sign, ISSUER};
+use wiremock::MockServer;
+
+/// `azp` is optional in the type, so deserialization alone accepts tokens without it.
+#[derive(Debug, serde::Deserialize)]
+struct AppClaims {
+    sub: String,
+    azp: Option<String>,
+}
+
+fn token(extra: serde_json::Value) -> String {
+    let now = chrono::Utc::now().timestamp();
+    let mut claims = serde_json::json!({
+        "sub": "user123",
+        "iss": ISSUER,
+        "iat": now,
+        "exp": now + 300,
+    });
+    claims
+        .as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    sign(&claims)
+}
+
+async fn strategy(server: &MockServer) -> JwtStrategy<AppClaims> {
+    mount_jwks(server).await;
+    JwtStrategy::new(
+        ValidationConfig::builder()
+            .jwks_url(format!("{}/jwks", server.uri()))
+            .issuer(ISSUER)
+            .required_claims(vec!["sub".to_string(), "azp".to_string()])
+            .build(),
+    )
+    .unwrap()
+}
+
+async fn authenticate(
+    strategy: &JwtStrategy<AppClaims>,
+    token: &str,
+) -> Result<Option<AppClaims>, AuthError> {
+    strategy.authenticate(&bearer(token)).await
+}
+
+#[tokio::test]
+async fn test_token_with_required_claims_is_accepted() {
+    let server = MockServer::start().await;
+    let strategy = strategy(&server).await;
+
+    let claims = authenticate(&strategy, &token(serde_json::json!({ "azp": "web-app" })))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(claims.sub, "user123");
+    assert_eq!(claims.azp.as_deref(), Some("web-app"));
+}
+
+#[tokio::test]
+async fn test_token_missing_a_required_claim_is_rejected() {
+    let server = MockServer::start().await;
+    let strategy = strategy(&server).await;
+
+    for extra in [serde_json::json!({}), serde_json::json!({ "azp": null })] {
+        let result = authenticate(&strategy, &token(extra)).await;
+        assert!(
+            matches!(&result, Err(AuthError::Token(message)) if message.contains("azp")),
+            "{result:?}"
+        );
+    }
+}
+
+#[test]
+fn test_missing_claims_are_listed() {
+    let required = ["sub", "azp", "tenant"].map(String::from);
+    let claims = serde_json::json!({ "sub": "user123" });
+
+    assert!(matches!(
+        check_required_claims(&claims, &required),
+        Err(ValidationError::Validation(message))
+            if message == "Missing required claims: azp, tenant"
+    ));
+    assert!(check_required_claims(&claims, &required[..1]).is_ok());
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use authkestra_engine::{token::Claims, TokenManager};
+use authkestra_resource::jwt::{
+    validate_jwt_generic, Jwks, JwksCache, StaticJwksSource, ValidationError,
+};
+use common::{identity, signer, signer_with_kid, ISSUER};
+use jsonwebtoken::{Algorithm, Validation};
+
+fn validation() -> Validation {
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_issuer(&[ISSUER]);
+    validation.validate_aud = false;
+    validation
+}
+
+fn pinned_jwks(manager: &TokenManager) -> Jwks {
+    Jwks {
+        keys: vec![manager.public_jwk().unwrap()],
+    }
+}
+
+#[tokio::test]
+async fn test_validates_against_static_jwks() {
+    let manager = signer();
+    let cache = JwksCache::from_static(pinned_jwks(&manager));
+    assert!(cache.is_static());
+
+    let token = manager
+        .issue_user_token(identity("user123"), 300, None, None)
+        .unwrap();
+    let claims = validate_jwt_generic::<Claims>(&token, &cache, &validation())
+        .await
+        .unwrap();
+    assert_eq!(claims.sub, "user123");
+
+    // Refreshing a static cache is a no-op that returns the pinned keys.
+    assert_eq!(cache.refresh().await.unwrap().keys.len(), 1);
+}
+
+#[tokio::test]
+async fn test_static_jwks_from_json() {
+    let manager = signer();
+    let json = serde_json::json!({ "keys": [manager.public_jwk().unwrap()] }).to_string();
+    let cache = JwksCache::from(StaticJwksSource::from_json(&json).unwrap());
+
+    let token = manager
+        .issue_user_token(identity("user123"), 300, None, None)
+        .unwrap();
+    assert!(
+        validate_jwt_generic::<Claims>(&token, &cache, &validation())
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_kid_is_not_fetched() {
+    let manager = signer();
+    let cache = JwksCache::from_static(pinned_jwks(&manager));
+
+    let other = signer_with_kid("kid-2");
+    let token = other
+        .issue_user_token(identity("user123"), 300, None, None)
+        .unwrap();
+
+    let result = validate_jwt_generic::<Claims>(&token, &cache, &validation()).await;
+    assert!(matches!(result, Err(ValidationError::KeyNotFound)));
+}
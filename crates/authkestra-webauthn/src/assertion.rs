@@ -0,0 +1,268 @@
+use crate::credential::Passkey;
+use crate::error::WebauthnError;
+use crate::store::PasskeyCredentialStore;
+use authkestra_engine::state::Identity;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// A `PublicKeyCredential` returned by `navigator.credentials.get()`, in the
+/// JSON form of `PublicKeyCredential.toJSON()`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AssertionCredential {
+    /// The base64url credential id.
+    pub id: String,
+    /// The authenticator's response.
+    pub response: AuthenticatorAssertionResponse,
+}
+
+/// The `response` of an [`AssertionCredential`], with binary fields base64url encoded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorAssertionResponse {
+    /// The JSON-serialized [`CollectedClientData`].
+    #[serde(rename = "clientDataJSON", with = "crate::base64url")]
+    pub client_data_json: Vec<u8>,
+    /// The raw [`AuthenticatorData`].
+    #[serde(with = "crate::base64url")]
+    pub authenticator_data: Vec<u8>,
+    /// The signature over `authenticator_data || SHA-256(client_data_json)`.
+    #[serde(with = "crate::base64url")]
+    pub signature: Vec<u8>,
+    /// The user handle, for discoverable credentials.
+    #[serde(default, with = "crate::base64url::option")]
+    pub user_handle: Option<Vec<u8>>,
+}
+
+/// The fixed-length prefix of the authenticator data.
+///
+/// Attested credential data and extensions, which may follow it, are not used
+/// by assertions and are ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthenticatorData {
+    /// SHA-256 of the RP ID the credential is scoped to.
+    pub rp_id_hash: [u8; 32],
+    /// The flags byte.
+    pub flags: u8,
+    /// The signature counter, or 0 if the authenticator has none.
+    pub sign_count: u32,
+}
+
+impl AuthenticatorData {
+    const USER_PRESENT: u8 = 0x01;
+    const USER_VERIFIED: u8 = 0x04;
+
+    /// Parse the first 37 bytes of raw authenticator data.
+    pub fn parse(bytes: &[u8]) -> Result<Self, WebauthnError> {
+        if bytes.len() < 37 {
+            return Err(WebauthnError::Malformed(format!(
+                "authenticator data is {} bytes, expected at least 37",
+                bytes.len()
+            )));
+        }
+        let mut rp_id_hash = [0u8; 32];
+        rp_id_hash.copy_from_slice(&bytes[..32]);
+        Ok(Self {
+            rp_id_hash,
+            flags: bytes[32],
+            sign_count: u32::from_be_bytes([bytes[33], bytes[34], bytes[35], bytes[36]]),
+        })
+    }
+
+    /// Whether the UP flag is set.
+    pub fn user_present(&self) -> bool {
+        self.flags & Self::USER_PRESENT != 0
+    }
+
+    /// Whether the UV flag is set.
+    pub fn user_verified(&self) -> bool {
+        self.flags & Self::USER_VERIFIED != 0
+    }
+}
+
+/// The client data the browser collected and the authenticator signed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectedClientData {
+    /// `webauthn.get` for assertions.
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// The base64url challenge.
+    pub challenge: String,
+    /// The origin of the page that called the WebAuthn API.
+    pub origin: String,
+    /// Whether the call came from a cross-origin iframe.
+    #[serde(default)]
+    pub cross_origin: bool,
+}
+
+/// A successfully verified assertion.
+#[derive(Clone, Debug)]
+pub struct VerifiedAssertion {
+    /// The identity of the passkey's user.
+    pub identity: Identity,
+    /// The new signature counter; store it on the [`Passkey`].
+    pub sign_count: u32,
+}
+
+/// The relying party the passkeys are scoped to.
+#[derive(Clone, Debug)]
+pub struct RelyingParty {
+    /// The RP ID, usually the registrable domain (e.g. `example.com`).
+    pub id: String,
+    /// The origins logins may come from (e.g. `https://example.com`).
+    pub origins: Vec<String>,
+    /// Whether to require the user verification (UV) flag.
+    pub require_user_verification: bool,
+}
+
+impl RelyingParty {
+    /// A relying party with RP ID `id` accepting logins from `origin`.
+    ///
+    /// User verification is required by default.
+    pub fn new(id: impl Into<String>, origin: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            origins: vec![origin.into()],
+            require_user_verification: true,
+        }
+    }
+
+    /// Accept logins from another origin as well.
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origins.push(origin.into());
+        self
+    }
+
+    /// Set whether the user verification (UV) flag is required.
+    pub fn with_user_verification(mut self, required: bool) -> Self {
+        self.require_user_verification = required;
+        self
+    }
+
+    /// Verify a passkey login.
+    ///
+    /// `challenge` is the challenge issued for this login and `passkey` the
+    /// stored credential matching `credential.id`. Checks the client data type,
+    /// challenge and origin, the RP ID hash and flags, the signature, and that
+    /// the signature counter increased. On success, persist the returned
+    /// [`sign_count`](VerifiedAssertion::sign_count) on the passkey.
+    #[tracing::instrument(skip_all, fields(user_id = %passkey.user_id))]
+    pub fn verify_assertion(
+        &self,
+        credential: &AssertionCredential,
+        challenge: &[u8],
+        passkey: &Passkey,
+    ) -> Result<VerifiedAssertion, WebauthnError> {
+        let response = &credential.response;
+
+        let credential_id = crate::base64url::decode(&credential.id)
+            .map_err(|e| WebauthnError::Malformed(format!("credential id: {e}")))?;
+        if credential_id != passkey.credential_id {
+            return Err(WebauthnError::CredentialMismatch);
+        }
+        if let Some(user_handle) = &response.user_handle {
+            if user_handle.as_slice() != passkey.user_id.as_bytes() {
+                return Err(WebauthnError::CredentialMismatch);
+            }
+        }
+
+        let client_data: CollectedClientData =
+            serde_json::from_slice(&response.client_data_json)
+                .map_err(|e| WebauthnError::Malformed(format!("client data: {e}")))?;
+        if client_data.type_ != "webauthn.get" {
+            return Err(WebauthnError::ClientDataType(client_data.type_));
+        }
+        if client_data.challenge != crate::base64url::encode(challenge) {
+            return Err(WebauthnError::ChallengeMismatch);
+        }
+        if !self.origins.contains(&client_data.origin) {
+            return Err(WebauthnError::OriginMismatch(client_data.origin));
+        }
+
+        let auth_data = AuthenticatorData::parse(&response.authenticator_data)?;
+        if auth_data.rp_id_hash[..] != Sha256::digest(self.id.as_bytes())[..] {
+            return Err(WebauthnError::RpIdMismatch);
+        }
+        if !auth_data.user_present() {
+            return Err(WebauthnError::UserNotPresent);
+        }
+        if self.require_user_verification && !auth_data.user_verified() {
+            return Err(WebauthnError::UserNotVerified);
+        }
+
+        let mut signed = response.authenticator_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&response.client_data_json));
+        passkey.public_key.verify(&signed, &response.signature)?;
+
+        // Authenticators without a counter always report 0.
+        if (auth_data.sign_count != 0 || passkey.sign_count != 0)
+            && auth_data.sign_count <= passkey.sign_count
+        {
+            tracing::warn!(
+                stored = passkey.sign_count,
+                received = auth_data.sign_count,
+                "passkey signature counter did not increase"
+            );
+            return Err(WebauthnError::CounterRegression {
+                stored: passkey.sign_count,
+                received: auth_data.sign_count,
+            });
+        }
+
+        let identity = Identity {
+            provider_id: "webauthn".to_string(),
+            external_id: passkey.user_id.clone(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::from([("credential_id".to_string(), credential.id.clone())]),
+            attributes_multi: HashMap::new(),
+        };
+        tracing::info!("passkey assertion verified");
+        Ok(VerifiedAssertion {
+            identity,
+            sign_count: auth_data.sign_count,
+        })
+    }
+
+    /// Verify a passkey login against the passkeys in `store`.
+    ///
+    /// Looks up the passkey for `credential.id`, runs
+    /// [`verify_assertion`](Self::verify_assertion) and stores the new
+    /// signature counter. The counter is only written if it still holds the
+    /// value the assertion was checked against, so of two logins replaying the
+    /// same counter value only one succeeds.
+    pub async fn authenticate(
+        &self,
+        store: &dyn PasskeyCredentialStore,
+        credential: &AssertionCredential,
+        challenge: &[u8],
+    ) -> Result<VerifiedAssertion, WebauthnError> {
+        let credential_id = crate::base64url::decode(&credential.id)
+            .map_err(|e| WebauthnError::Malformed(format!("credential id: {e}")))?;
+        let passkey = store
+            .credential(&credential_id)
+            .await?
+            .ok_or(WebauthnError::UnknownCredential)?;
+
+        let verified = self.verify_assertion(credential, challenge, &passkey)?;
+
+        // Authenticators without a counter leave nothing to update.
+        if verified.sign_count != passkey.sign_count
+            && !store
+                .update_counter(&credential_id, passkey.sign_count, verified.sign_count)
+                .await?
+        {
+            tracing::warn!(
+                user_id = %passkey.user_id,
+                "passkey signature counter changed during verification"
+            );
+            return Err(WebauthnError::CounterRegression {
+                stored: passkey.sign_count,
+                received: verified.sign_count,
+            });
+        }
+        Ok(verified)
+    }
+}
@@ -0,0 +1,73 @@
+use crate::error::WebauthnError;
+use serde::{Deserialize, Serialize};
+
+/// A passkey registered to a user.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Passkey {
+    /// The credential id chosen by the authenticator.
+    #[serde(with = "crate::base64url")]
+    pub credential_id: Vec<u8>,
+    /// The user the passkey belongs to; also its WebAuthn user handle.
+    pub user_id: String,
+    /// The credential public key.
+    pub public_key: CredentialPublicKey,
+    /// The highest signature counter seen so far.
+    pub sign_count: u32,
+}
+
+/// The public key of a passkey, decoded from its COSE key at registration.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "alg")]
+#[non_exhaustive]
+pub enum CredentialPublicKey {
+    /// ECDSA on P-256 with SHA-256 (COSE algorithm -7).
+    #[serde(rename = "ES256")]
+    Es256 {
+        /// The SEC1-encoded public point.
+        #[serde(with = "crate::base64url")]
+        sec1: Vec<u8>,
+    },
+    /// RSASSA-PKCS1-v1_5 with SHA-256 (COSE algorithm -257).
+    #[serde(rename = "RS256")]
+    Rs256 {
+        /// The big-endian modulus.
+        #[serde(with = "crate::base64url")]
+        n: Vec<u8>,
+        /// The big-endian public exponent.
+        #[serde(with = "crate::base64url")]
+        e: Vec<u8>,
+    },
+}
+
+impl CredentialPublicKey {
+    /// Verify `signature` over `message`.
+    ///
+    /// ES256 signatures are ASN.1 DER encoded, as authenticators produce them.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), WebauthnError> {
+        match self {
+            CredentialPublicKey::Es256 { sec1 } => {
+                use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+
+                let key = VerifyingKey::from_sec1_bytes(sec1)
+                    .map_err(|e| WebauthnError::InvalidKey(e.to_string()))?;
+                let signature =
+                    Signature::from_der(signature).map_err(|_| WebauthnError::InvalidSignature)?;
+                key.verify(message, &signature)
+                    .map_err(|_| WebauthnError::InvalidSignature)
+            }
+            CredentialPublicKey::Rs256 { n, e } => {
+                use rsa::pkcs1v15::{Signature, VerifyingKey};
+                use rsa::signature::Verifier;
+                use rsa::{BigUint, RsaPublicKey};
+
+                let key = RsaPublicKey::new(BigUint::from_bytes_be(n), BigUint::from_bytes_be(e))
+                    .map_err(|e| WebauthnError::InvalidKey(e.to_string()))?;
+                let signature =
+                    Signature::try_from(signature).map_err(|_| WebauthnError::InvalidSignature)?;
+                VerifyingKey::<sha2::Sha256>::new(key)
+                    .verify(message, &signature)
+                    .map_err(|_| WebauthnError::InvalidSignature)
+            }
+        }
+    }
+}
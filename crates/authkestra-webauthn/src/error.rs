@@ -0,0 +1,60 @@
+use authkestra_engine::error::AuthError;
+use authkestra_engine::store::StoreError;
+use thiserror::Error;
+
+/// Reasons a passkey assertion is rejected.
+#[derive(Debug, Error)]
+pub enum WebauthnError {
+    #[error("Malformed assertion: {0}")]
+    Malformed(String),
+
+    #[error("Expected client data type `webauthn.get`, got `{0}`")]
+    ClientDataType(String),
+
+    #[error("Challenge mismatch")]
+    ChallengeMismatch,
+
+    #[error("Origin not allowed: {0}")]
+    OriginMismatch(String),
+
+    #[error("RP ID hash mismatch")]
+    RpIdMismatch,
+
+    #[error("User presence flag not set")]
+    UserNotPresent,
+
+    #[error("User verification flag not set")]
+    UserNotVerified,
+
+    #[error("No passkey is registered for this credential")]
+    UnknownCredential,
+
+    #[error("Assertion is for another credential or user")]
+    CredentialMismatch,
+
+    #[error("Invalid signature")]
+    InvalidSignature,
+
+    #[error("Invalid credential public key: {0}")]
+    InvalidKey(String),
+
+    #[error(
+        "Signature counter did not increase (stored {stored}, received {received}); \
+         the authenticator may be cloned"
+    )]
+    CounterRegression { stored: u32, received: u32 },
+
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl From<WebauthnError> for AuthError {
+    fn from(err: WebauthnError) -> Self {
+        match err {
+            WebauthnError::Malformed(_)
+            | WebauthnError::InvalidKey(_)
+            | WebauthnError::Store(_) => AuthError::Provider(err.to_string()),
+            _ => AuthError::InvalidCredentials,
+        }
+    }
+}
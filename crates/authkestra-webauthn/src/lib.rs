@@ -0,0 +1,81 @@
+//! WebAuthn passkey verification for Authkestra.
+//!
+//! [`RelyingParty::verify_assertion`] checks a passkey login (the
+//! `PublicKeyCredential` returned by `navigator.credentials.get()`) against
+//! the challenge issued for it and the [`Passkey`] registered for the
+//! credential, and produces an [`Identity`](authkestra_engine::Identity).
+//!
+//! ES256 and RS256 credential keys are supported.
+//!
+//! Registered passkeys live in a [`PasskeyCredentialStore`];
+//! [`RelyingParty::authenticate`] looks the passkey up, verifies the assertion
+//! and atomically records the new signature counter.
+
+pub mod assertion;
+pub mod credential;
+pub mod error;
+pub mod store;
+
+pub use assertion::{
+    AssertionCredential, AuthenticatorAssertionResponse, AuthenticatorData, CollectedClientData,
+    RelyingParty, VerifiedAssertion,
+};
+pub use credential::{CredentialPublicKey, Passkey};
+pub use error::WebauthnError;
+pub use store::PasskeyCredentialStore;
+
+#[cfg(feature = "memory")]
+pub use store::memory::MemoryPasskeyStore;
+#[cfg(any(
+    feature = "sql-postgres",
+    feature = "sql-sqlite",
+    feature = "sql-mysql"
+))]
+pub use store::sql::SqlPasskeyStore;
+
+/// Serde helpers for unpadded base64url byte strings, the encoding WebAuthn
+/// JSON uses for binary fields.
+pub(crate) mod base64url {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn encode(bytes: &[u8]) -> String {
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    pub fn decode(value: &str) -> Result<Vec<u8>, base64::DecodeError> {
+        URL_SAFE_NO_PAD.decode(value.trim_end_matches('='))
+    }
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        decode(&value).map_err(serde::de::Error::custom)
+    }
+
+    /// The same, for optional fields.
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            bytes: &Option<Vec<u8>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match bytes {
+                Some(bytes) => super::serialize(bytes, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Vec<u8>>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|value| super::decode(&value).map_err(serde::de::Error::custom))
+                .transpose()
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::credential::Passkey;
+use crate::store::PasskeyCredentialStore;
+use async_trait::async_trait;
+use authkestra_engine::store::StoreError;
+
+/// An in-memory implementation of [`PasskeyCredentialStore`].
+///
+/// **Note**: This store is not persistent and will be cleared when the application restarts.
+/// It is primarily intended for development and testing.
+#[derive(Clone, Default)]
+pub struct MemoryPasskeyStore {
+    passkeys: Arc<Mutex<HashMap<Vec<u8>, Passkey>>>,
+}
+
+impl MemoryPasskeyStore {
+    /// Create a new, empty `MemoryPasskeyStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PasskeyCredentialStore for MemoryPasskeyStore {
+    async fn store_credential(&self, passkey: &Passkey) -> Result<(), StoreError> {
+        let mut passkeys = self.passkeys.lock().unwrap();
+        if passkeys.contains_key(&passkey.credential_id) {
+            return Err(StoreError::Internal(
+                "Credential id is already registered".to_string(),
+            ));
+        }
+        passkeys.insert(passkey.credential_id.clone(), passkey.clone());
+        Ok(())
+    }
+
+    async fn credentials_for_user(&self, user_id: &str) -> Result<Vec<Passkey>, StoreError> {
+        let passkeys = self.passkeys.lock().unwrap();
+        Ok(passkeys
+            .values()
+            .filter(|passkey| passkey.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn credential(&self, credential_id: &[u8]) -> Result<Option<Passkey>, StoreError> {
+        let passkeys = self.passkeys.lock().unwrap();
+        Ok(passkeys.get(credential_id).cloned())
+    }
+
+    async fn update_counter(
+        &self,
+        credential_id: &[u8],
+        expected: u32,
+        new: u32,
+    ) -> Result<bool, StoreError> {
+        let mut passkeys = self.passkeys.lock().unwrap();
+        match passkeys.get_mut(credential_id) {
+            Some(passkey) if passkey.sign_count == expected => {
+                passkey.sign_count = new;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credential::CredentialPublicKey;
+
+    fn passkey(credential_id: &[u8], user_id: &str) -> Passkey {
+        Passkey {
+            credential_id: credential_id.to_vec(),
+            user_id: user_id.to_string(),
+            public_key: CredentialPublicKey::Es256 { sec1: vec![4; 65] },
+            sign_count: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_and_lookup() {
+        let store = MemoryPasskeyStore::new();
+        store
+            .store_credential(&passkey(b"one", "alice"))
+            .await
+            .unwrap();
+        store
+            .store_credential(&passkey(b"two", "alice"))
+            .await
+            .unwrap();
+        store
+            .store_credential(&passkey(b"three", "bob"))
+            .await
+            .unwrap();
+
+        assert!(store
+            .store_credential(&passkey(b"one", "bob"))
+            .await
+            .is_err());
+        assert_eq!(store.credentials_for_user("alice").await.unwrap().len(), 2);
+        assert!(store
+            .credentials_for_user("carol")
+            .await
+            .unwrap()
+            .is_empty());
+        let found = store.credential(b"three").await.unwrap().unwrap();
+        assert_eq!(found.user_id, "bob");
+        assert!(store.credential(b"four").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_counter_compares_and_sets() {
+        let store = MemoryPasskeyStore::new();
+        store
+            .store_credential(&passkey(b"one", "alice"))
+            .await
+            .unwrap();
+
+        assert!(store.update_counter(b"one", 0, 5).await.unwrap());
+        // A second login that read the old counter loses.
+        assert!(!store.update_counter(b"one", 0, 5).await.unwrap());
+        assert!(!store.update_counter(b"unknown", 0, 1).await.unwrap());
+        assert_eq!(
+            store.credential(b"one").await.unwrap().unwrap().sign_count,
+            5
+        );
+    }
+}
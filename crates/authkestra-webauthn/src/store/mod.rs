@@ -0,0 +1,38 @@
+use crate::credential::Passkey;
+use async_trait::async_trait;
+use authkestra_engine::store::StoreError;
+
+/// Persists registered passkeys and their signature counters.
+#[async_trait]
+pub trait PasskeyCredentialStore: Send + Sync + 'static {
+    /// Register `passkey`. Fails if its credential id is already registered.
+    async fn store_credential(&self, passkey: &Passkey) -> Result<(), StoreError>;
+
+    /// The passkeys registered to `user_id`, e.g. to fill `allowCredentials`.
+    async fn credentials_for_user(&self, user_id: &str) -> Result<Vec<Passkey>, StoreError>;
+
+    /// The passkey with `credential_id`, if one is registered.
+    async fn credential(&self, credential_id: &[u8]) -> Result<Option<Passkey>, StoreError>;
+
+    /// Set the signature counter of `credential_id` to `new` if it is still
+    /// `expected`, returning whether it was updated.
+    ///
+    /// The compare and the write must be atomic: two logins with the same
+    /// counter value must not both succeed.
+    async fn update_counter(
+        &self,
+        credential_id: &[u8],
+        expected: u32,
+        new: u32,
+    ) -> Result<bool, StoreError>;
+}
+
+#[cfg(feature = "memory")]
+pub mod memory;
+
+#[cfg(any(
+    feature = "sql-postgres",
+    feature = "sql-sqlite",
+    feature = "sql-mysql"
+))]
+pub mod sql;
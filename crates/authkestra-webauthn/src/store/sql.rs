@@ -0,0 +1,276 @@
+use async_trait::async_trait;
+use authkestra_engine::store::StoreError;
+use sqlx::Database;
+
+use crate::credential::Passkey;
+use crate::store::PasskeyCredentialStore;
+
+/// A [`PasskeyCredentialStore`] backed by a SQL table.
+///
+/// Call [`migrate`](SqlPasskeyStore::migrate) once to create the table:
+/// one row per passkey, keyed by its base64url credential id, with the
+/// public key stored as JSON and an index on the user id.
+#[derive(Clone, Debug)]
+pub struct SqlPasskeyStore<DB: Database> {
+    #[allow(dead_code)]
+    pool: sqlx::Pool<DB>,
+    #[allow(dead_code)]
+    table_name: String,
+}
+
+/// Internal data model for a passkey row in the SQL database.
+#[derive(sqlx::FromRow)]
+pub struct SqlPasskeyModel {
+    pub credential_id: String,
+    pub user_id: String,
+    pub public_key: String,
+    pub sign_count: i64,
+}
+
+impl SqlPasskeyModel {
+    #[allow(dead_code)]
+    fn into_passkey(self) -> Result<Passkey, StoreError> {
+        let credential_id = crate::base64url::decode(&self.credential_id)
+            .map_err(|e| StoreError::Serialization(format!("Invalid credential id: {e}")))?;
+        let public_key = serde_json::from_str(&self.public_key).map_err(|e| {
+            tracing::error!(error = %e, "Deserialization error");
+            StoreError::Serialization(format!("Deserialization error: {e}"))
+        })?;
+        let sign_count = u32::try_from(self.sign_count)
+            .map_err(|e| StoreError::Serialization(format!("Invalid sign count: {e}")))?;
+        Ok(Passkey {
+            credential_id,
+            user_id: self.user_id,
+            public_key,
+            sign_count,
+        })
+    }
+}
+
+impl<DB: Database> SqlPasskeyStore<DB> {
+    pub fn new(pool: sqlx::Pool<DB>) -> Self {
+        Self {
+            pool,
+            table_name: "authkestra_passkeys".to_string(),
+        }
+    }
+
+    pub fn with_table_name(pool: sqlx::Pool<DB>, table_name: String) -> Self {
+        Self { pool, table_name }
+    }
+}
+
+macro_rules! impl_sql_passkey_store {
+    (
+        $backend:path,
+        $feature:literal,
+        $dialect_name:literal,
+        $insert_query:expr,
+        $select_user_query:expr,
+        $select_id_query:expr,
+        $update_counter_query:expr,
+        $($migrate_query:expr),+
+    ) => {
+        #[cfg(feature = $feature)]
+        #[async_trait]
+        impl PasskeyCredentialStore for SqlPasskeyStore<$backend> {
+            #[tracing::instrument(skip_all, fields(user_id = %passkey.user_id))]
+            async fn store_credential(&self, passkey: &Passkey) -> Result<(), StoreError> {
+                tracing::debug!(concat!("saving passkey to ", $dialect_name, " store"));
+                let query = format!($insert_query, self.table_name);
+                let public_key = serde_json::to_string(&passkey.public_key).map_err(|e| {
+                    tracing::error!(error = %e, "Serialization error");
+                    StoreError::Serialization(format!("Serialization error: {e}"))
+                })?;
+
+                sqlx::query(&query)
+                    .bind(crate::base64url::encode(&passkey.credential_id))
+                    .bind(&passkey.user_id)
+                    .bind(public_key)
+                    .bind(i64::from(passkey.sign_count))
+                    .bind(chrono::Utc::now())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!(error = %e, concat!($dialect_name, " insert error"));
+                        StoreError::Internal(format!("{} insert error: {}", $dialect_name, e))
+                    })?;
+                Ok(())
+            }
+
+            #[tracing::instrument(skip(self))]
+            async fn credentials_for_user(&self, user_id: &str) -> Result<Vec<Passkey>, StoreError> {
+                let query = format!($select_user_query, self.table_name);
+                let rows: Vec<SqlPasskeyModel> = sqlx::query_as(&query)
+                    .bind(user_id)
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!(error = %e, concat!($dialect_name, " select error"));
+                        StoreError::Internal(format!("{} select error: {}", $dialect_name, e))
+                    })?;
+                rows.into_iter().map(SqlPasskeyModel::into_passkey).collect()
+            }
+
+            #[tracing::instrument(skip_all)]
+            async fn credential(&self, credential_id: &[u8]) -> Result<Option<Passkey>, StoreError> {
+                let query = format!($select_id_query, self.table_name);
+                let row: Option<SqlPasskeyModel> = sqlx::query_as(&query)
+                    .bind(crate::base64url::encode(credential_id))
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!(error = %e, concat!($dialect_name, " select error"));
+                        StoreError::Internal(format!("{} select error: {}", $dialect_name, e))
+                    })?;
+                row.map(SqlPasskeyModel::into_passkey).transpose()
+            }
+
+            #[tracing::instrument(skip(self, credential_id))]
+            async fn update_counter(
+                &self,
+                credential_id: &[u8],
+                expected: u32,
+                new: u32,
+            ) -> Result<bool, StoreError> {
+                let query = format!($update_counter_query, self.table_name);
+                // The `WHERE` on the old value makes this a single atomic compare-and-set.
+                let result = sqlx::query(&query)
+                    .bind(i64::from(new))
+                    .bind(crate::base64url::encode(credential_id))
+                    .bind(i64::from(expected))
+                    .execute(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!(error = %e, concat!($dialect_name, " update error"));
+                        StoreError::Internal(format!("{} update error: {}", $dialect_name, e))
+                    })?;
+                Ok(result.rows_affected() == 1)
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl SqlPasskeyStore<$backend> {
+            /// Creates the passkey table and its user id index if they do not exist.
+            pub async fn migrate(&self) -> Result<(), StoreError> {
+                $(
+                    let query = format!($migrate_query, table = self.table_name);
+                    sqlx::query(&query)
+                        .execute(&self.pool)
+                        .await
+                        .map_err(|e| StoreError::Internal(format!("{} migration error: {}", $dialect_name, e)))?;
+                )+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_sql_passkey_store! {
+    sqlx::Postgres,
+    "sql-postgres",
+    "Postgres",
+    "INSERT INTO {} (credential_id, user_id, public_key, sign_count, created_at) VALUES ($1, $2, $3, $4, $5)",
+    "SELECT credential_id, user_id, public_key, sign_count FROM {} WHERE user_id = $1",
+    "SELECT credential_id, user_id, public_key, sign_count FROM {} WHERE credential_id = $1",
+    "UPDATE {} SET sign_count = $1 WHERE credential_id = $2 AND sign_count = $3",
+    "CREATE TABLE IF NOT EXISTS {table} (credential_id TEXT PRIMARY KEY, user_id TEXT NOT NULL, public_key TEXT NOT NULL, sign_count BIGINT NOT NULL, created_at TIMESTAMP WITH TIME ZONE NOT NULL)",
+    "CREATE INDEX IF NOT EXISTS {table}_user_idx ON {table}(user_id)"
+}
+
+impl_sql_passkey_store! {
+    sqlx::Sqlite,
+    "sql-sqlite",
+    "Sqlite",
+    "INSERT INTO {} (credential_id, user_id, public_key, sign_count, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+    "SELECT credential_id, user_id, public_key, sign_count FROM {} WHERE user_id = ?1",
+    "SELECT credential_id, user_id, public_key, sign_count FROM {} WHERE credential_id = ?1",
+    "UPDATE {} SET sign_count = ?1 WHERE credential_id = ?2 AND sign_count = ?3",
+    "CREATE TABLE IF NOT EXISTS {table} (credential_id TEXT PRIMARY KEY, user_id TEXT NOT NULL, public_key TEXT NOT NULL, sign_count INTEGER NOT NULL, created_at DATETIME NOT NULL)",
+    "CREATE INDEX IF NOT EXISTS {table}_user_idx ON {table}(user_id)"
+}
+
+impl_sql_passkey_store! {
+    sqlx::MySql,
+    "sql-mysql",
+    "MySql",
+    "INSERT INTO {} (credential_id, user_id, public_key, sign_count, created_at) VALUES (?, ?, ?, ?, ?)",
+    "SELECT credential_id, user_id, public_key, sign_count FROM {} WHERE user_id = ?",
+    "SELECT credential_id, user_id, public_key, sign_count FROM {} WHERE credential_id = ?",
+    "UPDATE {} SET sign_count = ? WHERE credential_id = ? AND sign_count = ?",
+    // Credential ids are up to 1023 bytes, so up to 1364 base64url characters.
+    "CREATE TABLE IF NOT EXISTS {table} (credential_id VARCHAR(1400) CHARACTER SET ascii PRIMARY KEY, user_id VARCHAR(255) NOT NULL, public_key TEXT NOT NULL, sign_count BIGINT NOT NULL, created_at TIMESTAMP(6) NOT NULL, INDEX {table}_user_idx (user_id))"
+}
+
+#[cfg(all(test, feature = "sql-sqlite"))]
+mod tests {
+    use super::*;
+    use crate::credential::CredentialPublicKey;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn setup_db() -> SqlPasskeyStore<sqlx::Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        let store = SqlPasskeyStore::new(pool);
+        store.migrate().await.unwrap();
+        store
+    }
+
+    fn passkey(credential_id: &[u8], user_id: &str) -> Passkey {
+        Passkey {
+            credential_id: credential_id.to_vec(),
+            user_id: user_id.to_string(),
+            public_key: CredentialPublicKey::Rs256 {
+                n: vec![0xc3; 256],
+                e: vec![1, 0, 1],
+            },
+            sign_count: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store_and_lookup() {
+        let store = setup_db().await;
+        store
+            .store_credential(&passkey(b"one", "alice"))
+            .await
+            .unwrap();
+        store
+            .store_credential(&passkey(b"two", "alice"))
+            .await
+            .unwrap();
+        store
+            .store_credential(&passkey(b"three", "bob"))
+            .await
+            .unwrap();
+
+        assert!(store
+            .store_credential(&passkey(b"one", "bob"))
+            .await
+            .is_err());
+        assert_eq!(store.credentials_for_user("alice").await.unwrap().len(), 2);
+        let found = store.credential(b"three").await.unwrap().unwrap();
+        assert_eq!(found, passkey(b"three", "bob"));
+        assert!(store.credential(b"four").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_update_counter_compares_and_sets() {
+        let store = setup_db().await;
+        store
+            .store_credential(&passkey(b"one", "alice"))
+            .await
+            .unwrap();
+
+        assert!(store.update_counter(b"one", 0, 5).await.unwrap());
+        assert!(!store.update_counter(b"one", 0, 5).await.unwrap());
+        assert!(!store.update_counter(b"unknown", 0, 1).await.unwrap());
+        assert_eq!(
+            store.credential(b"one").await.unwrap().unwrap().sign_count,
+            5
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use authkestra_webauthn::{
+    AssertionCredential, AuthenticatorAssertionResponse, CredentialPublicKey, Passkey,
+    RelyingParty, WebauthnError,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use sha2::{Digest, Sha256};
+
+const RSA_PEM: &[u8] = include_bytes!("fixtures/rsa_2048.pem");
+
+const RP_ID: &str = "example.com";
+const ORIGIN: &str = "https://example.com";
+const CHALLENGE: &[u8] = b"challenge-issued-for-this-login";
+
+enum Key {
+    Es256(p256::ecdsa::SigningKey),
+    Rs256(Box<rsa::pkcs1v15::SigningKey<Sha256>>),
+}
+
+/// A virtual authenticator holding one credential.
+struct Authenticator {
+    credential_id: Vec<u8>,
+    key: Key,
+    sign_count: u32,
+}
+
+impl Authenticator {
+    fn es256() -> Self {
+        Self {
+            credential_id: b"es256-credential".to_vec(),
+            key: Key::Es256(p256::ecdsa::SigningKey::from_slice(&[7u8; 32]).unwrap()),
+            sign_count: 0,
+        }
+    }
+
+    fn rs256() -> Self {
+        use rsa::pkcs8::DecodePrivateKey;
+        let pem = std::str::from_utf8(RSA_PEM).unwrap();
+        let key = rsa::RsaPrivateKey::from_pkcs8_pem(pem).unwrap();
+        Self {
+            credential_id: b"rs256-credential".to_vec(),
+            key: Key::Rs256(Box::new(rsa::pkcs1v15::SigningKey::new(key))),
+            sign_count: 0,
+        }
+    }
+
+    /// The passkey the relying party stored at registration.
+    fn passkey(&self) -> Passkey {
+        let public_key = match &self.key {
+            Key::Es256(key) => CredentialPublicKey::Es256 {
+                sec1: key
+                    .verifying_key()
+                    .to_encoded_point(false)
+                    .as_bytes()
+                    .to_vec(),
+            },
+            Key::Rs256(key) => {
+                use rsa::traits::PublicKeyParts;
+                let public = key.as_ref().as_ref().to_public_key();
+                CredentialPublicKey::Rs256 {
+                    n: public.n().to_bytes_be(),
+                    e: public.e().to_bytes_be(),
+                }
+            }
+        };
+        Passkey {
+            credential_id: self.credential_id.clone(),
+            user_id: "alice".to_string(),
+            public_key,
+            sign_count: self.sign_count,
+        }
+    }
+
+    /// Answer `navigator.credentials.get()` from `origin` for `rp_id`.
+    fn assert(&mut self, rp_id: &str, origin: &str, challenge: &[u8]) -> AssertionCredential {
+        self.sign_count += 1;
+        let mut authenticator_data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        authenticator_data.push(0x05); // UP | UV
+        authenticator_data.extend_from_slice(&self.sign_count.to_be_bytes());
+
+        let client_data_json = serde_json::to_vec(&serde_json::json!({
+            "type": "webauthn.get",
+            "challenge": URL_SAFE_NO_PAD.encode(challenge),
+            "origin": origin,
+            "crossOrigin": false,
+        }))
+        .unwrap();
+
+        let mut signed = authenticator_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature = match &self.key {
+            Key::Es256(key) => {
+                use p256::ecdsa::{signature::Signer, Signature};
+                let signature: Signature = key.sign(&signed);
+                signature.to_der().as_bytes().to_vec()
+            }
+            Key::Rs256(key) => {
+                use rsa::signature::{SignatureEncoding, Signer};
+                key.sign(&signed).to_vec()
+            }
+        };
+
+        AssertionCredential {
+            id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+            response: AuthenticatorAssertionResponse {
+                client_data_json,
+                authenticator_data,
+                signature,
+                user_handle: Some(b"alice".to_vec()),
+            },
+        }
+    }
+}
+
+fn rp() -> RelyingParty {
+    RelyingParty::new(RP_ID, ORIGIN)
+}
+
+#[test]
+fn test_es256_and_rs256_assertions_are_verified() {
+    for mut authenticator in [Authenticator::es256(), Authenticator::rs256()] {
+        let passkey = authenticator.passkey();
+        let credential = authenticator.assert(RP_ID, ORIGIN, CHALLENGE);
+
+        let verified = rp()
+            .verify_assertion(&credential, CHALLENGE, &passkey)
+            .unwrap();
+        assert_eq!(verified.identity.provider_id, "webauthn");
+        assert_eq!(verified.identity.external_id, "alice");
+        assert_eq!(verified.sign_count, 1);
+    }
+}
+
+#[test]
+fn test_assertion_json_round_trip() {
+    let mut authenticator = Authenticator::es256();
+    let passkey = authenticator.passkey();
+    let json = serde_json::to_string(&authenticator.assert(RP_ID, ORIGIN, CHALLENGE)).unwrap();
+    assert!(json.contains("\"clientDataJSON\""));
+
+    let credential: AssertionCredential = serde_json::from_str(&json).unwrap();
+    assert!(rp()
+        .verify_assertion(&credential, CHALLENGE, &passkey)
+        .is_ok());
+}
+
+#[test]
+fn test_wrong_challenge_origin_or_rp_id_is_rejected() {
+    let mut authenticator = Authenticator::es256();
+    let passkey = authenticator.passkey();
+
+    let credential = authenticator.assert(RP_ID, ORIGIN, b"another-challenge");
+    assert!(matches!(
+        rp().verify_assertion(&credential, CHALLENGE, &passkey),
+        Err(WebauthnError::ChallengeMismatch)
+    ));
+
+    let credential = authenticator.assert(RP_ID, "https://evil.example", CHALLENGE);
+    assert!(matches!(
+        rp().verify_assertion(&credential, CHALLENGE, &passkey),
+        Err(WebauthnError::OriginMismatch(_))
+    ));
+
+    let credential = authenticator.assert("evil.example", ORIGIN, CHALLENGE);
+    assert!(matches!(
+        rp().verify_assertion(&credential, CHALLENGE, &passkey),
+        Err(WebauthnError::RpIdMismatch)
+    ));
+}
+
+#[test]
+fn test_tampered_assertion_is_rejected() {
+    for mut authenticator in [Authenticator::es256(), Authenticator::rs256()] {
+        let passkey = authenticator.passkey();
+        let mut credential = authenticator.assert(RP_ID, ORIGIN, CHALLENGE);
+        // Claim a higher counter than the authenticator signed.
+        credential.response.authenticator_data[36] ^= 0xff;
+
+        assert!(matches!(
+            rp().verify_assertion(&credential, CHALLENGE, &passkey),
+            Err(WebauthnError::InvalidSignature)
+        ));
+    }
+}
+
+#[test]
+fn test_key_of_other_credential_is_rejected() {
+    let mut authenticator = Authenticator::es256();
+    let mut passkey = authenticator.passkey();
+    passkey.public_key = Authenticator::rs256().passkey().public_key;
+    let credential = authenticator.assert(RP_ID, ORIGIN, CHALLENGE);
+
+    assert!(matches!(
+        rp().verify_assertion(&credential, CHALLENGE, &passkey),
+        Err(WebauthnError::InvalidSignature)
+    ));
+}
+
+#[test]
+fn test_cloned_authenticator_is_detected() {
+    let mut authenticator = Authenticator::es256();
+    let mut passkey = authenticator.passkey();
+    let mut clone = Authenticator::es256();
+
+    let credential = authenticator.assert(RP_ID, ORIGIN, CHALLENGE);
+    passkey.sign_count = rp()
+        .verify_assertion(&credential, CHALLENGE, &passkey)
+        .unwrap()
+        .sign_count;
+
+    // The clone replays the same counter value.
+    let credential = clone.assert(RP_ID, ORIGIN, CHALLENGE);
+    assert!(matches!(
+        rp().verify_assertion(&credential, CHALLENGE, &passkey),
+        Err(WebauthnError::CounterRegression {
+            stored: 1,
+            received: 1
+        })
+    ));
+}
+
+#[cfg(feature = "memory")]
+mod store {
+    use super::*;
+    use async_trait::async_trait;
+    use authkestra_engine::store::StoreError;
+    use authkestra_webauthn::{MemoryPasskeyStore, PasskeyCredentialStore};
+
+    #[tokio::test]
+    async fn test_registered_passkey_authenticates() {
+        let store = MemoryPasskeyStore::new();
+        let mut authenticator = Authenticator::rs256();
+        store
+            .store_credential(&authenticator.passkey())
+            .await
+            .unwrap();
+        assert_eq!(store.credentials_for_user("alice").await.unwrap().len(), 1);
+
+        for expected in 1..=2 {
+            let credential = authenticator.assert(RP_ID, ORIGIN, CHALLENGE);
+            let verified = rp()
+                .authenticate(&store, &credential, CHALLENGE)
+                .await
+                .unwrap();
+            assert_eq!(verified.identity.external_id, "alice");
+            let stored = store.credential(b"rs256-credential").await.unwrap();
+            assert_eq!(stored.unwrap().sign_count, expected);
+        }
+
+        let credential = Authenticator::es256().assert(RP_ID, ORIGIN, CHALLENGE);
+        assert!(matches!(
+            rp().authenticate(&store, &credential, CHALLENGE).await,
+            Err(WebauthnError::UnknownCredential)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_decremented_counter_is_rejected() {
+        let store = MemoryPasskeyStore::new();
+        let mut authenticator = Authenticator::es256();
+        store
+            .store_credential(&authenticator.passkey())
+            .await
+            .unwrap();
+        authenticator.sign_count = 10;
+        let credential = authenticator.assert(RP_ID, ORIGIN, CHALLENGE);
+        rp().authenticate(&store, &credential, CHALLENGE)
+            .await
+            .unwrap();
+
+        authenticator.sign_count = 4;
+        let credential = authenticator.assert(RP_ID, ORIGIN, CHALLENGE);
+        assert!(matches!(
+            rp().authenticate(&store, &credential, CHALLENGE).await,
+            Err(WebauthnError::CounterRegression {
+                stored: 11,
+                received: 5
+            })
+        ));
+        let stored = store.credential(b"es256-credential").await.unwrap();
+        assert_eq!(stored.unwrap().sign_count, 11);
+    }
+
+    /// Serves the passkey as it was at registration, as a concurrent login
+    /// that read it before the counter was updated would see it.
+    struct StaleStore {
+        memory: MemoryPasskeyStore,
+        snapshot: Passkey,
+    }
+
+    #[async_trait]
+    impl PasskeyCredentialStore for StaleStore {
+        async fn store_credential(&self, passkey: &Passkey) -> Result<(), StoreError> {
+            self.memory.store_credential(passkey).await
+        }
+
+        async fn credentials_for_user(&self, user_id: &str) -> Result<Vec<Passkey>, StoreError> {
+            self.memory.credentials_for_user(user_id).await
+        }
+
+        async fn credential(&self, _credential_id: &[u8]) -> Result<Option<Passkey>, StoreError> {
+            Ok(Some(self.snapshot.clone()))
+        }
+
+        async fn update_counter(
+            &self,
+            credential_id: &[u8],
+            expected: u32,
+            new: u32,
+        ) -> Result<bool, StoreError> {
+            self.memory
+                .update_counter(credential_id, expected, new)
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_replay_of_counter_is_rejected() {
+        let mut authenticator = Authenticator::es256();
+        let mut clone = Authenticator::es256();
+        let store = StaleStore {
+            memory: MemoryPasskeyStore::new(),
+            snapshot: authenticator.passkey(),
+        };
+        store
+            .store_credential(&authenticator.passkey())
+            .await
+            .unwrap();
+
+        let credential = authenticator.assert(RP_ID, ORIGIN, CHALLENGE);
+        rp().authenticate(&store, &credential, CHALLENGE)
+            .await
+            .unwrap();
+
+        // Both logins checked counter 1 against the stored 0; only one may win.
+        let credential = clone.assert(RP_ID, ORIGIN, CHALLENGE);
+        assert!(matches!(
+            rp().authenticate(&store, &credential, CHALLENGE).await,
+            Err(WebauthnError::CounterRegression {
+                stored: 0,
+                received: 1
+            })
+        ));
+    }
+}
+
+/// An assertion produced by a real browser, see `tests/fixtures/README.md`.
+#[derive(serde::Deserialize)]
+struct BrowserFixture {
+    rp_id: String,
+    origin: String,
+    challenge: String,
+    passkey: Passkey,
+    credential: AssertionCredential,
+}
+
+#[test]
+#[ignore = "needs tests/fixtures/chrome_es256_assertion.json captured from Chrome's virtual authenticator"]
+fn test_chrome_virtual_authenticator_assertion() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/chrome_es256_assertion.json"
+    );
+    let fixture: BrowserFixture =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    let challenge = URL_SAFE_NO_PAD.decode(&fixture.challenge).unwrap();
+
+    let verified = RelyingParty::new(fixture.rp_id, fixture.origin)
+        .verify_assertion(&fixture.credential, &challenge, &fixture.passkey)
+        .unwrap();
+    assert_eq!(verified.identity.external_id, fixture.passkey.user_id);
+}
@@ -9,19 +9,13 @@
 use actix_files::Files;
 use actix_web::{get, web, App, HttpResponse, HttpServer, Responder};
 use authkestra::flow::{Engine, OAuth2Flow};
-use authkestra_actix::{ActixExt, ActixState, AuthSession};
+use authkestra_actix::{ActixExt, AuthSession, AuthkestraState};
 use authkestra_engine::auth::SessionStore;
-use authkestra_engine::{AkWebAppEngine, SessionConfig};
+use authkestra_engine::SessionConfig;
 use authkestra_providers::github::GithubProvider;
 use serde_json::json;
 use std::sync::Arc;
 
-#[derive(Clone, ActixState)]
-struct AppState {
-    #[authkestra(engine)]
-    auth: AkWebAppEngine,
-}
-
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // =========================================================================
@@ -74,20 +68,17 @@ async fn main() -> std::io::Result<()> {
         })
         .build();
 
-    let state = AppState {
-        auth: auth_engine.clone(),
-    };
+    // The extractors read the session store and config from this one piece
+    // of state, and the auth scope carries the engine itself.
+    let state = web::Data::new(AuthkestraState::from(&auth_engine));
 
     println!("🚀 Actix GitHub OAuth2 running on http://localhost:3000");
 
     HttpServer::new(move || {
-        let app_state = state.clone();
-        let config_state = app_state.clone();
         App::new()
-            .app_data(web::Data::new(app_state.clone()))
-            .configure(move |cfg| config_state.configure_authkestra(cfg))
+            .app_data(state.clone())
             .service(get_user)
-            .service(app_state.auth.actix_scope())
+            .service(auth_engine.actix_scope())
             .service(Files::new("/", "authkestra-examples/static").index_file("index.html"))
     })
     .bind(("0.0.0.0", 3000))?
@@ -128,6 +128,7 @@ async fn callback_handler(
         token_manager,
         3600, // 1 hour
         state.auth.session_config.clone(),
+        state.auth.consent_sink.as_ref(),
     )
     .await?;
 
@@ -38,7 +38,8 @@ async fn main() -> std::io::Result<()> {
         .issuer(issuer)
         .build();
 
-    let jwt_strategy = JwtStrategy::<UserIdentity>::new(validation_config);
+    let jwt_strategy =
+        JwtStrategy::<UserIdentity>::new(validation_config).map_err(std::io::Error::other)?;
 
     // 2. Configure the Resource Enforcer (Guard)
     let guard = Guard::builder().strategy(jwt_strategy).build();
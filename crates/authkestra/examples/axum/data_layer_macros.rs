@@ -57,7 +57,8 @@ async fn main() {
         "CREATE TABLE IF NOT EXISTS user_sessions (
             key TEXT PRIMARY KEY,
             value TEXT NOT NULL,
-            expires_at DATETIME NOT NULL
+            expires_at DATETIME NOT NULL,
+            created_at DATETIME NOT NULL
         )",
     )
     .execute(&pool)
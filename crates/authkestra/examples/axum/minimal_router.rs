@@ -0,0 +1,40 @@
+//! # Axum Minimal Router Example
+//!
+//! This example mounts the auth routes with a single call to `into_router`,
+//! which wires the engine state and the cookie layer itself.
+
+use authkestra::flow::Engine;
+use authkestra_axum::{AuthSession, AxumExt, AxumState};
+use authkestra_engine::auth::SessionStore;
+use authkestra_engine::SessionConfig;
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+#[tokio::main]
+async fn main() {
+    let session_store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::default());
+
+    let engine = Engine::builder()
+        .session_store(session_store)
+        .session_config(SessionConfig {
+            secure: false, // For local development
+            ..Default::default()
+        })
+        .build();
+
+    let app = Router::new()
+        .route("/api/user", get(get_user))
+        .with_state(AxumState::from(engine.clone()))
+        // Login, callback and logout routes, ready to merge.
+        .merge(engine.into_router());
+
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    println!("🚀 Axum Minimal Router running on http://localhost:3000");
+    axum::serve(listener, app).await.unwrap();
+}
+
+/// Returns the id of the logged-in user.
+async fn get_user(AuthSession(session): AuthSession) -> String {
+    session.identity.external_id
+}
@@ -13,7 +13,7 @@ use authkestra_engine::{token::TokenManager, AkApiEngine, Configured, Missing};
 use authkestra_providers::github::GithubProvider;
 use axum::{
     extract::{FromRef, Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Json},
     routing::get,
     Router,
@@ -85,12 +85,14 @@ async fn login_handler(
     Path(provider): Path<String>,
     State(state): State<AppState>,
     Query(params): Query<helpers::OAuthLoginParams>,
+    headers: HeaderMap,
     cookies: Cookies,
 ) -> impl IntoResponse {
     helpers::axum_login_handler::<AppState, Missing, Configured<Arc<TokenManager>>>(
         Path(provider),
         State(state),
         Query(params),
+        headers,
         cookies,
     )
     .await
@@ -119,6 +121,7 @@ async fn callback_handler(
         token_manager,
         3600, // 1 hour
         state.auth.session_config.clone(),
+        state.auth.consent_sink.as_ref(),
     )
     .await
     .map_err(|(status, msg)| {
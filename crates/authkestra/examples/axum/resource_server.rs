@@ -1,5 +1,5 @@
 use authkestra_axum::Jwt;
-use authkestra_resource::jwt::{JwksCache, ValidationConfig};
+use authkestra_resource::jwt::{JwksCache, ResourceServer};
 use axum::{
     extract::FromRef,
     response::{IntoResponse, Json},
@@ -63,26 +63,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenvy::dotenv().ok();
     let config = Config::from_env();
 
-    // 1. Configure Offline Validation components using the builder
-    let validation_config = ValidationConfig::builder()
-        .jwks_url(format!("{}/.well-known/jwks.json", config.issuer)) // Simplified for the example
-        .issuer(&config.issuer)
-        .audience(config.audience.as_deref().unwrap_or_default())
-        .build();
-
-    let jwks_cache = Arc::new(JwksCache::new(
-        validation_config.jwks_url,
-        validation_config.refresh_interval,
-    ));
-
-    let mut validation = jsonwebtoken::Validation::new(validation_config.algorithms[0]);
-    validation.algorithms = validation_config.algorithms;
-    if let Some(iss) = validation_config.issuer {
-        validation.set_issuer(&[iss]);
-    }
-    if let Some(aud) = config.audience.as_deref() {
-        validation.set_audience(&[aud]);
-    }
+    // 1. Discover the issuer and configure offline validation against its JWKS
+    let ResourceServer {
+        jwks_cache,
+        validation,
+    } = ResourceServer::from_issuer(&config.issuer, config.audience.as_deref()).await?;
 
     let state = AppState {
         jwks_cache,
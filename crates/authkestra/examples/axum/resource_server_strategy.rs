@@ -42,7 +42,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .issuer(issuer)
         .build();
 
-    let jwt_strategy = JwtStrategy::<UserIdentity>::new(validation_config);
+    let jwt_strategy = JwtStrategy::<UserIdentity>::new(validation_config)?;
 
     // 2. Configure the Resource Enforcer (Guard)
     let guard = Guard::builder().strategy(jwt_strategy).build();
@@ -23,15 +23,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .initiate_device_authorization(&["user", "repo"])
         .await?;
 
-    println!(
-        "\n1. Open your browser and go to: {}",
-        device_resp.verification_uri
-    );
-    println!("2. Enter the code: {}", device_resp.user_code);
-
-    if let Some(complete_uri) = &device_resp.verification_uri_complete {
-        println!("\nOR just open this URL directly: {complete_uri}");
-    }
+    println!("\n{}", device_resp.instructions());
 
     println!("\nWaiting for authorization...");
 
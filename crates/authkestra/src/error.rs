@@ -0,0 +1,129 @@
+//! A unified error type for applications.
+//!
+//! Library authors should keep using the granular errors of each crate;
+//! [`AuthkestraError`] lets application code use `?` across all of them.
+
+use authkestra_engine::error::AuthError;
+use authkestra_engine::store::StoreError;
+use thiserror::Error;
+
+/// Any error produced by the enabled `authkestra-*` crates.
+///
+/// Each variant is transparent: `Display` and `source` are those of the
+/// wrapped error, which is reached by matching the variant.
+#[derive(Debug, Error)]
+pub enum AuthkestraError {
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[cfg(feature = "resource")]
+    #[error(transparent)]
+    Validation(#[from] authkestra_resource::jwt::ValidationError),
+    #[cfg(feature = "oidc")]
+    #[error(transparent)]
+    Oidc(#[from] authkestra_oidc::OidcError),
+    #[cfg(feature = "axum")]
+    #[error(transparent)]
+    Axum(#[from] authkestra_axum::AxumError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_auth_error_converts() {
+        let err: AuthkestraError = AuthError::InvalidCode.into();
+        assert!(matches!(err, AuthkestraError::Auth(AuthError::InvalidCode)));
+        assert_eq!(err.to_string(), "Invalid code");
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn test_store_error_converts() {
+        let err: AuthkestraError = StoreError::NotFound.into();
+        assert_eq!(err.to_string(), "Not found");
+        assert!(matches!(err, AuthkestraError::Store(StoreError::NotFound)));
+    }
+
+    #[cfg(feature = "resource")]
+    #[test]
+    fn test_validation_error_converts() {
+        use authkestra_resource::jwt::ValidationError;
+
+        let err: AuthkestraError = ValidationError::KeyNotFound.into();
+        assert_eq!(err.to_string(), "Key not found in JWKS");
+        assert!(matches!(
+            err,
+            AuthkestraError::Validation(ValidationError::KeyNotFound)
+        ));
+    }
+
+    #[cfg(feature = "resource")]
+    #[test]
+    fn test_source_chain_reaches_the_inner_cause() {
+        use authkestra_resource::jwt::ValidationError;
+
+        let cause = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let cause_message = cause.to_string();
+        let err: AuthkestraError = ValidationError::Serialization(cause).into();
+
+        // Transparent: the wrapper reports the inner cause, not the
+        // `ValidationError` itself, as its source.
+        assert_eq!(
+            err.to_string(),
+            format!("Serialization error: {cause_message}")
+        );
+        let source = err.source().expect("the JSON error is the source");
+        assert_eq!(source.to_string(), cause_message);
+        assert!(source.downcast_ref::<serde_json::Error>().is_some());
+        assert!(source.source().is_none());
+    }
+
+    #[cfg(feature = "oidc")]
+    #[test]
+    fn test_oidc_error_converts() {
+        use authkestra_oidc::OidcError;
+
+        let err: AuthkestraError = OidcError::Discovery("unreachable".to_string()).into();
+        assert_eq!(err.to_string(), "Discovery error: unreachable");
+        assert!(matches!(
+            err,
+            AuthkestraError::Oidc(OidcError::Discovery(msg)) if msg == "unreachable"
+        ));
+    }
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn test_axum_error_converts() {
+        let err: AuthkestraError =
+            authkestra_axum::AxumError::Unauthorized("no session".into()).into();
+        assert_eq!(err.to_string(), "Unauthorized: no session");
+        assert!(matches!(
+            err,
+            AuthkestraError::Axum(authkestra_axum::AxumError::Unauthorized(_))
+        ));
+    }
+
+    #[test]
+    fn test_question_mark_across_crates() {
+        fn store() -> Result<(), StoreError> {
+            Err(StoreError::NotFound)
+        }
+        fn authenticate() -> Result<(), AuthError> {
+            Err(AuthError::InvalidCredentials)
+        }
+        fn app(step: u8) -> Result<(), AuthkestraError> {
+            match step {
+                0 => store()?,
+                _ => authenticate()?,
+            }
+            Ok(())
+        }
+
+        assert!(matches!(app(0), Err(AuthkestraError::Store(_))));
+        assert!(matches!(app(1), Err(AuthkestraError::Auth(_))));
+    }
+}
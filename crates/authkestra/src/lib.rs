@@ -5,6 +5,9 @@
 
 pub use authkestra_engine as core;
 
+pub mod error;
+pub use error::AuthkestraError;
+
 #[cfg(feature = "flow")]
 pub use authkestra_engine as flow;
 
@@ -17,6 +20,9 @@ pub use authkestra_engine as token;
 #[cfg(feature = "oidc")]
 pub use authkestra_oidc as oidc;
 
+#[cfg(feature = "webauthn")]
+pub use authkestra_webauthn as webauthn;
+
 #[cfg(feature = "axum")]
 pub use authkestra_axum as axum;
 
@@ -0,0 +1,102 @@
+use authkestra_axum::{AuthSession, AxumState};
+use authkestra_engine::{
+    state::Identity, store::memory::MemoryStore, AkWebAppEngine, Engine, Session, SessionConfig,
+    SessionStore,
+};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    routing::get,
+    Router,
+};
+use chrono::{Duration, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn engine() -> AkWebAppEngine {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    Engine::builder()
+        .session_store(store)
+        .session_config(SessionConfig {
+            max_age: Some(Duration::hours(1)),
+            absolute_max_age: Some(Duration::hours(12)),
+            ..Default::default()
+        })
+        .build()
+}
+
+/// A session created `age` ago that was kept alive until now.
+fn session(id: &str, age: Duration) -> Session {
+    Session {
+        id: id.to_string(),
+        identity: Identity {
+            provider_id: "mock".to_string(),
+            external_id: "alice".to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        },
+        expires_at: Utc::now() + Duration::hours(1),
+        created_at: Utc::now() - age,
+    }
+}
+
+async fn status(engine: &AkWebAppEngine, session_id: &str) -> StatusCode {
+    let app = Router::new()
+        .route("/me", get(|_: AuthSession| async { "ok" }))
+        .with_state(AxumState::from(engine.clone()));
+    let request = Request::builder()
+        .uri("/me")
+        .header(header::COOKIE, format!("authkestra_session={session_id}"))
+        .body(Body::empty())
+        .unwrap();
+    app.oneshot(request).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn test_touch_stops_at_the_absolute_cap() {
+    let engine = engine();
+    let store = engine.session_store();
+
+    let fresh = engine
+        .create_session(session("unused", Duration::zero()).identity)
+        .await
+        .unwrap();
+    let touched = engine.touch_session(&fresh.id).await.unwrap().unwrap();
+    assert!(touched.expires_at > Utc::now() + Duration::minutes(59));
+
+    // Ten minutes before the cap, touching only extends up to the cap.
+    let near = session("near", Duration::hours(12) - Duration::minutes(10));
+    store.save_session(&near).await.unwrap();
+    let touched = engine.touch_session("near").await.unwrap().unwrap();
+    assert_eq!(touched.expires_at, near.created_at + Duration::hours(12));
+    assert_eq!(status(&engine, "near").await, StatusCode::OK);
+
+    // Past the cap, the session is rejected although `expires_at` is ahead.
+    let old = session("old", Duration::hours(12) + Duration::seconds(1));
+    store.save_session(&old).await.unwrap();
+    assert!(!old.is_expired());
+    assert!(engine.touch_session("old").await.unwrap().is_none());
+    assert_eq!(status(&engine, "old").await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        store.load_session("old").await.unwrap().unwrap().expires_at,
+        old.expires_at
+    );
+}
+
+#[tokio::test]
+async fn test_sessions_are_uncapped_by_default() {
+    let engine = engine();
+    let uncapped = Engine::builder()
+        .session_store(engine.session_store())
+        .build();
+
+    let old = session("old", Duration::days(30));
+    uncapped.session_store().save_session(&old).await.unwrap();
+    assert!(uncapped.touch_session("old").await.unwrap().is_some());
+    assert_eq!(status(&uncapped, "old").await, StatusCode::OK);
+    assert_eq!(status(&engine, "old").await, StatusCode::UNAUTHORIZED);
+}
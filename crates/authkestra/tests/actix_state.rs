@@ -0,0 +1,101 @@
+use actix_web::{cookie::Cookie, test, web, App, HttpResponse};
+use authkestra_actix::{ActixExt, AuthSession, AuthToken, AuthkestraState, ValidCsrf};
+use authkestra_engine::{
+    state::Identity, store::memory::MemoryStore, AkEngine, Engine, Session, SessionStore,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn identity() -> Identity {
+    Identity {
+        provider_id: "mock".to_string(),
+        external_id: "alice".to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+fn engine() -> AkEngine {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    Engine::builder()
+        .session_store(store)
+        .jwt_secret(b"a-test-secret-of-at-least-32-bytes!!")
+        .build()
+}
+
+#[actix_web::test]
+async fn test_extractors_read_the_unified_state() {
+    let engine = engine();
+    let session = engine.create_session(identity()).await.unwrap();
+    let csrf_token = engine.session_config.csrf().issue(&session.id);
+    let jwt = engine.issue_token(identity(), 3600).unwrap();
+
+    // Only the unified state is registered; no separate store, config or token manager.
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AuthkestraState::from(&engine)))
+            .route(
+                "/me",
+                web::get().to(|AuthSession(session): AuthSession| async move {
+                    HttpResponse::Ok().body(session.identity.external_id)
+                }),
+            )
+            .route(
+                "/settings",
+                web::post()
+                    .to(|_: AuthSession, _: ValidCsrf| async { HttpResponse::Ok().finish() }),
+            )
+            .route(
+                "/api",
+                web::get().to(|AuthToken(claims): AuthToken| async move {
+                    HttpResponse::Ok().body(claims.sub)
+                }),
+            )
+            .service(engine.actix_scope()),
+    )
+    .await;
+    let cookie = || Cookie::new("authkestra_session", session.id.clone());
+
+    let request = test::TestRequest::get()
+        .uri("/me")
+        .cookie(cookie())
+        .to_request();
+    assert_eq!(test::call_and_read_body(&app, request).await, "alice");
+
+    let request = test::TestRequest::get().uri("/me").to_request();
+    assert_eq!(test::call_service(&app, request).await.status(), 401);
+
+    let request = test::TestRequest::post()
+        .uri("/settings")
+        .cookie(cookie())
+        .insert_header(("x-csrf-token", csrf_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, request).await.status(), 200);
+
+    let request = test::TestRequest::get()
+        .uri("/api")
+        .insert_header(("authorization", format!("Bearer {jwt}")))
+        .to_request();
+    assert_eq!(test::call_and_read_body(&app, request).await, "alice");
+
+    // The auth scope carries the engine itself.
+    let request = test::TestRequest::post()
+        .uri("/auth/logout")
+        .cookie(cookie())
+        .set_form([(
+            "logout_token",
+            engine.session_config.logout_token(&session.id),
+        )])
+        .to_request();
+    assert_eq!(test::call_service(&app, request).await.status(), 302);
+    assert!(engine
+        .session_store
+        .0
+        .load_session(&session.id)
+        .await
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,227 @@
+use async_trait::async_trait;
+use authkestra_engine::{
+    strategy::{TokenStrategy, TokenValidator},
+    AuthError,
+};
+use authkestra_resource::{AuthorizationEngine, Decision, Guard, LocalRules};
+use axum::{body::Body, extract::FromRef, http::Request, routing::delete, Router};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+#[derive(Clone)]
+struct User {
+    name: String,
+}
+
+struct StaticValidator;
+
+#[async_trait]
+impl TokenValidator for StaticValidator {
+    type Identity = User;
+
+    async fn validate(&self, token: &str) -> Result<Option<User>, AuthError> {
+        Ok((token == "token").then(|| User {
+            name: "alice".to_string(),
+        }))
+    }
+}
+
+struct AllowAll;
+
+#[async_trait]
+impl AuthorizationEngine<User> for AllowAll {
+    async fn authorize(&self, _: &User, _: &str, _: &str) -> Result<Decision, AuthError> {
+        Ok(Decision::Allow)
+    }
+}
+
+/// Denies deleting documents, like a policy engine would.
+struct DenyDeletes;
+
+#[async_trait]
+impl AuthorizationEngine<User> for DenyDeletes {
+    async fn authorize(
+        &self,
+        user: &User,
+        action: &str,
+        resource: &str,
+    ) -> Result<Decision, AuthError> {
+        if action == "DELETE" && resource.starts_with("/documents/") {
+            return Ok(Decision::Deny(format!(
+                "{} may not delete documents",
+                user.name
+            )));
+        }
+        Ok(Decision::Allow)
+    }
+}
+
+struct Unreachable;
+
+#[async_trait]
+impl AuthorizationEngine<User> for Unreachable {
+    async fn authorize(&self, _: &User, _: &str, _: &str) -> Result<Decision, AuthError> {
+        Err(AuthError::Network)
+    }
+}
+
+fn guard() -> Arc<Guard<User>> {
+    Arc::new(
+        Guard::builder()
+            .strategy(TokenStrategy::new(StaticValidator))
+            .build(),
+    )
+}
+
+#[derive(Clone)]
+struct AppState {
+    guard: Arc<Guard<User>>,
+    engine: Arc<dyn AuthorizationEngine<User>>,
+}
+
+impl FromRef<AppState> for Arc<Guard<User>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.guard.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn AuthorizationEngine<User>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.engine.clone()
+    }
+}
+
+async fn axum_delete(engine: impl AuthorizationEngine<User>, token: Option<&str>) -> (u16, String) {
+    let app = Router::new()
+        .route(
+            "/documents/{id}",
+            delete(
+                |authkestra_axum::Authorized(user): authkestra_axum::Authorized<User>| async move {
+                    user.name
+                },
+            ),
+        )
+        .with_state(AppState {
+            guard: guard(),
+            engine: Arc::new(engine),
+        });
+
+    let mut request = Request::delete("/documents/1");
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = app
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+async fn actix_delete(
+    engine: impl AuthorizationEngine<User>,
+    token: Option<&str>,
+) -> (u16, String) {
+    use actix_web::{test, web, App};
+
+    let engine: Arc<dyn AuthorizationEngine<User>> = Arc::new(engine);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(guard()))
+            .app_data(web::Data::new(engine))
+            .route(
+                "/documents/{id}",
+                web::delete().to(
+                    |authkestra_actix::Authorized(user): authkestra_actix::Authorized<User>| async move {
+                        user.name
+                    },
+                ),
+            ),
+    )
+    .await;
+
+    let mut request = test::TestRequest::delete().uri("/documents/1");
+    if let Some(token) = token {
+        request = request.insert_header(("authorization", format!("Bearer {token}")));
+    }
+    let response = test::call_service(&app, request.to_request()).await;
+    let status = response.status().as_u16();
+    let body = test::read_body(response).await;
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn forbidden_body() -> serde_json::Value {
+    serde_json::json!({ "error": "forbidden", "message": "alice may not delete documents" })
+}
+
+#[tokio::test]
+async fn test_axum_authorization_engine() {
+    assert_eq!(axum_delete(AllowAll, None).await.0, 401);
+    assert_eq!(
+        axum_delete(AllowAll, Some("token")).await,
+        (200, "alice".to_string())
+    );
+
+    let (status, body) = axum_delete(DenyDeletes, Some("token")).await;
+    assert_eq!(status, 403);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+        forbidden_body()
+    );
+
+    assert_eq!(axum_delete(Unreachable, Some("token")).await.0, 500);
+
+    let rules =
+        LocalRules::new().allow("DELETE", "/documents/", |user: &User| user.name == "alice");
+    assert_eq!(axum_delete(rules, Some("token")).await.0, 200);
+    assert_eq!(axum_delete(LocalRules::new(), Some("token")).await.0, 403);
+}
+
+#[actix_web::test]
+async fn test_actix_authorization_engine() {
+    assert_eq!(actix_delete(AllowAll, None).await.0, 401);
+    assert_eq!(
+        actix_delete(AllowAll, Some("token")).await,
+        (200, "alice".to_string())
+    );
+
+    let (status, body) = actix_delete(DenyDeletes, Some("token")).await;
+    assert_eq!(status, 403);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+        forbidden_body()
+    );
+
+    assert_eq!(actix_delete(Unreachable, Some("token")).await.0, 500);
+}
+
+#[tokio::test]
+async fn test_axum_authorization_engine_sees_the_nested_path() {
+    let rules = LocalRules::new().allow("DELETE", "/api/documents", |_: &User| true);
+    let documents = Router::new().route(
+        "/documents/{id}",
+        delete(
+            |authkestra_axum::Authorized(user): authkestra_axum::Authorized<User>| async move {
+                user.name
+            },
+        ),
+    );
+    let app = Router::new().nest("/api", documents).with_state(AppState {
+        guard: guard(),
+        engine: Arc::new(rules),
+    });
+
+    let response = app
+        .oneshot(
+            Request::delete("/api/documents/1")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
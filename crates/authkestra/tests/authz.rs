@@ -0,0 +1,213 @@
+use async_trait::async_trait;
+use authkestra_engine::{
+    strategy::{TokenStrategy, TokenValidator},
+    AuthError,
+};
+use authkestra_resource::{Decision, Guard, Policy};
+use axum::{body::Body, http::Request, routing::get, Router};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+#[derive(Clone)]
+struct User {
+    name: String,
+    admin: bool,
+}
+
+struct StaticValidator;
+
+#[async_trait]
+impl TokenValidator for StaticValidator {
+    type Identity = User;
+
+    async fn validate(&self, token: &str) -> Result<Option<User>, AuthError> {
+        Ok(match token {
+            "admin-token" => Some(User {
+                name: "alice".to_string(),
+                admin: true,
+            }),
+            "user-token" => Some(User {
+                name: "bob".to_string(),
+                admin: false,
+            }),
+            _ => None,
+        })
+    }
+}
+
+struct AdminOnly;
+
+impl Policy<User> for AdminOnly {
+    fn evaluate(user: &User) -> Decision {
+        if user.admin {
+            Decision::Allow
+        } else {
+            Decision::Deny("admin role required".to_string())
+        }
+    }
+}
+
+fn guard() -> Arc<Guard<User>> {
+    Arc::new(
+        Guard::builder()
+            .strategy(TokenStrategy::new(StaticValidator))
+            .build(),
+    )
+}
+
+async fn axum_get(token: Option<&str>) -> (u16, String) {
+    let app = Router::new()
+        .route(
+            "/admin",
+            get(
+                |authkestra_axum::Authz(user, _): authkestra_axum::Authz<User, AdminOnly>| async move {
+                    user.name
+                },
+            ),
+        )
+        .with_state(guard());
+
+    let mut request = Request::builder().uri("/admin");
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = app
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+async fn actix_get(token: Option<&str>) -> (u16, String) {
+    use actix_web::{test, web, App};
+
+    let app = test::init_service(App::new().app_data(web::Data::new(guard())).route(
+        "/admin",
+        web::get().to(
+            |authkestra_actix::Authz(user, _): authkestra_actix::Authz<User, AdminOnly>| async move {
+                user.name
+            },
+        ),
+    ))
+    .await;
+
+    let mut request = test::TestRequest::get().uri("/admin");
+    if let Some(token) = token {
+        request = request.insert_header(("authorization", format!("Bearer {token}")));
+    }
+    let response = test::call_service(&app, request.to_request()).await;
+    let status = response.status().as_u16();
+    let body = test::read_body(response).await;
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn forbidden_body() -> serde_json::Value {
+    serde_json::json!({ "error": "forbidden", "message": "admin role required" })
+}
+
+#[tokio::test]
+async fn test_axum_authz_distinguishes_401_and_403() {
+    assert_eq!(axum_get(None).await.0, 401);
+    assert_eq!(axum_get(Some("forged")).await.0, 401);
+
+    let (status, body) = axum_get(Some("user-token")).await;
+    assert_eq!(status, 403);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+        forbidden_body()
+    );
+
+    assert_eq!(
+        axum_get(Some("admin-token")).await,
+        (200, "alice".to_string())
+    );
+}
+
+#[actix_web::test]
+async fn test_actix_authz_distinguishes_401_and_403() {
+    assert_eq!(actix_get(None).await.0, 401);
+    assert_eq!(actix_get(Some("forged")).await.0, 401);
+
+    let (status, body) = actix_get(Some("user-token")).await;
+    assert_eq!(status, 403);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+        forbidden_body()
+    );
+
+    assert_eq!(
+        actix_get(Some("admin-token")).await,
+        (200, "alice".to_string())
+    );
+}
+
+struct ScopedValidator;
+
+#[async_trait]
+impl TokenValidator for ScopedValidator {
+    type Identity = authkestra_engine::Claims;
+
+    async fn validate(&self, token: &str) -> Result<Option<Self::Identity>, AuthError> {
+        Ok(Some(
+            serde_json::from_value(serde_json::json!({
+                "sub": "alice",
+                "exp": 0,
+                "iat": 0,
+                "scope": token,
+            }))
+            .unwrap(),
+        ))
+    }
+}
+
+struct ReadOrders;
+
+impl authkestra_resource::ScopeRequirement for ReadOrders {
+    const SCOPES: &'static [&'static str] = &["orders:read"];
+}
+
+#[tokio::test]
+async fn test_axum_require_scopes() {
+    type ReadOrdersAuthz = authkestra_axum::Authz<
+        authkestra_engine::Claims,
+        authkestra_axum::RequireScopes<ReadOrders>,
+    >;
+
+    let guard = Arc::new(
+        Guard::builder()
+            .strategy(TokenStrategy::new(ScopedValidator))
+            .build(),
+    );
+    let app = Router::new()
+        .route(
+            "/orders",
+            get(|authkestra_axum::Authz(claims, _): ReadOrdersAuthz| async move { claims.sub }),
+        )
+        .with_state(guard);
+    let call = |scope: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri("/orders")
+                .header("authorization", format!("Bearer {scope}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    let response = call("profile orders:read").await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let response = call("profile").await.unwrap();
+    assert_eq!(response.status(), 403);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+        serde_json::json!({ "error": "forbidden", "message": "missing scope: orders:read" })
+    );
+}
@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use authkestra_axum::Auth;
+use authkestra_engine::{
+    store::{memory::MemoryStore, KvStore},
+    strategy::{AuthenticationStrategy, CachingStrategy, TokenStrategy, TokenValidator},
+    AuthError,
+};
+use authkestra_resource::Guard;
+use axum::{body::Body, http::Request, routing::get, Router};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct User {
+    id: String,
+    exp: i64,
+}
+
+/// Stands in for an introspection endpoint, counting how often it is hit.
+struct CountingValidator {
+    calls: Arc<AtomicUsize>,
+    exp: i64,
+}
+
+#[async_trait]
+impl TokenValidator for CountingValidator {
+    type Identity = User;
+
+    async fn validate(&self, token: &str) -> Result<Option<User>, AuthError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(token.starts_with("opaque-").then(|| User {
+            id: token.trim_start_matches("opaque-").to_string(),
+            exp: self.exp,
+        }))
+    }
+}
+
+fn strategy(calls: &Arc<AtomicUsize>, exp: i64, store: MemoryStore<User>) -> CachingStrategy<User> {
+    CachingStrategy::new(
+        TokenStrategy::new(CountingValidator {
+            calls: calls.clone(),
+            exp,
+        }),
+        store,
+    )
+    .with_ttl(Duration::from_secs(300))
+    .with_expiry(|user: &User| chrono::DateTime::from_timestamp(user.exp, 0))
+}
+
+fn app(strategy: CachingStrategy<User>) -> Router {
+    let guard = Arc::new(Guard::<User>::builder().strategy(strategy).build());
+    Router::new()
+        .route("/", get(|Auth(user): Auth<User>| async move { user.id }))
+        .with_state(guard)
+}
+
+async fn call(app: &Router, token: Option<&str>) -> u16 {
+    let mut request = Request::builder().uri("/");
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn test_inner_strategy_runs_once_per_token() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    let app = app(strategy(&calls, exp, MemoryStore::new()));
+
+    for _ in 0..3 {
+        assert_eq!(call(&app, Some("opaque-alice")).await, 200);
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    assert_eq!(call(&app, Some("opaque-bob")).await, 200);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_failed_and_missing_credentials_are_not_cached() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    let app = app(strategy(&calls, exp, MemoryStore::new()));
+
+    assert_eq!(call(&app, Some("forged")).await, 401);
+    assert_eq!(call(&app, Some("forged")).await, 401);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    assert_eq!(call(&app, None).await, 401);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_expired_identities_are_not_cached() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let exp = chrono::Utc::now().timestamp() - 10;
+    let app = app(strategy(&calls, exp, MemoryStore::new()));
+
+    call(&app, Some("opaque-alice")).await;
+    call(&app, Some("opaque-alice")).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_cache_key_is_not_the_raw_token() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    let store = MemoryStore::new();
+    let strategy = strategy(&calls, exp, store.clone());
+
+    let parts = Request::builder()
+        .header("authorization", "Bearer opaque-alice")
+        .body(())
+        .unwrap()
+        .into_parts()
+        .0;
+    strategy.authenticate(&parts).await.unwrap();
+    strategy.authenticate(&parts).await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    assert_eq!(store.get("opaque-alice").await.unwrap(), None);
+    assert_eq!(
+        store.get("strategy_cache:opaque-alice").await.unwrap(),
+        None
+    );
+}
@@ -0,0 +1,129 @@
+use authkestra_actix::ClientIp as ActixClientIp;
+use authkestra_axum::ClientIp;
+use authkestra_engine::TrustedProxies;
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, FromRef},
+    http::Request,
+    routing::get,
+    Router,
+};
+use std::net::SocketAddr;
+use tower::ServiceExt;
+
+#[derive(Clone, FromRef)]
+struct AppState {
+    trusted: TrustedProxies,
+}
+
+async fn axum_client_ip(trusted: TrustedProxies, peer: &str, xff: Option<&str>) -> String {
+    let app = Router::new()
+        .route(
+            "/",
+            get(|ClientIp(ip): ClientIp| async move { ip.to_string() }),
+        )
+        .with_state(AppState { trusted });
+
+    let mut request = Request::builder().uri("/");
+    if let Some(xff) = xff {
+        request = request.header("x-forwarded-for", xff);
+    }
+    let mut request = request.body(Body::empty()).unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_axum_spoofed_xff_without_trusted_proxies() {
+    let ip = axum_client_ip(
+        TrustedProxies::default(),
+        "203.0.113.7:5000",
+        Some("1.1.1.1"),
+    )
+    .await;
+    assert_eq!(ip, "203.0.113.7");
+}
+
+#[tokio::test]
+async fn test_axum_spoofed_xff_behind_trusted_proxy() {
+    let trusted = TrustedProxies::new(["10.0.0.0/8"]).unwrap();
+    let ip = axum_client_ip(
+        trusted.clone(),
+        "10.0.0.2:5000",
+        Some("1.1.1.1, 198.51.100.23"),
+    )
+    .await;
+    assert_eq!(ip, "198.51.100.23");
+
+    // A client talking to the app directly cannot spoof its address.
+    let ip = axum_client_ip(trusted.clone(), "203.0.113.7:5000", Some("1.1.1.1")).await;
+    assert_eq!(ip, "203.0.113.7");
+
+    let ip = axum_client_ip(trusted, "10.0.0.2:5000", None).await;
+    assert_eq!(ip, "10.0.0.2");
+}
+
+#[tokio::test]
+async fn test_axum_missing_connect_info_is_rejected() {
+    let app = Router::new()
+        .route("/", get(|_: ClientIp| async {}))
+        .with_state(AppState {
+            trusted: TrustedProxies::default(),
+        });
+    let response = app
+        .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 500);
+}
+
+async fn actix_client_ip(trusted: Option<TrustedProxies>, peer: &str, xff: &str) -> String {
+    use actix_web::{test, web, App};
+
+    let mut app = App::new();
+    if let Some(trusted) = trusted {
+        app = app.app_data(web::Data::new(trusted));
+    }
+    let app = test::init_service(app.route(
+        "/",
+        web::get().to(|ActixClientIp(ip): ActixClientIp| async move { ip.to_string() }),
+    ))
+    .await;
+
+    let request = test::TestRequest::get()
+        .uri("/")
+        .peer_addr(peer.parse().unwrap())
+        .insert_header(("x-forwarded-for", xff))
+        .to_request();
+    let body = test::call_and_read_body(&app, request).await;
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[actix_web::test]
+async fn test_actix_spoofed_xff_without_trusted_proxies() {
+    let ip = actix_client_ip(None, "203.0.113.7:5000", "1.1.1.1").await;
+    assert_eq!(ip, "203.0.113.7");
+}
+
+#[actix_web::test]
+async fn test_actix_spoofed_xff_behind_trusted_proxy() {
+    let trusted = TrustedProxies::new(["10.0.0.0/8"]).unwrap();
+    let ip = actix_client_ip(
+        Some(trusted.clone()),
+        "10.0.0.2:5000",
+        "1.1.1.1, 198.51.100.23, 10.0.0.9",
+    )
+    .await;
+    assert_eq!(ip, "198.51.100.23");
+
+    let ip = actix_client_ip(Some(trusted), "203.0.113.7:5000", "1.1.1.1").await;
+    assert_eq!(ip, "203.0.113.7");
+}
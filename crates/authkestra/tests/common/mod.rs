@@ -0,0 +1,45 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+mod provider;
+#[allow(unused_imports)]
+pub use provider::*;
+
+use authkestra_engine::TokenManager;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// A 2048-bit RSA key for signing test tokens.
+pub const RSA_PEM: &[u8] = include_bytes!("../fixtures/rsa_2048.pem");
+
+/// Boots a mock issuer serving discovery and JWKS, and returns a signer for its tokens.
+pub async fn mock_issuer() -> (MockServer, TokenManager) {
+    let server = MockServer::start().await;
+    let manager =
+        TokenManager::new_asymmetric(RSA_PEM, Some(server.uri()), Some("kid-1".to_string()))
+            .unwrap();
+
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "issuer": server.uri(),
+            "authorization_endpoint": format!("{}/authorize", server.uri()),
+            "token_endpoint": format!("{}/token", server.uri()),
+            "jwks_uri": format!("{}/jwks", server.uri()),
+            "id_token_signing_alg_values_supported": ["RS256"],
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "keys": [manager.public_jwk().unwrap()],
+        })))
+        .mount(&server)
+        .await;
+
+    (server, manager)
+}
@@ -0,0 +1,176 @@
+//! The mock OAuth provider and identities used by the flow tests.
+
+use async_trait::async_trait;
+use authkestra_engine::{
+    state::{Identity, OAuthToken},
+    AuthError, OAuthProvider, Provider, ProviderConfig,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// User `user123` of the `mock` provider.
+pub fn identity() -> Identity {
+    identity_for("mock")
+}
+
+/// User `user123` of `provider_id`.
+pub fn identity_for(provider_id: &str) -> Identity {
+    Identity {
+        provider_id: provider_id.to_string(),
+        external_id: "user123".to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+/// A bearer token issued now, without expiry or refresh token.
+pub fn oauth_token(scope: Option<&str>) -> OAuthToken {
+    OAuthToken {
+        access_token: "access".to_string(),
+        token_type: "Bearer".to_string(),
+        expires_in: None,
+        issued_at: chrono::Utc::now(),
+        refresh_token: None,
+        scope: scope.map(str::to_string),
+        id_token: None,
+    }
+}
+
+/// An OAuth provider that authorizes at
+/// `https://{id}.example/authorize?client_id=abc&state=...` and exchanges any
+/// code for user `user123`, or the identity and token it was given.
+///
+/// The refresh token `valid-refresh` is exchanged for a fresh access token and
+/// the rotated refresh token `rotated-refresh`; any other is rejected.
+#[derive(Clone)]
+pub struct MockProvider {
+    id: &'static str,
+    scope: Option<&'static str>,
+    identity: Option<Identity>,
+    token: Option<OAuthToken>,
+    reject_codes: bool,
+    exchanges: Arc<AtomicUsize>,
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self {
+            id: "mock",
+            scope: None,
+            identity: None,
+            token: None,
+            reject_codes: false,
+            exchanges: Arc::default(),
+        }
+    }
+}
+
+impl MockProvider {
+    /// The provider `mock`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `id` as the provider id.
+    pub fn with_id(mut self, id: &'static str) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Grant `scope` with every exchanged token.
+    pub fn with_scope(mut self, scope: &'static str) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    /// Exchange every code for `identity` instead of user `user123`.
+    pub fn with_identity(mut self, identity: Identity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Exchange every code for `token`, overriding [`with_scope`](Self::with_scope).
+    pub fn with_token(mut self, token: OAuthToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Reject every code with [`AuthError::InvalidCode`].
+    pub fn rejecting_codes(mut self) -> Self {
+        self.reject_codes = true;
+        self
+    }
+
+    /// Counts the code exchanges, shared with clones of this provider.
+    pub fn exchanges(&self) -> Arc<AtomicUsize> {
+        self.exchanges.clone()
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    async fn config(&self) -> ProviderConfig {
+        ProviderConfig {
+            id: self.id.to_string(),
+            name: self.id.to_string(),
+            extra: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for MockProvider {
+    fn provider_id(&self) -> &str {
+        self.id
+    }
+
+    fn get_authorization_url(
+        &self,
+        state: &str,
+        _scopes: &[&str],
+        _code_challenge: Option<&str>,
+        _nonce: Option<&str>,
+    ) -> String {
+        format!(
+            "https://{}.example/authorize?client_id=abc&state={state}",
+            self.id
+        )
+    }
+
+    async fn exchange_code_for_identity(
+        &self,
+        _code: &str,
+        _code_verifier: Option<&str>,
+        _nonce: Option<&str>,
+    ) -> Result<(Identity, OAuthToken), AuthError> {
+        self.exchanges.fetch_add(1, Ordering::SeqCst);
+        if self.reject_codes {
+            return Err(AuthError::InvalidCode);
+        }
+        let identity = self
+            .identity
+            .clone()
+            .unwrap_or_else(|| identity_for(self.id));
+        let token = self
+            .token
+            .clone()
+            .unwrap_or_else(|| oauth_token(self.scope));
+        Ok((identity, token))
+    }
+
+    async fn refresh_token(&self, refresh_token: &str) -> Result<OAuthToken, AuthError> {
+        if refresh_token != "valid-refresh" {
+            return Err(AuthError::Provider("invalid_grant".to_string()));
+        }
+        Ok(OAuthToken {
+            access_token: "fresh-access".to_string(),
+            expires_in: Some(3600),
+            refresh_token: Some("rotated-refresh".to_string()),
+            ..oauth_token(None)
+        })
+    }
+}
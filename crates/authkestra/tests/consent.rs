@@ -0,0 +1,272 @@
+mod common;
+
+use async_trait::async_trait;
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{
+    ConsentRecord, ConsentSink, Engine, OAuth2Flow, Scopes, Session, SessionStore,
+};
+use axum::{body::Body, http::Request};
+use common::MockProvider;
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+
+#[derive(Clone, Default)]
+struct RecordingSink(Arc<Mutex<Vec<ConsentRecord>>>);
+
+#[async_trait]
+impl ConsentSink for RecordingSink {
+    async fn record(&self, record: ConsentRecord) {
+        self.0.lock().unwrap().push(record);
+    }
+}
+
+fn set_cookie(response: &axum::response::Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with(&format!("{name}=")))
+        .map(|v| v.split(';').next().unwrap().to_string())
+}
+
+#[tokio::test]
+async fn test_consent_record_is_emitted_with_granted_scopes() {
+    let sink = RecordingSink::default();
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new().with_scope("read:user")))
+        .session_store(store)
+        .consent_sink(Arc::new(sink.clone()))
+        .build();
+    let app = engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login/mock?scope=read:user%20repo")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let location = response.headers()["location"].to_str().unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = set_cookie(&response, "ak_state").unwrap();
+    assert!(sink.0.lock().unwrap().is_empty());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/auth/callback/mock?code=abc&state={state}"))
+                .header("cookie", state_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(response.status().is_redirection());
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].subject, "mock:user123");
+    assert_eq!(records[0].provider, "mock");
+    assert_eq!(records[0].scopes, Scopes::parse("read:user"));
+    assert_eq!(records[0].denied, Scopes::parse("repo"));
+}
+
+#[actix_web::test]
+async fn test_actix_consent_record_is_emitted() {
+    use actix_web::{test, web, App};
+    use authkestra_actix::ActixExt;
+
+    let sink = RecordingSink::default();
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new().with_scope("read:user")))
+        .session_store(store)
+        .consent_sink(Arc::new(sink.clone()))
+        .build();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(engine.clone()))
+            .service(engine.actix_scope()),
+    )
+    .await;
+
+    let request = test::TestRequest::get()
+        .uri("/auth/login/mock?scope=read:user")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    let location = response
+        .headers()
+        .get("location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "ak_state")
+        .unwrap()
+        .into_owned();
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/auth/callback/mock?code=abc&state={state}"))
+        .cookie(state_cookie)
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert!(response.status().is_redirection());
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].scopes, Scopes::parse("read:user"));
+    assert!(records[0].denied.is_empty());
+}
+
+#[tokio::test]
+async fn test_jwt_callback_emits_consent_record() {
+    use authkestra_axum::helpers::{handle_oauth_callback_jwt, OAuthCallbackParams};
+    use authkestra_engine::TokenManager;
+    use axum::{extract::Query, response::IntoResponse, routing::get};
+
+    let sink = RecordingSink::default();
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new().with_scope("read:user")))
+        .session_store(store)
+        .build();
+    let config = engine.session_config.clone();
+    let callback_sink = sink.clone();
+    let jwt_callback = move |cookies: tower_cookies::Cookies,
+                             Query(params): Query<OAuthCallbackParams>| {
+        let (config, sink) = (config.clone(), callback_sink.clone());
+        async move {
+            let flow = OAuth2Flow::new(MockProvider::new().with_scope("read:user"));
+            let tokens = Arc::new(TokenManager::new(b"secret", None));
+            handle_oauth_callback_jwt(&flow, cookies, params, tokens, 60, config, &sink)
+                .await
+                .into_response()
+        }
+    };
+    let app = engine
+        .axum_router()
+        .route("/jwt/callback", get(jwt_callback))
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login/mock?scope=read:user")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let location = response.headers()["location"].to_str().unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = set_cookie(&response, "ak_state").unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/jwt/callback?code=abc&state={state}"))
+                .header("cookie", state_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].subject, "mock:user123");
+    assert_eq!(records[0].scopes, Scopes::parse("read:user"));
+}
+
+#[actix_web::test]
+async fn test_actix_jwt_callback_emits_consent_record() {
+    use actix_web::{test, web, App, HttpRequest};
+    use authkestra_actix::helpers::{handle_oauth_callback_jwt, OAuthCallbackParams};
+    use authkestra_actix::ActixExt;
+    use authkestra_engine::TokenManager;
+
+    let sink = RecordingSink::default();
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new().with_scope("read:user")))
+        .session_store(store)
+        .build();
+    let config = engine.session_config.clone();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(engine.clone()))
+            .app_data(web::Data::new(sink.clone()))
+            .app_data(web::Data::new(config))
+            .route(
+                "/jwt/callback",
+                web::get().to(
+                    |req: HttpRequest,
+                     params: web::Query<OAuthCallbackParams>,
+                     config: web::Data<authkestra_engine::SessionConfig>,
+                     sink: web::Data<RecordingSink>| async move {
+                        let flow = OAuth2Flow::new(MockProvider::new().with_scope("read:user"));
+                        let tokens = Arc::new(TokenManager::new(b"secret", None));
+                        handle_oauth_callback_jwt(
+                            &flow,
+                            &req,
+                            params.into_inner(),
+                            tokens,
+                            60,
+                            config.get_ref().clone(),
+                            sink.get_ref(),
+                        )
+                        .await
+                    },
+                ),
+            )
+            .service(engine.actix_scope()),
+    )
+    .await;
+
+    let request = test::TestRequest::get()
+        .uri("/auth/login/mock?scope=read:user")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    let location = response
+        .headers()
+        .get("location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "ak_state")
+        .unwrap()
+        .into_owned();
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/jwt/callback?code=abc&state={state}"))
+        .cookie(state_cookie)
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), 200);
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].subject, "mock:user123");
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{Engine, OAuth2Flow, Session, SessionStore};
+use axum::{body::Body, http::Request, Router};
+use common::MockProvider;
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+/// Records `(span name, request_id)` for every span with a `request_id` field.
+#[derive(Clone, Default)]
+struct RequestIdSpans(Arc<Mutex<Vec<(String, String)>>>);
+
+impl<S: tracing::Subscriber> Layer<S> for RequestIdSpans {
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        struct RequestId(Option<String>);
+        impl Visit for RequestId {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "request_id" {
+                    self.0 = Some(format!("{value:?}"));
+                }
+            }
+        }
+
+        let mut visitor = RequestId(None);
+        attrs.record(&mut visitor);
+        if let Some(request_id) = visitor.0 {
+            self.0
+                .lock()
+                .unwrap()
+                .push((attrs.metadata().name().to_string(), request_id));
+        }
+    }
+}
+
+fn set_cookie(response: &axum::response::Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with(&format!("{name}=")))
+        .map(|v| v.split(';').next().unwrap().to_string())
+}
+
+fn app() -> Router {
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new()))
+        .session_store(store)
+        .build();
+    engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new())
+}
+
+#[tokio::test]
+async fn test_login_and_callback_share_request_id() {
+    let spans = RequestIdSpans::default();
+    let _guard = tracing_subscriber::registry()
+        .with(spans.clone())
+        .set_default();
+    let app = app();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login/mock")
+                .header("x-request-id", "login-abc")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-request-id"], "login-abc");
+    let location = response.headers()["location"].to_str().unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = set_cookie(&response, "ak_state").unwrap();
+
+    // The callback is a separate request, redirected by the provider with a new id.
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/auth/callback/mock?code=abc&state={state}"))
+                .header("cookie", state_cookie)
+                .header("x-request-id", "callback-xyz")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(response.status().is_redirection());
+    assert_eq!(response.headers()["x-request-id"], "login-abc");
+
+    assert_eq!(
+        *spans.0.lock().unwrap(),
+        [
+            ("oauth_login".to_string(), "login-abc".to_string()),
+            ("oauth_callback".to_string(), "login-abc".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_request_id_is_generated_when_absent_or_invalid() {
+    for header in [None, Some(""), Some("has spaces in it")] {
+        let mut request = Request::builder().uri("/auth/login/mock");
+        if let Some(header) = header {
+            request = request.header("x-request-id", header);
+        }
+        let response = app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let id = response.headers()["x-request-id"].to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(id).is_ok(), "{header:?} -> {id}");
+    }
+}
+
+#[actix_web::test]
+async fn test_actix_login_and_callback_share_request_id() {
+    use actix_web::{test, web, App};
+    use authkestra_actix::ActixExt;
+
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new()))
+        .session_store(store)
+        .build();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(engine.clone()))
+            .service(engine.actix_scope()),
+    )
+    .await;
+
+    let request = test::TestRequest::get()
+        .uri("/auth/login/mock")
+        .insert_header(("x-request-id", "login-abc"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.headers().get("x-request-id").unwrap(), "login-abc");
+    let location = response
+        .headers()
+        .get("location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "ak_state")
+        .unwrap()
+        .into_owned();
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/auth/callback/mock?code=abc&state={state}"))
+        .cookie(state_cookie)
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert!(response.status().is_redirection());
+    assert_eq!(response.headers().get("x-request-id").unwrap(), "login-abc");
+}
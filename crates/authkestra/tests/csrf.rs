@@ -0,0 +1,165 @@
+use authkestra_axum::{helpers::verify_csrf, AxumError, AxumState, HeaderCookies, ValidCsrf};
+use authkestra_engine::{
+    state::Identity, store::memory::MemoryStore, AkWebAppEngine, Engine, Session, SessionStore,
+};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    routing::post,
+    Form, Router,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+async fn engine() -> (AkWebAppEngine, Session) {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    let engine = Engine::builder().session_store(store).build();
+    let session = engine
+        .create_session(Identity {
+            provider_id: "mock".to_string(),
+            external_id: "alice".to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        })
+        .await
+        .unwrap();
+    (engine, session)
+}
+
+#[derive(serde::Deserialize)]
+struct SettingsForm {
+    csrf_token: Option<String>,
+}
+
+async fn app() -> (Router, Session, String) {
+    let (engine, session) = engine().await;
+    let token = engine.session_config.csrf().issue(&session.id);
+    let session_config = engine.session_config.clone();
+    let app = Router::new()
+        .route("/api/settings", post(|_: ValidCsrf| async { "saved" }))
+        .route(
+            "/settings",
+            post(
+                move |cookies: HeaderCookies, Form(form): Form<SettingsForm>| async move {
+                    verify_csrf(&session_config, &cookies, form.csrf_token.as_deref())?;
+                    Ok::<_, AxumError>("saved")
+                },
+            ),
+        )
+        .with_state(AxumState::from(engine));
+    (app, session, token)
+}
+
+async fn post_header(app: &Router, session_id: &str, token: Option<&str>) -> StatusCode {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri("/api/settings")
+        .header(header::COOKIE, format!("authkestra_session={session_id}"));
+    if let Some(token) = token {
+        request = request.header("x-csrf-token", token);
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn post_form(app: &Router, session_id: &str, body: String) -> StatusCode {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/settings")
+        .header(header::COOKIE, format!("authkestra_session={session_id}"))
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(body))
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn test_axum_csrf_header() {
+    let (app, session, token) = app().await;
+
+    assert_eq!(
+        post_header(&app, &session.id, Some(&token)).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        post_header(&app, &session.id, Some("forged")).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        post_header(&app, &session.id, None).await,
+        StatusCode::FORBIDDEN
+    );
+    // A token is only valid for the session it was issued for.
+    assert_eq!(
+        post_header(&app, "another-session", Some(&token)).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn test_axum_csrf_form_field() {
+    let (app, session, token) = app().await;
+
+    assert_eq!(
+        post_form(&app, &session.id, format!("csrf_token={token}")).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        post_form(&app, &session.id, "csrf_token=forged".to_string()).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        post_form(&app, &session.id, String::new()).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn test_logout_token_is_not_a_form_token() {
+    let (engine, session) = engine().await;
+    let config = &engine.session_config;
+    let logout_token = config.logout_token(&session.id);
+
+    assert!(config.verify_logout_token(&session.id, &logout_token));
+    assert!(!config.csrf().verify(&session.id, &logout_token));
+}
+
+#[actix_web::test]
+async fn test_actix_csrf_header() {
+    use actix_web::{cookie::Cookie, test, web, App, HttpResponse};
+    use authkestra_actix::ValidCsrf;
+
+    let (engine, session) = engine().await;
+    let token = engine.session_config.csrf().issue(&session.id);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(engine.session_config.clone()))
+            .route(
+                "/api/settings",
+                web::post().to(|_: ValidCsrf| async { HttpResponse::Ok().finish() }),
+            ),
+    )
+    .await;
+
+    for (header, expected) in [
+        (Some(token.as_str()), 200),
+        (Some("forged"), 403),
+        (None, 403),
+    ] {
+        let mut request = test::TestRequest::post()
+            .uri("/api/settings")
+            .cookie(Cookie::new("authkestra_session", session.id.clone()));
+        if let Some(header) = header {
+            request = request.insert_header(("x-csrf-token", header));
+        }
+        let response = test::call_service(&app, request.to_request()).await;
+        assert_eq!(response.status().as_u16(), expected, "{header:?}");
+    }
+}
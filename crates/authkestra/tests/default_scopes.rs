@@ -0,0 +1,81 @@
+use authkestra_engine::{OAuth2Flow, OAuthProvider, Scopes};
+use authkestra_oidc::OidcProvider;
+use authkestra_providers::{
+    discord::DiscordProvider, github::GithubProvider, google::GoogleProvider,
+};
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+macro_rules! provider {
+    ($provider:ident) => {
+        $provider::new(
+            "client-id".to_string(),
+            "secret".to_string(),
+            "https://app.example/callback".to_string(),
+        )
+        .with_test_urls(
+            "https://idp.example/authorize".to_string(),
+            "https://idp.example/token".to_string(),
+            "https://idp.example/userinfo".to_string(),
+        )
+    };
+}
+
+fn scope<P: OAuthProvider>(flow: &OAuth2Flow<P>, requested: &[&str]) -> String {
+    let request = flow.build_authorization_request(requested, None);
+    // The state records the scopes that were actually requested.
+    assert_eq!(
+        request.flow_state.scopes,
+        Scopes::parse(request.scope.as_deref().unwrap())
+    );
+    request.scope.unwrap()
+}
+
+#[test]
+fn test_provider_defaults_apply_when_none_are_requested() {
+    let github = OAuth2Flow::new(provider!(GithubProvider));
+    assert_eq!(scope(&github, &[]), "read:user user:email");
+    let google = OAuth2Flow::new(provider!(GoogleProvider));
+    assert_eq!(scope(&google, &[]), "openid email profile");
+    let discord = OAuth2Flow::new(provider!(DiscordProvider));
+    assert_eq!(scope(&discord, &[]), "identify email");
+}
+
+#[tokio::test]
+async fn test_oidc_defaults_apply_when_none_are_requested() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "issuer": server.uri(),
+            "authorization_endpoint": format!("{}/authorize", server.uri()),
+            "token_endpoint": format!("{}/token", server.uri()),
+            "jwks_uri": format!("{}/jwks", server.uri()),
+        })))
+        .mount(&server)
+        .await;
+    let provider = OidcProvider::discover(
+        "client-id".to_string(),
+        "secret".to_string(),
+        "https://app.example/callback".to_string(),
+        &server.uri(),
+        Duration::from_secs(3600),
+    )
+    .await
+    .unwrap();
+
+    let flow = OAuth2Flow::new(provider);
+    let request = flow.build_authorization_request(&[], None);
+    assert_eq!(request.scope.as_deref(), Some("openid email profile"));
+}
+
+#[test]
+fn test_requested_and_builder_scopes_take_precedence() {
+    let github = OAuth2Flow::new(provider!(GithubProvider)).with_scopes(vec!["repo"]);
+    assert_eq!(scope(&github, &[]), "repo");
+    assert_eq!(scope(&github, &["gist"]), "gist");
+
+    let google = OAuth2Flow::new(provider!(GoogleProvider));
+    assert_eq!(scope(&google, &["openid"]), "openid");
+}
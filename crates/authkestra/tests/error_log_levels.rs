@@ -0,0 +1,167 @@
+mod common;
+
+use authkestra_axum::AxumExt;
+use authkestra_engine::{
+    auth::{ErrorKind, ErrorLevels},
+    AuthError, Engine, OAuth2Flow, Session, SessionStore,
+};
+use axum::{body::Body, http::Request};
+use common::MockProvider;
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+/// Records the level of every event with a `kind` field.
+#[derive(Clone, Default)]
+struct Levels(Arc<Mutex<Vec<Level>>>);
+
+impl<S: Subscriber> Layer<S> for Levels {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().fields().field("kind").is_some() {
+            self.0.lock().unwrap().push(*event.metadata().level());
+        }
+    }
+}
+
+fn logged_level(log: impl FnOnce()) -> Level {
+    let levels = Levels::default();
+    let subscriber = tracing_subscriber::registry().with(levels.clone());
+    tracing::subscriber::with_default(subscriber, log);
+    let recorded = levels.0.lock().unwrap();
+    assert_eq!(recorded.len(), 1);
+    recorded[0]
+}
+
+#[test]
+fn test_error_kind_names() {
+    assert_eq!(AuthError::CsrfMismatch.kind(), ErrorKind::CsrfMismatch);
+    assert_eq!(ErrorKind::CsrfMismatch.as_str(), "csrf_mismatch");
+    assert_eq!(AuthError::Timeout.kind().to_string(), "timeout");
+}
+
+#[test]
+fn test_default_error_log_levels() {
+    assert_eq!(
+        logged_level(|| AuthError::CsrfMismatch.log("request failed")),
+        Level::INFO
+    );
+    assert_eq!(
+        logged_level(|| AuthError::Network.log("request failed")),
+        Level::WARN
+    );
+    assert_eq!(
+        logged_level(|| AuthError::Session("connection reset".to_string()).log("request failed")),
+        Level::ERROR
+    );
+}
+
+#[test]
+fn test_error_levels_override_by_kind() {
+    let levels = ErrorLevels::new()
+        .with_level(ErrorKind::Network, Level::ERROR)
+        .with_level(ErrorKind::CsrfMismatch, Level::DEBUG);
+
+    assert_eq!(
+        logged_level(|| levels.log(&AuthError::Network, "request failed")),
+        Level::ERROR
+    );
+    assert_eq!(
+        logged_level(|| levels.log(&AuthError::CsrfMismatch, "request failed")),
+        Level::DEBUG
+    );
+    assert_eq!(
+        logged_level(|| levels.log(&AuthError::Timeout, "request failed")),
+        Level::WARN
+    );
+
+    // The overrides belong to the mapping, not to the process.
+    assert_eq!(
+        logged_level(|| AuthError::Network.log("request failed")),
+        Level::WARN
+    );
+}
+
+#[tokio::test]
+async fn test_engine_error_levels_apply_to_callback() {
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new()))
+        .session_store(store)
+        .error_levels(ErrorLevels::new().with_level(ErrorKind::CsrfMismatch, Level::DEBUG))
+        .build();
+    let app = engine.into_router();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login/mock")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let state_cookie = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with("ak_state="))
+        .map(|v| v.split(';').next().unwrap().to_string())
+        .unwrap();
+
+    let levels = Levels::default();
+    let _guard = tracing_subscriber::registry()
+        .with(levels.clone())
+        .set_default();
+    app.oneshot(
+        Request::builder()
+            .uri("/auth/callback/mock?code=abc&state=forged")
+            .header("cookie", state_cookie)
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(*levels.0.lock().unwrap(), [Level::DEBUG]);
+}
+
+#[tokio::test]
+async fn test_engine_error_levels_apply_to_extractors_on_app_routes() {
+    let engine = Engine::builder()
+        .jwt_secret(b"secret")
+        .error_levels(
+            ErrorLevels::new()
+                .with_level(ErrorKind::Token, Level::ERROR)
+                .with_level(ErrorKind::InvalidToken, Level::ERROR),
+        )
+        .build();
+    // The extractor reads the levels from the state, without `axum_router`.
+    let app = axum::Router::new()
+        .route(
+            "/me",
+            axum::routing::get(|_: authkestra_axum::AuthToken| async { "ok" }),
+        )
+        .with_state(authkestra_axum::AxumState::from(engine));
+
+    let levels = Levels::default();
+    let _guard = tracing_subscriber::registry()
+        .with(levels.clone())
+        .set_default();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/me")
+                .header("authorization", "Bearer not-a-jwt")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 401);
+    assert_eq!(*levels.0.lock().unwrap(), [Level::ERROR]);
+}
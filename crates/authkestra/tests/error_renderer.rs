@@ -0,0 +1,138 @@
+use authkestra_axum::{render_errors, AxumError, ErrorRenderer};
+use axum::{
+    body::Body,
+    http::{header, Request},
+    middleware::from_fn_with_state,
+    routing::get,
+    Router,
+};
+use tower::ServiceExt;
+
+fn app(renderer: Option<ErrorRenderer>) -> Router {
+    let app = Router::new()
+        .route(
+            "/",
+            get(|| async { Err::<(), _>(AxumError::Unauthorized("<no session>".to_string())) }),
+        )
+        .route(
+            "/cookie",
+            get(|| async {
+                (
+                    [(header::SET_COOKIE, "ak_state=; Max-Age=0")],
+                    AxumError::BadRequest("Invalid state".to_string()),
+                )
+            }),
+        )
+        .route("/ok", get(|| async { "fine" }));
+    match renderer {
+        Some(renderer) => app.layer(from_fn_with_state(renderer, render_errors)),
+        None => app,
+    }
+}
+
+async fn call(app: Router, uri: &str, accept: Option<&str>) -> (u16, String, String) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(accept) = accept {
+        request = request.header(header::ACCEPT, accept);
+    }
+    let response = app
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let content_type = response.headers()[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        content_type,
+        String::from_utf8(body.to_vec()).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_json_is_the_default() {
+    for app in [app(None), app(Some(ErrorRenderer::Json))] {
+        let (status, content_type, body) = call(app, "/", Some("text/html")).await;
+        assert_eq!(status, 401);
+        assert_eq!(content_type, "application/json");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "error": "unauthorized", "message": "<no session>" })
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_plain_renderer() {
+    let (status, content_type, body) = call(app(Some(ErrorRenderer::Plain)), "/", None).await;
+    assert_eq!(status, 401);
+    assert_eq!(content_type, "text/plain; charset=utf-8");
+    assert_eq!(body, "<no session>");
+}
+
+#[tokio::test]
+async fn test_html_renderer_escapes_message() {
+    let (status, content_type, body) = call(app(Some(ErrorRenderer::Html)), "/", None).await;
+    assert_eq!(status, 401);
+    assert_eq!(content_type, "text/html; charset=utf-8");
+    assert!(body.starts_with("<!DOCTYPE html>"));
+    assert!(body.contains("<h1>401 Unauthorized</h1>"));
+    assert!(body.contains("<p>&lt;no session&gt;</p>"));
+}
+
+#[tokio::test]
+async fn test_auto_renderer_honours_accept() {
+    let cases = [
+        (None, "application/json"),
+        (Some("*/*"), "application/json"),
+        (Some("application/json"), "application/json"),
+        (
+            Some("text/html,application/xhtml+xml,*/*;q=0.8"),
+            "text/html; charset=utf-8",
+        ),
+        (Some("text/plain"), "text/plain; charset=utf-8"),
+        (
+            Some("text/html;q=0.5, text/plain;q=0.9"),
+            "text/plain; charset=utf-8",
+        ),
+        (Some("text/html;q=0, application/xml"), "application/json"),
+    ];
+    for (accept, expected) in cases {
+        let (status, content_type, _) = call(app(Some(ErrorRenderer::Auto)), "/", accept).await;
+        assert_eq!(status, 401);
+        assert_eq!(content_type, expected, "Accept: {accept:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_renderer_keeps_headers_and_ignores_success() {
+    let response = app(Some(ErrorRenderer::Plain))
+        .oneshot(
+            Request::builder()
+                .uri("/cookie")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    assert_eq!(
+        response.headers()[header::SET_COOKIE],
+        "ak_state=; Max-Age=0"
+    );
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/plain; charset=utf-8"
+    );
+
+    let (status, content_type, body) = call(app(Some(ErrorRenderer::Html)), "/ok", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(content_type, "text/plain; charset=utf-8");
+    assert_eq!(body, "fine");
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use authkestra_actix::ActixExt;
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{
+    state::OAuth2State, AkWebAppEngine, Engine, OAuth2Flow, Session, SessionConfig, SessionStore,
+};
+use axum::{body::Body, http::Request};
+use common::MockProvider;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn engine(session_config: SessionConfig) -> AkWebAppEngine {
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new()))
+        .session_store(store)
+        .session_config(session_config)
+        .build()
+}
+
+fn session_config() -> SessionConfig {
+    SessionConfig {
+        max_age: Some(chrono::Duration::days(7)),
+        flow_state_ttl: chrono::Duration::minutes(5),
+        ..Default::default()
+    }
+}
+
+/// Asserts the state cookie and the state it carries expire after the flow TTL.
+fn assert_flow_state_ttl(set_cookie: &str, config: &SessionConfig) {
+    let attributes: Vec<&str> = set_cookie.split(';').map(str::trim).collect();
+    assert!(attributes.contains(&"Max-Age=300"), "{set_cookie}");
+
+    let value = attributes[0].strip_prefix("ak_state=").unwrap();
+    let state = OAuth2State::decrypt(value, &config.state_encryption_key).unwrap();
+    let remaining = state.expires_at - chrono::Utc::now().timestamp();
+    assert!((295..=300).contains(&remaining), "{remaining}");
+}
+
+#[tokio::test]
+async fn test_axum_state_cookie_uses_flow_ttl() {
+    let engine = engine(session_config());
+    let app = engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login/mock")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let set_cookie = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with("ak_state="))
+        .unwrap();
+    assert_flow_state_ttl(set_cookie, &session_config());
+}
+
+#[actix_web::test]
+async fn test_actix_state_cookie_uses_flow_ttl() {
+    use actix_web::{test, App};
+
+    let engine = engine(session_config());
+    let app = test::init_service(App::new().service(engine.actix_scope())).await;
+
+    let request = test::TestRequest::get()
+        .uri("/auth/login/mock")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    let set_cookie = response
+        .headers()
+        .get_all("set-cookie")
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with("ak_state="))
+        .unwrap();
+    assert_flow_state_ttl(set_cookie, &session_config());
+}
+
+#[test]
+fn test_flow_ttl_defaults_to_ten_minutes() {
+    assert_eq!(
+        SessionConfig::default().flow_state_ttl,
+        chrono::Duration::minutes(10)
+    );
+}
@@ -0,0 +1,107 @@
+use authkestra_engine::{ClientCredentialsFlow, OAuthProvider};
+use authkestra_providers::google::GoogleProvider;
+use authkestra_resource::jwt::JwksCache;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next};
+use std::time::Duration;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Tags every outbound request, as a tracing middleware would.
+struct TraceId;
+
+#[async_trait::async_trait]
+impl Middleware for TraceId {
+    async fn handle(
+        &self,
+        mut req: reqwest::Request,
+        extensions: &mut http::Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<reqwest::Response> {
+        req.headers_mut()
+            .insert("x-trace-id", "trace-123".parse().unwrap());
+        next.run(req, extensions).await
+    }
+}
+
+fn client() -> ClientWithMiddleware {
+    ClientBuilder::new(reqwest::Client::new())
+        .with(TraceId)
+        .build()
+}
+
+/// Answers `http_method endpoint` only when the trace header is present.
+async fn traced(server: &MockServer, http_method: &str, endpoint: &str, body: serde_json::Value) {
+    Mock::given(method(http_method))
+        .and(path(endpoint))
+        .and(header("x-trace-id", "trace-123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+fn token() -> serde_json::Value {
+    serde_json::json!({
+        "access_token": "test_access_token",
+        "token_type": "Bearer",
+        "expires_in": 3600,
+    })
+}
+
+#[tokio::test]
+async fn test_middleware_reaches_the_token_endpoint() {
+    let server = MockServer::start().await;
+    traced(&server, "POST", "/token", token()).await;
+
+    let flow = ClientCredentialsFlow::new(
+        "client".to_string(),
+        "secret".to_string(),
+        format!("{}/token", server.uri()),
+    )
+    .with_http_client(client());
+
+    let token = flow.get_token(None).await.unwrap();
+    assert_eq!(token.access_token, "test_access_token");
+}
+
+#[tokio::test]
+async fn test_middleware_reaches_the_provider_endpoints() {
+    let server = MockServer::start().await;
+    traced(&server, "POST", "/token", token()).await;
+    traced(
+        &server,
+        "GET",
+        "/userinfo",
+        serde_json::json!({ "sub": "google-123", "email_verified": true }),
+    )
+    .await;
+
+    let provider = GoogleProvider::new(
+        "client".to_string(),
+        "secret".to_string(),
+        format!("{}/callback", server.uri()),
+    )
+    .with_test_urls(
+        format!("{}/auth", server.uri()),
+        format!("{}/token", server.uri()),
+        format!("{}/userinfo", server.uri()),
+    )
+    .with_http_client(client());
+
+    let (identity, _) = provider
+        .exchange_code_for_identity("code", None, None)
+        .await
+        .unwrap();
+    assert_eq!(identity.external_id, "google-123");
+}
+
+#[tokio::test]
+async fn test_middleware_reaches_the_jwks_endpoint() {
+    let server = MockServer::start().await;
+    traced(&server, "GET", "/jwks", serde_json::json!({ "keys": [] })).await;
+
+    let cache = JwksCache::new(format!("{}/jwks", server.uri()), Duration::from_secs(60))
+        .with_http_client(client());
+
+    assert!(cache.refresh().await.unwrap().keys.is_empty());
+}
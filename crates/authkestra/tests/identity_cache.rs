@@ -0,0 +1,114 @@
+use async_trait::async_trait;
+use authkestra_axum::{helpers::require_auth, Auth, Authz};
+use authkestra_engine::{
+    strategy::{TokenStrategy, TokenValidator},
+    AuthError,
+};
+use authkestra_resource::{Decision, Guard, Policy};
+use axum::{body::Body, http::Request, middleware::from_fn_with_state, routing::get, Router};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+#[derive(Clone, Debug)]
+struct User {
+    id: String,
+}
+
+/// Accepts `token-<id>`, counting how often the guard runs it.
+struct CountingValidator(Arc<AtomicUsize>);
+
+#[async_trait]
+impl TokenValidator for CountingValidator {
+    type Identity = User;
+
+    async fn validate(&self, token: &str) -> Result<Option<User>, AuthError> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(token
+            .strip_prefix("token-")
+            .map(|id| User { id: id.to_string() }))
+    }
+}
+
+struct AnyUser;
+
+impl Policy<User> for AnyUser {
+    fn evaluate(_user: &User) -> Decision {
+        Decision::Allow
+    }
+}
+
+fn guard(calls: &Arc<AtomicUsize>) -> Arc<Guard<User>> {
+    Arc::new(
+        Guard::<User>::builder()
+            .strategy(TokenStrategy::new(CountingValidator(calls.clone())))
+            .build(),
+    )
+}
+
+async fn call(app: Router, uri: &str, token: Option<&str>) -> (u16, String) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = app
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_guard_runs_once_with_layer_and_extractor() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let guard = guard(&calls);
+    let app = Router::new()
+        .route("/", get(|Auth(user): Auth<User>| async move { user.id }))
+        .layer(from_fn_with_state(guard.clone(), require_auth::<User>))
+        .with_state(guard);
+
+    assert_eq!(
+        call(app, "/", Some("token-alice")).await,
+        (200, "alice".to_string())
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_guard_runs_once_for_several_extractors() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let guard = guard(&calls);
+    let app = Router::new()
+        .route(
+            "/",
+            get(
+                |Auth(user): Auth<User>, Authz(same, _): Authz<User, AnyUser>| async move {
+                    assert_eq!(user.id, same.id);
+                    user.id
+                },
+            ),
+        )
+        .with_state(guard);
+
+    assert_eq!(call(app, "/", Some("token-bob")).await.0, 200);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_layer_rejects_unauthenticated_requests() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let guard = guard(&calls);
+    let app = Router::new()
+        .route("/", get(|| async { "reached" }))
+        .layer(from_fn_with_state(guard.clone(), require_auth::<User>))
+        .with_state(guard);
+
+    let (status, body) = call(app.clone(), "/", Some("garbage")).await;
+    assert_eq!(status, 401);
+    assert!(!body.contains("reached"));
+    assert_eq!(call(app, "/", None).await.0, 401);
+}
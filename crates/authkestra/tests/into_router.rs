@@ -0,0 +1,60 @@
+mod common;
+
+use authkestra_axum::AxumExt;
+use authkestra_engine::{Engine, OAuth2Flow};
+use axum::{
+    body::Body,
+    http::{header, Request},
+    routing::get,
+    Router,
+};
+use common::MockProvider;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn app() -> Router {
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new().rejecting_codes()))
+        .session_store(Arc::new(
+            authkestra_engine::store::memory::MemoryStore::default(),
+        ))
+        .build();
+
+    Router::new()
+        .route("/", get(|| async { "home" }))
+        .merge(engine.into_router())
+}
+
+async fn send(request: Request<Body>) -> axum::response::Response {
+    app().oneshot(request).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_merged_router_serves_app_and_auth_routes() {
+    let home = send(Request::get("/").body(Body::empty()).unwrap()).await;
+    assert_eq!(home.status(), 200);
+
+    let login = send(
+        Request::get("/auth/login/mock")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert!(login.status().is_redirection());
+    let location = login.headers()[header::LOCATION].to_str().unwrap();
+    assert!(location.starts_with("https://mock.example/authorize?client_id=abc&state="));
+    // The cookie layer is included: the login state is set as a cookie.
+    assert!(login.headers().contains_key(header::SET_COOKIE));
+
+    let callback = send(
+        Request::get("/auth/callback/mock?code=abc&state=unknown")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert!(callback.status().is_client_error());
+
+    // Logout is mounted: without a session it just redirects.
+    let logout = send(Request::post("/auth/logout").body(Body::empty()).unwrap()).await;
+    assert!(logout.status().is_redirection());
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{AuthError, Engine, OAuth2Flow};
+use axum::{body::Body, http::Request, Router};
+use common::MockProvider;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn app() -> Router {
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new().rejecting_codes()))
+        .session_store(Arc::new(
+            authkestra_engine::store::memory::MemoryStore::default(),
+        ))
+        .build();
+
+    engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new())
+}
+
+async fn login(query: &str) -> axum::response::Response {
+    app()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/auth/login/mock{query}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_prompt_and_login_hint_are_forwarded() {
+    let response = login("?prompt=login&login_hint=jane%2Bdev%40example.com").await;
+    assert!(response.status().is_redirection());
+
+    let location = response.headers()["location"].to_str().unwrap();
+    assert!(location.starts_with("https://mock.example/authorize?client_id=abc&state="));
+    assert!(location.contains("&prompt=login"));
+    assert!(location.contains("&login_hint=jane%2Bdev%40example.com"));
+}
+
+#[tokio::test]
+async fn test_login_without_extra_params_is_unchanged() {
+    let response = login("").await;
+    let location = response.headers()["location"].to_str().unwrap();
+    assert!(!location.contains("prompt="));
+    assert!(!location.contains("login_hint="));
+}
+
+#[tokio::test]
+async fn test_invalid_prompt_is_rejected() {
+    let response = login("?prompt=reauth").await;
+    assert_eq!(response.status(), 400);
+}
+
+#[test]
+fn test_authorization_param_errors_are_typed() {
+    use authkestra_engine::{AuthorizationParams, Prompt};
+
+    assert!(matches!(
+        "reauth".parse::<Prompt>(),
+        Err(AuthError::InvalidRequest(_))
+    ));
+
+    let params = AuthorizationParams {
+        prompt: Some(Prompt::Login),
+        ..Default::default()
+    };
+    assert!(matches!(
+        params.apply("not a url"),
+        Err(AuthError::Config(_))
+    ));
+    assert_eq!(
+        AuthorizationParams::default().apply("not a url").unwrap(),
+        "not a url"
+    );
+}
@@ -0,0 +1,85 @@
+use authkestra_axum::{redirect_to_login, AuthSession, AxumState, LoginRedirect};
+use authkestra_engine::{
+    state::Identity, store::memory::MemoryStore, Engine, Session, SessionStore,
+};
+use axum::{body::Body, http::Request, middleware::from_fn_with_state, routing::get, Router};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn identity() -> Identity {
+    Identity {
+        provider_id: "mock".to_string(),
+        external_id: "alice".to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+async fn call(
+    app: &Router,
+    method: &str,
+    accept: &str,
+    cookie: Option<&str>,
+) -> axum::response::Response {
+    let mut request = Request::builder()
+        .method(method)
+        .uri("/dashboard?tab=1")
+        .header("accept", accept);
+    if let Some(cookie) = cookie {
+        request = request.header("cookie", cookie);
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+const BROWSER: &str = "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8";
+
+#[tokio::test]
+async fn test_browsers_are_redirected_and_api_clients_get_401() {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    let engine = Engine::builder().session_store(store).build();
+    let session = engine.create_session(identity()).await.unwrap();
+    let cookie = format!("authkestra_session={}", session.id);
+
+    let login = LoginRedirect::new("/auth/login/mock").next_param("success_url");
+    let app = Router::new()
+        .route(
+            "/dashboard",
+            get(|AuthSession(session): AuthSession| async move { session.identity.external_id })
+                .post(|_: AuthSession| async { "posted" }),
+        )
+        .layer(from_fn_with_state(login, redirect_to_login))
+        .with_state(AxumState::from(engine));
+
+    let response = call(&app, "GET", BROWSER, None).await;
+    assert_eq!(response.status(), 303);
+    assert_eq!(
+        response.headers()["location"],
+        "/auth/login/mock?success_url=%2Fdashboard%3Ftab%3D1"
+    );
+
+    for (method, accept) in [
+        ("GET", "application/json"),
+        ("GET", "*/*"),
+        ("POST", BROWSER),
+    ] {
+        let response = call(&app, method, accept, None).await;
+        assert_eq!(response.status(), 401, "{method} {accept}");
+        assert!(response.headers().get("location").is_none());
+    }
+
+    let response = call(&app, "GET", BROWSER, Some(&cookie)).await;
+    assert_eq!(response.status(), 200);
+}
+
+#[test]
+fn test_login_url_with_a_query() {
+    let login = LoginRedirect::new("/login?theme=dark");
+    assert_eq!(login.location("/a b"), "/login?theme=dark&next=%2Fa+b");
+}
@@ -0,0 +1,148 @@
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{
+    state::Identity, store::memory::MemoryStore, AkWebAppEngine, Engine, Session, SessionStore,
+};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn engine() -> (AkWebAppEngine, Arc<dyn SessionStore>) {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    let engine = Engine::builder().session_store(store.clone()).build();
+    (engine, store)
+}
+
+async fn login(engine: &AkWebAppEngine) -> Session {
+    engine
+        .create_session(Identity {
+            provider_id: "mock".to_string(),
+            external_id: "alice".to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        })
+        .await
+        .unwrap()
+}
+
+fn app(engine: AkWebAppEngine) -> Router {
+    engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new())
+}
+
+fn logout(method: &str, session_id: &str, form: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri("/auth/logout")
+        .header(header::COOKIE, format!("authkestra_session={session_id}"))
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(form.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_get_and_tokenless_logout_are_rejected() {
+    let (engine, store) = engine();
+    let session = login(&engine).await;
+    let other = login(&engine).await;
+    let app = app(engine.clone());
+
+    // An `<img src="/auth/logout">` cannot log the user out.
+    let response = app
+        .clone()
+        .oneshot(logout("GET", &session.id, ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    for form in [
+        String::new(),
+        "logout_token=forged".to_string(),
+        // A token for another session.
+        format!(
+            "logout_token={}",
+            engine.session_config.logout_token(&other.id)
+        ),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(logout("POST", &session.id, &form))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{form}");
+    }
+    assert!(store.load_session(&session.id).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_logout_with_valid_token_succeeds() {
+    let (engine, store) = engine();
+    let session = login(&engine).await;
+    let token = engine.session_config.logout_token(&session.id);
+
+    let response = app(engine)
+        .oneshot(logout(
+            "POST",
+            &session.id,
+            &format!("logout_token={token}"),
+        ))
+        .await
+        .unwrap();
+    assert!(response.status().is_redirection());
+    assert!(store.load_session(&session.id).await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn test_actix_logout_requires_token() {
+    use actix_web::{cookie::Cookie, test, web, App};
+    use authkestra_actix::ActixExt;
+
+    let (engine, store) = engine();
+    let session = login(&engine).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(engine.clone()))
+            .service(engine.actix_scope()),
+    )
+    .await;
+
+    let request = test::TestRequest::get()
+        .uri("/auth/logout")
+        .cookie(Cookie::new("authkestra_session", session.id.clone()))
+        .to_request();
+    assert!(test::call_service(&app, request)
+        .await
+        .status()
+        .is_client_error());
+
+    let request = test::TestRequest::post()
+        .uri("/auth/logout")
+        .cookie(Cookie::new("authkestra_session", session.id.clone()))
+        .set_form([("logout_token", "forged")])
+        .to_request();
+    assert_eq!(test::call_service(&app, request).await.status(), 403);
+    assert!(store.load_session(&session.id).await.unwrap().is_some());
+
+    let request = test::TestRequest::post()
+        .uri("/auth/logout")
+        .cookie(Cookie::new("authkestra_session", session.id.clone()))
+        .set_form([(
+            "logout_token",
+            engine.session_config.logout_token(&session.id),
+        )])
+        .to_request();
+    assert!(test::call_service(&app, request)
+        .await
+        .status()
+        .is_redirection());
+    assert!(store.load_session(&session.id).await.unwrap().is_none());
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use async_trait::async_trait;
+use authkestra_engine::{
+    state::Identity,
+    strategy::{MapIdentity, SessionProvider, SessionStrategy},
+    AuthError, Claims, TokenManager,
+};
+use authkestra_resource::{
+    jwt::{JwtStrategy, ValidationConfig},
+    Guard,
+};
+use common::RSA_PEM;
+use http::Request;
+use std::collections::HashMap;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const ISSUER: &str = "https://issuer.example";
+
+/// The application's user, whichever strategy authenticated it.
+#[derive(Debug, PartialEq)]
+struct AppUser {
+    id: String,
+    via: &'static str,
+}
+
+impl TryFrom<Identity> for AppUser {
+    type Error = AuthError;
+
+    fn try_from(identity: Identity) -> Result<Self, AuthError> {
+        if identity.email_verified != Some(true) {
+            return Err(AuthError::UnverifiedEmail);
+        }
+        Ok(AppUser {
+            id: identity.subject(),
+            via: "session",
+        })
+    }
+}
+
+fn identity(external_id: &str, email_verified: bool) -> Identity {
+    Identity {
+        provider_id: "github".to_string(),
+        external_id: external_id.to_string(),
+        email: Some(format!("{external_id}@example.com")),
+        email_verified: Some(email_verified),
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+/// Sessions `sid-<user>`, with `bob`'s email unverified.
+struct Sessions;
+
+#[async_trait]
+impl SessionProvider for Sessions {
+    type Identity = Identity;
+
+    async fn load_session(&self, session_id: &str) -> Result<Option<Identity>, AuthError> {
+        Ok(session_id
+            .strip_prefix("sid-")
+            .map(|user| identity(user, user != "bob")))
+    }
+}
+
+fn manager() -> TokenManager {
+    TokenManager::new_asymmetric(RSA_PEM, Some(ISSUER.to_string()), Some("kid-1".to_string()))
+        .unwrap()
+}
+
+async fn guard(server: &MockServer) -> Guard<AppUser> {
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "keys": [manager().public_jwk().unwrap()] })),
+        )
+        .mount(server)
+        .await;
+    let jwt = JwtStrategy::<Claims>::new(
+        ValidationConfig::builder()
+            .jwks_url(format!("{}/jwks", server.uri()))
+            .issuer(ISSUER)
+            .build(),
+    )
+    .unwrap();
+
+    Guard::builder()
+        .strategy(MapIdentity::new(jwt, |claims: Claims| AppUser {
+            id: claims.sub,
+            via: "jwt",
+        }))
+        .strategy(MapIdentity::try_new(
+            SessionStrategy::new(Sessions, "authkestra_session"),
+            AppUser::try_from,
+        ))
+        .build()
+}
+
+async fn authenticate(
+    guard: &Guard<AppUser>,
+    headers: &[(&str, &str)],
+) -> Result<Option<AppUser>, AuthError> {
+    let mut request = Request::builder();
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    guard
+        .authenticate(&request.body(()).unwrap().into_parts().0)
+        .await
+}
+
+#[tokio::test]
+async fn test_guard_unifies_jwt_and_session_identities() {
+    let server = MockServer::start().await;
+    let guard = guard(&server).await;
+
+    let token = manager()
+        .issue_user_token(identity("alice", true), 300, None, None)
+        .unwrap();
+    let bearer = format!("Bearer {token}");
+    assert_eq!(
+        authenticate(&guard, &[("authorization", &bearer)])
+            .await
+            .unwrap(),
+        Some(AppUser {
+            id: "alice".to_string(),
+            via: "jwt",
+        })
+    );
+
+    assert_eq!(
+        authenticate(&guard, &[("cookie", "authkestra_session=sid-carol")])
+            .await
+            .unwrap(),
+        Some(AppUser {
+            id: "github:carol".to_string(),
+            via: "session",
+        })
+    );
+
+    assert!(matches!(
+        authenticate(&guard, &[("cookie", "authkestra_session=sid-bob")]).await,
+        Err(AuthError::UnverifiedEmail)
+    ));
+    assert_eq!(authenticate(&guard, &[]).await.unwrap(), None);
+}
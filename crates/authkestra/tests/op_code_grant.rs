@@ -0,0 +1,227 @@
+use authkestra_axum::{AxumState, OpExt};
+use authkestra_engine::{
+    pkce::Pkce, state::Identity, store::memory::MemoryStore, store::KvStore, AkEngine, Engine,
+    SessionStore, TokenManager,
+};
+use authkestra_op::{
+    client::{ClientRegistration, GrantType},
+    code::AuthorizationCode,
+    config::OpConfig,
+    device::DeviceCodeSession,
+    refresh::RefreshToken,
+    store::CompositeOpStore,
+    OpStore,
+};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const REDIRECT_URI: &str = "https://client.example/callback";
+
+#[derive(Clone, AxumState)]
+struct AppState {
+    #[authkestra(engine)]
+    auth: AkEngine,
+
+    #[authkestra(store)]
+    op_store: Arc<dyn OpStore>,
+
+    #[authkestra(store)]
+    config: OpConfig,
+}
+
+/// An OP with one public client requiring PKCE, and a session id for a logged-in user.
+async fn op() -> (Router, Arc<TokenManager>, String) {
+    let clients = MemoryStore::<ClientRegistration>::new();
+    clients
+        .set(
+            "spa",
+            ClientRegistration {
+                client_id: "spa".to_string(),
+                client_secret_hash: None,
+                redirect_uris: vec![REDIRECT_URI.to_string()],
+                grant_types: vec![GrantType::AuthorizationCode],
+                scopes: vec!["openid".to_string(), "profile".to_string()],
+                require_pkce: true,
+                allowed_audiences: vec![],
+            },
+            std::time::Duration::from_secs(3600),
+        )
+        .await
+        .unwrap();
+    let op_store: Arc<dyn OpStore> = Arc::new(CompositeOpStore::new(
+        clients,
+        MemoryStore::<AuthorizationCode>::new(),
+        MemoryStore::<RefreshToken>::new(),
+        MemoryStore::<DeviceCodeSession>::new(),
+    ));
+
+    let tokens = Arc::new(TokenManager::new(
+        b"op-code-grant-test-secret-of-32-bytes!",
+        Some("https://op.example".to_string()),
+    ));
+    let session_store: Arc<dyn SessionStore> = Arc::new(MemoryStore::new());
+    let auth = Engine::builder()
+        .session_store(session_store)
+        .token_manager(tokens.clone())
+        .build();
+    let session = auth
+        .create_session(Identity {
+            provider_id: "local".to_string(),
+            external_id: "alice".to_string(),
+            email: None,
+            email_verified: None,
+            username: Some("alice".to_string()),
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        })
+        .await
+        .unwrap();
+
+    let state = AppState {
+        auth,
+        op_store,
+        config: OpConfig {
+            issuer: "https://op.example".to_string(),
+            scopes_supported: vec!["openid".to_string(), "profile".to_string()],
+            response_types_supported: vec!["code".to_string()],
+            grant_types_supported: vec!["authorization_code".to_string()],
+            id_token_signing_alg: "RS256".to_string(),
+            authorization_code_ttl_secs: 60,
+            access_token_ttl_secs: 300,
+            device_code_ttl_secs: 600,
+            token_exchange_enabled: false,
+        },
+    };
+    let app = Router::new()
+        .merge(state.op_axum_router())
+        .with_state(state)
+        .layer(tower_cookies::CookieManagerLayer::new());
+    (app, tokens, session.id)
+}
+
+async fn authorize(app: &Router, session_id: &str, query: &str) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/authorize?{query}"))
+                .header(header::COOKIE, format!("authkestra_session={session_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+async fn token(app: &Router, form: &str) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/token")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(form.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn authorize_query(pkce: &Pkce, redirect_uri: &str) -> String {
+    format!(
+        "response_type=code&client_id=spa&redirect_uri={}&scope=openid%20profile&state=xyz\
+         &code_challenge={}&code_challenge_method=S256",
+        urlencode(redirect_uri),
+        pkce.code_challenge
+    )
+}
+
+fn urlencode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+/// The `code` query parameter of the redirect to the client.
+fn code_from(response: &axum::response::Response) -> String {
+    let location = response.headers()[header::LOCATION].to_str().unwrap();
+    assert!(location.starts_with(REDIRECT_URI), "{location}");
+    let url = url::Url::parse(location).unwrap();
+    let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    assert_eq!(query["state"], "xyz");
+    query["code"].clone()
+}
+
+#[tokio::test]
+async fn test_authorize_code_token_round_trip() {
+    let (app, tokens, session_id) = op().await;
+    let pkce = Pkce::new();
+
+    let response = authorize(&app, &session_id, &authorize_query(&pkce, REDIRECT_URI)).await;
+    assert!(response.status().is_redirection());
+    let code = code_from(&response);
+
+    let form = format!(
+        "grant_type=authorization_code&code={code}&client_id=spa&redirect_uri={}&code_verifier={}",
+        urlencode(REDIRECT_URI),
+        pkce.code_verifier
+    );
+    let (status, body) = token(&app, &form).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["token_type"], "Bearer");
+    let claims = tokens
+        .validate_token(body["access_token"].as_str().unwrap(), None)
+        .unwrap();
+    assert_eq!(claims.sub, "alice");
+
+    // Codes are single-use.
+    let (status, body) = token(&app, &form).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "invalid_grant");
+}
+
+#[tokio::test]
+async fn test_unregistered_redirect_uri_is_not_redirected_to() {
+    let (app, _, session_id) = op().await;
+    let query = authorize_query(&Pkce::new(), "https://attacker.example/callback");
+
+    let response = authorize(&app, &session_id, &query).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.headers().get(header::LOCATION).is_none());
+}
+
+#[tokio::test]
+async fn test_pkce_is_enforced() {
+    let (app, _, session_id) = op().await;
+
+    // No code_challenge: the error is sent back to the registered redirect_uri.
+    let query = format!(
+        "response_type=code&client_id=spa&redirect_uri={}&scope=openid&state=xyz",
+        urlencode(REDIRECT_URI)
+    );
+    let response = authorize(&app, &session_id, &query).await;
+    let location = response.headers()[header::LOCATION].to_str().unwrap();
+    assert!(location.contains("error=invalid_request"), "{location}");
+
+    // A code exchanged with the wrong verifier is rejected.
+    let pkce = Pkce::new();
+    let response = authorize(&app, &session_id, &authorize_query(&pkce, REDIRECT_URI)).await;
+    let code = code_from(&response);
+    let form = format!(
+        "grant_type=authorization_code&code={code}&client_id=spa&redirect_uri={}&code_verifier={}",
+        urlencode(REDIRECT_URI),
+        Pkce::new().code_verifier
+    );
+    let (status, body) = token(&app, &form).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "invalid_grant");
+}
@@ -0,0 +1,105 @@
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{
+    state::Identity, store::memory::MemoryStore, AkWebAppEngine, Engine, SameSite, Session,
+    SessionConfig, SessionStore,
+};
+use axum::{
+    body::Body,
+    http::{header, Request},
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+async fn engine() -> (AkWebAppEngine, Session) {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .session_store(store)
+        .session_config(SessionConfig {
+            same_site: SameSite::None,
+            secure: true,
+            partitioned: true,
+            ..Default::default()
+        })
+        .build();
+    let session = engine
+        .create_session(Identity {
+            provider_id: "mock".to_string(),
+            external_id: "alice".to_string(),
+            email: None,
+            email_verified: None,
+            username: None,
+            attributes: HashMap::new(),
+            attributes_multi: HashMap::new(),
+        })
+        .await
+        .unwrap();
+    (engine, session)
+}
+
+fn assert_partitioned(set_cookie: &str) {
+    assert!(
+        set_cookie.starts_with("authkestra_session="),
+        "{set_cookie}"
+    );
+    for attribute in ["SameSite=None", "Secure", "Partitioned"] {
+        assert!(
+            set_cookie.split("; ").any(|a| a == attribute),
+            "{attribute} missing from {set_cookie}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_axum_session_cookie_is_partitioned() {
+    let (engine, session) = engine().await;
+    let token = engine.session_config.logout_token(&session.id);
+    let app = engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/auth/logout")
+                .header(header::COOKIE, format!("authkestra_session={}", session.id))
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(format!("logout_token={token}")))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(response.status().is_redirection());
+    let set_cookie = response.headers().get(header::SET_COOKIE).unwrap();
+    assert_partitioned(set_cookie.to_str().unwrap());
+}
+
+#[actix_web::test]
+async fn test_actix_session_cookie_is_partitioned() {
+    use actix_web::{cookie::Cookie, test, web, App};
+    use authkestra_actix::ActixExt;
+
+    let (engine, session) = engine().await;
+    let token = engine.session_config.logout_token(&session.id);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(engine.clone()))
+            .service(engine.actix_scope()),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/auth/logout")
+        .cookie(Cookie::new("authkestra_session", session.id.clone()))
+        .set_form([("logout_token", token)])
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert!(response.status().is_redirection());
+    let set_cookie = response
+        .headers()
+        .get(actix_web::http::header::SET_COOKIE)
+        .unwrap();
+    assert_partitioned(set_cookie.to_str().unwrap());
+}
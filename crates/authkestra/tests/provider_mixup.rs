@@ -0,0 +1,77 @@
+mod common;
+
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{Engine, OAuth2Flow, Session, SessionStore};
+use axum::{body::Body, http::Request, Router};
+use common::MockProvider;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn provider(id: &'static str) -> (MockProvider, Arc<AtomicUsize>) {
+    let provider = MockProvider::new().with_id(id);
+    let exchanges = provider.exchanges();
+    (provider, exchanges)
+}
+
+async fn call(app: &Router, uri: &str, cookie: Option<&str>) -> axum::response::Response {
+    let mut request = Request::builder().uri(uri);
+    if let Some(cookie) = cookie {
+        request = request.header("cookie", cookie);
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_state_minted_for_another_provider_is_rejected() {
+    let (github, github_exchanges) = provider("github");
+    let (google, google_exchanges) = provider("google");
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(github))
+        .provider(OAuth2Flow::new(google))
+        .session_store(store)
+        .build();
+    let app: Router = engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new());
+
+    let response = call(&app, "/auth/login/github", None).await;
+    let location = response.headers()["location"].to_str().unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with("ak_state="))
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+
+    let response = call(
+        &app,
+        &format!("/auth/callback/google?code=abc&state={state}"),
+        Some(&state_cookie),
+    )
+    .await;
+    assert!(response.status().is_client_error(), "{}", response.status());
+    assert_eq!(google_exchanges.load(Ordering::SeqCst), 0);
+
+    // The same state is still good for the provider it was minted for.
+    let response = call(
+        &app,
+        &format!("/auth/callback/github?code=abc&state={state}"),
+        Some(&state_cookie),
+    )
+    .await;
+    assert!(response.status().is_redirection(), "{}", response.status());
+    assert_eq!(github_exchanges.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{Engine, OAuth2Flow, UnknownProviderBody, UnknownProviderResponse};
+use axum::{body::Body, http::Request, Router};
+use common::MockProvider;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// The provider `tenant-a`, whose codes are all rejected.
+fn tenant() -> OAuth2Flow<MockProvider> {
+    OAuth2Flow::new(MockProvider::new().with_id("tenant-a").rejecting_codes())
+}
+
+#[tokio::test]
+async fn test_register_provider_after_build() {
+    let engine = Engine::builder()
+        .session_store(Arc::new(
+            authkestra_engine::store::memory::MemoryStore::default(),
+        ))
+        .build();
+    assert!(engine.providers.is_empty());
+
+    engine.register_provider(tenant());
+    assert!(engine.providers.contains("tenant-a"));
+
+    let response = engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new())
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login/tenant-a")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert!(response.status().is_redirection());
+    let location = response.headers()["location"].to_str().unwrap();
+    assert!(location.starts_with("https://tenant-a.example/authorize?client_id=abc&state="));
+}
+
+async fn login(app: &Router) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login/tenant-a")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_providers_change_while_serving() {
+    let engine = Engine::builder()
+        .session_store(Arc::new(
+            authkestra_engine::store::memory::MemoryStore::default(),
+        ))
+        .unknown_provider_response(UnknownProviderResponse {
+            status: 404,
+            body: UnknownProviderBody::Text,
+        })
+        .build();
+    // The router is built from a clone; the registry is shared with it.
+    let app = engine
+        .axum_router()
+        .with_state(AxumState::from(engine.clone()))
+        .layer(tower_cookies::CookieManagerLayer::new());
+    assert_eq!(login(&app).await.status(), 404);
+
+    engine.register_provider(tenant());
+    assert_eq!(engine.provider_ids(), ["tenant-a"]);
+    assert!(login(&app).await.status().is_redirection());
+
+    assert!(engine.remove_provider("tenant-a"));
+    assert!(!engine.remove_provider("tenant-a"));
+    assert!(engine.provider_ids().is_empty());
+    assert_eq!(login(&app).await.status(), 404);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{Engine, OAuth2Flow, Session, SessionConfig, SessionStore};
+use axum::{body::Body, http::Request, Router};
+use common::MockProvider;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn set_cookie<'a>(response: &'a axum::response::Response, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with(&format!("{name}=")))
+}
+
+/// Runs login then callback, returning the session cookie header and the stored session.
+async fn login(query: &str) -> (String, Session) {
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new()))
+        .session_store(store.clone())
+        .session_config(SessionConfig {
+            max_age: Some(chrono::Duration::hours(1)),
+            remember_max_age: Some(chrono::Duration::days(30)),
+            ..Default::default()
+        })
+        .build();
+    let app: Router = engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/auth/login/mock{query}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let location = response.headers()["location"].to_str().unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = set_cookie(&response, "ak_state")
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/auth/callback/mock?code=abc&state={state}"))
+                .header("cookie", state_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(response.status().is_redirection());
+
+    let session_cookie = set_cookie(&response, "authkestra_session")
+        .unwrap()
+        .to_string();
+    let session_id = session_cookie
+        .split(';')
+        .next()
+        .unwrap()
+        .trim_start_matches("authkestra_session=");
+    let session = store.load_session(session_id).await.unwrap().unwrap();
+    (session_cookie, session)
+}
+
+fn remaining_hours(session: &Session) -> i64 {
+    (session.remaining_ttl().unwrap() + chrono::Duration::minutes(1)).num_hours()
+}
+
+#[tokio::test]
+async fn test_default_login_uses_max_age() {
+    let (cookie, session) = login("").await;
+    assert!(cookie.contains("Max-Age=3600"));
+    assert_eq!(remaining_hours(&session), 1);
+}
+
+#[tokio::test]
+async fn test_remember_me_uses_remember_max_age() {
+    let (cookie, session) = login("?remember=true").await;
+    assert!(cookie.contains("Max-Age=2592000"));
+    assert_eq!(remaining_hours(&session), 30 * 24);
+
+    let (cookie, _) = login("?remember=false").await;
+    assert!(cookie.contains("Max-Age=3600"));
+}
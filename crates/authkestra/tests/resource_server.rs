@@ -0,0 +1,207 @@
+mod common;
+
+use authkestra_axum::Jwt;
+use authkestra_engine::TokenManager;
+use authkestra_resource::jwt::{JwksCache, ResourceServer};
+use axum::{extract::FromRef, routing::get, Router};
+use common::{identity_for, mock_issuer, RSA_PEM};
+use serde::Deserialize;
+use std::sync::Arc;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[derive(Deserialize)]
+struct MyClaims {
+    sub: String,
+}
+
+#[derive(Clone)]
+struct AppState {
+    resource: ResourceServer,
+}
+
+impl FromRef<AppState> for Arc<JwksCache> {
+    fn from_ref(state: &AppState) -> Self {
+        state.resource.jwks_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for jsonwebtoken::Validation {
+    fn from_ref(state: &AppState) -> Self {
+        state.resource.validation.clone()
+    }
+}
+
+/// Serves a router protected by `Jwt<MyClaims>` and returns its base URL.
+async fn serve(resource: ResourceServer) -> String {
+    let app = Router::new()
+        .route(
+            "/protected",
+            get(|Jwt(claims): Jwt<MyClaims>| async move { claims.sub }),
+        )
+        .with_state(AppState { resource });
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+async fn get_protected(base: &str, token: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{base}/protected"))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_from_issuer_validates_tokens_with_audience() {
+    let (issuer, manager) = mock_issuer().await;
+    let resource = ResourceServer::from_issuer(&issuer.uri(), Some("my-api"))
+        .await
+        .unwrap();
+    let base = serve(resource).await;
+
+    let token = manager
+        .issue_user_token(identity_for("oidc"), 300, None, Some("my-api".to_string()))
+        .unwrap();
+    let res = get_protected(&base, &token).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.text().await.unwrap(), "user123");
+
+    let wrong_aud = manager
+        .issue_user_token(
+            identity_for("oidc"),
+            300,
+            None,
+            Some("other-api".to_string()),
+        )
+        .unwrap();
+    assert_eq!(get_protected(&base, &wrong_aud).await.status(), 401);
+}
+
+#[tokio::test]
+async fn test_from_issuer_without_audience() {
+    let (issuer, manager) = mock_issuer().await;
+    let resource = ResourceServer::from_issuer(&issuer.uri(), None)
+        .await
+        .unwrap();
+    let base = serve(resource).await;
+
+    let token = manager
+        .issue_user_token(identity_for("oidc"), 300, None, Some("any-api".to_string()))
+        .unwrap();
+    assert_eq!(get_protected(&base, &token).await.status(), 200);
+
+    let other = TokenManager::new_asymmetric(
+        RSA_PEM,
+        Some("https://evil.example".to_string()),
+        Some("kid-1".to_string()),
+    )
+    .unwrap();
+    let wrong_iss = other
+        .issue_user_token(identity_for("oidc"), 300, None, None)
+        .unwrap();
+    assert_eq!(get_protected(&base, &wrong_iss).await.status(), 401);
+}
+
+/// A token for `issuer` that expired an hour ago, well past the validation leeway.
+fn expired_token(issuer: &str) -> String {
+    let now = chrono::Utc::now().timestamp();
+    let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+    header.kid = Some("kid-1".to_string());
+    jsonwebtoken::encode(
+        &header,
+        &serde_json::json!({ "sub": "user123", "iss": issuer, "exp": now - 3600 }),
+        &jsonwebtoken::EncodingKey::from_rsa_pem(RSA_PEM).unwrap(),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_expired_token_gets_an_oauth_error() {
+    let (issuer, _) = mock_issuer().await;
+    let resource = ResourceServer::from_issuer(&issuer.uri(), None)
+        .await
+        .unwrap();
+    let base = serve(resource).await;
+    let token = expired_token(&issuer.uri());
+
+    for accept in [None, Some("application/json")] {
+        let mut request = reqwest::Client::new()
+            .get(format!("{base}/protected"))
+            .bearer_auth(&token);
+        if let Some(accept) = accept {
+            request = request.header("accept", accept);
+        }
+        let res = request.send().await.unwrap();
+        assert_eq!(res.status(), 401);
+        let challenge = res.headers()["www-authenticate"].to_str().unwrap();
+        assert!(
+            challenge.starts_with(r#"Bearer error="invalid_token", error_description=""#),
+            "{challenge}"
+        );
+        assert_eq!(res.headers()["content-type"], "application/json");
+        let body: serde_json::Value = res.json().await.unwrap();
+        assert_eq!(body["error"], "invalid_token");
+        assert!(body["error_description"]
+            .as_str()
+            .unwrap()
+            .contains("ExpiredSignature"));
+    }
+
+    // Browsers get a page.
+    let res = reqwest::Client::new()
+        .get(format!("{base}/protected"))
+        .bearer_auth(&token)
+        .header("accept", "text/html,application/xhtml+xml,*/*;q=0.8")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+    assert!(res.headers().contains_key("www-authenticate"));
+    assert_eq!(res.headers()["content-type"], "text/html; charset=utf-8");
+    let page = res.text().await.unwrap();
+    assert!(page.contains("<h1>401 Unauthorized</h1>"), "{page}");
+    assert!(page.contains("ExpiredSignature"), "{page}");
+
+    // A request without a token gets a bare challenge.
+    let res = reqwest::Client::new()
+        .get(format!("{base}/protected"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+    assert_eq!(res.headers()["www-authenticate"], "Bearer");
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["error"], "invalid_request");
+}
+
+#[tokio::test]
+async fn test_from_issuer_keeps_advertised_asymmetric_algorithms() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "issuer": server.uri(),
+            "authorization_endpoint": format!("{}/authorize", server.uri()),
+            "token_endpoint": format!("{}/token", server.uri()),
+            "jwks_uri": format!("{}/jwks", server.uri()),
+            "id_token_signing_alg_values_supported": ["HS256", "ES256", "RS256", "none"],
+        })))
+        .mount(&server)
+        .await;
+
+    let resource = ResourceServer::from_issuer(&server.uri(), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        resource.validation.algorithms,
+        [
+            jsonwebtoken::Algorithm::RS256,
+            jsonwebtoken::Algorithm::ES256
+        ]
+    );
+}
@@ -0,0 +1,137 @@
+use authkestra_axum::{helpers::logout, AuthSession, AxumState, HeaderCookies};
+use authkestra_engine::{
+    state::Identity, Configured, Engine, Missing, Session, SessionConfig, SessionStore,
+};
+use axum::{
+    body::Body,
+    http::{header, Request},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn identity(id: &str) -> Identity {
+    Identity {
+        provider_id: "mock".to_string(),
+        external_id: id.to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+fn config() -> SessionConfig {
+    SessionConfig {
+        cookie_name: "authkestra_session".to_string(),
+        fallback_cookie_names: vec!["authly_session".to_string()],
+        ..Default::default()
+    }
+}
+
+fn engine(store: Arc<dyn SessionStore>) -> Engine<Configured<Arc<dyn SessionStore>>, Missing> {
+    Engine::builder()
+        .session_store(store)
+        .session_config(config())
+        .build()
+}
+
+async fn call(app: &Router, cookie: &str) -> (u16, String) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/")
+                .header(header::COOKIE, cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_legacy_cookie_resolves_session() {
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = engine(store);
+    let session = engine.create_session(identity("alice")).await.unwrap();
+
+    let app = Router::new()
+        .route(
+            "/",
+            get(|AuthSession(session): AuthSession| async move { session.identity.external_id }),
+        )
+        .with_state(AxumState::from(engine));
+
+    let (status, body) = call(&app, &format!("authly_session={}", session.id)).await;
+    assert_eq!(status, 200);
+    assert_eq!(body, "alice");
+
+    let (status, _) = call(&app, "unrelated_session=whatever").await;
+    assert_eq!(status, 401);
+}
+
+#[tokio::test]
+async fn test_primary_cookie_takes_precedence() {
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = engine(store);
+    let legacy = engine.create_session(identity("legacy")).await.unwrap();
+    let current = engine.create_session(identity("current")).await.unwrap();
+
+    let app = Router::new()
+        .route(
+            "/",
+            get(|AuthSession(session): AuthSession| async move { session.identity.external_id }),
+        )
+        .with_state(AxumState::from(engine));
+
+    let cookie = format!(
+        "authly_session={}; authkestra_session={}",
+        legacy.id, current.id
+    );
+    assert_eq!(call(&app, &cookie).await, (200, "current".to_string()));
+
+    // A stale primary cookie falls through to the legacy one.
+    let cookie = format!("authkestra_session=gone; authly_session={}", legacy.id);
+    assert_eq!(call(&app, &cookie).await, (200, "legacy".to_string()));
+}
+
+#[tokio::test]
+async fn test_logout_clears_legacy_session() {
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = engine(store.clone());
+    let session = engine.create_session(identity("alice")).await.unwrap();
+
+    let mut headers = axum::http::HeaderMap::new();
+    headers.insert(
+        header::COOKIE,
+        format!("authly_session={}", session.id).parse().unwrap(),
+    );
+    let cookies = HeaderCookies::from_headers(&headers);
+    let redirect = logout(cookies.clone(), store.clone(), config(), "/")
+        .await
+        .unwrap();
+    let response = (cookies, redirect).into_response();
+
+    assert!(store.load_session(&session.id).await.unwrap().is_none());
+    let removed: Vec<_> = response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|v| v.to_str().unwrap().split('=').next().unwrap().to_string())
+        .collect();
+    assert!(removed.contains(&"authkestra_session".to_string()));
+    assert!(removed.contains(&"authly_session".to_string()));
+}
@@ -0,0 +1,152 @@
+use async_trait::async_trait;
+use authkestra_axum::AxumState;
+use authkestra_engine::{
+    state::Identity, store::memory::MemoryStore, AuthError, Engine, Session, SessionStore,
+};
+use axum::{body::Body, http::Request, routing::get, Router};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// A memory store whose backend can be taken down.
+#[derive(Default)]
+struct FlakyStore {
+    inner: MemoryStore<Session>,
+    down: AtomicBool,
+}
+
+#[async_trait]
+impl SessionStore for FlakyStore {
+    async fn load_session(&self, id: &str) -> Result<Option<Session>, AuthError> {
+        if self.down.load(Ordering::SeqCst) {
+            return Err(AuthError::Session("connection refused".to_string()));
+        }
+        self.inner.load_session(id).await
+    }
+
+    async fn save_session(&self, session: &Session) -> Result<(), AuthError> {
+        self.inner.save_session(session).await
+    }
+
+    async fn delete_session(&self, id: &str) -> Result<(), AuthError> {
+        self.inner.delete_session(id).await
+    }
+}
+
+fn identity() -> Identity {
+    Identity {
+        provider_id: "mock".to_string(),
+        external_id: "alice".to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+/// A flaky store and the id of a session saved in it.
+async fn setup() -> (
+    Arc<FlakyStore>,
+    Engine<authkestra_engine::Configured<Arc<dyn SessionStore>>>,
+    String,
+) {
+    let store = Arc::new(FlakyStore::default());
+    let engine = Engine::builder()
+        .session_store(store.clone() as Arc<dyn SessionStore>)
+        .build();
+    let session = engine.create_session(identity()).await.unwrap();
+    (store, engine, session.id)
+}
+
+#[tokio::test]
+async fn test_axum_backend_error_is_not_a_logout() {
+    let (store, engine, session_id) = setup().await;
+    let app = Router::new()
+        .route(
+            "/me",
+            get(
+                |authkestra_axum::AuthSession(session): authkestra_axum::AuthSession| async move {
+                    session.identity.external_id
+                },
+            ),
+        )
+        .with_state(AxumState::from(engine));
+    let call = |cookie: String| {
+        app.clone().oneshot(
+            Request::get("/me")
+                .header("cookie", cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+    let cookie = format!("authkestra_session={session_id}");
+
+    assert_eq!(call(cookie.clone()).await.unwrap().status(), 200);
+
+    store.down.store(true, Ordering::SeqCst);
+    assert_eq!(call(cookie.clone()).await.unwrap().status(), 500);
+
+    store.down.store(false, Ordering::SeqCst);
+    assert_eq!(call(cookie).await.unwrap().status(), 200);
+    let unknown = call("authkestra_session=unknown".to_string())
+        .await
+        .unwrap();
+    assert_eq!(unknown.status(), 401);
+}
+
+#[actix_web::test]
+async fn test_actix_backend_error_is_not_a_logout() {
+    use actix_web::{cookie::Cookie, test, web, App, HttpResponse};
+
+    let (store, engine, session_id) = setup().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(authkestra_actix::AuthkestraState::from(
+                &engine,
+            )))
+            .route(
+                "/me",
+                web::get().to(
+                    |authkestra_actix::AuthSession(session): authkestra_actix::AuthSession| async move {
+                        HttpResponse::Ok().body(session.identity.external_id)
+                    },
+                ),
+            ),
+    )
+    .await;
+    let request = |session_id: &str| {
+        test::TestRequest::get()
+            .uri("/me")
+            .cookie(Cookie::new("authkestra_session", session_id.to_string()))
+            .to_request()
+    };
+
+    assert_eq!(
+        test::call_service(&app, request(&session_id))
+            .await
+            .status(),
+        200
+    );
+
+    store.down.store(true, Ordering::SeqCst);
+    assert_eq!(
+        test::call_service(&app, request(&session_id))
+            .await
+            .status(),
+        500
+    );
+
+    store.down.store(false, Ordering::SeqCst);
+    assert_eq!(
+        test::call_service(&app, request(&session_id))
+            .await
+            .status(),
+        200
+    );
+    assert_eq!(
+        test::call_service(&app, request("unknown")).await.status(),
+        401
+    );
+}
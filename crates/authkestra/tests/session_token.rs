@@ -0,0 +1,157 @@
+use authkestra_axum::{helpers::session_token_router, AxumState};
+use authkestra_engine::{
+    state::Identity, store::memory::MemoryStore, AkEngine, Engine, Session, SessionStore,
+};
+use axum::{body::Body, http::Request, Router};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+/// Records the `event` field of every `authkestra::security` event.
+#[derive(Clone, Default)]
+struct SecurityEvents(Arc<Mutex<Vec<String>>>);
+
+impl<S: Subscriber> Layer<S> for SecurityEvents {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        struct EventName<'a>(&'a mut Vec<String>);
+        impl Visit for EventName<'_> {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                if field.name() == "event" {
+                    self.0.push(value.to_string());
+                }
+            }
+            fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+        }
+        if event.metadata().target() == "authkestra::security" {
+            event.record(&mut EventName(&mut self.0.lock().unwrap()));
+        }
+    }
+}
+
+fn engine() -> AkEngine {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    Engine::builder()
+        .session_store(store)
+        .jwt_secret(b"a-test-secret-of-at-least-32-bytes!!")
+        .build()
+}
+
+fn identity() -> Identity {
+    Identity {
+        provider_id: "mock".to_string(),
+        external_id: "alice".to_string(),
+        email: Some("alice@example.com".to_string()),
+        email_verified: Some(true),
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+async fn post_token(app: Router, cookie: Option<&str>) -> (u16, serde_json::Value) {
+    let mut request = Request::post("/auth/token");
+    if let Some(cookie) = cookie {
+        request = request.header("cookie", cookie);
+    }
+    let response = app
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_session_is_exchanged_for_a_valid_jwt() {
+    let engine = engine();
+    let session = engine.create_session(identity()).await.unwrap();
+    let token_manager = engine.token_manager();
+    let app = Router::new()
+        .merge(session_token_router(300))
+        .with_state(AxumState::from(engine));
+
+    let cookie = format!("authkestra_session={}", session.id);
+    let (status, body) = post_token(app, Some(&cookie)).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["token_type"], "Bearer");
+    assert_eq!(body["expires_in"], 300);
+
+    let claims = token_manager
+        .validate_token(body["access_token"].as_str().unwrap(), None)
+        .unwrap();
+    assert_eq!(claims.sub, "alice");
+    assert!(claims.exp - claims.iat <= 300);
+    assert_eq!(
+        claims.identity.unwrap().email.as_deref(),
+        Some("alice@example.com")
+    );
+}
+
+#[tokio::test]
+async fn test_token_requires_a_valid_session() {
+    let app = || {
+        Router::new()
+            .merge(session_token_router(300))
+            .with_state(AxumState::from(engine()))
+    };
+
+    let (status, body) = post_token(app(), None).await;
+    assert_eq!(status, 401);
+    assert!(body.get("access_token").is_none());
+
+    let (status, _) = post_token(app(), Some("authkestra_session=unknown")).await;
+    assert_eq!(status, 401);
+}
+
+#[actix_web::test]
+async fn test_actix_session_is_exchanged_for_a_valid_jwt() {
+    use actix_web::{cookie::Cookie, test, web, App};
+    use authkestra_actix::{helpers::session_token_resource, ActixExt, AuthkestraState};
+
+    let engine = engine();
+    let session = engine.create_session(identity()).await.unwrap();
+    let token_manager = engine.token_manager();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AuthkestraState::from(&engine)))
+            .service(session_token_resource(300))
+            .service(engine.actix_scope()),
+    )
+    .await;
+
+    let events = SecurityEvents::default();
+    let _guard = tracing_subscriber::registry()
+        .with(events.clone())
+        .set_default();
+    let request = test::TestRequest::post()
+        .uri("/auth/token")
+        .cookie(Cookie::new("authkestra_session", session.id.clone()))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(body["token_type"], "Bearer");
+    assert_eq!(body["expires_in"], 300);
+    assert_eq!(*events.0.lock().unwrap(), ["session_token_issued"]);
+
+    let claims = token_manager
+        .validate_token(body["access_token"].as_str().unwrap(), None)
+        .unwrap();
+    assert_eq!(claims.sub, "alice");
+    assert!(claims.exp - claims.iat <= 300);
+
+    let request = test::TestRequest::post().uri("/auth/token").to_request();
+    assert_eq!(test::call_service(&app, request).await.status(), 401);
+    let request = test::TestRequest::post()
+        .uri("/auth/token")
+        .cookie(Cookie::new("authkestra_session", "unknown"))
+        .to_request();
+    assert_eq!(test::call_service(&app, request).await.status(), 401);
+    assert_eq!(events.0.lock().unwrap().len(), 1);
+}
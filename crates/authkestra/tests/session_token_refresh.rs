@@ -0,0 +1,138 @@
+mod common;
+
+use authkestra_axum::{AuthSessionWithToken, AxumState};
+use authkestra_engine::{state::Identity, Engine, OAuth2Flow};
+use axum::{body::Body, http::Request, routing::get, Json, Router};
+use common::MockProvider;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn identity(expires_at: i64, refresh_token: Option<&str>) -> Identity {
+    let mut attributes = HashMap::new();
+    attributes.insert("access_token".to_string(), "expired-access".to_string());
+    attributes.insert("expires_at".to_string(), expires_at.to_string());
+    if let Some(rt) = refresh_token {
+        attributes.insert("refresh_token".to_string(), rt.to_string());
+    }
+    Identity {
+        provider_id: "mock".to_string(),
+        external_id: "user123".to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes,
+        attributes_multi: HashMap::new(),
+    }
+}
+
+async fn handler(auth: AuthSessionWithToken) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "access_token": auth.access_token,
+        "stale": auth.token_stale,
+    }))
+}
+
+async fn call(app: Router, session_id: &str) -> serde_json::Value {
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/")
+                .header("cookie", format!("authkestra_session={session_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_expired_token_is_refreshed_and_session_saved() {
+    let store = Arc::new(authkestra_engine::store::memory::MemoryStore::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new().rejecting_codes()))
+        .session_store(store.clone())
+        .build();
+
+    let expired = chrono::Utc::now().timestamp() - 60;
+    let session = engine
+        .create_session(identity(expired, Some("valid-refresh")))
+        .await
+        .unwrap();
+
+    let app = Router::new()
+        .route("/", get(handler))
+        .with_state(AxumState::from(engine));
+
+    let body = call(app, &session.id).await;
+    assert_eq!(body["access_token"], "fresh-access");
+    assert_eq!(body["stale"], false);
+
+    let saved = authkestra_engine::SessionStore::load_session(store.as_ref(), &session.id)
+        .await
+        .unwrap()
+        .unwrap();
+    let attributes = &saved.identity.attributes;
+    assert_eq!(attributes["access_token"], "fresh-access");
+    assert_eq!(attributes["refresh_token"], "rotated-refresh");
+    assert!(attributes["expires_at"].parse::<i64>().unwrap() > chrono::Utc::now().timestamp());
+}
+
+#[tokio::test]
+async fn test_failed_refresh_returns_session_with_stale_token() {
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new().rejecting_codes()))
+        .session_store(Arc::new(
+            authkestra_engine::store::memory::MemoryStore::default(),
+        ))
+        .build();
+
+    let expired = chrono::Utc::now().timestamp() - 60;
+    let revoked = engine
+        .create_session(identity(expired, Some("revoked-refresh")))
+        .await
+        .unwrap();
+    let no_refresh = engine
+        .create_session(identity(expired, None))
+        .await
+        .unwrap();
+
+    let app = Router::new()
+        .route("/", get(handler))
+        .with_state(AxumState::from(engine));
+
+    for session in [revoked, no_refresh] {
+        let body = call(app.clone(), &session.id).await;
+        assert_eq!(body["access_token"], "expired-access");
+        assert_eq!(body["stale"], true);
+    }
+}
+
+#[tokio::test]
+async fn test_valid_token_is_not_refreshed() {
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new().rejecting_codes()))
+        .session_store(Arc::new(
+            authkestra_engine::store::memory::MemoryStore::default(),
+        ))
+        .build();
+
+    let valid = chrono::Utc::now().timestamp() + 3600;
+    let session = engine
+        .create_session(identity(valid, Some("valid-refresh")))
+        .await
+        .unwrap();
+
+    let app = Router::new()
+        .route("/", get(handler))
+        .with_state(AxumState::from(engine));
+
+    let body = call(app, &session.id).await;
+    assert_eq!(body["access_token"], "expired-access");
+    assert_eq!(body["stale"], false);
+}
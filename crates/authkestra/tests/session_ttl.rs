@@ -0,0 +1,132 @@
+mod common;
+
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{Engine, OAuth2Flow, Session, SessionConfig, SessionStore};
+use axum::{body::Body, http::Request, Router};
+use chrono::Duration;
+use common::{identity, MockProvider};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn set_cookie<'a>(response: &'a axum::response::Response, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with(&format!("{name}=")))
+}
+
+fn config() -> SessionConfig {
+    SessionConfig {
+        session_ttl: Some(Duration::days(7)),
+        cookie_max_age: Some(Duration::minutes(10)),
+        ..Default::default()
+    }
+}
+
+/// The `Max-Age` attribute of a `set-cookie` header.
+fn max_age(set_cookie: &str) -> i64 {
+    set_cookie
+        .split(';')
+        .find_map(|attr| attr.trim().strip_prefix("Max-Age="))
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_login_cookie_and_session_expire_independently() {
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new()))
+        .session_store(store.clone())
+        .session_config(config())
+        .build();
+    let app: Router = engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login/mock")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let location = response.headers()["location"].to_str().unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = set_cookie(&response, "ak_state")
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/auth/callback/mock?code=abc&state={state}"))
+                .header("cookie", state_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let session_cookie = set_cookie(&response, "authkestra_session").unwrap();
+    assert_eq!(max_age(session_cookie), 600);
+
+    let session_id = session_cookie
+        .split(';')
+        .next()
+        .unwrap()
+        .trim_start_matches("authkestra_session=");
+    let session = store.load_session(session_id).await.unwrap().unwrap();
+    let ttl = session.expires_at - chrono::Utc::now();
+    assert!(ttl > Duration::days(7) - Duration::minutes(1) && ttl <= Duration::days(7));
+}
+
+#[tokio::test]
+async fn test_create_session_uses_session_ttl() {
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .session_store(store)
+        .session_config(SessionConfig {
+            session_ttl: Some(Duration::minutes(5)),
+            cookie_max_age: Some(Duration::days(30)),
+            ..Default::default()
+        })
+        .build();
+
+    let session = engine.create_session(identity()).await.unwrap();
+    let ttl = session.expires_at - chrono::Utc::now();
+    assert!(ttl > Duration::minutes(4) && ttl <= Duration::minutes(5));
+
+    let cookie = authkestra_axum::helpers::create_axum_cookie(&engine.session_config, session.id);
+    assert_eq!(
+        cookie.max_age(),
+        Some(tower_cookies::cookie::time::Duration::days(30))
+    );
+}
+
+#[test]
+fn test_actix_cookie_uses_cookie_max_age() {
+    let cookie = authkestra_actix::helpers::create_actix_cookie(&config(), "id".to_string());
+    assert_eq!(
+        cookie.max_age(),
+        Some(actix_web::cookie::time::Duration::minutes(10))
+    );
+
+    let browser_session = SessionConfig {
+        max_age: None,
+        ..Default::default()
+    };
+    let cookie = authkestra_actix::helpers::create_actix_cookie(&browser_session, "id".to_string());
+    assert_eq!(cookie.max_age(), None);
+}
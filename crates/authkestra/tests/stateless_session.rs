@@ -0,0 +1,166 @@
+use authkestra_axum::{AuthSession, AxumState};
+use authkestra_engine::{
+    state::Identity, Engine, Session, SessionConfig, SessionStore, StatelessSession,
+};
+use axum::{
+    body::Body,
+    http::{header, Request},
+    routing::get,
+    Router,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const SECRET: &[u8] = b"stateless-session-test-secret-32";
+
+fn identity() -> Identity {
+    Identity {
+        provider_id: "mock".to_string(),
+        external_id: "alice".to_string(),
+        email: Some("alice@example.com".to_string()),
+        email_verified: None,
+        username: None,
+        attributes: HashMap::from([("access_token".to_string(), "upstream".to_string())]),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+fn app(engine: authkestra_engine::AkWebAppEngine) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(|AuthSession(session): AuthSession| async move {
+                format!(
+                    "{} {}",
+                    session.identity.subject(),
+                    session.identity.email.unwrap_or_default()
+                )
+            }),
+        )
+        .with_state(AxumState::from(engine))
+}
+
+async fn call(app: &Router, session: &str) -> (u16, String) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/")
+                .header(header::COOKIE, format!("authkestra_session={session}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn engine(max_age: chrono::Duration) -> authkestra_engine::AkWebAppEngine {
+    let store: Arc<dyn SessionStore> = Arc::new(
+        StatelessSession::new(SECRET)
+            .unwrap()
+            .with_claims(["email"]),
+    );
+    Engine::builder()
+        .session_store(store)
+        .session_config(SessionConfig {
+            max_age: Some(max_age),
+            ..Default::default()
+        })
+        .build()
+}
+
+#[tokio::test]
+async fn test_valid_stateless_session() {
+    let engine = engine(chrono::Duration::hours(1));
+    let session = engine.create_session(identity()).await.unwrap();
+    // The cookie value is the signed token; nothing else was stored.
+    assert_eq!(session.id.split('.').count(), 3);
+
+    let (status, body) = call(&app(engine), &session.id).await;
+    assert_eq!(status, 200);
+    assert_eq!(body, "mock:alice alice@example.com");
+}
+
+#[tokio::test]
+async fn test_expired_stateless_session() {
+    let store = StatelessSession::new(SECRET).unwrap();
+    let token = store
+        .create_session(&Session {
+            id: String::new(),
+            identity: identity(),
+            expires_at: chrono::Utc::now() - chrono::Duration::seconds(1),
+            created_at: chrono::Utc::now(),
+        })
+        .await
+        .unwrap();
+
+    let (status, _) = call(&app(engine(chrono::Duration::hours(1))), &token).await;
+    assert_eq!(status, 401);
+}
+
+#[tokio::test]
+async fn test_tampered_stateless_session() {
+    let engine = engine(chrono::Duration::hours(1));
+    let session = engine.create_session(identity()).await.unwrap();
+    let app = app(engine);
+
+    // Flip the first character of the signature.
+    let signature_start = session.id.rfind('.').unwrap() + 1;
+    let mut tampered = session.id.clone();
+    let flipped = if tampered[signature_start..].starts_with('A') {
+        "B"
+    } else {
+        "A"
+    };
+    tampered.replace_range(signature_start..signature_start + 1, flipped);
+    assert_eq!(call(&app, &tampered).await.0, 401);
+    assert_eq!(call(&app, &session.id).await.0, 200);
+
+    // Signed with a different secret.
+    let other = StatelessSession::new(b"some-other-secret-of-32-bytes!!!").unwrap();
+    let foreign = other
+        .create_session(&Session {
+            id: String::new(),
+            identity: identity(),
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+            created_at: chrono::Utc::now(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(call(&app, &foreign).await.0, 401);
+}
+
+#[tokio::test]
+async fn test_rotated_secret_keeps_sessions() {
+    const NEXT: &[u8] = b"stateless-session-next-secret-32";
+    let old_engine = engine(chrono::Duration::hours(1));
+    let old_cookie = old_engine.create_session(identity()).await.unwrap().id;
+
+    let store: Arc<dyn SessionStore> = Arc::new(
+        StatelessSession::new(SECRET)
+            .unwrap()
+            .with_claims(["email"])
+            .rotate(NEXT, chrono::Duration::hours(1))
+            .unwrap(),
+    );
+    let engine = Engine::builder().session_store(store).build();
+
+    // Touching the session yields the cookie to write back, signed with the new secret.
+    let touched = engine.touch_session(&old_cookie).await.unwrap().unwrap();
+    assert_ne!(touched.id, old_cookie);
+    let next_only = StatelessSession::new(NEXT).unwrap();
+    assert!(next_only.verify(&old_cookie).is_err());
+    assert!(next_only.verify(&touched.id).is_ok());
+
+    let app = app(engine);
+    let (status, body) = call(&app, &old_cookie).await;
+    assert_eq!(status, 200);
+    assert_eq!(body, "mock:alice alice@example.com");
+    assert_eq!(call(&app, &touched.id).await.0, 200);
+}
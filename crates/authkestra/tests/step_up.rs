@@ -0,0 +1,152 @@
+use actix_web::{test as actix_test, web, App, HttpResponse};
+use authkestra_axum::{AxumState, StepUp};
+use authkestra_engine::{
+    state::Identity, store::memory::MemoryStore, AkEngine, Engine, Session, SessionStore,
+    TokenManager,
+};
+use axum::{body::Body, http::Request, routing::post, Router};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+const SECRET: &[u8] = b"a-test-secret-of-at-least-32-bytes!!";
+
+fn engine() -> AkEngine {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    Engine::builder()
+        .session_store(store)
+        .jwt_secret(SECRET)
+        .build()
+}
+
+/// A regular token, a level 1 and a level 2 step-up token, an expired
+/// level 2 step-up token and a level 2 token signed with another secret.
+fn tokens(engine: &AkEngine) -> [String; 5] {
+    let identity = Identity {
+        provider_id: "mock".to_string(),
+        external_id: "alice".to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    };
+    let manager = engine.token_manager();
+    let forger = TokenManager::new(b"another-secret-of-at-least-32-bytes!", None);
+    [
+        engine.issue_token(identity, 3600).unwrap(),
+        manager
+            .issue_stepup_token("alice", 1, Duration::from_secs(300))
+            .unwrap(),
+        manager
+            .issue_stepup_token("alice", 2, Duration::from_secs(300))
+            .unwrap(),
+        manager
+            .issue_stepup_token("alice", 2, Duration::from_secs(0))
+            .unwrap(),
+        forger
+            .issue_stepup_token("alice", 2, Duration::from_secs(300))
+            .unwrap(),
+    ]
+}
+
+#[test]
+fn test_require_acr() {
+    let manager = TokenManager::new(SECRET, None);
+    let token = manager
+        .issue_stepup_token("alice", 2, Duration::from_secs(300))
+        .unwrap();
+    let claims = manager.validate_token(&token, None).unwrap();
+    assert_eq!(claims.acr(), Some(2));
+    assert_eq!(claims.extra["acr"], "2");
+    assert!(claims.extra.contains_key("auth_time"));
+    assert!(claims.require_acr(1).is_ok());
+    assert!(claims.require_acr(2).is_ok());
+    assert!(matches!(
+        claims.require_acr(3),
+        Err(authkestra_engine::AuthError::StepUpRequired)
+    ));
+}
+
+#[tokio::test]
+async fn test_axum_step_up() {
+    let engine = engine();
+    let [regular, level1, level2, expired, forged] = tokens(&engine);
+    let app = Router::new()
+        .route(
+            "/account/delete",
+            post(|StepUp(claims): StepUp<2>| async move { claims.sub }),
+        )
+        .with_state(AxumState::from(engine));
+
+    let call = |token: Option<&str>| {
+        let mut request = Request::builder().method("POST").uri("/account/delete");
+        if let Some(token) = token {
+            request = request.header("authorization", format!("Bearer {token}"));
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+    let body = |response: axum::response::Response| async move {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    };
+
+    let response = call(Some(&level2)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(body(response).await, "alice");
+
+    for token in [&regular, &level1, &expired] {
+        let response = call(Some(token)).await.unwrap();
+        assert_eq!(response.status(), 403);
+        assert!(body(response).await.contains("step-up required"));
+    }
+
+    assert_eq!(call(Some(&forged)).await.unwrap().status(), 401);
+    assert_eq!(call(None).await.unwrap().status(), 401);
+}
+
+#[actix_web::test]
+async fn test_actix_step_up() {
+    let engine = engine();
+    let [regular, level1, level2, expired, forged] = tokens(&engine);
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(web::Data::new(authkestra_actix::AuthkestraState::from(
+                &engine,
+            )))
+            .route(
+                "/account/delete",
+                web::post().to(
+                    |authkestra_actix::StepUp(claims): authkestra_actix::StepUp<2>| async move {
+                        HttpResponse::Ok().body(claims.sub)
+                    },
+                ),
+            ),
+    )
+    .await;
+    let request = |token: Option<&str>| {
+        let mut request = actix_test::TestRequest::post().uri("/account/delete");
+        if let Some(token) = token {
+            request = request.insert_header(("authorization", format!("Bearer {token}")));
+        }
+        request.to_request()
+    };
+
+    let body = actix_test::call_and_read_body(&app, request(Some(&level2))).await;
+    assert_eq!(body, "alice");
+
+    for token in [&regular, &level1, &expired] {
+        let response = actix_test::call_service(&app, request(Some(token))).await;
+        assert_eq!(response.status(), 403);
+        let body = actix_test::read_body(response).await;
+        assert_eq!(body, "step-up required");
+    }
+
+    let response = actix_test::call_service(&app, request(Some(&forged))).await;
+    assert_eq!(response.status(), 401);
+    let response = actix_test::call_service(&app, request(None)).await;
+    assert_eq!(response.status(), 401);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{Engine, OAuth2Flow, Session, SessionConfig, SessionStore};
+use axum::{body::Body, http::Request, Router};
+use common::MockProvider;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn app(session_config: SessionConfig) -> Router {
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(MockProvider::new()))
+        .session_store(store)
+        .session_config(session_config)
+        .build();
+    engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new())
+}
+
+async fn call(app: &Router, uri: &str, cookie: Option<&str>) -> axum::response::Response {
+    let mut request = Request::builder().uri(uri);
+    if let Some(cookie) = cookie {
+        request = request.header("cookie", cookie);
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+/// Logs in with `success_url` and returns where the callback redirects to.
+async fn redirect_after_login(app: &Router, success_url: &str) -> String {
+    let success_url: String =
+        url::form_urlencoded::byte_serialize(success_url.as_bytes()).collect();
+    let response = call(
+        app,
+        &format!("/auth/login/mock?success_url={success_url}"),
+        None,
+    )
+    .await;
+    let location = response.headers()["location"].to_str().unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with("ak_state="))
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+
+    let response = call(
+        app,
+        &format!("/auth/callback/mock?code=abc&state={state}"),
+        Some(&state_cookie),
+    )
+    .await;
+    assert!(response.status().is_redirection(), "{}", response.status());
+    response.headers()["location"].to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_only_relative_paths_are_followed_by_default() {
+    let app = app(SessionConfig::default());
+
+    for (success_url, expected) in [
+        ("/ok", "/ok"),
+        ("/ok?tab=1#top", "/ok?tab=1#top"),
+        ("//evil.com", "/"),
+        ("https://evil.com", "/"),
+        ("/\\evil.com", "/"),
+        ("/\t/evil.com", "/"),
+        ("javascript:alert(1)", "/"),
+    ] {
+        assert_eq!(
+            redirect_after_login(&app, success_url).await,
+            expected,
+            "{success_url:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_allowlisted_origins_are_followed() {
+    let app = app(SessionConfig {
+        allowed_redirect_origins: vec!["https://app.example.com".to_string()],
+        ..Default::default()
+    });
+
+    for (success_url, expected) in [
+        (
+            "https://app.example.com/welcome",
+            "https://app.example.com/welcome",
+        ),
+        ("/ok", "/ok"),
+        ("https://evil.com", "/"),
+        ("http://app.example.com/welcome", "/"),
+        ("https://app.example.com.evil.com/", "/"),
+    ] {
+        assert_eq!(
+            redirect_after_login(&app, success_url).await,
+            expected,
+            "{success_url:?}"
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use async_trait::async_trait;
+use authkestra_engine::{
+    strategy::{TokenStrategy, TokenValidator},
+    AuthError,
+};
+use authkestra_resource::{Guard, TenantBinding, TenantSource};
+use axum::{body::Body, http::Request, routing::get, Router};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+#[derive(Clone, serde::Serialize)]
+struct User {
+    name: String,
+    tenant_id: String,
+}
+
+/// Accepts `<tenant>-token` for a user of that tenant.
+struct TenantValidator;
+
+#[async_trait]
+impl TokenValidator for TenantValidator {
+    type Identity = User;
+
+    async fn validate(&self, token: &str) -> Result<Option<User>, AuthError> {
+        Ok(token.strip_suffix("-token").map(|tenant| User {
+            name: "alice".to_string(),
+            tenant_id: tenant.to_string(),
+        }))
+    }
+}
+
+/// Reads the request's tenant from the `X-Tenant-Id` header.
+struct TenantHeader;
+
+impl TenantBinding for TenantHeader {
+    const SOURCE: TenantSource = TenantSource::Header("x-tenant-id");
+}
+
+fn guard() -> Arc<Guard<User>> {
+    Arc::new(
+        Guard::builder()
+            .strategy(TokenStrategy::new(TenantValidator))
+            .build(),
+    )
+}
+
+async fn axum_get(uri: &str, token: Option<&str>, tenant: Option<&str>) -> (u16, String) {
+    let app = Router::new()
+        .route(
+            "/tenants/{tenant_id}/orders",
+            get(
+                |authkestra_axum::TenantAuth(user, _): authkestra_axum::TenantAuth<User>| async move {
+                    user.tenant_id
+                },
+            ),
+        )
+        .route(
+            "/orders",
+            get(
+                |authkestra_axum::TenantAuth(user, _): authkestra_axum::TenantAuth<
+                    User,
+                    TenantHeader,
+                >| async move { user.tenant_id },
+            ),
+        )
+        .with_state(guard());
+
+    let mut request = Request::builder().uri(uri);
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    if let Some(tenant) = tenant {
+        request = request.header("x-tenant-id", tenant);
+    }
+    let response = app
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+async fn actix_get(uri: &str, token: Option<&str>, tenant: Option<&str>) -> (u16, String) {
+    use actix_web::{test, web, App};
+
+    let app =
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(guard()))
+                .route(
+                    "/tenants/{tenant_id}/orders",
+                    web::get().to(
+                        |authkestra_actix::TenantAuth(user, _): authkestra_actix::TenantAuth<
+                            User,
+                        >| async move { user.tenant_id },
+                    ),
+                )
+                .route(
+                    "/orders",
+                    web::get().to(
+                        |authkestra_actix::TenantAuth(user, _): authkestra_actix::TenantAuth<
+                            User,
+                            TenantHeader,
+                        >| async move { user.tenant_id },
+                    ),
+                ),
+        )
+        .await;
+
+    let mut request = test::TestRequest::get().uri(uri);
+    if let Some(token) = token {
+        request = request.insert_header(("authorization", format!("Bearer {token}")));
+    }
+    if let Some(tenant) = tenant {
+        request = request.insert_header(("x-tenant-id", tenant));
+    }
+    let response = test::call_service(&app, request.to_request()).await;
+    let status = response.status().as_u16();
+    let body = test::read_body(response).await;
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn forbidden_body() -> serde_json::Value {
+    serde_json::json!({
+        "error": "forbidden",
+        "message": "Identity does not belong to this tenant"
+    })
+}
+
+#[tokio::test]
+async fn test_axum_tenant_binding() {
+    assert_eq!(
+        axum_get("/tenants/acme/orders", Some("acme-token"), None).await,
+        (200, "acme".to_string())
+    );
+
+    let (status, body) = axum_get("/tenants/globex/orders", Some("acme-token"), None).await;
+    assert_eq!(status, 403);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+        forbidden_body()
+    );
+    assert_eq!(axum_get("/tenants/acme/orders", None, None).await.0, 401);
+
+    assert_eq!(
+        axum_get("/orders", Some("acme-token"), Some("acme")).await,
+        (200, "acme".to_string())
+    );
+    assert_eq!(
+        axum_get("/orders", Some("acme-token"), Some("globex"))
+            .await
+            .0,
+        403
+    );
+    assert_eq!(axum_get("/orders", Some("acme-token"), None).await.0, 400);
+}
+
+#[actix_web::test]
+async fn test_actix_tenant_binding() {
+    assert_eq!(
+        actix_get("/tenants/acme/orders", Some("acme-token"), None).await,
+        (200, "acme".to_string())
+    );
+
+    let (status, body) = actix_get("/tenants/globex/orders", Some("acme-token"), None).await;
+    assert_eq!(status, 403);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+        forbidden_body()
+    );
+    assert_eq!(actix_get("/tenants/acme/orders", None, None).await.0, 401);
+
+    assert_eq!(
+        actix_get("/orders", Some("acme-token"), Some("acme")).await,
+        (200, "acme".to_string())
+    );
+    assert_eq!(
+        actix_get("/orders", Some("acme-token"), Some("globex"))
+            .await
+            .0,
+        403
+    );
+    assert_eq!(actix_get("/orders", Some("acme-token"), None).await.0, 400);
+}
@@ -0,0 +1,164 @@
+use actix_web::{cookie::Cookie, test, web, App, HttpResponse};
+use authkestra_axum::AxumState;
+use authkestra_engine::{
+    state::Identity,
+    store::memory::MemoryStore,
+    token::{SubjectSource, TokenManager},
+    AkEngine, Engine, Session, SessionStore,
+};
+use axum::{body::Body, http::Request, routing::get, Router};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn identity(external_id: &str) -> Identity {
+    Identity {
+        provider_id: "mock".to_string(),
+        external_id: external_id.to_string(),
+        email: None,
+        email_verified: None,
+        username: None,
+        attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
+    }
+}
+
+fn engine() -> AkEngine {
+    engine_with(TokenManager::new(
+        b"a-test-secret-of-at-least-32-bytes!!",
+        None,
+    ))
+}
+
+fn engine_with(tokens: TokenManager) -> AkEngine {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    Engine::builder()
+        .session_store(store)
+        .token_manager(Arc::new(tokens))
+        .build()
+}
+
+/// A session for alice, a token for alice and a token for bob.
+async fn credentials(engine: &AkEngine) -> (String, String, String) {
+    let session = engine.create_session(identity("alice")).await.unwrap();
+    let alice = engine.issue_token(identity("alice"), 3600).unwrap();
+    let bob = engine.issue_token(identity("bob"), 3600).unwrap();
+    (format!("authkestra_session={}", session.id), alice, bob)
+}
+
+fn describe(session: Option<Session>, claims: Option<authkestra_engine::Claims>) -> String {
+    format!(
+        "{}/{}",
+        session.map_or("-".to_string(), |s| s.identity.external_id),
+        claims.map_or("-".to_string(), |c| c.sub)
+    )
+}
+
+#[tokio::test]
+async fn test_axum_bound_auth() {
+    axum_bound_auth(engine(), "alice", "bob").await;
+}
+
+/// Tokens carrying `provider:external_id` in `sub` bind to their session too.
+#[tokio::test]
+async fn test_axum_bound_auth_with_qualified_subject() {
+    let tokens = TokenManager::new(b"a-test-secret-of-at-least-32-bytes!!", None)
+        .with_subject_source(SubjectSource::Subject);
+    axum_bound_auth(engine_with(tokens), "mock:alice", "mock:bob").await;
+}
+
+async fn axum_bound_auth(engine: AkEngine, alice_sub: &str, bob_sub: &str) {
+    let (cookie, alice, bob) = credentials(&engine).await;
+    let app =
+        Router::new()
+            .route(
+                "/me",
+                get(|auth: authkestra_axum::BoundAuth| async move {
+                    describe(auth.session, auth.claims)
+                }),
+            )
+            .with_state(AxumState::from(engine));
+
+    let call = |cookie: Option<&str>, token: Option<&str>| {
+        let mut request = Request::builder().uri("/me");
+        if let Some(cookie) = cookie {
+            request = request.header("cookie", cookie);
+        }
+        if let Some(token) = token {
+            request = request.header("authorization", format!("Bearer {token}"));
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+    let body = |response: axum::response::Response| async move {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    };
+
+    let response = call(Some(&cookie), Some(&alice)).await.unwrap();
+    assert_eq!(body(response).await, format!("alice/{alice_sub}"));
+    let response = call(Some(&cookie), None).await.unwrap();
+    assert_eq!(body(response).await, "alice/-");
+    let response = call(None, Some(&bob)).await.unwrap();
+    assert_eq!(body(response).await, format!("-/{bob_sub}"));
+
+    let response = call(Some(&cookie), Some(&bob)).await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = call(None, None).await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = call(Some(&cookie), Some("garbage")).await.unwrap();
+    assert_eq!(response.status(), 401);
+}
+
+#[actix_web::test]
+async fn test_actix_bound_auth() {
+    actix_bound_auth(engine(), "alice", "bob").await;
+}
+
+#[actix_web::test]
+async fn test_actix_bound_auth_with_qualified_subject() {
+    let tokens = TokenManager::new(b"a-test-secret-of-at-least-32-bytes!!", None)
+        .with_subject_source(SubjectSource::Subject);
+    actix_bound_auth(engine_with(tokens), "mock:alice", "mock:bob").await;
+}
+
+async fn actix_bound_auth(engine: AkEngine, alice_sub: &str, bob_sub: &str) {
+    let (cookie, alice, bob) = credentials(&engine).await;
+    let session_id = cookie.split('=').nth(1).unwrap().to_string();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(authkestra_actix::AuthkestraState::from(
+                &engine,
+            )))
+            .route(
+                "/me",
+                web::get().to(|auth: authkestra_actix::BoundAuth| async move {
+                    HttpResponse::Ok().body(describe(auth.session, auth.claims))
+                }),
+            ),
+    )
+    .await;
+    let request = |with_cookie: bool, token: Option<&str>| {
+        let mut request = test::TestRequest::get().uri("/me");
+        if with_cookie {
+            request = request.cookie(Cookie::new("authkestra_session", session_id.clone()));
+        }
+        if let Some(token) = token {
+            request = request.insert_header(("authorization", format!("Bearer {token}")));
+        }
+        request.to_request()
+    };
+
+    let body = test::call_and_read_body(&app, request(true, Some(&alice))).await;
+    assert_eq!(body, format!("alice/{alice_sub}"));
+    let body = test::call_and_read_body(&app, request(true, None)).await;
+    assert_eq!(body, "alice/-");
+    let body = test::call_and_read_body(&app, request(false, Some(&bob))).await;
+    assert_eq!(body, format!("-/{bob_sub}"));
+
+    let response = test::call_service(&app, request(true, Some(&bob))).await;
+    assert_eq!(response.status(), 401);
+    let response = test::call_service(&app, request(false, None)).await;
+    assert_eq!(response.status(), 401);
+}
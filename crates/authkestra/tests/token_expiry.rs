@@ -0,0 +1,76 @@
+mod common;
+
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{state::OAuthToken, Engine, OAuth2Flow, Session, SessionStore};
+use axum::{body::Body, http::Request, Router};
+use common::MockProvider;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn set_cookie<'a>(response: &'a axum::response::Response, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with(&format!("{name}=")))
+        .map(|v| v.split(';').next().unwrap())
+}
+
+#[tokio::test]
+async fn test_stored_expiry_counts_from_token_fetch_time() {
+    let fetched_at =
+        chrono::DateTime::from_timestamp(chrono::Utc::now().timestamp() - 600, 0).unwrap();
+    let store: Arc<dyn SessionStore> =
+        Arc::new(authkestra_engine::store::memory::MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        // The token was fetched ten minutes before the callback stores it.
+        .provider(OAuth2Flow::new(MockProvider::new().with_token(
+            OAuthToken {
+                expires_in: Some(3600),
+                issued_at: fetched_at,
+                ..common::oauth_token(None)
+            },
+        )))
+        .session_store(store.clone())
+        .build();
+    let app: Router = engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/auth/login/mock")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let location = response.headers()["location"].to_str().unwrap();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let state_cookie = set_cookie(&response, "ak_state").unwrap().to_string();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/auth/callback/mock?code=abc&state={state}"))
+                .header("cookie", state_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(response.status().is_redirection());
+
+    let session_id = set_cookie(&response, "authkestra_session")
+        .unwrap()
+        .trim_start_matches("authkestra_session=");
+    let session = store.load_session(session_id).await.unwrap().unwrap();
+    assert_eq!(
+        session.identity.attributes["expires_at"],
+        (fetched_at.timestamp() + 3600).to_string()
+    );
+}
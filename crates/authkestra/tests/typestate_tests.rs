@@ -20,8 +20,10 @@ async fn test_typestate_session_flow() {
         provider_id: "test".to_string(),
         external_id: "user1".to_string(),
         email: None,
+        email_verified: None,
         username: None,
         attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
     };
     let session = auth.create_session(identity).await;
     assert!(session.is_ok());
@@ -44,8 +46,10 @@ fn test_typestate_token_flow() {
         provider_id: "test".to_string(),
         external_id: "user1".to_string(),
         email: None,
+        email_verified: None,
         username: None,
         attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
     };
     let token = auth.issue_token(identity, 3600);
     assert!(token.is_ok());
@@ -68,8 +72,10 @@ async fn test_typestate_full_flow() {
         provider_id: "test".to_string(),
         external_id: "user1".to_string(),
         email: None,
+        email_verified: None,
         username: None,
         attributes: HashMap::new(),
+        attributes_multi: HashMap::new(),
     };
 
     // Both should be available
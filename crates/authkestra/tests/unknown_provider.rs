@@ -0,0 +1,80 @@
+use authkestra_axum::{AxumExt, AxumState};
+use authkestra_engine::{AkWebAppEngine, Engine, UnknownProviderBody, UnknownProviderResponse};
+use axum::{body::Body, http::Request};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn engine_builder() -> authkestra_engine::EngineBuilder<
+    authkestra_engine::Configured<Arc<dyn authkestra_engine::auth::SessionStore>>,
+    authkestra_engine::Missing,
+> {
+    Engine::builder().session_store(Arc::new(
+        authkestra_engine::store::memory::MemoryStore::default(),
+    ))
+}
+
+async fn get(engine: AkWebAppEngine, uri: &str) -> axum::response::Response {
+    engine
+        .axum_router()
+        .with_state(AxumState::from(engine))
+        .layer(tower_cookies::CookieManagerLayer::new())
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+async fn body_string(response: axum::response::Response) -> String {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_unknown_provider_defaults_to_json_bad_request() {
+    for uri in ["/auth/login/nope", "/auth/callback/nope?code=c&state=s"] {
+        let response = get(engine_builder().build(), uri).await;
+        assert_eq!(response.status(), 400);
+        assert_eq!(response.headers()["content-type"], "application/json");
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "error": "unknown_provider", "valid": [] })
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_provider_lists_valid_providers() {
+    let engine = engine_builder()
+        .provider(authkestra_engine::OAuth2Flow::new(
+            authkestra_providers::github::GithubProvider::new(
+                "id".to_string(),
+                "secret".to_string(),
+                "http://localhost/auth/callback/github".to_string(),
+            ),
+        ))
+        .build();
+
+    let response = get(engine, "/auth/login/nope").await;
+    assert_eq!(response.status(), 400);
+
+    let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+    assert_eq!(body["error"], "unknown_provider");
+    assert_eq!(body["valid"], serde_json::json!(["github"]));
+}
+
+#[tokio::test]
+async fn test_unknown_provider_response_is_configurable() {
+    let engine = engine_builder()
+        .unknown_provider_response(UnknownProviderResponse {
+            status: 404,
+            body: UnknownProviderBody::Text,
+        })
+        .build();
+
+    let response = get(engine, "/auth/login/nope").await;
+    assert_eq!(response.status(), 404);
+    assert_eq!(body_string(response).await, "Provider nope not found");
+}
@@ -0,0 +1,120 @@
+use async_trait::async_trait;
+use authkestra_engine::{
+    error::AuthError, store::memory::MemoryStore, ConfigIssue, Engine, OAuth2Flow, Session,
+    SessionStore,
+};
+use authkestra_oidc::OidcProvider;
+use authkestra_providers::{github::GithubProvider, google::GoogleProvider};
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+struct DownStore;
+
+#[async_trait]
+impl SessionStore for DownStore {
+    async fn load_session(&self, _id: &str) -> Result<Option<Session>, AuthError> {
+        Err(AuthError::Session("connection refused".to_string()))
+    }
+
+    async fn save_session(&self, _session: &Session) -> Result<(), AuthError> {
+        Err(AuthError::Session("connection refused".to_string()))
+    }
+
+    async fn delete_session(&self, _id: &str) -> Result<(), AuthError> {
+        Err(AuthError::Session("connection refused".to_string()))
+    }
+}
+
+#[tokio::test]
+async fn test_relative_redirect_uri_is_flagged() {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(GithubProvider::new(
+            "id".to_string(),
+            "secret".to_string(),
+            "/auth/github/callback".to_string(),
+        )))
+        .provider(OAuth2Flow::new(GoogleProvider::new(
+            "id".to_string(),
+            "secret".to_string(),
+            "https://app.example/auth/google/callback".to_string(),
+        )))
+        .session_store(store)
+        .jwt_secret(b"")
+        .build();
+
+    let issues = engine.validate(false).await.unwrap_err();
+    assert_eq!(issues.len(), 2, "{issues:?}");
+    assert!(matches!(
+        &issues[0],
+        ConfigIssue::RelativeRedirectUri { provider, uri }
+            if provider == "github" && uri == "/auth/github/callback"
+    ));
+    assert!(matches!(issues[1], ConfigIssue::MissingSigningKey));
+}
+
+#[tokio::test]
+async fn test_valid_config_passes() {
+    let store: Arc<dyn SessionStore> = Arc::new(MemoryStore::<Session>::default());
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(GithubProvider::new(
+            "id".to_string(),
+            "secret".to_string(),
+            "http://localhost:3000/auth/github/callback".to_string(),
+        )))
+        .session_store(store)
+        .jwt_secret(b"a-secret-of-reasonable-length")
+        .build();
+
+    engine.validate(true).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_unreachable_jwks_and_session_store_are_flagged() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "issuer": server.uri(),
+            "authorization_endpoint": format!("{}/authorize", server.uri()),
+            "token_endpoint": format!("{}/token", server.uri()),
+            "jwks_uri": format!("{}/jwks", server.uri()),
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+
+    let provider = OidcProvider::discover(
+        "client-1".to_string(),
+        "secret".to_string(),
+        "http://localhost/callback".to_string(),
+        &server.uri(),
+        Duration::from_secs(3600),
+    )
+    .await
+    .unwrap();
+    let engine = Engine::builder()
+        .provider(OAuth2Flow::new(provider))
+        .session_store(Arc::new(DownStore))
+        .build();
+
+    // The JWKS is only fetched for a network check.
+    let issues = engine.validate(false).await.unwrap_err();
+    assert_eq!(issues.len(), 1, "{issues:?}");
+    assert!(matches!(issues[0], ConfigIssue::SessionStoreUnavailable(_)));
+
+    let issues = engine.validate(true).await.unwrap_err();
+    assert_eq!(issues.len(), 2, "{issues:?}");
+    assert!(matches!(
+        &issues[0],
+        ConfigIssue::UnreachableJwks { provider, uri, .. }
+            if provider == "oidc" && uri == &format!("{}/jwks", server.uri())
+    ));
+    assert!(matches!(issues[1], ConfigIssue::SessionStoreUnavailable(_)));
+}
@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use authkestra_axum::{TokenQueryParam, WsAuth};
+use authkestra_engine::{
+    strategy::{TokenStrategy, TokenValidator},
+    AuthError,
+};
+use authkestra_resource::Guard;
+use axum::{body::Body, http::Request, routing::get, Router};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+struct StaticValidator;
+
+#[async_trait]
+impl TokenValidator for StaticValidator {
+    type Identity = String;
+
+    async fn validate(&self, token: &str) -> Result<Option<String>, AuthError> {
+        Ok((token == "short-lived-token").then(|| "user123".to_string()))
+    }
+}
+
+struct TicketParam;
+
+impl TokenQueryParam for TicketParam {
+    const NAME: &'static str = "ticket";
+}
+
+fn app() -> Router {
+    let guard = Arc::new(
+        Guard::<String>::builder()
+            .strategy(TokenStrategy::new(StaticValidator))
+            .build(),
+    );
+
+    Router::new()
+        .route(
+            "/events",
+            get(|WsAuth(user, _): WsAuth<String>| async move { user }),
+        )
+        .route(
+            "/ticket",
+            get(|WsAuth(user, _): WsAuth<String, TicketParam>| async move { user }),
+        )
+        .with_state(guard)
+}
+
+async fn get_status(uri: &str) -> (u16, String) {
+    let response = app()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_ws_auth_accepts_access_token_query() {
+    let (status, body) = get_status("/events?access_token=short-lived-token").await;
+    assert_eq!(status, 200);
+    assert_eq!(body, "user123");
+}
+
+#[tokio::test]
+async fn test_ws_auth_rejects_missing_or_invalid_token() {
+    assert_eq!(get_status("/events").await.0, 401);
+    assert_eq!(get_status("/events?access_token=").await.0, 401);
+    assert_eq!(get_status("/events?access_token=forged").await.0, 401);
+}
+
+#[tokio::test]
+async fn test_ws_auth_custom_query_param() {
+    assert_eq!(get_status("/ticket?ticket=short-lived-token").await.0, 200);
+    assert_eq!(
+        get_status("/ticket?access_token=short-lived-token").await.0,
+        401
+    );
+}